@@ -9,12 +9,15 @@
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
 
-use quest_shadowplay::capture::FrameCompressor;
+use quest_shadowplay::capture::{CaptureClock, FrameCompressor, FramePacer};
 use quest_shadowplay::CapturedFrame;
 
-use super::{CaptureError, FrameCapture};
+use super::{CaptureCapabilities, CaptureError, FrameCapture};
+
+/// Simulated Quest 3 eye resolution
+const EYE_WIDTH: u32 = 1832;
+const EYE_HEIGHT: u32 = 1920;
 
 /// Android screen capture using MediaProjection.
 ///
@@ -42,7 +45,11 @@ impl Default for AndroidCapture {
 }
 
 impl FrameCapture for AndroidCapture {
-    fn start(&self, on_frame: Arc<dyn Fn(CapturedFrame) + Send + Sync>) -> Result<(), CaptureError> {
+    fn start(
+        &self,
+        compressor: Arc<FrameCompressor>,
+        on_frame: Arc<dyn Fn(CapturedFrame) + Send + Sync>,
+    ) -> Result<(), CaptureError> {
         if self.is_active.swap(true, Ordering::SeqCst) {
             return Err(CaptureError::AlreadyRunning);
         }
@@ -59,30 +66,30 @@ impl FrameCapture for AndroidCapture {
         thread::spawn(move || {
             log::info!("Android capture started at {} FPS (simulated)", fps);
 
-            let compressor = FrameCompressor::new(80);
-            let frame_duration = Duration::from_micros(1_000_000 / fps as u64);
+            let mut pacer = FramePacer::new(fps);
             let mut frame_number = 0u32;
 
             // Simulated Quest 3 resolution
-            let width = 1832u32;  // Quest 3 eye resolution
-            let height = 1920u32;
+            let width = EYE_WIDTH;
+            let height = EYE_HEIGHT;
+            let clock = CaptureClock::new();
 
             while is_active.load(Ordering::SeqCst) {
+                // Maintain frame rate
+                pacer.wait();
                 let frame_start = std::time::Instant::now();
 
-                // Generate test frame (replace with real MediaProjection)
-                if let Some(frame) = generate_test_frame(&compressor, frame_number, width, height) {
+                // Generate test frame (replace with real MediaProjection,
+                // stamped from the image's own timestamp)
+                let timestamp_ns = clock.at(frame_start);
+                if let Some(frame) =
+                    generate_test_frame(&compressor, frame_number, width, height, timestamp_ns)
+                {
                     on_frame(frame);
                 }
 
                 frame_number = frame_number.wrapping_add(1);
 
-                // Maintain frame rate
-                let elapsed = frame_start.elapsed();
-                if elapsed < frame_duration {
-                    thread::sleep(frame_duration - elapsed);
-                }
-
                 if frame_number % (fps * 10) == 0 {
                     log::info!("Android capture: {} frames", frame_number);
                 }
@@ -105,6 +112,14 @@ impl FrameCapture for AndroidCapture {
     fn source_name(&self) -> &'static str {
         "Android MediaProjection"
     }
+
+    fn capabilities(&self) -> CaptureCapabilities {
+        CaptureCapabilities {
+            fps: self.fps,
+            width: EYE_WIDTH,
+            height: EYE_HEIGHT,
+        }
+    }
 }
 
 /// Generates a test frame (placeholder for real MediaProjection).
@@ -113,6 +128,7 @@ fn generate_test_frame(
     frame_number: u32,
     width: u32,
     height: u32,
+    timestamp_ns: u64,
 ) -> Option<CapturedFrame> {
     // For Quest, generate a more interesting VR-like pattern
     let mut rgba = vec![0u8; (width * height * 4) as usize];
@@ -143,7 +159,7 @@ fn generate_test_frame(
     }
 
     match compressor.compress(&rgba, width, height) {
-        Ok(data) => Some(CapturedFrame::new(data, 0, width, height)),
+        Ok(data) => Some(CapturedFrame::with_timestamp(data, 0, width, height, timestamp_ns)),
         Err(e) => {
             if frame_number % 100 == 0 {
                 log::warn!("Frame compression failed: {}", e);
@@ -9,13 +9,14 @@
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
 
 use core_graphics::display::{CGDisplay, CGRect};
-use quest_shadowplay::capture::FrameCompressor;
+use image::{imageops, RgbaImage};
+use quest_shadowplay::capture::{CaptureClock, FrameCompressor, FramePacer};
+use quest_shadowplay::config::ScaleFilter;
 use quest_shadowplay::CapturedFrame;
 
-use super::{CaptureError, FrameCapture};
+use super::{CaptureCapabilities, CaptureError, FrameCapture};
 
 /// Target resolution: 1080p (1920x1080) for testing
 const TARGET_WIDTH: u32 = 1920;
@@ -28,6 +29,7 @@ const TARGET_HEIGHT: u32 = 1080;
 pub struct MacOSCapture {
     is_active: Arc<AtomicBool>,
     fps: u32,
+    scale_filter: ScaleFilter,
 }
 
 impl MacOSCapture {
@@ -36,8 +38,15 @@ impl MacOSCapture {
         Self {
             is_active: Arc::new(AtomicBool::new(false)),
             fps: 30, // 30 FPS for Mac testing
+            scale_filter: ScaleFilter::default(),
         }
     }
+
+    /// Sets how screenshots are resampled down to 1080p.
+    pub fn with_scale_filter(mut self, scale_filter: ScaleFilter) -> Self {
+        self.scale_filter = scale_filter;
+        self
+    }
 }
 
 impl Default for MacOSCapture {
@@ -47,26 +56,37 @@ impl Default for MacOSCapture {
 }
 
 impl FrameCapture for MacOSCapture {
-    fn start(&self, on_frame: Arc<dyn Fn(CapturedFrame) + Send + Sync>) -> Result<(), CaptureError> {
+    fn start(
+        &self,
+        compressor: Arc<FrameCompressor>,
+        on_frame: Arc<dyn Fn(CapturedFrame) + Send + Sync>,
+    ) -> Result<(), CaptureError> {
         if self.is_active.swap(true, Ordering::SeqCst) {
             return Err(CaptureError::AlreadyRunning);
         }
 
         let is_active = Arc::clone(&self.is_active);
         let fps = self.fps;
+        let scale_filter = self.scale_filter;
 
+        // Frames are compressed at the shared, live quality (config
+        // `jpeg_quality`, 80 by default) rather than the fixed 70 this
+        // capture used to pick for speed; lower it with set_jpeg_quality
         thread::spawn(move || {
             log::info!("macOS capture started: {}x{} @ {} FPS", TARGET_WIDTH, TARGET_HEIGHT, fps);
 
-            let compressor = FrameCompressor::new(70); // Lower quality for speed
-            let frame_duration = Duration::from_micros(1_000_000 / fps as u64);
+            let mut pacer = FramePacer::new(fps);
             let mut frame_number = 0u32;
+            let clock = CaptureClock::new();
+            let start_time = std::time::Instant::now();
 
             while is_active.load(Ordering::SeqCst) {
+                // Maintain frame rate
+                pacer.wait();
                 let frame_start = std::time::Instant::now();
 
                 // Capture screen
-                match capture_main_display(&compressor) {
+                match capture_main_display(&compressor, scale_filter, clock.at(frame_start)) {
                     Ok(frame) => {
                         on_frame(frame);
                     }
@@ -80,15 +100,9 @@ impl FrameCapture for MacOSCapture {
 
                 frame_number = frame_number.wrapping_add(1);
 
-                // Maintain frame rate
-                let elapsed = frame_start.elapsed();
-                if elapsed < frame_duration {
-                    thread::sleep(frame_duration - elapsed);
-                }
-
                 // Log progress periodically
                 if frame_number % (fps * 10) == 0 {
-                    let fps_actual = frame_number as f32 / frame_start.elapsed().as_secs_f32().max(0.001);
+                    let fps_actual = frame_number as f32 / start_time.elapsed().as_secs_f32().max(0.001);
                     log::info!("macOS capture: {} frames (~{:.1} FPS)", frame_number, fps_actual);
                 }
             }
@@ -110,10 +124,24 @@ impl FrameCapture for MacOSCapture {
     fn source_name(&self) -> &'static str {
         "macOS Screen Capture"
     }
+
+    fn capabilities(&self) -> CaptureCapabilities {
+        CaptureCapabilities {
+            fps: self.fps,
+            width: TARGET_WIDTH,
+            height: TARGET_HEIGHT,
+        }
+    }
 }
 
 /// Captures the main display using Core Graphics, with downscaling.
-fn capture_main_display(compressor: &FrameCompressor) -> Result<CapturedFrame, String> {
+///
+/// `timestamp_ns` is when the screenshot was requested.
+fn capture_main_display(
+    compressor: &FrameCompressor,
+    scale_filter: ScaleFilter,
+    timestamp_ns: u64,
+) -> Result<CapturedFrame, String> {
     // Get main display bounds
     let display = CGDisplay::main();
     let bounds = display.bounds();
@@ -139,26 +167,65 @@ fn capture_main_display(compressor: &FrameCompressor) -> Result<CapturedFrame, S
     let dst_width = TARGET_WIDTH;
     let dst_height = TARGET_HEIGHT;
     
-    // Calculate scale factors
-    let scale_x = src_width as f32 / dst_width as f32;
-    let scale_y = src_height as f32 / dst_height as f32;
-
     // Get raw pixel data
     let data = image.data();
     let pixel_data = data.bytes();
 
-    // Downscale using nearest-neighbor (fast) while converting BGRA→RGBA
+    let rgba = match scale_filter {
+        ScaleFilter::Nearest => downscale_nearest(
+            pixel_data,
+            bytes_per_row,
+            (src_width, src_height),
+            (dst_width, dst_height),
+        ),
+        filter => {
+            // Convert at full size, then let the filter blend neighbors
+            let full = RgbaImage::from_raw(
+                src_width,
+                src_height,
+                bgra_to_rgba(pixel_data, bytes_per_row, src_width, src_height),
+            )
+            .ok_or_else(|| "Screenshot smaller than its reported size".to_string())?;
+            imageops::resize(&full, dst_width, dst_height, filter.filter_type()).into_raw()
+        }
+    };
+
+    // Compress to JPEG
+    let compressed = compressor
+        .compress(&rgba, dst_width, dst_height)
+        .map_err(|e| format!("Compression failed: {}", e))?;
+
+    Ok(CapturedFrame::with_timestamp(
+        compressed,
+        0,
+        dst_width,
+        dst_height,
+        timestamp_ns,
+    ))
+}
+
+/// Downscales BGRA rows to RGBA by picking the nearest source pixel (fast).
+fn downscale_nearest(
+    pixel_data: &[u8],
+    bytes_per_row: usize,
+    (src_width, src_height): (u32, u32),
+    (dst_width, dst_height): (u32, u32),
+) -> Vec<u8> {
+    // Calculate scale factors
+    let scale_x = src_width as f32 / dst_width as f32;
+    let scale_y = src_height as f32 / dst_height as f32;
+
     let mut rgba = vec![0u8; (dst_width * dst_height * 4) as usize];
-    
+
     for dst_y in 0..dst_height {
         let src_y = ((dst_y as f32 * scale_y) as u32).min(src_height - 1);
         let dst_row_offset = (dst_y * dst_width * 4) as usize;
-        
+
         for dst_x in 0..dst_width {
             let src_x = ((dst_x as f32 * scale_x) as u32).min(src_width - 1);
             let src_idx = (src_y as usize * bytes_per_row) + (src_x as usize * 4);
             let dst_idx = dst_row_offset + (dst_x * 4) as usize;
-            
+
             if src_idx + 3 < pixel_data.len() {
                 rgba[dst_idx] = pixel_data[src_idx + 2];     // R (from B)
                 rgba[dst_idx + 1] = pixel_data[src_idx + 1]; // G
@@ -168,10 +235,17 @@ fn capture_main_display(compressor: &FrameCompressor) -> Result<CapturedFrame, S
         }
     }
 
-    // Compress to JPEG
-    let compressed = compressor
-        .compress(&rgba, dst_width, dst_height)
-        .map_err(|e| format!("Compression failed: {}", e))?;
+    rgba
+}
 
-    Ok(CapturedFrame::new(compressed, 0, dst_width, dst_height))
+/// Converts padded BGRA rows to tightly packed RGBA at full size.
+fn bgra_to_rgba(pixel_data: &[u8], bytes_per_row: usize, width: u32, height: u32) -> Vec<u8> {
+    let row_bytes = width as usize * 4;
+    let mut rgba = Vec::with_capacity(row_bytes * height as usize);
+    for row in pixel_data.chunks(bytes_per_row).take(height as usize) {
+        for bgra in row[..row_bytes.min(row.len())].chunks_exact(4) {
+            rgba.extend_from_slice(&[bgra[2], bgra[1], bgra[0], 255]);
+        }
+    }
+    rgba
 }
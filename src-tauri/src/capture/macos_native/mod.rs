@@ -42,6 +42,38 @@ impl Default for CaptureConfig {
     }
 }
 
+impl CaptureConfig {
+    /// Creates a config at the given size and rate, with a bitrate
+    /// scaled from the 1080p60 default
+    pub fn with_mode(width: u32, height: u32, fps: u32) -> Self {
+        let default = Self::default();
+        let scale = (width * height * fps) as f64
+            / (default.width * default.height * default.fps) as f64;
+        Self {
+            width,
+            height,
+            fps,
+            bitrate: (default.bitrate as f64 * scale).max(2_000_000.0) as u32,
+            ..default
+        }
+    }
+
+    /// Modes to try in order when the preferred one is rejected:
+    /// 1080p60, then 1080p30, then 720p30
+    pub fn fallback_ladder() -> Vec<Self> {
+        vec![
+            Self::default(),
+            Self::with_mode(1920, 1080, 30),
+            Self::with_mode(1280, 720, 30),
+        ]
+    }
+
+    /// Short description, e.g. "1920x1080 @ 60fps"
+    pub fn describe(&self) -> String {
+        format!("{}x{} @ {}fps", self.width, self.height, self.fps)
+    }
+}
+
 /// Statistics from the capture pipeline
 #[derive(Debug, Default)]
 pub struct CaptureStats {
@@ -185,6 +217,40 @@ impl Drop for NativeCaptureHandle {
     }
 }
 
+impl NativeCaptureHandle {
+    /// Creates and starts capture with the first mode in `ladder` that
+    /// works, so an unusual display mode still records something.
+    ///
+    /// The chosen mode is in the returned handle's `config`.
+    pub fn start_with_fallback(ladder: &[CaptureConfig], output_path: &Path) -> Result<Self, String> {
+        let mut errors = Vec::new();
+
+        for config in ladder {
+            let mode = config.describe();
+            match Self::new(config.clone()).and_then(|handle| {
+                handle.start(output_path)?;
+                Ok(handle)
+            }) {
+                Ok(handle) => {
+                    if !errors.is_empty() {
+                        log::warn!("Native capture fell back to {}", mode);
+                    }
+                    return Ok(handle);
+                }
+                Err(e) => {
+                    log::warn!("Native capture at {} failed: {}", mode, e);
+                    errors.push(format!("{}: {}", mode, e));
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            return Err("No capture modes to try".to_string());
+        }
+        Err(format!("All capture modes failed ({})", errors.join("; ")))
+    }
+}
+
 // Safety: The Swift handle is thread-safe (uses dispatch queues internally)
 #[cfg(target_os = "macos")]
 unsafe impl Send for NativeCaptureHandle {}
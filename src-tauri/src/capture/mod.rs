@@ -7,6 +7,8 @@
 //! - Other: Simulated test frames
 
 use std::sync::Arc;
+use quest_shadowplay::capture::FrameCompressor;
+use quest_shadowplay::config::ScaleFilter;
 use quest_shadowplay::CapturedFrame;
 
 // ============================================
@@ -42,8 +44,15 @@ pub use android::AndroidCapture;
 /// - No cross-platform contamination
 pub trait FrameCapture: Send + Sync {
     /// Starts capturing frames.
-    /// Frames are sent to the provided callback.
-    fn start(&self, on_frame: Arc<dyn Fn(CapturedFrame) + Send + Sync>) -> Result<(), CaptureError>;
+    /// Frames are compressed with the shared `compressor` (so quality
+    /// changes apply live) and sent to the provided callback. Each frame
+    /// is stamped from a `CaptureClock` when its pixels are grabbed, not
+    /// when compression finishes.
+    fn start(
+        &self,
+        compressor: Arc<FrameCompressor>,
+        on_frame: Arc<dyn Fn(CapturedFrame) + Send + Sync>,
+    ) -> Result<(), CaptureError>;
 
     /// Stops capturing frames.
     fn stop(&self);
@@ -54,6 +63,20 @@ pub trait FrameCapture: Send + Sync {
 
     /// Returns the name of this capture source (for logging/UI).
     fn source_name(&self) -> &'static str;
+
+    /// Returns the frame rate and resolution this source produces.
+    fn capabilities(&self) -> CaptureCapabilities;
+}
+
+/// Frame rate and resolution of a capture source.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CaptureCapabilities {
+    /// Frames per second the source targets
+    pub fps: u32,
+    /// Frame width in pixels
+    pub width: u32,
+    /// Frame height in pixels
+    pub height: u32,
 }
 
 /// Errors that can occur during capture.
@@ -93,14 +116,18 @@ impl std::error::Error for CaptureError {}
 /// - macOS: ScreenCaptureKit + VideoToolbox (hardware accelerated)
 /// - Android: MediaProjection (when implemented)
 /// - Other: Simulated test pattern frames
-pub fn create_capture() -> Box<dyn FrameCapture> {
+///
+/// `scale_filter` is how screenshots are resampled where the source
+/// captures above its output size (macOS).
+#[cfg_attr(not(target_os = "macos"), allow(unused_variables))]
+pub fn create_capture(scale_filter: ScaleFilter) -> Box<dyn FrameCapture> {
     #[cfg(target_os = "macos")]
     {
         // Use legacy CoreGraphics capture for now
         // Native capture writes directly to MP4, different interface
         log::info!("Platform: macOS - using Core Graphics screen capture");
         log::info!("Note: Use 'start_native_recording' for hardware-accelerated 60fps capture");
-        Box::new(macos::MacOSCapture::new())
+        Box::new(macos::MacOSCapture::new().with_scale_filter(scale_filter))
     }
 
     #[cfg(target_os = "android")]
@@ -6,12 +6,10 @@
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
-use std::time::{Duration, Instant};
-
-use quest_shadowplay::capture::FrameCompressor;
+use quest_shadowplay::capture::{CaptureClock, FrameCompressor, FramePacer};
 use quest_shadowplay::CapturedFrame;
 
-use super::{CaptureError, FrameCapture};
+use super::{CaptureCapabilities, CaptureError, FrameCapture};
 
 /// Simulated capture that generates test pattern frames.
 pub struct SimulatedCapture {
@@ -45,7 +43,11 @@ impl SimulatedCapture {
 }
 
 impl FrameCapture for SimulatedCapture {
-    fn start(&self, on_frame: Arc<dyn Fn(CapturedFrame) + Send + Sync>) -> Result<(), CaptureError> {
+    fn start(
+        &self,
+        compressor: Arc<FrameCompressor>,
+        on_frame: Arc<dyn Fn(CapturedFrame) + Send + Sync>,
+    ) -> Result<(), CaptureError> {
         if self.is_active.swap(true, Ordering::SeqCst) {
             return Err(CaptureError::AlreadyRunning);
         }
@@ -58,26 +60,24 @@ impl FrameCapture for SimulatedCapture {
         thread::spawn(move || {
             log::info!("Simulated capture started: {}x{} @ {} FPS", width, height, fps);
 
-            let compressor = FrameCompressor::new(80);
-            let frame_duration = Duration::from_micros(1_000_000 / fps as u64);
+            let mut pacer = FramePacer::new(fps);
             let mut frame_number = 0u32;
-            let start_time = Instant::now();
+            let clock = CaptureClock::new();
 
             while is_active.load(Ordering::SeqCst) {
+                // Maintain frame rate
+                pacer.wait();
+
                 // Generate frame
-                if let Some(frame) = generate_test_frame(&compressor, frame_number, width, height) {
+                let timestamp_ns = clock.now_ns();
+                if let Some(frame) =
+                    generate_test_frame(&compressor, frame_number, width, height, timestamp_ns)
+                {
                     on_frame(frame);
                 }
 
                 frame_number = frame_number.wrapping_add(1);
 
-                // Maintain frame rate
-                let elapsed = start_time.elapsed();
-                let expected = frame_duration * frame_number;
-                if expected > elapsed {
-                    thread::sleep(expected - elapsed);
-                }
-
                 // Log progress periodically
                 if frame_number % (fps * 5) == 0 {
                     log::debug!("Simulated: {} frames captured", frame_number);
@@ -101,6 +101,14 @@ impl FrameCapture for SimulatedCapture {
     fn source_name(&self) -> &'static str {
         "Simulated"
     }
+
+    fn capabilities(&self) -> CaptureCapabilities {
+        CaptureCapabilities {
+            fps: self.fps,
+            width: self.width,
+            height: self.height,
+        }
+    }
 }
 
 impl Default for SimulatedCapture {
@@ -115,6 +123,7 @@ fn generate_test_frame(
     frame_number: u32,
     width: u32,
     height: u32,
+    timestamp_ns: u64,
 ) -> Option<CapturedFrame> {
     let mut rgba = vec![0u8; (width * height * 4) as usize];
 
@@ -142,7 +151,7 @@ fn generate_test_frame(
 
     // Compress to JPEG
     match compressor.compress(&rgba, width, height) {
-        Ok(data) => Some(CapturedFrame::new(data, 0, width, height)),
+        Ok(data) => Some(CapturedFrame::with_timestamp(data, 0, width, height, timestamp_ns)),
         Err(e) => {
             log::warn!("Frame compression failed: {}", e);
             None
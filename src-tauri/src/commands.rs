@@ -4,11 +4,17 @@
 //! They bridge the UI to the Rust backend.
 
 use std::sync::Arc;
-use tauri::State;
+use tauri::{AppHandle, Emitter, State};
 
 use crate::state::{AppState, ClipInfo};
-use quest_shadowplay::encoder::VideoEncoder;
-use quest_shadowplay::storage::StorageManager;
+use quest_shadowplay::input::InputState;
+use quest_shadowplay::StorageErrorKind;
+use quest_shadowplay::encoder::{
+    self, marks_to_ffmetadata, marks_to_webvtt, AspectMode, ColorRange, ColorSpace, ExportAspect,
+    StereoLayout, VideoEncoder,
+};
+use quest_shadowplay::config::{InputAction, Rotation, TriggerButton};
+use quest_shadowplay::storage::{self, ClipMetadata, SaveOutput, SavedSettings, StorageManager};
 
 #[cfg(target_os = "macos")]
 use crate::capture::macos_native::{CaptureConfig, NativeCaptureHandle};
@@ -22,7 +28,63 @@ pub struct StatusInfo {
     pub buffer_fill_percent: f32,
     pub frame_count: usize,
     pub buffer_capacity: usize,
+    /// Old frames pushed out of the full buffer so far
+    pub frames_overwritten: u64,
     pub clips_count: usize,
+    /// Clips saved since recording last started
+    pub session_clips_saved: u32,
+    /// Recording stops after this many clips per session (0 = never)
+    pub session_clip_limit: u32,
+    /// Raw size ÷ stored size of the buffered frames (0 = empty)
+    pub compression_ratio: f32,
+    /// Seconds until recording auto-stops (None = no limit or idle)
+    pub session_remaining_seconds: Option<f32>,
+    /// Recording was stopped by the session time or clip limit
+    pub auto_stopped: bool,
+    /// New frames are dropped until the last save is acknowledged
+    pub paused_after_save: bool,
+    /// How long saving the buffer now would take on this device
+    pub estimated_save_seconds: f32,
+}
+
+/// Active capture source information sent to the frontend
+#[derive(serde::Serialize)]
+pub struct CaptureInfo {
+    pub source_name: String,
+    pub fps: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// JPEG quality and its effect on buffer memory
+#[derive(serde::Serialize)]
+pub struct JpegQualityInfo {
+    pub quality: u8,
+    /// Expected buffer size once it has filled at this quality
+    pub estimated_memory_mb: f32,
+    /// Current buffer size (older frames keep their old quality)
+    pub buffer_memory_mb: f32,
+    /// Cautions about the config at this quality, e.g. memory use
+    pub warnings: Vec<String>,
+}
+
+/// Heuristic quality of a bitrate, for guidance next to a slider
+#[derive(serde::Serialize)]
+pub struct QualityEstimateInfo {
+    pub bitrate: u32,
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    /// Bits available per pixel per frame
+    pub bits_per_pixel: f64,
+    /// Kush motion factor covered (1 = low, 2 = medium, 4 = high)
+    pub motion_factor: f64,
+    /// "poor", "fair", "good" or "excellent"
+    pub rating: String,
+    /// Bitrate for a "good" rating at this resolution and frame rate
+    pub good_bitrate: u32,
+    /// Bitrate for an "excellent" rating
+    pub excellent_bitrate: u32,
 }
 
 /// Result of a save operation
@@ -47,16 +109,19 @@ pub async fn start_recording(state: State<'_, Arc<AppState>>) -> Result<bool, St
 
     log::info!("Starting recording...");
 
-    // Create callback to push frames to buffer
-    let buffer = Arc::clone(&state.buffer);
+    // Create callback to push frames to buffer, stopping once the
+    // session limit passes
+    let app = Arc::clone(&state);
     let callback = std::sync::Arc::new(move |frame| {
-        buffer.push_frame(frame);
+        if app.check_session_limit() {
+            app.push_frame(frame);
+        }
     });
 
     // Start platform-specific capture
     {
         let capture = state.capture.lock();
-        if let Err(e) = capture.start(callback) {
+        if let Err(e) = capture.start(Arc::clone(&state.compressor), callback) {
             log::error!("Failed to start capture: {}", e);
             return Err(format!("Failed to start capture: {}", e));
         }
@@ -88,14 +153,72 @@ pub async fn stop_recording(state: State<'_, Arc<AppState>>) -> Result<bool, Str
     Ok(true)
 }
 
+/// Resumes recording that was paused after a save
+#[tauri::command]
+pub async fn acknowledge_save(state: State<'_, Arc<AppState>>) -> Result<bool, String> {
+    let resumed = state.acknowledge_save();
+    if resumed {
+        log::info!("Save acknowledged, recording resumed");
+    }
+    Ok(resumed)
+}
+
 // ============================================
 // CLIP MANAGEMENT COMMANDS
 // ============================================
 
 /// Saves the current buffer as a clip
+///
+/// `outputs` names extra files to write from the same decode: "proxy",
+/// "gif" and/or "frames" (numbered JPEGs for MP4 export). Without it,
+/// the configured defaults are used. "mp4" also exports the clip as
+/// [`export_to_mp4`] does once it is saved; that step only decodes
+/// frames for 3D packing, so it doesn't repeat the decode.
 #[tauri::command]
-pub async fn save_clip(state: State<'_, Arc<AppState>>) -> Result<SaveResult, String> {
+pub async fn save_clip(
+    state: State<'_, Arc<AppState>>,
+    outputs: Option<Vec<String>>,
+) -> Result<SaveResult, String> {
     log::info!("Saving clip...");
+    let triggered_at = chrono::Local::now();
+
+    // One save at a time, so two can't claim the same clip name
+    let app_state = Arc::clone(&state);
+    let Some(_saving) = app_state.begin_save() else {
+        return Ok(SaveResult {
+            success: false,
+            message: "A save is already in progress".to_string(),
+            clip_id: None,
+        });
+    };
+
+    let mut export_mp4 = false;
+    let mut outputs = match outputs {
+        None => SaveOutput::defaults(&state.config),
+        Some(names) => {
+            let mut outputs = Vec::new();
+            for name in names {
+                if name == "mp4" {
+                    export_mp4 = true;
+                    continue;
+                }
+                match SaveOutput::from_name(&name) {
+                    Some(output) => outputs.push(output),
+                    None => {
+                        return Ok(SaveResult {
+                            success: false,
+                            message: format!("Unknown save output: {}", name),
+                            clip_id: None,
+                        });
+                    }
+                }
+            }
+            outputs
+        }
+    };
+    if !outputs.contains(&SaveOutput::Master) {
+        outputs.insert(0, SaveOutput::Master);
+    }
 
     // Get frames from buffer
     let frames = state.snapshot_frames();
@@ -111,13 +234,16 @@ pub async fn save_clip(state: State<'_, Arc<AppState>>) -> Result<SaveResult, St
     let frame_count = frames.len();
     log::info!("Saving {} frames...", frame_count);
 
-    // Generate output path
-    let output_path = StorageManager::generate_filename(
-        state.clips_directory.to_str().unwrap_or("")
+    // Generate output path, in the day's folder if organizing by date
+    let clip_directory = StorageManager::clip_directory_at(
+        state.clips_directory.to_str().unwrap_or(""),
+        triggered_at,
+        state.config.organize_by_date,
     );
+    let output_path = StorageManager::generate_filename_at(&clip_directory, triggered_at);
 
-    // Ensure directory exists
-    if let Err(e) = std::fs::create_dir_all(&state.clips_directory) {
+    // Ensure directory exists, even if deleted since startup
+    if let Err(e) = storage::recreate_if_missing(std::path::Path::new(&clip_directory)) {
         return Ok(SaveResult {
             success: false,
             message: format!("Failed to create directory: {}", e),
@@ -125,22 +251,59 @@ pub async fn save_clip(state: State<'_, Arc<AppState>>) -> Result<SaveResult, St
         });
     }
 
-    // Encode frames
-    match VideoEncoder::encode_frames(&frames, &output_path, &state.config) {
-        Ok(()) => {
-            let clip_id = std::path::Path::new(&output_path)
-                .file_name()
-                .map(|s| s.to_string_lossy().to_string());
+    // Check the clip fits before encoding, freeing old clips if allowed
+    let needed = VideoEncoder::estimated_clip_bytes(&frames);
+    let room = StorageManager::new(&state.clips_directory.to_string_lossy())
+        .and_then(|manager| manager.make_room_for(needed, state.config.auto_cleanup));
+    if let Err(e) = room {
+        log::warn!("Not saving clip: {}", e);
+        let message = match e.storage_kind() {
+            Some(StorageErrorKind::DiskFull) => {
+                format!("Not saved, free up space and try again ({})", e)
+            }
+            _ => e.to_string(),
+        };
+        return Ok(SaveResult {
+            success: false,
+            message,
+            clip_id: None,
+        });
+    }
+
+    // Encode frames, and any extra outputs from the same decode
+    let clip_path = std::path::Path::new(&output_path);
+    let marks = state.marks();
+    match storage::write_outputs(&frames, &marks, clip_path, &outputs, &state.config) {
+        Ok(_) => {
+            let clip_id = Some(AppState::clip_id(&state.clips_directory, clip_path));
 
             log::info!("Clip saved: {}", output_path);
 
-            // Clear the buffer after successful save
-            state.buffer.clear();
-            log::info!("Buffer cleared after save");
+            let metadata = ClipMetadata {
+                triggered_at: Some(triggered_at),
+                saved_at: Some(chrono::Local::now()),
+                app: state.recorded_app(),
+                ..ClipMetadata::for_frames(&frames, state.config.target_fps)
+            };
+            if let Err(e) = metadata.save(std::path::Path::new(&output_path)) {
+                log::warn!("Failed to write clip metadata: {}", e);
+            }
+
+            // Keep recording, clear or pause, as configured
+            state.finish_save();
+
+            let mut message = format!("Saved {} frames", frame_count);
+            if let (true, Some(id)) = (export_mp4, &clip_id) {
+                let export = export_to_mp4(state, id.clone(), None, None).await?;
+                if !export.success {
+                    log::warn!("Clip saved, but MP4 export failed: {}", export.message);
+                    message = format!("{} (MP4 export failed: {})", message, export.message);
+                }
+            }
 
             Ok(SaveResult {
                 success: true,
-                message: format!("Saved {} frames", frame_count),
+                message,
                 clip_id,
             })
         }
@@ -165,10 +328,236 @@ pub async fn get_status(state: State<'_, Arc<AppState>>) -> Result<StatusInfo, S
         buffer_fill_percent: state.buffer_fill() * 100.0,
         frame_count: state.frame_count(),
         buffer_capacity: state.config.buffer_frame_count(),
+        frames_overwritten: state.buffer.evicted_count(),
         clips_count,
+        session_clips_saved: state.session_clips_saved(),
+        session_clip_limit: state.config.auto_stop_after_clips,
+        compression_ratio: state.buffer.average_compression_ratio(),
+        session_remaining_seconds: state.session.remaining().map(|d| d.as_secs_f32()),
+        auto_stopped: state.was_auto_stopped(),
+        paused_after_save: state.is_paused_after_save(),
+        estimated_save_seconds: estimated_save_seconds(&state),
     })
 }
 
+/// Estimates the save time of the buffered footage.
+fn estimated_save_seconds(state: &AppState) -> f32 {
+    let footage = state.frame_count() as f32 / state.config.target_fps.max(1) as f32;
+    encoder::estimated_encoding_time(footage).as_secs_f32()
+}
+
+/// Times a sample save so save-time estimates match this device
+///
+/// Returns seconds of saving per second of footage.
+#[tauri::command]
+pub async fn calibrate_encoder(state: State<'_, Arc<AppState>>) -> Result<f64, String> {
+    let config = state.config.clone();
+    tauri::async_runtime::spawn_blocking(move || encoder::calibrate_encoder(&config))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Gets the active capture source and what it produces
+#[tauri::command]
+pub async fn get_capture_info(state: State<'_, Arc<AppState>>) -> Result<CaptureInfo, String> {
+    let capture = state.capture.lock();
+    let caps = capture.capabilities();
+
+    Ok(CaptureInfo {
+        source_name: capture.source_name().to_string(),
+        fps: caps.fps,
+        width: caps.width,
+        height: caps.height,
+    })
+}
+
+/// Estimates how a bitrate will look (Kush gauge)
+///
+/// Resolution and frame rate default to the capture source's.
+#[tauri::command]
+pub async fn estimate_quality(
+    state: State<'_, Arc<AppState>>,
+    bitrate: u32,
+    width: Option<u32>,
+    height: Option<u32>,
+    fps: Option<u32>,
+) -> Result<QualityEstimateInfo, String> {
+    let caps = state.capture.lock().capabilities();
+    let width = width.unwrap_or(caps.width);
+    let height = height.unwrap_or(caps.height);
+    let fps = fps.unwrap_or(caps.fps);
+
+    let estimate = encoder::estimate_quality(bitrate, width, height, fps);
+    Ok(QualityEstimateInfo {
+        bitrate,
+        width,
+        height,
+        fps,
+        bits_per_pixel: estimate.bits_per_pixel,
+        motion_factor: estimate.motion_factor,
+        rating: estimate.rating.as_str().to_string(),
+        good_bitrate: encoder::kush_bitrate(width, height, fps, 2.0),
+        excellent_bitrate: encoder::kush_bitrate(width, height, fps, 4.0),
+    })
+}
+
+/// Gets the live JPEG quality and buffer memory
+#[tauri::command]
+pub async fn get_jpeg_quality(state: State<'_, Arc<AppState>>) -> Result<JpegQualityInfo, String> {
+    Ok(jpeg_quality_info(&state))
+}
+
+/// Changes the JPEG quality used for new frames (1-100)
+#[tauri::command]
+pub async fn set_jpeg_quality(
+    state: State<'_, Arc<AppState>>,
+    quality: u8,
+) -> Result<JpegQualityInfo, String> {
+    state.compressor.set_quality(quality).map_err(|e| e.to_string())?;
+    log::info!("JPEG quality set to {}", quality);
+    Ok(jpeg_quality_info(&state))
+}
+
+/// Marks the current moment so the next clip holding it gets a chapter
+/// and subtitle there. Returns false if nothing has been captured yet
+#[tauri::command]
+pub async fn add_mark(
+    state: State<'_, Arc<AppState>>,
+    label: Option<String>,
+) -> Result<bool, String> {
+    Ok(state.add_mark(label))
+}
+
+/// Sets which app or game is being recorded, stored with clips saved
+/// from now on (None = unknown)
+#[tauri::command]
+pub async fn set_recorded_app(
+    state: State<'_, Arc<AppState>>,
+    app: Option<String>,
+) -> Result<(), String> {
+    state.set_recorded_app(app);
+    Ok(())
+}
+
+/// Reports the current quality with estimated and actual memory use.
+fn jpeg_quality_info(state: &AppState) -> JpegQualityInfo {
+    const MB: f32 = 1024.0 * 1024.0;
+    let quality = state.jpeg_quality();
+    let config = quest_shadowplay::Config {
+        jpeg_quality: quality,
+        ..state.config.clone()
+    };
+
+    JpegQualityInfo {
+        quality,
+        estimated_memory_mb: config.estimated_memory_mb(),
+        buffer_memory_mb: state.buffer.memory_bytes() as f32 / MB,
+        warnings: config.warnings().iter().map(|w| w.to_string()).collect(),
+    }
+}
+
+/// Gets the button combo that saves a clip
+#[tauri::command]
+pub async fn get_trigger_button(state: State<'_, Arc<AppState>>) -> Result<TriggerButton, String> {
+    Ok(state.input.lock().trigger_button().clone())
+}
+
+/// Changes the main button combo that saves a clip and remembers it
+///
+/// Takes a preset (`"both_grips"`) or a custom list of controls
+/// (`{ "custom": ["left_grip", "right_trigger"] }`). Extra combos from
+/// the config keep saving too.
+#[tauri::command]
+pub async fn set_trigger_button(
+    state: State<'_, Arc<AppState>>,
+    button: TriggerButton,
+) -> Result<TriggerButton, String> {
+    // Also refuses a combo already bound to pause, clear or mark
+    state
+        .input
+        .lock()
+        .check_trigger_button(&button)
+        .map_err(|e| e.to_string())?;
+
+    // Save first, so a failed write leaves the old binding in place
+    let mut settings = SavedSettings::load(&state.clips_directory).unwrap_or_else(|e| {
+        log::warn!("Replacing unreadable settings: {}", e);
+        SavedSettings::default()
+    });
+    settings.trigger_button = Some(button.clone());
+    settings.save(&state.clips_directory).map_err(|e| e.to_string())?;
+
+    state
+        .input
+        .lock()
+        .set_primary_trigger_button(button.clone())
+        .map_err(|e| e.to_string())?;
+    log::info!("Trigger button set to {:?}", button);
+    Ok(button)
+}
+
+/// Feeds the latest controller state to the input handler
+///
+/// The UI polls connected gamepads and sends their state here. Mark
+/// bindings are handled here; returns true when the save combo was
+/// pressed, so the UI can save a clip.
+#[tauri::command]
+pub async fn update_input(
+    state: State<'_, Arc<AppState>>,
+    input: InputState,
+) -> Result<bool, String> {
+    let (actions, save) = {
+        let mut handler = state.input.lock();
+        handler.update(input);
+        (handler.check_actions(), handler.check_save_triggered())
+    };
+    for action in actions {
+        if action == InputAction::Mark {
+            state.add_mark(None);
+        }
+    }
+    Ok(save)
+}
+
+/// Gets the default export directory (None = the clips directory)
+#[tauri::command]
+pub async fn get_export_directory(
+    state: State<'_, Arc<AppState>>,
+) -> Result<Option<String>, String> {
+    Ok(state.configured_export_directory())
+}
+
+/// Changes the default export directory and remembers it
+///
+/// `None` sends exports back to the clips directory. Fails if the
+/// directory isn't writable.
+#[tauri::command]
+pub async fn set_export_directory(
+    state: State<'_, Arc<AppState>>,
+    directory: Option<String>,
+) -> Result<Option<String>, String> {
+    if let Some(directory) = &directory {
+        storage::ensure_writable_directory(std::path::Path::new(directory))
+            .map_err(|e| e.to_string())?;
+    }
+
+    // Save first, so a failed write leaves the old directory in place
+    let mut settings = SavedSettings::load(&state.clips_directory).unwrap_or_else(|e| {
+        log::warn!("Replacing unreadable settings: {}", e);
+        SavedSettings::default()
+    });
+    settings.export_directory = directory.clone();
+    settings
+        .save(&state.clips_directory)
+        .map_err(|e| e.to_string())?;
+
+    state.set_export_directory(directory.clone());
+
+    log::info!("Export directory set to {:?}", directory);
+    Ok(directory)
+}
+
 /// Lists all saved clips
 #[tauri::command]
 pub async fn list_clips(state: State<'_, Arc<AppState>>) -> Result<Vec<ClipInfo>, String> {
@@ -182,36 +571,408 @@ pub async fn delete_clip(state: State<'_, Arc<AppState>>, id: String) -> Result<
     Ok(true)
 }
 
+/// Trims static frames (paused menus etc.) from both ends of a clip,
+/// saving the result as a new `<name>_trimmed.qsp` clip
+#[tauri::command]
+pub async fn auto_trim_clip(
+    state: State<'_, Arc<AppState>>,
+    id: String,
+) -> Result<SaveResult, String> {
+    let input = match state.resolve_clip_path(&id) {
+        Ok(path) => path,
+        Err(e) => {
+            return Ok(SaveResult {
+                success: false,
+                message: e.to_string(),
+                clip_id: None,
+            });
+        }
+    };
+
+    // Next to the source, named from its stem whatever its extension
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+    let output = input.with_file_name(format!("{}_trimmed.qsp", stem));
+    if output == input {
+        return Ok(SaveResult {
+            success: false,
+            message: format!("Refusing to trim {} over itself", id),
+            clip_id: None,
+        });
+    }
+    let trimmed_id = AppState::clip_id(&state.clips_directory, &output);
+
+    match VideoEncoder::auto_trim_static(
+        &input.to_string_lossy(),
+        &output.to_string_lossy(),
+        quest_shadowplay::encoder::DEFAULT_STATIC_THRESHOLD,
+    ) {
+        Ok(kept) => Ok(SaveResult {
+            success: true,
+            message: format!("Kept frames {} to {}", kept.start, kept.end),
+            clip_id: Some(trimmed_id),
+        }),
+        Err(e) => Ok(SaveResult {
+            success: false,
+            message: format!("Failed to trim: {}", e),
+            clip_id: None,
+        }),
+    }
+}
+
+/// Result of re-deriving a clip's frame rate
+#[derive(serde::Serialize)]
+pub struct RepairResult {
+    pub success: bool,
+    pub message: String,
+    /// Frame rate the header declared
+    pub old_fps: u32,
+    /// Frame rate the timestamps show, now in the header
+    pub new_fps: u32,
+}
+
+/// Fixes the playback speed of a clip saved with a wrong declared
+/// frame rate, by rewriting its header from the frame timestamps
+#[tauri::command]
+pub async fn repair_clip_fps(
+    state: State<'_, Arc<AppState>>,
+    id: String,
+) -> Result<RepairResult, String> {
+    let path = match state.resolve_clip_path(&id) {
+        Ok(path) => path,
+        Err(e) => {
+            return Ok(RepairResult {
+                success: false,
+                message: e.to_string(),
+                old_fps: 0,
+                new_fps: 0,
+            });
+        }
+    };
+
+    match VideoEncoder::repair_fps(&path.to_string_lossy()) {
+        Ok((old_fps, new_fps)) => Ok(RepairResult {
+            success: true,
+            message: if old_fps == new_fps {
+                format!("Frame rate already correct ({} fps)", new_fps)
+            } else {
+                format!("Frame rate changed from {} to {} fps", old_fps, new_fps)
+            },
+            old_fps,
+            new_fps,
+        }),
+        Err(e) => Ok(RepairResult {
+            success: false,
+            message: format!("Failed to repair: {}", e),
+            old_fps: 0,
+            new_fps: 0,
+        }),
+    }
+}
+
+/// Result of checking and repairing the whole clips library
+#[derive(serde::Serialize)]
+pub struct LibraryRepairResult {
+    pub success: bool,
+    pub message: String,
+    /// Nothing was changed; counts are what a real run would do
+    pub dry_run: bool,
+    pub clips_checked: usize,
+    /// Clips that can't be read, left in place
+    pub damaged_ids: Vec<String>,
+    /// Leftovers of deleted clips
+    pub removed: Vec<String>,
+    pub sidecars_written: usize,
+    pub thumbnails_written: usize,
+    pub fps_repaired: usize,
+}
+
+/// Checks every clip and tidies the library: removes leftovers of
+/// deleted clips, writes missing sidecars and thumbnails, and fixes
+/// wrong frame rates. `dry_run` (default false) only reports.
+#[tauri::command]
+pub async fn repair_library(
+    state: State<'_, Arc<AppState>>,
+    dry_run: Option<bool>,
+) -> Result<LibraryRepairResult, String> {
+    let dry_run = dry_run.unwrap_or(false);
+    let file_name = |path: &std::path::Path| {
+        path.file_name().unwrap_or_default().to_string_lossy().to_string()
+    };
+
+    let report = StorageManager::new(&state.clips_directory.to_string_lossy()).and_then(|manager| {
+        manager
+            .with_scale_filter(state.config.scale_filter)
+            .repair_library(dry_run)
+    });
+    match report {
+        Ok(report) => Ok(LibraryRepairResult {
+            success: true,
+            message: report.summary(),
+            dry_run,
+            clips_checked: report.clips_checked,
+            damaged_ids: report.damaged.iter().map(|p| file_name(p)).collect(),
+            removed: report.orphans_removed.iter().map(|p| file_name(p)).collect(),
+            sidecars_written: report.sidecars_written,
+            thumbnails_written: report.thumbnails_written,
+            fps_repaired: report.fps_repaired,
+        }),
+        Err(e) => Ok(LibraryRepairResult {
+            success: false,
+            message: format!("Failed to repair library: {}", e),
+            dry_run,
+            clips_checked: 0,
+            damaged_ids: Vec::new(),
+            removed: Vec::new(),
+            sidecars_written: 0,
+            thumbnails_written: 0,
+            fps_repaired: 0,
+        }),
+    }
+}
+
+/// Cuts capture stalls longer than `max_gap_ms` (default 250) out of a
+/// clip, saving the result as a new `<name>_smooth.qsp` clip
+#[tauri::command]
+pub async fn remove_clip_gaps(
+    state: State<'_, Arc<AppState>>,
+    id: String,
+    max_gap_ms: Option<u32>,
+) -> Result<SaveResult, String> {
+    let input = match state.resolve_clip_path(&id) {
+        Ok(path) => path,
+        Err(e) => {
+            return Ok(SaveResult {
+                success: false,
+                message: e.to_string(),
+                clip_id: None,
+            });
+        }
+    };
+
+    let smooth_id = id.replace(".qsp", "_smooth.qsp");
+    let output = state.clips_directory.join(&smooth_id);
+
+    match VideoEncoder::remove_gaps(
+        &input.to_string_lossy(),
+        &output.to_string_lossy(),
+        max_gap_ms.unwrap_or(250),
+    ) {
+        Ok(removed) => Ok(SaveResult {
+            success: true,
+            message: format!("Removed {:.2}s of stalls", removed.as_secs_f64()),
+            clip_id: Some(smooth_id),
+        }),
+        Err(e) => Ok(SaveResult {
+            success: false,
+            message: format!("Failed to remove gaps: {}", e),
+            clip_id: None,
+        }),
+    }
+}
+
+/// Packs a two-eye clip into a 3D clip, `"sbs"` (side-by-side,
+/// `<name>_sbs.qsp`) or `"tb"` (top-bottom, `<name>_tb.qsp`), or blends
+/// the eyes into a flat clip with `"mono"` (`<name>_mono.qsp`)
+///
+/// Written to the export directory; `output_dir` overrides it for this
+/// export only. The result has a clip ID only if the file landed in the
+/// clips directory.
+#[tauri::command]
+pub async fn export_stereo(
+    state: State<'_, Arc<AppState>>,
+    id: String,
+    layout: String,
+    output_dir: Option<String>,
+) -> Result<SaveResult, String> {
+    let layout = match layout.as_str() {
+        "sbs" => StereoLayout::SideBySide,
+        "tb" => StereoLayout::TopBottom,
+        "mono" => StereoLayout::MonoAverage,
+        other => return Err(format!("Unknown stereo layout: {}", other)),
+    };
+
+    let input = match state.resolve_clip_path(&id) {
+        Ok(path) => path,
+        Err(e) => {
+            return Ok(SaveResult {
+                success: false,
+                message: e.to_string(),
+                clip_id: None,
+            });
+        }
+    };
+
+    let suffix = match layout {
+        StereoLayout::SideBySide => "_sbs.qsp",
+        StereoLayout::TopBottom => "_tb.qsp",
+        StereoLayout::MonoAverage => "_mono.qsp",
+    };
+    let export_directory = match state.export_directory(output_dir) {
+        Ok(directory) => directory,
+        Err(e) => {
+            return Ok(SaveResult {
+                success: false,
+                message: e,
+                clip_id: None,
+            });
+        }
+    };
+    let stereo_name = input
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .replace(".qsp", suffix);
+    let output = export_directory.join(&stereo_name);
+    let stereo_id = output
+        .starts_with(&state.clips_directory)
+        .then(|| AppState::clip_id(&state.clips_directory, &output));
+
+    match VideoEncoder::export_stereo(
+        &input.to_string_lossy(),
+        &output.to_string_lossy(),
+        layout,
+        state.jpeg_quality(),
+    ) {
+        Ok((written, unmatched)) => Ok(SaveResult {
+            success: true,
+            message: format!(
+                "Wrote {} frames ({} unmatched eye frames) to {:?}",
+                written, unmatched, output
+            ),
+            clip_id: stereo_id,
+        }),
+        Err(e) => Ok(SaveResult {
+            success: false,
+            message: format!("Failed to export stereo: {}", e),
+            clip_id: None,
+        }),
+    }
+}
+
+/// Marks or unmarks a clip as a favorite (protected from cleanup)
+#[tauri::command]
+pub async fn set_clip_favorite(
+    state: State<'_, Arc<AppState>>,
+    id: String,
+    favorite: bool,
+) -> Result<bool, String> {
+    let path = state.resolve_clip_path(&id).map_err(|e| e.to_string())?;
+    ClipMetadata::set_favorite(&path, favorite)
+        .map_err(|e| format!("Failed to update favorite: {}", e))?;
+    Ok(favorite)
+}
+
 /// Gets a thumbnail for a clip (base64 encoded)
+///
+/// Served from the `<name>.thumb.jpg` cache when it is up to date,
+/// otherwise made from the first frame and cached.
 #[tauri::command]
 pub async fn get_clip_thumbnail(
     state: State<'_, Arc<AppState>>,
     id: String,
 ) -> Result<Option<String>, String> {
-    let path = state.clips_directory.join(&id);
-
-    if !path.exists() {
+    let Ok(path) = state.resolve_clip_path(&id) else {
         return Ok(None);
-    }
+    };
 
-    // Try to read the clip and extract first frame
-    match quest_shadowplay::encoder::FrameReader::open(path.to_str().unwrap_or("")) {
-        Ok(reader) => {
-            if let Some(frame) = reader.frames().first() {
-                // Frame data is already JPEG, just base64 encode it
-                let base64_data = base64::Engine::encode(
-                    &base64::engine::general_purpose::STANDARD,
-                    &frame.data
-                );
-                return Ok(Some(format!("data:image/jpeg;base64,{}", base64_data)));
-            }
+    match storage::cached_thumbnail(&path, state.config.scale_filter) {
+        Ok(jpeg) => {
+            let base64_data = base64::Engine::encode(
+                &base64::engine::general_purpose::STANDARD,
+                &jpeg
+            );
+            Ok(Some(format!("data:image/jpeg;base64,{}", base64_data)))
         }
         Err(e) => {
-            log::warn!("Failed to read clip for thumbnail: {}", e);
+            log::warn!("Failed to make thumbnail for {}: {}", id, e);
+            Ok(None)
         }
     }
+}
 
-    Ok(None)
+/// Progress of background thumbnail generation, sent as a
+/// `thumbnail-progress` event after each clip
+#[derive(Clone, serde::Serialize)]
+pub struct ThumbnailProgress {
+    pub clip_id: String,
+    pub done: usize,
+    pub total: usize,
+}
+
+/// Caches thumbnails for every clip in the background
+///
+/// Returns the number of clips queued straight away; progress arrives
+/// as `thumbnail-progress` events.
+#[tauri::command]
+pub async fn generate_all_thumbnails(
+    app: AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<usize, String> {
+    let clips = state
+        .list_clips()
+        .map_err(|e| format!("Failed to list clips: {}", e))?;
+    let total = clips.len();
+    let clips_directory = state.clips_directory.clone();
+    let scale_filter = state.config.scale_filter;
+
+    std::thread::spawn(move || {
+        for (i, clip) in clips.into_iter().enumerate() {
+            let path = clips_directory.join(&clip.id);
+            if let Err(e) = storage::cached_thumbnail(&path, scale_filter) {
+                log::warn!("Failed to make thumbnail for {}: {}", clip.id, e);
+            }
+            let progress = ThumbnailProgress {
+                clip_id: clip.id,
+                done: i + 1,
+                total,
+            };
+            if let Err(e) = app.emit("thumbnail-progress", progress) {
+                log::warn!("Failed to send thumbnail progress: {}", e);
+            }
+        }
+        log::info!("Thumbnails ready for {} clips", total);
+    });
+
+    Ok(total)
+}
+
+/// Quality comparison between two clips
+#[derive(serde::Serialize)]
+pub struct CompareResult {
+    pub matched_frames: usize,
+    pub unmatched_frames: usize,
+    pub mean_psnr_db: f64,
+    pub min_psnr_db: f64,
+    pub mean_ssim: f64,
+    pub size_a_bytes: u64,
+    pub size_b_bytes: u64,
+}
+
+/// Compares two clips of the same moment (PSNR/SSIM and file sizes)
+#[tauri::command]
+pub async fn compare_clips(
+    state: State<'_, Arc<AppState>>,
+    id_a: String,
+    id_b: String,
+) -> Result<CompareResult, String> {
+    let path_a = state.resolve_clip_path(&id_a).map_err(|e| e.to_string())?;
+    let path_b = state.resolve_clip_path(&id_b).map_err(|e| e.to_string())?;
+
+    let report = VideoEncoder::compare(
+        path_a.to_str().unwrap_or(""),
+        path_b.to_str().unwrap_or(""),
+    )
+    .map_err(|e| format!("Failed to compare clips: {}", e))?;
+
+    Ok(CompareResult {
+        matched_frames: report.matched_frames,
+        unmatched_frames: report.unmatched_frames,
+        mean_psnr_db: report.mean_psnr_db,
+        min_psnr_db: report.min_psnr_db,
+        mean_ssim: report.mean_ssim,
+        size_a_bytes: report.size_a_bytes,
+        size_b_bytes: report.size_b_bytes,
+    })
 }
 
 /// Result of MP4 export
@@ -223,20 +984,53 @@ pub struct ExportResult {
 }
 
 /// Exports a clip to MP4 using ffmpeg
+///
+/// `aspect` optionally reframes to 16:9: "crop", "pad" or "stretch".
+/// `output_dir` overrides where the MP4 is written for this export only.
 #[tauri::command]
 pub async fn export_to_mp4(
     state: State<'_, Arc<AppState>>,
     id: String,
+    aspect: Option<String>,
+    output_dir: Option<String>,
 ) -> Result<ExportResult, String> {
-    let qsp_path = state.clips_directory.join(&id);
-    
-    if !qsp_path.exists() {
-        return Ok(ExportResult {
-            success: false,
-            message: format!("Clip not found: {}", id),
-            mp4_path: None,
-        });
-    }
+    let aspect = match aspect.as_deref() {
+        None => None,
+        Some("crop") => Some(ExportAspect::widescreen(AspectMode::CropCenter)),
+        Some("pad") => Some(ExportAspect::widescreen(AspectMode::Pad)),
+        Some("stretch") => Some(ExportAspect::widescreen(AspectMode::Stretch)),
+        Some(other) => {
+            return Ok(ExportResult {
+                success: false,
+                message: format!("Unknown aspect mode: {}", other),
+                mp4_path: None,
+            });
+        }
+    };
+    let aspect = aspect.map(|aspect| aspect.with_filter(state.config.scale_filter));
+
+    let qsp_path = match state.resolve_clip_path(&id) {
+        Ok(path) => path,
+        Err(e) => {
+            return Ok(ExportResult {
+                success: false,
+                message: e.to_string(),
+                mp4_path: None,
+            });
+        }
+    };
+
+    // Check the destination before spending time decoding frames
+    let export_directory = match state.export_directory(output_dir) {
+        Ok(directory) => directory,
+        Err(e) => {
+            return Ok(ExportResult {
+                success: false,
+                message: e,
+                mp4_path: None,
+            });
+        }
+    };
 
     log::info!("Exporting {} to MP4...", id);
 
@@ -261,6 +1055,37 @@ pub async fn export_to_mp4(
         });
     }
 
+    // 3D export packs each moment's two eyes into one frame. A flat
+    // export keeps the first frame's eye: `clip_fps` is a per-eye rate,
+    // so both eyes interleaved would play at half speed
+    let stereo_layout = state.config.stereo_mode.layout();
+    let packed;
+    let frames = match stereo_layout {
+        None if frames.iter().any(|f| f.eye_index != frames[0].eye_index) => {
+            let eye = frames[0].eye_index;
+            packed = frames.iter().filter(|f| f.eye_index == eye).cloned().collect::<Vec<_>>();
+            log::info!("Exporting eye {} only ({} frames)", eye, packed.len());
+            &packed[..]
+        }
+        None => frames,
+        Some(layout) => {
+            let quality = state.jpeg_quality();
+            match VideoEncoder::pack_stereo(frames, layout, reader.fps(), quality) {
+                Ok(frames) => {
+                    packed = frames;
+                    &packed[..]
+                }
+                Err(e) => {
+                    return Ok(ExportResult {
+                        success: false,
+                        message: format!("Failed to pack stereo frames: {}", e),
+                        mp4_path: None,
+                    });
+                }
+            }
+        }
+    };
+
     log::info!("Exporting {} frames to MP4...", frames.len());
 
     // Create temp directory for frames
@@ -273,58 +1098,156 @@ pub async fn export_to_mp4(
         });
     }
 
-    // Write frames as JPEG files
-    for (i, frame) in frames.iter().enumerate() {
-        let frame_path = temp_dir.join(format!("frame_{:05}.jpg", i));
-        if let Err(e) = std::fs::write(&frame_path, &frame.data) {
-            return Ok(ExportResult {
-                success: false,
-                message: format!("Failed to write frame {}: {}", i, e),
-                mp4_path: None,
-            });
-        }
+    // Write frames as JPEG files, reframed if requested
+    let quality = state.jpeg_quality();
+    if let Err(e) = encoder::dump_frames(frames, &temp_dir, aspect.as_ref(), quality) {
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        return Ok(ExportResult {
+            success: false,
+            message: format!("Failed to write frames: {}", e),
+            mp4_path: None,
+        });
     }
 
-    // Calculate FPS from timestamps
-    let fps = if frames.len() > 1 {
-        let first_ts = frames.first().unwrap().timestamp_ns;
-        let last_ts = frames.last().unwrap().timestamp_ns;
-        let duration_ns = last_ts.saturating_sub(first_ts);
-        if duration_ns > 0 {
-            let duration_secs = duration_ns as f64 / 1_000_000_000.0;
-            (frames.len() as f64 / duration_secs).round() as u32
-        } else {
-            30
-        }
-    } else {
-        30
-    };
+    // Calculate FPS from timestamps (stalls clamped), as the encoder does
+    let fps = VideoEncoder::clip_fps(frames, reader.fps());
 
     log::info!("Detected FPS: {}", fps);
 
+    // Keyframes at scene changes (from stored motion scores) and at
+    // least every max_keyframe_interval frames or keyframe_interval_seconds,
+    // rather than a fixed GOP
+    let keyframes = VideoEncoder::keyframe_indices(
+        frames,
+        state.config.scene_cut_threshold,
+        state.config.max_keyframe_interval,
+        state.config.keyframe_interval_seconds,
+        fps,
+    );
+    let keyframe_times = keyframes
+        .iter()
+        .map(|&i| format!("{:.3}", i as f64 / fps.max(1) as f64))
+        .collect::<Vec<_>>()
+        .join(",");
+    let mut keyframe_args = vec![
+        "-force_key_frames".to_string(),
+        keyframe_times,
+        "-g".to_string(),
+        state.config.max_keyframe_interval.to_string(),
+    ];
+    if reader.has_motion_scores() {
+        // Our scene cuts replace libx264's own detection
+        keyframe_args.extend(["-sc_threshold".to_string(), "0".to_string()]);
+    }
+    log::info!("Placing {} keyframes", keyframes.len());
+
+    // Rotation goes in the MP4 display matrix, so pixels stay as captured.
+    // ffmpeg takes it on the input; -noautorotate stops it from turning
+    // the pixels itself while re-encoding.
+    let mut rotation_args: Vec<String> = Vec::new();
+    if state.config.rotation != Rotation::None {
+        rotation_args = vec![
+            "-noautorotate".to_string(),
+            "-display_rotation".to_string(),
+            state.config.rotation.display_rotation().to_string(),
+        ];
+        log::info!("Rotating {} degrees clockwise", state.config.rotation.degrees());
+    }
+
+    // Convert to YUV with the matrix players assume for the output size,
+    // and say so in the stream, rather than ffmpeg's untagged BT.601
+    let (width, height) = (frames[0].width, frames[0].height);
+    let (out_width, out_height) = aspect
+        .and_then(|aspect| aspect.output_dimensions(width, height).ok())
+        .unwrap_or((width, height));
+    let color_args =
+        ColorSpace::for_resolution(out_width, out_height).ffmpeg_args(ColorRange::Limited);
+
+    // An H.264 frame packing message tells 3D players how the eyes are laid out
+    let mut stereo_args: Vec<String> = Vec::new();
+    if let Some(packing) = stereo_layout.and_then(|layout| layout.frame_packing_type()) {
+        stereo_args = vec!["-x264-params".to_string(), format!("frame-packing={}", packing)];
+        log::info!("Exporting 3D ({:?})", state.config.stereo_mode);
+    }
+
     // Output MP4 path
     let mp4_name = id.replace(".qsp", ".mp4");
-    let mp4_path = state.clips_directory.join(&mp4_name);
+    let mp4_path = export_directory.join(&mp4_name);
 
     // Prepare paths for ffmpeg
-    let input_pattern = temp_dir.join("frame_%05d.jpg");
+    let input_pattern = temp_dir.join(encoder::FRAME_FILE_PATTERN);
     let input_pattern_str = input_pattern.to_str().unwrap().to_string();
     let output_path_str = mp4_path.to_str().unwrap().to_string();
     let fps_str = fps.to_string();
 
+    // Recorded sound goes in as a second input and comes out as AAC
+    let mut audio_args: Vec<String> = Vec::new();
+    if let Some(audio) = reader.audio() {
+        let wav_path = temp_dir.join("audio.wav");
+        match std::fs::write(&wav_path, audio.to_wav()) {
+            Ok(()) => {
+                audio_args = vec!["-i".to_string(), wav_path.to_string_lossy().to_string()];
+                log::info!("Adding {:.1}s of audio", audio.duration_secs());
+            }
+            Err(e) => log::warn!("Failed to write audio, exporting without it: {}", e),
+        }
+    }
+    let has_audio = !audio_args.is_empty();
+
+    // Marks become MP4 chapters and a .vtt subtitle file next to the MP4
+    let chapters_path = temp_dir.join("chapters.txt");
+    let mut chapter_args: Vec<String> = Vec::new();
+    let marked_range = reader.time_range_ns().filter(|_| !reader.marks().is_empty());
+    if let Some((start_ns, last_ns)) = marked_range {
+        let end_ns = last_ns + 1_000_000_000 / fps.max(1) as u64;
+
+        let vtt_path = mp4_path.with_extension("vtt");
+        if let Err(e) = std::fs::write(&vtt_path, marks_to_webvtt(reader.marks(), start_ns, end_ns)) {
+            log::warn!("Failed to write subtitles {:?}: {}", vtt_path, e);
+        }
+
+        match std::fs::write(&chapters_path, marks_to_ffmetadata(reader.marks(), start_ns, end_ns)) {
+            Ok(()) => {
+                // Chapters come after the frames and any audio input
+                let chapters_input = if has_audio { 2 } else { 1 };
+                chapter_args = vec![
+                    "-i".to_string(),
+                    chapters_path.to_string_lossy().to_string(),
+                    "-map".to_string(),
+                    "0:v".to_string(),
+                ];
+                if has_audio {
+                    chapter_args.extend(["-map".to_string(), "1:a".to_string()]);
+                }
+                chapter_args.extend(["-map_chapters".to_string(), chapters_input.to_string()]);
+            }
+            Err(e) => log::warn!("Failed to write chapters: {}", e),
+        }
+        log::info!("Exporting {} marks as chapters and subtitles", reader.marks().len());
+    }
+
     log::info!("Running ffmpeg: input={}, output={}, fps={}", input_pattern_str, output_path_str, fps_str);
 
     let output = std::process::Command::new("ffmpeg")
         .args([
             "-y",  // Overwrite
             "-framerate", &fps_str,
-            "-i", &input_pattern_str,
+        ])
+        .args(&rotation_args)
+        .args(["-i", &input_pattern_str])
+        .args(&audio_args)
+        .args(&chapter_args)
+        .args([
             "-c:v", "libx264",
             "-preset", "fast",
             "-crf", "23",
             "-pix_fmt", "yuv420p",
-            &output_path_str,
         ])
+        .args(&color_args)
+        .args(&stereo_args)
+        .args(&keyframe_args)
+        .args(if has_audio { &["-c:a", "aac", "-b:a", "160k"][..] } else { &[] })
+        .arg(&output_path_str)
         .output();
 
     // Cleanup temp files
@@ -360,6 +1283,139 @@ pub async fn export_to_mp4(
     }
 }
 
+/// Result of dumping the buffer as JPEG files
+#[derive(serde::Serialize)]
+pub struct DumpResult {
+    pub success: bool,
+    pub message: String,
+    pub frame_count: usize,
+}
+
+/// Writes the live buffer as numbered JPEGs with a `manifest.json` of
+/// timestamps and sizes, for inspecting exactly what was captured
+/// without an encoder in the way
+///
+/// Goes to `output_dir` if given, otherwise to a new `frames_<time>`
+/// folder in the export directory.
+#[tauri::command]
+pub async fn dump_buffer_frames(
+    state: State<'_, Arc<AppState>>,
+    output_dir: Option<String>,
+) -> Result<DumpResult, String> {
+    let frames = state.buffer.snapshot();
+    if frames.is_empty() {
+        return Ok(DumpResult {
+            success: false,
+            message: "No frames in buffer".to_string(),
+            frame_count: 0,
+        });
+    }
+
+    let new_folder = output_dir.is_none();
+    let directory = match state.export_directory(output_dir) {
+        Ok(directory) if new_folder => directory.join(format!(
+            "frames_{}",
+            chrono::Local::now().format("%Y%m%d_%H%M%S")
+        )),
+        Ok(directory) => directory,
+        Err(e) => {
+            return Ok(DumpResult {
+                success: false,
+                message: e,
+                frame_count: 0,
+            });
+        }
+    };
+    match encoder::dump_frames(&frames, &directory, None, state.jpeg_quality()) {
+        Ok(manifest) => {
+            log::info!("Dumped {} frames to {:?}", manifest.len(), directory);
+            Ok(DumpResult {
+                success: true,
+                message: format!("Dumped {} frames", manifest.len()),
+                frame_count: manifest.len(),
+            })
+        }
+        Err(e) => {
+            log::error!("Failed to dump frames: {}", e);
+            Ok(DumpResult {
+                success: false,
+                message: format!("Dump failed: {}", e),
+                frame_count: 0,
+            })
+        }
+    }
+}
+
+// ============================================
+// CAPABILITY COMMANDS
+// ============================================
+
+/// Encode/export paths available on this machine
+#[derive(serde::Serialize)]
+pub struct Capabilities {
+    /// ffmpeg was found on PATH
+    pub ffmpeg: bool,
+    /// Clips can be exported to MP4 (ffmpeg with libx264)
+    pub mp4_export: bool,
+    /// A hardware H.264 encoder is available (native capture or ffmpeg)
+    pub hardware_h264: bool,
+    /// A hardware H.265 encoder is available through ffmpeg
+    pub hardware_h265: bool,
+    /// Built-in software encoder (JPEG frames in .qsp clips), always compiled in
+    pub software_encoder: bool,
+    /// Native record-to-MP4 is compiled in (macOS only)
+    pub native_recording: bool,
+}
+
+/// ffmpeg encoders backed by hardware, by codec
+const HARDWARE_H264_ENCODERS: &[&str] =
+    &["h264_videotoolbox", "h264_nvenc", "h264_qsv", "h264_vaapi", "h264_amf"];
+const HARDWARE_H265_ENCODERS: &[&str] =
+    &["hevc_videotoolbox", "hevc_nvenc", "hevc_qsv", "hevc_vaapi", "hevc_amf"];
+
+/// Returns the encoder list printed by `ffmpeg -encoders`, or None if
+/// ffmpeg can't be run
+fn ffmpeg_encoders() -> Option<String> {
+    let output = std::process::Command::new("ffmpeg")
+        .args(["-hide_banner", "-encoders"])
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Returns true if any of the named encoders appears in the list
+fn has_encoder(encoders: &str, names: &[&str]) -> bool {
+    encoders
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .any(|encoder| names.contains(&encoder))
+}
+
+/// Reports which encode and export paths are available, so the UI can
+/// disable options that won't work
+#[tauri::command]
+pub async fn get_capabilities() -> Result<Capabilities, String> {
+    let encoders = ffmpeg_encoders();
+    let probed = |names: &[&str]| {
+        encoders.as_deref().map(|list| has_encoder(list, names)).unwrap_or(false)
+    };
+    let native = cfg!(target_os = "macos");
+
+    Ok(Capabilities {
+        ffmpeg: encoders.is_some(),
+        mp4_export: probed(&["libx264"]),
+        hardware_h264: native || probed(HARDWARE_H264_ENCODERS),
+        hardware_h265: probed(HARDWARE_H265_ENCODERS),
+        software_encoder: true,
+        native_recording: native,
+    })
+}
+
 // ============================================
 // NATIVE RECORDING COMMANDS (macOS only)
 // ============================================
@@ -414,35 +1470,25 @@ pub async fn start_native_recording(
             chrono::Local::now().format("%Y%m%d_%H%M%S")
         ));
 
-        // Create capture with default config (1080p60)
-        let config = CaptureConfig::default();
-        
-        match NativeCaptureHandle::new(config) {
+        // Try 1080p60 first, stepping down if the display mode is rejected
+        let ladder = CaptureConfig::fallback_ladder();
+
+        match NativeCaptureHandle::start_with_fallback(&ladder, &output_path) {
             Ok(handle) => {
-                match handle.start(&output_path) {
-                    Ok(()) => {
-                        let path_str = output_path.to_string_lossy().to_string();
-                        *capture_guard = Some(handle);
-                        log::info!("Native recording started: {}", path_str);
-                        Ok(NativeRecordingResult {
-                            success: true,
-                            message: "Recording at 1080p 60fps with hardware encoding".to_string(),
-                            output_path: Some(path_str),
-                        })
-                    }
-                    Err(e) => {
-                        Ok(NativeRecordingResult {
-                            success: false,
-                            message: format!("Failed to start: {}", e),
-                            output_path: None,
-                        })
-                    }
-                }
+                let path_str = output_path.to_string_lossy().to_string();
+                let mode = handle.config.describe();
+                *capture_guard = Some(handle);
+                log::info!("Native recording started at {}: {}", mode, path_str);
+                Ok(NativeRecordingResult {
+                    success: true,
+                    message: format!("Recording at {} with hardware encoding", mode),
+                    output_path: Some(path_str),
+                })
             }
             Err(e) => {
                 Ok(NativeRecordingResult {
                     success: false,
-                    message: format!("Failed to create capture: {}", e),
+                    message: format!("Failed to start: {}", e),
                     output_path: None,
                 })
             }
@@ -17,16 +17,27 @@ use std::sync::Arc;
 use tauri::Manager;
 
 fn main() {
-    // Initialize logging
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    // Read the config first, since it says whether to also log to a
+    // file, then start logging before anything else can go wrong
+    let config = AppState::load_config();
+    match &config {
+        Ok(config) => quest_shadowplay::init_logging_with_config(config),
+        Err(_) => quest_shadowplay::init_logging(),
+    }
 
     log::info!("Starting Quest Shadowplay...");
+    let state = match config.and_then(AppState::new) {
+        Ok(state) => Arc::new(state),
+        Err(e) => {
+            log::error!("Failed to initialize application state: {}", e);
+            std::process::exit(1);
+        }
+    };
+    log::info!("Clips directory: {:?}", state.clips_directory);
 
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
-        .setup(|app| {
-            // Initialize application state
-            let state = Arc::new(AppState::new()?);
+        .setup(move |app| {
             app.manage(state);
 
             log::info!("Application initialized successfully");
@@ -35,12 +46,35 @@ fn main() {
         .invoke_handler(tauri::generate_handler![
             commands::start_recording,
             commands::stop_recording,
+            commands::acknowledge_save,
             commands::save_clip,
             commands::get_status,
+            commands::calibrate_encoder,
+            commands::get_capture_info,
+            commands::estimate_quality,
+            commands::get_jpeg_quality,
+            commands::set_jpeg_quality,
+            commands::get_trigger_button,
+            commands::set_trigger_button,
+            commands::update_input,
+            commands::add_mark,
+            commands::set_recorded_app,
+            commands::get_export_directory,
+            commands::set_export_directory,
             commands::list_clips,
             commands::delete_clip,
+            commands::set_clip_favorite,
+            commands::auto_trim_clip,
+            commands::remove_clip_gaps,
+            commands::repair_clip_fps,
+            commands::repair_library,
+            commands::export_stereo,
             commands::get_clip_thumbnail,
+            commands::generate_all_thumbnails,
             commands::export_to_mp4,
+            commands::dump_buffer_frames,
+            commands::compare_clips,
+            commands::get_capabilities,
             // Native hardware-accelerated recording (macOS)
             commands::start_native_recording,
             commands::stop_native_recording,
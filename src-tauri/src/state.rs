@@ -5,18 +5,22 @@
 #![allow(dead_code)]
 
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
 
 use parking_lot::Mutex;
-use quest_shadowplay::{Config, SharedFrameBuffer, CapturedFrame};
+use quest_shadowplay::{Config, InputHandler, CaptureBuffer, CapturedFrame};
+use quest_shadowplay::capture::{FrameCompressor, SessionTimer, WarmupFilter};
+use quest_shadowplay::encoder::ClipMark;
+use quest_shadowplay::error::ShadowplayResult;
+use quest_shadowplay::storage::{self, ClipMetadata, SavedSettings, StorageManager};
 
 use crate::capture::{FrameCapture, create_capture};
 
 /// Shared application state
 pub struct AppState {
     /// Frame buffer for storing captured frames
-    pub buffer: Arc<SharedFrameBuffer>,
+    pub buffer: Arc<CaptureBuffer>,
 
     /// Application configuration
     pub config: Config,
@@ -24,50 +28,132 @@ pub struct AppState {
     /// Is recording currently active?
     is_recording: AtomicBool,
 
+    /// Discards the first frames after recording starts
+    warmup: WarmupFilter,
+
+    /// Limits how long one recording session may run
+    pub session: SessionTimer,
+
+    /// Was recording last stopped by the session time or clip limit?
+    auto_stopped: AtomicBool,
+
+    /// Clips saved since recording last started
+    session_clips: AtomicU32,
+
+    /// Are frames being dropped until a save is acknowledged?
+    paused_after_save: AtomicBool,
+
     /// Directory for saved clips
     pub clips_directory: PathBuf,
 
+    /// Directory for exports, if not the clips directory (live)
+    export_directory: Mutex<Option<String>>,
+
     /// Platform-agnostic capture handler
     pub capture: Mutex<Box<dyn FrameCapture>>,
+
+    /// Compressor shared with the capture thread (quality is live)
+    pub compressor: Arc<FrameCompressor>,
+
+    /// Watches controller input for the save combo (binding is live)
+    pub input: Mutex<InputHandler>,
+
+    /// Marked moments still in the buffer, oldest first
+    marks: Mutex<Vec<ClipMark>>,
+
+    /// Marks added since startup, for default labels
+    marks_added: AtomicU32,
+
+    /// Is a clip being saved?
+    is_saving: AtomicBool,
+
+    /// App or game being recorded, if one was reported
+    recorded_app: Mutex<Option<String>>,
 }
 
 impl AppState {
-    /// Creates a new application state
-    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let config = Config::default();
-
+    /// Reads the config: the defaults for this platform's clips
+    /// directory, with any environment overrides
+    ///
+    /// Logs nothing, so it can run before logging starts and say
+    /// whether to log to a file there.
+    pub fn load_config() -> Result<Config, Box<dyn std::error::Error>> {
         // Determine clips directory based on platform
         let clips_directory = Self::get_clips_directory();
 
+        let config = Config::builder()
+            .output_directory(clips_directory.to_string_lossy())
+            .build()
+            .with_env_overrides()?;
+
         // Create clips directory if it doesn't exist
-        if !clips_directory.exists() {
-            std::fs::create_dir_all(&clips_directory)?;
-        }
+        std::fs::create_dir_all(&config.output_directory)?;
+        Ok(config)
+    }
 
+    /// Creates the application state from a config made by
+    /// [`AppState::load_config`]
+    pub fn new(mut config: Config) -> Result<Self, Box<dyn std::error::Error>> {
+        let clips_directory = PathBuf::from(&config.output_directory);
         log::info!("Clips directory: {:?}", clips_directory);
 
-        let buffer = Arc::new(SharedFrameBuffer::new(
+        // Lay settings changed in a previous run over the defaults; they
+        // don't overlap the environment overrides
+        match SavedSettings::load(&clips_directory) {
+            Ok(settings) => settings.apply(&mut config),
+            Err(e) => log::warn!("Could not load saved settings: {}", e),
+        }
+
+        let buffer = Arc::new(CaptureBuffer::new_with_byte_limit(
             config.buffer_duration_seconds,
             config.target_fps,
+            config.max_buffer_bytes,
         ));
 
-        let capture = create_capture();
+        let capture = create_capture(config.scale_filter);
         log::info!("Using capture source: {}", capture.source_name());
 
+        let warmup = WarmupFilter::new(config.warmup_discard_frames);
+        let session = SessionTimer::new(config.max_session_seconds);
+        let mut compressor = FrameCompressor::new(config.jpeg_quality);
+        compressor.set_size_cap(config.max_frame_bytes, config.min_capped_jpeg_quality);
+        let compressor = Arc::new(compressor);
+        let input = Mutex::new(InputHandler::from_config(&config)?);
+        let export_directory = Mutex::new(config.export_directory.clone());
+
         Ok(Self {
             buffer,
             config,
             is_recording: AtomicBool::new(false),
+            warmup,
+            session,
+            auto_stopped: AtomicBool::new(false),
+            session_clips: AtomicU32::new(0),
+            paused_after_save: AtomicBool::new(false),
             clips_directory,
+            export_directory,
             capture: Mutex::new(capture),
+            compressor,
+            input,
+            marks: Mutex::new(Vec::new()),
+            marks_added: AtomicU32::new(0),
+            is_saving: AtomicBool::new(false),
+            recorded_app: Mutex::new(None),
         })
     }
 
-    /// Gets the clips directory for the current platform
+    /// Gets the clips directory for the current platform, unless
+    /// `QSP_OUTPUT_DIR` names one
     fn get_clips_directory() -> PathBuf {
+        if let Some(directory) = std::env::var_os(quest_shadowplay::config::ENV_OUTPUT_DIR) {
+            return PathBuf::from(directory);
+        }
+
         #[cfg(target_os = "android")]
         {
-            PathBuf::from("/sdcard/QuestShadowplay")
+            quest_shadowplay::storage::android_output_directory().unwrap_or_else(|| {
+                PathBuf::from(quest_shadowplay::storage::ANDROID_DEFAULT_DIRECTORY)
+            })
         }
 
         #[cfg(not(target_os = "android"))]
@@ -78,14 +164,55 @@ impl AppState {
         }
     }
 
+    /// Returns the live JPEG quality, which `set_jpeg_quality` may have
+    /// changed since `config.jpeg_quality` was read
+    pub fn jpeg_quality(&self) -> u8 {
+        self.compressor.quality()
+    }
+
     /// Returns whether recording is active
     pub fn is_recording(&self) -> bool {
         self.is_recording.load(Ordering::SeqCst)
     }
 
-    /// Sets the recording state
+    /// Sets the recording state, starting or stopping the session clock
     pub fn set_recording(&self, recording: bool) {
         self.is_recording.store(recording, Ordering::SeqCst);
+        if recording {
+            self.auto_stopped.store(false, Ordering::SeqCst);
+            self.session_clips.store(0, Ordering::SeqCst);
+            self.paused_after_save.store(false, Ordering::SeqCst);
+            self.warmup.restart();
+            self.session.start();
+        } else {
+            self.session.stop();
+        }
+    }
+
+    /// Stops capture if the session limit has passed.
+    ///
+    /// Returns true if recording should go on.
+    pub fn check_session_limit(&self) -> bool {
+        if !self.session.is_expired() {
+            return true;
+        }
+
+        if self.is_recording.swap(false, Ordering::SeqCst) {
+            self.capture.lock().stop();
+            self.session.stop();
+            self.auto_stopped.store(true, Ordering::SeqCst);
+            log::warn!(
+                "Session limit of {}s reached, recording stopped",
+                self.config.max_session_seconds
+            );
+        }
+        false
+    }
+
+    /// Returns whether recording was last stopped by the session time
+    /// or clip limit
+    pub fn was_auto_stopped(&self) -> bool {
+        self.auto_stopped.load(Ordering::SeqCst)
     }
 
     /// Returns the buffer fill percentage
@@ -98,30 +225,114 @@ impl AppState {
         self.buffer.frame_count()
     }
 
-    /// Adds a frame to the buffer
+    /// Adds a frame to the buffer, unless paused after a save or still
+    /// in the warmup discard
     pub fn push_frame(&self, frame: CapturedFrame) {
-        self.buffer.push_frame(frame);
+        if !self.paused_after_save.load(Ordering::SeqCst) && !self.warmup.discard() {
+            self.buffer.push_frame(frame);
+        }
     }
 
-    /// Gets a snapshot of all frames for saving
-    pub fn snapshot_frames(&self) -> Vec<CapturedFrame> {
-        self.buffer.snapshot()
+    /// Applies the configured post-save behavior after a clip is saved,
+    /// and stops recording once the session has saved
+    /// `auto_stop_after_clips` clips
+    pub fn finish_save(&self) {
+        if self.config.post_save.after_save(&self.buffer) {
+            self.paused_after_save.store(true, Ordering::SeqCst);
+        }
+
+        let saved = self.session_clips.fetch_add(1, Ordering::SeqCst) + 1;
+        let limit = self.config.auto_stop_after_clips;
+        if limit > 0 && saved >= limit && self.is_recording.swap(false, Ordering::SeqCst) {
+            self.capture.lock().stop();
+            self.session.stop();
+            self.auto_stopped.store(true, Ordering::SeqCst);
+            log::info!("Saved {} clips this session, recording stopped", saved);
+        }
+    }
+
+    /// Returns how many clips were saved since recording last started
+    pub fn session_clips_saved(&self) -> u32 {
+        self.session_clips.load(Ordering::SeqCst)
+    }
+
+    /// Resumes capture paused after a save.
+    ///
+    /// Returns true if it was paused.
+    pub fn acknowledge_save(&self) -> bool {
+        self.paused_after_save.swap(false, Ordering::SeqCst)
+    }
+
+    /// Returns whether capture is paused waiting for a save to be acknowledged
+    pub fn is_paused_after_save(&self) -> bool {
+        self.paused_after_save.load(Ordering::SeqCst)
+    }
+
+    /// Gets a snapshot of the frames to save, honoring the save offset.
+    /// Frames are shared with the buffer, so capture continues unaffected
+    /// while they are encoded
+    pub fn snapshot_frames(&self) -> Vec<Arc<CapturedFrame>> {
+        let offset_ns = self.config.save_offset_ms as u64 * 1_000_000;
+        self.buffer.snapshot_shared_ending_before(offset_ns)
+    }
+
+    /// Marks the newest frame so clips holding it get a chapter and
+    /// subtitle there. Returns false if the buffer is empty
+    pub fn add_mark(&self, label: Option<String>) -> bool {
+        let Some(timestamp_ns) = self.buffer.newest_timestamp_ns() else {
+            log::warn!("Ignoring mark: no frames in the buffer");
+            return false;
+        };
+        let number = self.marks_added.fetch_add(1, Ordering::SeqCst) + 1;
+        let label = label.unwrap_or_else(|| format!("Mark {}", number));
+        log::info!("Marked moment: {}", label);
+        self.marks.lock().push(ClipMark::new(timestamp_ns, label));
+        true
+    }
+
+    /// Sets which app or game is being recorded, for clip metadata
+    pub fn set_recorded_app(&self, app: Option<String>) {
+        *self.recorded_app.lock() = app;
+    }
+
+    /// Returns the app or game being recorded, if one was reported
+    pub fn recorded_app(&self) -> Option<String> {
+        self.recorded_app.lock().clone()
+    }
+
+    /// Returns the marks still covered by the buffer, oldest first,
+    /// forgetting older ones
+    pub fn marks(&self) -> Vec<ClipMark> {
+        let mut marks = self.marks.lock();
+        let oldest_kept = self.buffer.oldest_timestamp_ns().unwrap_or(u64::MAX);
+        marks.retain(|m| m.timestamp_ns >= oldest_kept);
+        marks.clone()
     }
 
     /// Lists all saved clips
     pub fn list_clips(&self) -> Result<Vec<ClipInfo>, std::io::Error> {
         let mut clips = Vec::new();
 
-        if !self.clips_directory.exists() {
+        // Recreate the directory if it was deleted while running
+        if let Err(e) = storage::recreate_if_missing(&self.clips_directory) {
+            log::warn!("Failed to recreate clips directory: {}", e);
             return Ok(clips);
         }
 
-        for entry in std::fs::read_dir(&self.clips_directory)? {
+        // Clips are at the top level or in date folders
+        let folders = storage::clip_folders(&self.clips_directory)
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        let mut entries = Vec::new();
+        for folder in folders {
+            entries.extend(std::fs::read_dir(folder)?);
+        }
+
+        for entry in entries {
             let entry = entry?;
             let path = entry.path();
 
-            // Check for our clip format
-            if path.extension().map(|e| e == "qsp").unwrap_or(false) {
+            // Check for our clip format (proxies belong to their master)
+            if path.extension().map(|e| e == "qsp").unwrap_or(false) && !storage::is_proxy(&path) {
                 if let Ok(metadata) = entry.metadata() {
                     let filename = path.file_name()
                         .unwrap_or_default()
@@ -131,12 +342,17 @@ impl AppState {
                     // Parse timestamp from filename (clip_YYYYMMDD_HHMMSS.qsp)
                     let timestamp = Self::parse_clip_timestamp(&filename);
 
+                    let proxy_id = storage::existing_proxy(&path)
+                        .map(|p| Self::clip_id(&self.clips_directory, &p));
+
                     clips.push(ClipInfo {
-                        id: filename.clone(),
+                        id: Self::clip_id(&self.clips_directory, &path),
+                        proxy_id,
                         path: path.clone(),
                         filename,
                         size_bytes: metadata.len(),
                         timestamp,
+                        favorite: ClipMetadata::is_favorite(&path),
                     });
                 }
             }
@@ -148,6 +364,17 @@ impl AppState {
         Ok(clips)
     }
 
+    /// Returns a clip's ID: its path within the clips directory, using
+    /// `/` (e.g. `2024-03-05/clip_20240305_140709.qsp`)
+    pub fn clip_id(clips_directory: &std::path::Path, path: &std::path::Path) -> String {
+        let relative = path.strip_prefix(clips_directory).unwrap_or(path);
+        relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
     /// Parses timestamp from clip filename
     fn parse_clip_timestamp(filename: &str) -> Option<chrono::DateTime<chrono::Local>> {
         // Format: clip_YYYYMMDD_HHMMSS.qsp
@@ -170,14 +397,60 @@ impl AppState {
         None
     }
 
-    /// Deletes a clip by ID
-    pub fn delete_clip(&self, id: &str) -> Result<(), std::io::Error> {
-        let path = self.clips_directory.join(id);
-        if path.exists() {
-            std::fs::remove_file(&path)?;
-            log::info!("Deleted clip: {}", id);
+    /// Picks the directory an export is written to
+    ///
+    /// Uses `requested` if given, then the configured export directory,
+    /// then the clips directory. Fails if the directory isn't writable.
+    pub fn export_directory(&self, requested: Option<String>) -> Result<PathBuf, String> {
+        let directory = requested
+            .or_else(|| self.export_directory.lock().clone())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| self.clips_directory.clone());
+        storage::ensure_writable_directory(&directory).map_err(|e| e.to_string())?;
+        Ok(directory)
+    }
+
+    /// Returns the configured export directory (None = clips directory)
+    pub fn configured_export_directory(&self) -> Option<String> {
+        self.export_directory.lock().clone()
+    }
+
+    /// Changes where exports go by default (None = clips directory)
+    pub fn set_export_directory(&self, directory: Option<String>) {
+        *self.export_directory.lock() = directory;
+    }
+
+    /// Finds the file for a clip ID from the UI, refusing anything that
+    /// would reach outside the clips directory
+    pub fn resolve_clip_path(&self, id: &str) -> ShadowplayResult<PathBuf> {
+        StorageManager::new(&self.clips_directory.to_string_lossy())?.resolve_clip_name(id)
+    }
+
+    /// Deletes a clip by ID (its file name), refusing anything that
+    /// would reach outside the clips directory
+    pub fn delete_clip(&self, id: &str) -> ShadowplayResult<()> {
+        StorageManager::new(&self.clips_directory.to_string_lossy())?.delete_clip_by_name(id)
+    }
+
+    /// Starts a save, or returns `None` if one is already running
+    ///
+    /// Saves run one at a time, so two can't pick the same free clip
+    /// name. The save ends when the returned guard is dropped.
+    pub fn begin_save(&self) -> Option<SaveGuard<'_>> {
+        if self.is_saving.swap(true, Ordering::SeqCst) {
+            return None;
         }
-        Ok(())
+        Some(SaveGuard(&self.is_saving))
+    }
+}
+
+/// Ends a save started with [`AppState::begin_save`] when dropped,
+/// however the save ended
+pub struct SaveGuard<'a>(&'a AtomicBool);
+
+impl Drop for SaveGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
     }
 }
 
@@ -185,12 +458,15 @@ impl AppState {
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct ClipInfo {
     pub id: String,
+    /// Low-resolution copy used for previews (exports use the clip)
+    pub proxy_id: Option<String>,
     #[serde(skip)]
     pub path: PathBuf,
     pub filename: String,
     pub size_bytes: u64,
     #[serde(serialize_with = "serialize_datetime_option")]
     pub timestamp: Option<chrono::DateTime<chrono::Local>>,
+    pub favorite: bool,
 }
 
 fn serialize_datetime_option<S>(
@@ -7,10 +7,30 @@
 //! Like a circular sushi conveyor belt with exactly 900 spots.
 //! When spot #901 arrives, spot #1 is removed to make room.
 //! This keeps exactly 10 seconds of footage without growing forever.
+//! A belt can also have a weight limit: busy scenes make heavier
+//! frames, so old ones come off early to stay under it.
 
 mod ring_buffer;
+mod spsc;
+mod sync;
 
 pub use ring_buffer::RingBuffer;
+pub use spsc::SpscFrameBuffer;
+pub use sync::{stereo_sync_report, StereoSyncReport};
+
+/// The buffer the capture path records into: [`SpscFrameBuffer`] with
+/// the `spsc-buffer` feature, [`SharedFrameBuffer`] without.
+#[cfg(feature = "spsc-buffer")]
+pub type CaptureBuffer = SpscFrameBuffer;
+
+/// The buffer the capture path records into: [`SpscFrameBuffer`] with
+/// the `spsc-buffer` feature, [`SharedFrameBuffer`] without.
+#[cfg(not(feature = "spsc-buffer"))]
+pub type CaptureBuffer = SharedFrameBuffer;
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
 use crate::capture::CapturedFrame;
 use parking_lot::RwLock;
@@ -25,17 +45,34 @@ use parking_lot::RwLock;
 /// - Capture system WRITES new frames
 /// - Save system READS frames when saving
 /// - UI might READ to show buffer status
+///
+/// Frames are held behind `Arc`s, so a snapshot only copies pointers
+/// while holding the lock. Copying the frame data happens after the
+/// lock is released, so a save never stalls the capture thread.
 pub struct SharedFrameBuffer {
     /// The ring buffer, protected by a read-write lock
-    inner: RwLock<RingBuffer<CapturedFrame>>,
+    inner: RwLock<RingBuffer<Arc<CapturedFrame>>>,
 
     /// Maximum frames this buffer can hold
     capacity: usize,
+
+    /// Maximum bytes of frame data to hold (0 = no byte limit)
+    max_bytes: usize,
+
+    /// Running total of held frame data, only changed under the write lock
+    bytes: AtomicUsize,
+
+    /// Frames pushed out to make room since the buffer was created
+    evicted: AtomicU64,
 }
 
 impl SharedFrameBuffer {
     /// Creates a new buffer for the given duration and frame rate.
     ///
+    /// A duration or frame rate that works out to no frames at all is
+    /// raised to a one-frame buffer, with a warning, so a bad setting
+    /// can't leave a buffer that silently holds nothing.
+    ///
     /// ## Example
     /// ```
     /// # use quest_shadowplay::buffer::SharedFrameBuffer;
@@ -44,7 +81,17 @@ impl SharedFrameBuffer {
     /// assert_eq!(buffer.capacity(), 900);
     /// ```
     pub fn new(duration_seconds: f32, fps: u32) -> Self {
-        let capacity = (duration_seconds * fps as f32).ceil() as usize;
+        Self::new_with_byte_limit(duration_seconds, fps, 0)
+    }
+
+    /// Creates a buffer that also holds at most `max_bytes` of frame data.
+    ///
+    /// Frames are evicted oldest first until a new one fits, so busy
+    /// scenes shorten the buffer instead of growing memory. A single
+    /// frame bigger than the limit is still kept, alone. `max_bytes` of
+    /// 0 means no byte limit.
+    pub fn new_with_byte_limit(duration_seconds: f32, fps: u32, max_bytes: usize) -> Self {
+        let capacity = frame_capacity(duration_seconds, fps);
 
         log::info!(
             "Creating frame buffer: {:.1}s at {} FPS = {} frames",
@@ -52,19 +99,58 @@ impl SharedFrameBuffer {
             fps,
             capacity
         );
+        if max_bytes > 0 {
+            log::info!("Frame buffer limited to {} bytes", max_bytes);
+        }
 
         Self {
             inner: RwLock::new(RingBuffer::new(capacity)),
             capacity,
+            max_bytes,
+            bytes: AtomicUsize::new(0),
+            evicted: AtomicU64::new(0),
         }
     }
 
     /// Adds a new frame to the buffer.
     ///
-    /// If the buffer is full, the oldest frame is automatically removed.
+    /// If the buffer is full, the oldest frame is automatically removed
+    /// and returned (with a byte limit, several may go; the newest of
+    /// them is returned). It is shared with any snapshot still holding
+    /// it; `Arc::try_unwrap` reclaims it when not. Every eviction is
+    /// counted in [`SharedFrameBuffer::evicted_count`].
     /// This is called ~90 times per second, so it must be fast.
-    pub fn push_frame(&self, frame: CapturedFrame) {
-        self.inner.write().push(frame);
+    pub fn push_frame(&self, frame: CapturedFrame) -> Option<Arc<CapturedFrame>> {
+        let frame = Arc::new(frame);
+        let mut inner = self.inner.write();
+        let size = frame.data.len();
+        let mut held = self.bytes.load(Ordering::Relaxed);
+        let mut evicted = None;
+        let mut evicted_count = 0;
+
+        // Each frame is evicted at most once, so this is O(1) amortized
+        while self.max_bytes > 0 && held + size > self.max_bytes && !inner.is_empty() {
+            evicted = inner.pop_oldest();
+            held -= evicted.as_ref().map_or(0, |f| f.data.len());
+            evicted_count += 1;
+        }
+        if let Some(oldest) = inner.push(frame) {
+            held -= oldest.data.len();
+            evicted_count += 1;
+            evicted = Some(oldest);
+        }
+
+        self.bytes.store(held + size, Ordering::Relaxed);
+        if evicted_count > 0 {
+            self.evicted.fetch_add(evicted_count, Ordering::Relaxed);
+        }
+        evicted
+    }
+
+    /// Returns how many frames have been pushed out to make room, for a
+    /// "frames overwritten" counter.
+    pub fn evicted_count(&self) -> u64 {
+        self.evicted.load(Ordering::Relaxed)
     }
 
     /// Takes a snapshot of all current frames.
@@ -72,13 +158,160 @@ impl SharedFrameBuffer {
     /// Returns frames in chronological order (oldest first).
     /// The original buffer is NOT modified - recording continues.
     pub fn snapshot(&self) -> Vec<CapturedFrame> {
+        unshare(self.snapshot_shared())
+    }
+
+    /// Takes a snapshot of all current frames without copying them.
+    ///
+    /// Only the `Arc` pointers are cloned, so the lock is held briefly
+    /// however big the buffer is.
+    pub fn snapshot_shared(&self) -> Vec<Arc<CapturedFrame>> {
         self.inner.read().get_all_cloned()
     }
 
+    /// Takes a snapshot of only the last `window` of footage, oldest first.
+    ///
+    /// Walks back from the newest frame and stops at the first frame
+    /// older than the window, so a short clip doesn't copy the whole
+    /// buffer. A window longer than the buffer returns everything. A
+    /// frame stamped after the newest one counts as age zero rather
+    /// than underflowing.
+    pub fn snapshot_last(&self, window: Duration) -> Vec<CapturedFrame> {
+        let window_ns = u64::try_from(window.as_nanos()).unwrap_or(u64::MAX);
+        let mut frames: Vec<_> = {
+            let inner = self.inner.read();
+            let newest = match inner.peek_newest() {
+                Some(frame) => frame.timestamp_ns,
+                None => return Vec::new(),
+            };
+            inner
+                .iter()
+                .rev()
+                .take_while(|f| newest.saturating_sub(f.timestamp_ns) <= window_ns)
+                .cloned()
+                .collect()
+        };
+        frames.reverse();
+        unshare(frames)
+    }
+
+    /// Returns copies of the frames whose timestamps fall within
+    /// `start_ns..=end_ns`, oldest first.
+    pub fn peek_range(&self, start_ns: u64, end_ns: u64) -> Vec<CapturedFrame> {
+        let frames: Vec<_> = self
+            .inner
+            .read()
+            .iter()
+            .filter(|f| f.timestamp_ns >= start_ns && f.timestamp_ns <= end_ns)
+            .cloned()
+            .collect();
+        unshare(frames)
+    }
+
+    /// Takes a snapshot of one eye's frames (0 = left, 1 = right),
+    /// oldest first.
+    pub fn snapshot_by_eye(&self, eye: u32) -> Vec<CapturedFrame> {
+        let frames: Vec<_> = self
+            .inner
+            .read()
+            .iter()
+            .filter(|f| f.eye_index == eye)
+            .cloned()
+            .collect();
+        unshare(frames)
+    }
+
+    /// Checks that left and right frames with the same index were
+    /// captured together, within half a frame at `fps` (see
+    /// [`StereoSyncReport`]).
+    pub fn stereo_sync_report(&self, fps: u32) -> StereoSyncReport {
+        // Hashing is slow; do it after letting go of the lock
+        let frames = self.snapshot_shared();
+        let eye = |index| frames.iter().filter(|f| f.eye_index == index).map(|f| &**f).collect();
+        let (left, right): (Vec<_>, Vec<_>) = (eye(0), eye(1));
+        stereo_sync_report(&left, &right, 1_000_000_000 / fps.max(1) as u64)
+    }
+
+    /// Takes a snapshot split into left and right eye streams of equal
+    /// length, where frame `i` of each was captured at the same moment.
+    ///
+    /// Each frame is paired with the nearest-in-time frame of the other
+    /// eye. Frames left without a partner, such as the extra frame when
+    /// one eye was captured first, are dropped.
+    pub fn snapshot_stereo(&self) -> (Vec<CapturedFrame>, Vec<CapturedFrame>) {
+        let (left, right): (Vec<_>, Vec<_>) = {
+            let inner = self.inner.read();
+            let eye = |index| inner.iter().filter(|f| f.eye_index == index).cloned().collect();
+            (eye(0), eye(1))
+        };
+
+        let distance =
+            |a: &CapturedFrame, b: &CapturedFrame| a.timestamp_ns.abs_diff(b.timestamp_ns);
+        let (mut paired_left, mut paired_right) = (Vec::new(), Vec::new());
+        let (mut i, mut j) = (0, 0);
+        while i < left.len() && j < right.len() {
+            let here = distance(&left[i], &right[j]);
+            if left.get(i + 1).is_some_and(|next| distance(next, &right[j]) < here) {
+                // A later left frame is closer: this one has no partner
+                i += 1;
+            } else if right.get(j + 1).is_some_and(|next| distance(&left[i], next) < here) {
+                j += 1;
+            } else {
+                paired_left.push(Arc::clone(&left[i]));
+                paired_right.push(Arc::clone(&right[j]));
+                i += 1;
+                j += 1;
+            }
+        }
+        (unshare(paired_left), unshare(paired_right))
+    }
+
+    /// Takes a snapshot of the frames captured at least `offset_ns`
+    /// before the newest frame.
+    ///
+    /// Used to shift the saved window back in time to account for the
+    /// delay between an exciting moment and the user pressing save.
+    /// Returns an empty list if the buffer holds less than `offset_ns`
+    /// of footage.
+    pub fn snapshot_ending_before(&self, offset_ns: u64) -> Vec<CapturedFrame> {
+        unshare(self.snapshot_shared_ending_before(offset_ns))
+    }
+
+    /// Like [`SharedFrameBuffer::snapshot_ending_before`], without
+    /// copying the frames.
+    ///
+    /// This is what a "hot save" encodes from: the snapshot is fixed at
+    /// trigger time, while capture keeps pushing into the buffer. The
+    /// lock is held only to copy the `Arc` pointers, and frames pushed
+    /// out of the buffer meanwhile live on in the snapshot.
+    pub fn snapshot_shared_ending_before(&self, offset_ns: u64) -> Vec<Arc<CapturedFrame>> {
+        let inner = self.inner.read();
+        let newest = match inner.peek_newest() {
+            Some(frame) => frame.timestamp_ns,
+            None => return Vec::new(),
+        };
+
+        match newest.checked_sub(offset_ns) {
+            Some(end_ns) => inner.iter().filter(|f| f.timestamp_ns <= end_ns).cloned().collect(),
+            None => Vec::new(),
+        }
+    }
+
     /// Returns how full the buffer is (0.0 = empty, 1.0 = full).
+    ///
+    /// With a byte limit, this is whichever of the frame count and the
+    /// byte budget is closer to full.
     pub fn fill_percentage(&self) -> f32 {
+        if self.capacity == 0 {
+            return 0.0;
+        }
         let len = self.inner.read().len();
-        len as f32 / self.capacity as f32
+        let frames = len as f32 / self.capacity as f32;
+        if self.max_bytes == 0 {
+            return frames;
+        }
+        let bytes = self.memory_bytes() as f32 / self.max_bytes as f32;
+        frames.max(bytes).min(1.0)
     }
 
     /// Returns the number of frames currently stored.
@@ -86,6 +319,45 @@ impl SharedFrameBuffer {
         self.inner.read().len()
     }
 
+    /// Returns the timestamp of the oldest frame held, if any.
+    pub fn oldest_timestamp_ns(&self) -> Option<u64> {
+        self.inner.read().peek_oldest().map(|f| f.timestamp_ns)
+    }
+
+    /// Returns the timestamp of the newest frame held, if any.
+    pub fn newest_timestamp_ns(&self) -> Option<u64> {
+        self.inner.read().peek_newest().map(|f| f.timestamp_ns)
+    }
+
+    /// Returns the bytes of frame data currently held.
+    pub fn memory_bytes(&self) -> usize {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    /// Returns the byte limit, if the buffer has one.
+    pub fn byte_limit(&self) -> Option<usize> {
+        (self.max_bytes > 0).then_some(self.max_bytes)
+    }
+
+    /// Returns how much the held frames are compressed overall
+    /// (uncompressed bytes ÷ stored bytes), or 0.0 if empty.
+    ///
+    /// Static menus compress far better than busy scenes, so this shows
+    /// how the current content affects buffer length per megabyte.
+    pub fn average_compression_ratio(&self) -> f32 {
+        let (uncompressed, compressed) = self
+            .inner
+            .read()
+            .iter()
+            .fold((0usize, 0usize), |(u, c), f| {
+                (u + f.uncompressed_size(), c + f.compressed_size())
+            });
+        if compressed == 0 {
+            return 0.0;
+        }
+        uncompressed as f32 / compressed as f32
+    }
+
     /// Returns the maximum number of frames.
     pub fn capacity(&self) -> usize {
         self.capacity
@@ -93,8 +365,33 @@ impl SharedFrameBuffer {
 
     /// Clears all frames from the buffer.
     pub fn clear(&self) {
-        self.inner.write().clear();
+        let mut inner = self.inner.write();
+        inner.clear();
+        self.bytes.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Returns how many frames a buffer of `duration_seconds` at `fps`
+/// holds, raising an empty buffer to one frame with a warning.
+fn frame_capacity(duration_seconds: f32, fps: u32) -> usize {
+    let capacity = (duration_seconds * fps as f32).ceil() as usize;
+    if capacity == 0 {
+        log::warn!(
+            "Buffer of {}s at {} FPS holds no frames; using 1 frame",
+            duration_seconds,
+            fps
+        );
+        return 1;
     }
+    capacity
+}
+
+/// Copies shared frames out, after the buffer lock has been released.
+fn unshare(frames: Vec<Arc<CapturedFrame>>) -> Vec<CapturedFrame> {
+    frames
+        .into_iter()
+        .map(|f| Arc::try_unwrap(f).unwrap_or_else(|shared| (*shared).clone()))
+        .collect()
 }
 
 // ============================================
@@ -117,6 +414,19 @@ mod tests {
         assert_eq!(buffer.fill_percentage(), 0.0);
     }
 
+    #[test]
+    fn test_zero_capacity_raised_to_one() {
+        for buffer in [SharedFrameBuffer::new(10.0, 0), SharedFrameBuffer::new(0.0, 90)] {
+            assert_eq!(buffer.capacity(), 1);
+            assert_eq!(buffer.fill_percentage(), 0.0);
+
+            buffer.push_frame(dummy_frame(0));
+            buffer.push_frame(dummy_frame(1));
+            assert_eq!(buffer.frame_count(), 1);
+            assert_eq!(buffer.fill_percentage(), 1.0);
+        }
+    }
+
     #[test]
     fn test_push_and_snapshot() {
         let buffer = SharedFrameBuffer::new(1.0, 10);
@@ -135,12 +445,155 @@ mod tests {
         let buffer = SharedFrameBuffer::new(1.0, 5);
 
         // Add 8 frames to a 5-capacity buffer
-        for i in 0..8 {
-            buffer.push_frame(dummy_frame(i));
+        for i in 0..5 {
+            assert!(buffer.push_frame(dummy_frame(i)).is_none());
+        }
+        for i in 5..8 {
+            assert!(buffer.push_frame(dummy_frame(i)).is_some());
         }
 
         // Should only have 5 frames
         assert_eq!(buffer.frame_count(), 5);
+        assert_eq!(buffer.evicted_count(), 3);
+        assert_eq!(buffer.memory_bytes(), 500);
+    }
+
+    #[test]
+    fn test_byte_limit() {
+        let buffer = SharedFrameBuffer::new_with_byte_limit(1.0, 10, 1_000);
+        assert_eq!(buffer.byte_limit(), Some(1_000));
+        let sized =
+            |bytes: usize, ts: u64| CapturedFrame::with_timestamp(vec![0u8; bytes], 0, 1, 1, ts);
+
+        for i in 0..4 {
+            buffer.push_frame(sized(200, i));
+        }
+        assert_eq!(buffer.memory_bytes(), 800);
+        assert_eq!(buffer.fill_percentage(), 0.8);
+
+        // A busy frame evicts the two oldest to fit
+        let evicted = buffer.push_frame(sized(500, 4)).unwrap();
+        assert_eq!(evicted.timestamp_ns, 1);
+        assert_eq!(buffer.evicted_count(), 2);
+        assert_eq!(buffer.frame_count(), 3);
+        assert_eq!(buffer.memory_bytes(), 900);
+        assert_eq!(buffer.oldest_timestamp_ns(), Some(2));
+
+        // A frame over the whole budget is kept on its own
+        buffer.push_frame(sized(2_000, 5));
+        assert_eq!(buffer.frame_count(), 1);
+        assert_eq!(buffer.memory_bytes(), 2_000);
+        assert_eq!(buffer.fill_percentage(), 1.0);
+
+        // Frame count still applies; the running total follows evictions
+        let buffer = SharedFrameBuffer::new_with_byte_limit(1.0, 3, 1_000_000);
+        for i in 0..5 {
+            buffer.push_frame(sized(10 + i as usize, i));
+        }
+        assert_eq!(buffer.memory_bytes(), 12 + 13 + 14);
+        buffer.clear();
+        assert_eq!(buffer.memory_bytes(), 0);
+    }
+
+    #[test]
+    fn test_average_compression_ratio() {
+        let buffer = SharedFrameBuffer::new(1.0, 10);
+        assert_eq!(buffer.average_compression_ratio(), 0.0);
+
+        // 40,000 raw bytes each, stored in 100 and 400 bytes
+        buffer.push_frame(dummy_frame(0));
+        buffer.push_frame(CapturedFrame::new(vec![0u8; 400], 0, 100, 100));
+        assert_eq!(buffer.average_compression_ratio(), 160.0);
+    }
+
+    #[test]
+    fn test_peek_range() {
+        let buffer = SharedFrameBuffer::new(1.0, 10);
+
+        for i in 0..5 {
+            buffer.push_frame(CapturedFrame::with_timestamp(vec![0u8; 10], 0, 1, 1, i * 100));
+        }
+
+        let range = buffer.peek_range(100, 300);
+        let timestamps: Vec<_> = range.iter().map(|f| f.timestamp_ns).collect();
+        assert_eq!(timestamps, vec![100, 200, 300]);
+    }
+
+    #[test]
+    fn test_snapshot_ending_before() {
+        let buffer = SharedFrameBuffer::new(1.0, 10);
+
+        for i in 0..5 {
+            buffer.push_frame(CapturedFrame::with_timestamp(vec![0u8; 10], 0, 1, 1, i * 100));
+        }
+
+        // Newest is 400, so an offset of 150 ends the window at 250
+        let frames = buffer.snapshot_ending_before(150);
+        let timestamps: Vec<_> = frames.iter().map(|f| f.timestamp_ns).collect();
+        assert_eq!(timestamps, vec![0, 100, 200]);
+
+        // No offset returns everything
+        assert_eq!(buffer.snapshot_ending_before(0).len(), 5);
+
+        // Offset deeper than the buffer returns nothing
+        assert!(buffer.snapshot_ending_before(1_000).is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_stereo() {
+        let buffer = SharedFrameBuffer::new(1.0, 20);
+        // The left eye starts a frame early; the right eye runs on
+        for (timestamp_ns, eye) in [(0, 0), (10, 0), (12, 1), (20, 0), (22, 1), (30, 0), (32, 1)] {
+            buffer.push_frame(CapturedFrame::with_timestamp(vec![0; 10], eye, 4, 4, timestamp_ns));
+        }
+        buffer.push_frame(CapturedFrame::with_timestamp(vec![0; 10], 1, 4, 4, 42));
+
+        let times =
+            |frames: &[CapturedFrame]| frames.iter().map(|f| f.timestamp_ns).collect::<Vec<_>>();
+        assert_eq!(times(&buffer.snapshot_by_eye(1)), vec![12, 22, 32, 42]);
+
+        let (left, right) = buffer.snapshot_stereo();
+        assert_eq!(times(&left), vec![10, 20, 30]);
+        assert_eq!(times(&right), vec![12, 22, 32]);
+    }
+
+    #[test]
+    fn test_snapshot_last() {
+        let buffer = SharedFrameBuffer::new(1.0, 10);
+        assert!(buffer.snapshot_last(Duration::from_secs(3)).is_empty());
+
+        let at_ms =
+            |ms: u64| CapturedFrame::with_timestamp(vec![0u8; 10], 0, 1, 1, ms * 1_000_000);
+        for i in 0..10 {
+            buffer.push_frame(at_ms(i * 100));
+        }
+
+        let frames = buffer.snapshot_last(Duration::from_millis(250));
+        let timestamps: Vec<_> = frames.iter().map(|f| f.timestamp_ns / 1_000_000).collect();
+        assert_eq!(timestamps, vec![700, 800, 900]);
+
+        // Longer than the buffer returns everything
+        assert_eq!(buffer.snapshot_last(Duration::MAX).len(), 10);
+
+        // A frame stamped after the newest one is kept, not a panic
+        buffer.push_frame(at_ms(950));
+        buffer.push_frame(at_ms(940));
+        assert_eq!(buffer.snapshot_last(Duration::from_millis(50)).len(), 3);
+    }
+
+    #[test]
+    fn test_snapshot_shares_frames() {
+        let buffer = SharedFrameBuffer::new(1.0, 10);
+        buffer.push_frame(dummy_frame(0));
+
+        // Shared snapshots point at the buffered frame; owned ones copy it
+        let shared = buffer.snapshot_shared();
+        assert!(Arc::ptr_eq(&shared[0], &buffer.snapshot_shared()[0]));
+        assert_eq!(buffer.snapshot()[0].data, shared[0].data);
+
+        // A snapshot keeps its frames after the buffer moves on
+        buffer.clear();
+        assert_eq!(shared[0].data.len(), 100);
     }
 
     #[test]
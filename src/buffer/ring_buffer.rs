@@ -36,20 +36,46 @@ impl<T> RingBuffer<T> {
     /// assert_eq!(buffer.capacity(), 100);
     /// ```
     pub fn new(capacity: usize) -> Self {
-        Self {
-            data: VecDeque::with_capacity(capacity),
+        let mut buffer = Self {
+            data: VecDeque::new(),
             capacity,
-        }
+        };
+        buffer.reserve_exact(capacity);
+        buffer
+    }
+
+    /// Allocates room for `additional` more items than are stored now.
+    ///
+    /// The buffer starts with room for its full capacity, so steady-state
+    /// pushes never allocate. Call this before raising how much it will
+    /// hold, so the allocation happens here rather than mid-capture.
+    pub fn reserve_exact(&mut self, additional: usize) {
+        self.data.reserve_exact(additional);
+    }
+
+    /// Returns how many items fit before the storage must reallocate.
+    pub fn allocated(&self) -> usize {
+        self.data.capacity()
     }
 
     /// Adds an item to the buffer.
     ///
-    /// If the buffer is full, the oldest item is removed first.
-    pub fn push(&mut self, item: T) {
-        if self.data.len() >= self.capacity {
-            self.data.pop_front();
-        }
+    /// If the buffer is full, the oldest item is removed first and
+    /// returned, so callers can count or reuse it; `None` if nothing was
+    /// evicted. Never allocates once the buffer has room for its capacity.
+    pub fn push(&mut self, item: T) -> Option<T> {
+        let evicted = if self.data.len() >= self.capacity {
+            self.data.pop_front()
+        } else {
+            None
+        };
         self.data.push_back(item);
+        evicted
+    }
+
+    /// Removes and returns the oldest item.
+    pub fn pop_oldest(&mut self) -> Option<T> {
+        self.data.pop_front()
     }
 
     /// Returns the number of items currently stored.
@@ -87,8 +113,9 @@ impl<T> RingBuffer<T> {
         self.data.back()
     }
 
-    /// Returns an iterator over all items (oldest to newest).
-    pub fn iter(&self) -> impl Iterator<Item = &T> {
+    /// Returns an iterator over all items (oldest to newest); `.rev()`
+    /// walks newest first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> {
         self.data.iter()
     }
 
@@ -152,10 +179,9 @@ mod tests {
     fn test_overflow() {
         let mut buffer = RingBuffer::new(3);
 
-        // Add 5 items to capacity-3 buffer
-        for i in 1..=5 {
-            buffer.push(i);
-        }
+        // Add 5 items to capacity-3 buffer; 1 and 2 are pushed out
+        let evicted: Vec<_> = (1..=5).filter_map(|i| buffer.push(i)).collect();
+        assert_eq!(evicted, vec![1, 2]);
 
         // Should only have 3, 4, 5
         assert_eq!(buffer.len(), 3);
@@ -166,6 +192,27 @@ mod tests {
         assert_eq!(all, vec![3, 4, 5]);
     }
 
+    #[test]
+    fn test_no_reallocation_when_full() {
+        let mut buffer = RingBuffer::new(90);
+        let allocated = buffer.allocated();
+        assert!(allocated >= 90);
+
+        // Three full fill cycles, then a clear and refill
+        for i in 0..270 {
+            buffer.push(i);
+            assert_eq!(buffer.allocated(), allocated);
+        }
+        buffer.clear();
+        for i in 0..90 {
+            buffer.push(i);
+        }
+        assert_eq!(buffer.allocated(), allocated);
+
+        buffer.reserve_exact(200);
+        assert!(buffer.allocated() >= 290);
+    }
+
     #[test]
     fn test_clear() {
         let mut buffer = RingBuffer::new(5);
@@ -0,0 +1,473 @@
+//! # Single-Producer Frame Buffer
+//!
+//! A lock-free frame buffer for exactly one writer, built on atomics.
+//!
+//! ## Plain English
+//!
+//! The normal buffer has one lock on the whole belt: while the save
+//! thread looks at it, the capture thread waits. Here every spot on
+//! the belt is a single atomic pointer, and a counter says where the
+//! next frame goes. The capture thread swaps its frame into a spot
+//! and never waits. To look at a frame, a reader briefly lifts it off
+//! its spot, copies the pointer and puts it back; if the capture
+//! thread filled the spot meanwhile, the reader throws the old frame
+//! away instead.
+
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use super::{frame_capacity, stereo_sync_report, unshare, StereoSyncReport};
+use crate::capture::CapturedFrame;
+
+/// One spot in the buffer: a frame and its position in the stream.
+struct Slot {
+    /// How many frames were pushed before this one
+    seq: u64,
+    frame: Arc<CapturedFrame>,
+}
+
+/// Lock-free frame buffer for one capture thread.
+///
+/// Offers the same methods as [`super::SharedFrameBuffer`] that the
+/// capture path uses, so it can stand in for it (see
+/// [`super::CaptureBuffer`]).
+///
+/// `push_frame` is wait-free: it swaps the new frame into its slot and
+/// moves on, whatever readers are doing. Reading never blocks the
+/// producer, and a reader only waits for another reader that is
+/// copying one pointer out of the same slot. Frames overwritten while
+/// a snapshot runs are left out of it, so a snapshot is always whole
+/// frames, oldest first, with no gaps.
+///
+/// It is built for one reader at a time (the save thread). More are
+/// safe, but two readers racing the producer on one slot can drop a
+/// frame early.
+pub struct SpscFrameBuffer {
+    /// Pre-allocated spots, reused round-robin; null while empty or
+    /// while a reader holds the frame
+    slots: Vec<AtomicPtr<Slot>>,
+
+    /// Frames pushed so far; only the producer writes it
+    head: AtomicU64,
+
+    /// Frames before this one were evicted by the byte limit or cleared
+    floor: AtomicU64,
+
+    /// Frames before this one were cleared, not evicted
+    cleared: AtomicU64,
+
+    /// Maximum bytes of frame data to hold (0 = no byte limit)
+    max_bytes: usize,
+
+    /// Running total of held frame data; added on push, taken off by
+    /// whichever thread frees the frame
+    bytes: AtomicUsize,
+
+    /// Frames pushed out to make room since the buffer was created
+    evicted: AtomicU64,
+}
+
+impl SpscFrameBuffer {
+    /// Creates a buffer for the given duration and frame rate.
+    pub fn new(duration_seconds: f32, fps: u32) -> Self {
+        Self::new_with_byte_limit(duration_seconds, fps, 0)
+    }
+
+    /// Creates a buffer that also holds at most `max_bytes` of frame data.
+    ///
+    /// As with [`super::SharedFrameBuffer::new_with_byte_limit`], a
+    /// single frame bigger than the limit is still kept, alone.
+    pub fn new_with_byte_limit(duration_seconds: f32, fps: u32, max_bytes: usize) -> Self {
+        let capacity = frame_capacity(duration_seconds, fps);
+        log::info!("Creating single-producer frame buffer: {} frames", capacity);
+        if max_bytes > 0 {
+            log::info!("Frame buffer limited to {} bytes", max_bytes);
+        }
+
+        Self {
+            slots: (0..capacity).map(|_| AtomicPtr::new(ptr::null_mut())).collect(),
+            head: AtomicU64::new(0),
+            floor: AtomicU64::new(0),
+            cleared: AtomicU64::new(0),
+            max_bytes,
+            bytes: AtomicUsize::new(0),
+            evicted: AtomicU64::new(0),
+        }
+    }
+
+    /// Adds a frame, replacing the oldest one once full.
+    ///
+    /// Must only be called from one thread. Returns the replaced frame,
+    /// unless a reader was holding it (the reader frees it instead).
+    ///
+    /// If a reader holds the oldest frame when the byte limit needs it
+    /// gone, the buffer stays over the limit until the reader lets go
+    /// and the next push, instead of evicting newer frames in its place.
+    pub fn push_frame(&self, frame: CapturedFrame) -> Option<Arc<CapturedFrame>> {
+        let seq = self.head.load(Ordering::Relaxed);
+        self.bytes.fetch_add(frame.data.len(), Ordering::Relaxed);
+        let slot = Box::into_raw(Box::new(Slot {
+            seq,
+            frame: Arc::new(frame),
+        }));
+
+        // Publish only once the slot holds the new frame
+        let old = self.slots[self.index(seq)].swap(slot, Ordering::AcqRel);
+        self.head.store(seq + 1, Ordering::Release);
+
+        // SAFETY: a non-null pointer swapped out of a slot came from
+        // `Box::into_raw`, and the swap made this thread its only owner
+        let old = (!old.is_null()).then(|| unsafe { Box::from_raw(old) });
+        let mut evicted = old.and_then(|old| self.release(*old));
+
+        // Each frame is evicted at most once, so this is O(1) amortized
+        while self.max_bytes > 0 && self.bytes.load(Ordering::Relaxed) > self.max_bytes {
+            let oldest = self.first_seq(seq + 1);
+            if oldest >= seq {
+                break;
+            }
+            self.floor.fetch_max(oldest + 1, Ordering::AcqRel);
+            match self.take(oldest) {
+                Some(old) => evicted = self.release(*old).or(evicted),
+                // A reader holding it frees it on return, seeing the
+                // floor. Its bytes still count until then, so stop here
+                // rather than evict more frames to make up for them
+                None => break,
+            }
+        }
+        evicted
+    }
+
+    /// Returns how many frames have been pushed out to make room.
+    pub fn evicted_count(&self) -> u64 {
+        self.evicted.load(Ordering::Relaxed)
+    }
+
+    /// Takes a snapshot of all current frames, oldest first.
+    pub fn snapshot(&self) -> Vec<CapturedFrame> {
+        unshare(self.snapshot_shared())
+    }
+
+    /// Takes a snapshot of all current frames without copying them.
+    pub fn snapshot_shared(&self) -> Vec<Arc<CapturedFrame>> {
+        let head = self.head.load(Ordering::Acquire);
+        let start = self.first_seq(head);
+
+        let mut frames = Vec::with_capacity((head - start) as usize);
+        for seq in start..head {
+            match self.peek(seq, |slot| Arc::clone(&slot.frame)) {
+                Some(frame) => frames.push(frame),
+                // Evicted mid-snapshot: keep only frames after the gap
+                None => frames.clear(),
+            }
+        }
+        frames
+    }
+
+    /// Like [`super::SharedFrameBuffer::snapshot_shared_ending_before`]:
+    /// the frames captured at least `offset_ns` before the newest one.
+    pub fn snapshot_shared_ending_before(&self, offset_ns: u64) -> Vec<Arc<CapturedFrame>> {
+        let mut frames = self.snapshot_shared();
+        let end_ns = match frames.last() {
+            Some(newest) => newest.timestamp_ns.checked_sub(offset_ns),
+            None => return frames,
+        };
+        match end_ns {
+            Some(end_ns) => frames.retain(|f| f.timestamp_ns <= end_ns),
+            None => frames.clear(),
+        }
+        frames
+    }
+
+    /// Checks that left and right frames with the same index were
+    /// captured together (see [`StereoSyncReport`]).
+    pub fn stereo_sync_report(&self, fps: u32) -> StereoSyncReport {
+        let frames = self.snapshot_shared();
+        let eye = |index| frames.iter().filter(|f| f.eye_index == index).map(|f| &**f).collect();
+        let (left, right): (Vec<_>, Vec<_>) = (eye(0), eye(1));
+        stereo_sync_report(&left, &right, 1_000_000_000 / fps.max(1) as u64)
+    }
+
+    /// Returns how full the buffer is (0.0 = empty, 1.0 = full).
+    ///
+    /// With a byte limit, this is whichever of the frame count and the
+    /// byte budget is closer to full.
+    pub fn fill_percentage(&self) -> f32 {
+        let frames = self.frame_count() as f32 / self.capacity() as f32;
+        if self.max_bytes == 0 {
+            return frames;
+        }
+        let bytes = self.memory_bytes() as f32 / self.max_bytes as f32;
+        frames.max(bytes).min(1.0)
+    }
+
+    /// Returns the number of frames currently stored.
+    pub fn frame_count(&self) -> usize {
+        let head = self.head.load(Ordering::Acquire);
+        (head - self.first_seq(head)) as usize
+    }
+
+    /// Returns the timestamp of the oldest frame held, if any.
+    pub fn oldest_timestamp_ns(&self) -> Option<u64> {
+        let head = self.head.load(Ordering::Acquire);
+        (self.first_seq(head)..head).find_map(|seq| self.peek(seq, |s| s.frame.timestamp_ns))
+    }
+
+    /// Returns the timestamp of the newest frame held, if any.
+    pub fn newest_timestamp_ns(&self) -> Option<u64> {
+        let head = self.head.load(Ordering::Acquire);
+        (self.first_seq(head)..head)
+            .rev()
+            .find_map(|seq| self.peek(seq, |s| s.frame.timestamp_ns))
+    }
+
+    /// Returns the bytes of frame data currently held.
+    ///
+    /// Cleared or evicted frames a reader is still holding count until
+    /// it lets go of them.
+    pub fn memory_bytes(&self) -> usize {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    /// Returns the byte limit, if the buffer has one.
+    pub fn byte_limit(&self) -> Option<usize> {
+        (self.max_bytes > 0).then_some(self.max_bytes)
+    }
+
+    /// Returns how much the held frames are compressed overall
+    /// (uncompressed bytes ÷ stored bytes), or 0.0 if empty.
+    pub fn average_compression_ratio(&self) -> f32 {
+        let (uncompressed, compressed) = self
+            .snapshot_shared()
+            .iter()
+            .fold((0usize, 0usize), |(u, c), f| {
+                (u + f.uncompressed_size(), c + f.compressed_size())
+            });
+        if compressed == 0 {
+            return 0.0;
+        }
+        uncompressed as f32 / compressed as f32
+    }
+
+    /// Returns the maximum number of frames.
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Clears all frames from the buffer.
+    ///
+    /// Safe to call from any thread; frames pushed meanwhile are kept.
+    pub fn clear(&self) {
+        let head = self.head.load(Ordering::Acquire);
+        self.cleared.fetch_max(head, Ordering::Relaxed);
+        self.floor.fetch_max(head, Ordering::AcqRel);
+
+        // Returning a frame below the floor frees it
+        for seq in head.saturating_sub(self.capacity() as u64)..head {
+            if let Some(slot) = self.take(seq) {
+                self.put_back(slot);
+            }
+        }
+    }
+
+    /// Returns the slot a frame number lands in.
+    fn index(&self, seq: u64) -> usize {
+        (seq % self.capacity() as u64) as usize
+    }
+
+    /// Returns the number of the oldest frame still held, given `head`.
+    fn first_seq(&self, head: u64) -> u64 {
+        let overwritten = head.saturating_sub(self.capacity() as u64);
+        self.floor.load(Ordering::Acquire).max(overwritten).min(head)
+    }
+
+    /// Lifts whatever is in `seq`'s slot out of it, leaving it empty.
+    fn take(&self, seq: u64) -> Option<Box<Slot>> {
+        let slot = self.slots[self.index(seq)].swap(ptr::null_mut(), Ordering::AcqRel);
+        // SAFETY: see `push_frame`; the swap made this thread the owner
+        (!slot.is_null()).then(|| unsafe { Box::from_raw(slot) })
+    }
+
+    /// Returns a slot lifted by [`Self::take`], or frees it if it has
+    /// been evicted or overwritten since.
+    fn put_back(&self, slot: Box<Slot>) {
+        if slot.seq < self.first_seq(self.head.load(Ordering::Acquire)) {
+            self.release(*slot);
+            return;
+        }
+
+        let index = self.index(slot.seq);
+        let slot = Box::into_raw(slot);
+        let result = self.slots[index].compare_exchange(
+            ptr::null_mut(),
+            slot,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        );
+        if result.is_err() {
+            // SAFETY: the exchange failed, so this thread still owns it
+            self.release(*unsafe { Box::from_raw(slot) });
+        }
+    }
+
+    /// Runs `read` on frame `seq`, or returns `None` if it is gone.
+    fn peek<R>(&self, seq: u64, read: impl FnOnce(&Slot) -> R) -> Option<R> {
+        let slot = loop {
+            if let Some(slot) = self.take(seq) {
+                break slot;
+            }
+            if seq < self.first_seq(self.head.load(Ordering::Acquire)) {
+                return None;
+            }
+            // Another reader holds it for a moment
+            std::hint::spin_loop();
+        };
+        let result = (slot.seq == seq).then(|| read(&slot));
+        self.put_back(slot);
+        result
+    }
+
+    /// Frees a slot's hold on its frame, counting it as evicted unless
+    /// it was cleared. Returns the frame if it was evicted.
+    fn release(&self, slot: Slot) -> Option<Arc<CapturedFrame>> {
+        self.bytes.fetch_sub(slot.frame.data.len(), Ordering::Relaxed);
+        if slot.seq < self.cleared.load(Ordering::Relaxed) {
+            return None;
+        }
+        self.evicted.fetch_add(1, Ordering::Relaxed);
+        Some(slot.frame)
+    }
+}
+
+impl Drop for SpscFrameBuffer {
+    fn drop(&mut self) {
+        for slot in &mut self.slots {
+            let slot = std::mem::replace(slot.get_mut(), ptr::null_mut());
+            if !slot.is_null() {
+                // SAFETY: nothing else can reach the slots any more
+                drop(unsafe { Box::from_raw(slot) });
+            }
+        }
+    }
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+    use std::thread;
+
+    /// A frame whose every byte and timestamp encode `n`.
+    fn numbered_frame(n: u64) -> CapturedFrame {
+        CapturedFrame::with_timestamp(vec![n as u8; 64], 0, 4, 4, n)
+    }
+
+    #[test]
+    fn test_push_and_snapshot() {
+        let buffer = SpscFrameBuffer::new(1.0, 3);
+        assert_eq!(buffer.frame_count(), 0);
+
+        for n in 0..5 {
+            buffer.push_frame(numbered_frame(n));
+        }
+        let times: Vec<_> = buffer.snapshot().iter().map(|f| f.timestamp_ns).collect();
+        assert_eq!(times, vec![2, 3, 4]);
+        assert_eq!(buffer.fill_percentage(), 1.0);
+        assert_eq!(buffer.memory_bytes(), 3 * 64);
+        assert_eq!(buffer.evicted_count(), 2);
+        assert_eq!(buffer.oldest_timestamp_ns(), Some(2));
+        assert_eq!(buffer.newest_timestamp_ns(), Some(4));
+    }
+
+    #[test]
+    fn test_byte_limit_and_clear() {
+        let buffer = SpscFrameBuffer::new_with_byte_limit(1.0, 10, 3 * 64);
+        for n in 0..5 {
+            buffer.push_frame(numbered_frame(n));
+        }
+        let times: Vec<_> = buffer.snapshot().iter().map(|f| f.timestamp_ns).collect();
+        assert_eq!(times, vec![2, 3, 4]);
+        assert_eq!((buffer.memory_bytes(), buffer.evicted_count()), (3 * 64, 2));
+
+        buffer.clear();
+        assert_eq!(buffer.frame_count(), 0);
+        assert_eq!(buffer.memory_bytes(), 0);
+        assert_eq!(buffer.evicted_count(), 2);
+        assert!(buffer.snapshot().is_empty());
+
+        buffer.push_frame(numbered_frame(5));
+        let times: Vec<_> = buffer.snapshot().iter().map(|f| f.timestamp_ns).collect();
+        assert_eq!(times, vec![5]);
+    }
+
+    #[test]
+    fn test_byte_limit_waits_for_held_frame() {
+        let buffer = SpscFrameBuffer::new_with_byte_limit(1.0, 10, 3 * 64);
+        for n in 0..3 {
+            buffer.push_frame(numbered_frame(n));
+        }
+
+        // A reader is copying the oldest frame when a push goes over
+        let held = buffer.take(0).unwrap();
+        assert!(buffer.push_frame(numbered_frame(3)).is_none());
+        assert_eq!(buffer.memory_bytes(), 4 * 64);
+
+        // Letting go frees it, and only it was evicted
+        buffer.put_back(held);
+        let times: Vec<_> = buffer.snapshot().iter().map(|f| f.timestamp_ns).collect();
+        assert_eq!(times, vec![1, 2, 3]);
+        assert_eq!((buffer.memory_bytes(), buffer.evicted_count()), (3 * 64, 1));
+    }
+
+    #[test]
+    fn test_concurrent_snapshots_not_torn() {
+        const FRAMES: u64 = 20_000;
+        let buffer = Arc::new(SpscFrameBuffer::new(1.0, 50));
+        let done = Arc::new(AtomicBool::new(false));
+
+        let producer = {
+            let (buffer, done) = (Arc::clone(&buffer), Arc::clone(&done));
+            thread::spawn(move || {
+                for n in 0..FRAMES {
+                    buffer.push_frame(numbered_frame(n));
+                }
+                done.store(true, Ordering::SeqCst);
+            })
+        };
+
+        let consumer = {
+            let buffer = Arc::clone(&buffer);
+            thread::spawn(move || {
+                let mut snapshots = 0;
+                loop {
+                    let finished = done.load(Ordering::SeqCst);
+                    let frames = buffer.snapshot_shared();
+                    for pair in frames.windows(2) {
+                        assert_eq!(pair[1].timestamp_ns, pair[0].timestamp_ns + 1);
+                    }
+                    for frame in &frames {
+                        assert!(frame.data.iter().all(|&b| b == frame.timestamp_ns as u8));
+                    }
+                    snapshots += 1;
+                    if finished {
+                        return snapshots;
+                    }
+                }
+            })
+        };
+
+        producer.join().unwrap();
+        assert!(consumer.join().unwrap() > 0);
+
+        let frames = buffer.snapshot();
+        assert_eq!(frames.len(), 50);
+        assert_eq!(frames.last().unwrap().timestamp_ns, FRAMES - 1);
+        // Every frame freed was taken off the count exactly once
+        assert_eq!(buffer.memory_bytes(), 50 * 64);
+    }
+}
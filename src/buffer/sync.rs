@@ -0,0 +1,148 @@
+//! # Stereo Sync Check
+//!
+//! Checks that the left and right eye streams still line up.
+//!
+//! ## Plain English
+//!
+//! In a stereo recording, the 10th left-eye picture should show the
+//! same moment as the 10th right-eye picture. If one eye drops frames
+//! that the other doesn't, the two slide apart and a 3D export looks
+//! wrong. This compares the eyes frame by frame and reports how far
+//! apart they are, before anything is exported. It also spots the
+//! opposite problem: both "eyes" holding the exact same picture, which
+//! usually means one eye's image was read twice.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use crate::capture::CapturedFrame;
+
+/// How well the left and right eye streams line up.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StereoSyncReport {
+    /// Frames held for the left eye
+    pub left_frames: usize,
+
+    /// Frames held for the right eye
+    pub right_frames: usize,
+
+    /// Frame pairs compared (the shorter eye's count)
+    pub pairs: usize,
+
+    /// Largest gap between paired frames' timestamps
+    pub max_drift_ns: u64,
+
+    /// Average gap between paired frames' timestamps
+    pub mean_drift_ns: u64,
+
+    /// Pairs further apart than half a frame interval
+    pub out_of_sync: usize,
+
+    /// Index of the first such pair, where the eyes started to slide
+    pub first_out_of_sync: Option<usize>,
+
+    /// Pairs whose two images have the same checksum, i.e. one image twice
+    pub identical_pairs: usize,
+}
+
+impl StereoSyncReport {
+    /// Returns true if both eyes have the same number of frames, every
+    /// pair is within tolerance, and no pair is one image twice.
+    pub fn is_synced(&self) -> bool {
+        self.left_frames == self.right_frames
+            && self.out_of_sync == 0
+            && self.identical_pairs == 0
+    }
+}
+
+/// Compares left and right frames with the same index, oldest first.
+///
+/// Pairs more than half of `frame_interval_ns` apart count as out of
+/// sync. Image data is compared by checksum.
+pub fn stereo_sync_report(
+    left: &[&CapturedFrame],
+    right: &[&CapturedFrame],
+    frame_interval_ns: u64,
+) -> StereoSyncReport {
+    let tolerance_ns = frame_interval_ns / 2;
+    let mut report = StereoSyncReport {
+        left_frames: left.len(),
+        right_frames: right.len(),
+        pairs: left.len().min(right.len()),
+        ..StereoSyncReport::default()
+    };
+
+    let mut total_drift_ns = 0u64;
+    for (index, (l, r)) in left.iter().zip(right).enumerate() {
+        let drift_ns = l.timestamp_ns.abs_diff(r.timestamp_ns);
+        total_drift_ns += drift_ns;
+        report.max_drift_ns = report.max_drift_ns.max(drift_ns);
+        if drift_ns > tolerance_ns {
+            report.out_of_sync += 1;
+            report.first_out_of_sync.get_or_insert(index);
+        }
+        if checksum(&l.data) == checksum(&r.data) {
+            report.identical_pairs += 1;
+        }
+    }
+    if report.pairs > 0 {
+        report.mean_drift_ns = total_drift_ns / report.pairs as u64;
+    }
+
+    if !report.is_synced() {
+        log::warn!(
+            "Stereo out of sync: {} left / {} right frames, {} pairs drifted (max {:.1}ms), \
+             {} identical",
+            report.left_frames,
+            report.right_frames,
+            report.out_of_sync,
+            report.max_drift_ns as f64 / 1e6,
+            report.identical_pairs
+        );
+    }
+    report
+}
+
+/// Returns a checksum of frame data, for cheap equality checks.
+fn checksum(data: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eye_frame(eye: u32, timestamp_ns: u64) -> CapturedFrame {
+        let data = vec![eye as u8, (timestamp_ns / 1000) as u8];
+        CapturedFrame::with_timestamp(data, eye, 1, 1, timestamp_ns)
+    }
+
+    #[test]
+    fn test_sync_report() {
+        let ms = 1_000_000u64;
+        let left: Vec<_> = (0..4).map(|i| eye_frame(0, i * 10 * ms)).collect();
+        let right: Vec<_> = (0..4).map(|i| eye_frame(1, i * 10 * ms + ms)).collect();
+        let (left, right): (Vec<_>, Vec<_>) = (left.iter().collect(), right.iter().collect());
+
+        let report = stereo_sync_report(&left, &right, 10 * ms);
+        assert!(report.is_synced());
+        assert_eq!((report.pairs, report.max_drift_ns, report.mean_drift_ns), (4, ms, ms));
+
+        // The right eye drops its second frame: everything after slides
+        let dropped = [right[0], right[2], right[3]];
+        let report = stereo_sync_report(&left, &dropped, 10 * ms);
+        assert!(!report.is_synced());
+        assert_eq!((report.out_of_sync, report.first_out_of_sync), (2, Some(1)));
+
+        // Both eyes holding one image
+        let report = stereo_sync_report(&left, &left, 10 * ms);
+        assert_eq!(report.identical_pairs, 4);
+        assert!(!report.is_synced());
+    }
+}
@@ -2,10 +2,13 @@
 //!
 //! Structures for representing captured VR frames.
 
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 use jpeg_encoder::{ColorType, Encoder};
 
+use super::pixel::{AlphaHandling, FrameFormat};
+
 // ============================================
 // CAPTURED FRAME
 // ============================================
@@ -21,9 +24,12 @@ use jpeg_encoder::{ColorType, Encoder};
 /// - Image dimensions
 #[derive(Clone, Debug)]
 pub struct CapturedFrame {
-    /// JPEG-compressed image data
+    /// Image data, encoded as described by `format`
     pub data: Vec<u8>,
 
+    /// How `data` is encoded (JPEG unless reduced color depth is on)
+    pub format: FrameFormat,
+
     /// Timestamp in nanoseconds since Unix epoch
     pub timestamp_ns: u64,
 
@@ -35,6 +41,9 @@ pub struct CapturedFrame {
 
     /// Image height in pixels
     pub height: u32,
+
+    /// Change from the previous frame (0.0-1.0), if motion scoring is on
+    pub motion_score: Option<f32>,
 }
 
 impl CapturedFrame {
@@ -42,17 +51,14 @@ impl CapturedFrame {
     ///
     /// Automatically sets the timestamp to now.
     pub fn new(data: Vec<u8>, eye_index: u32, width: u32, height: u32) -> Self {
-        let timestamp_ns = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|d| d.as_nanos() as u64)
-            .unwrap_or(0);
-
         Self {
             data,
-            timestamp_ns,
+            format: FrameFormat::Jpeg,
+            timestamp_ns: now_ns(),
             eye_index,
             width,
             height,
+            motion_score: None,
         }
     }
 
@@ -66,13 +72,27 @@ impl CapturedFrame {
     ) -> Self {
         Self {
             data,
+            format: FrameFormat::Jpeg,
             timestamp_ns,
             eye_index,
             width,
             height,
+            motion_score: None,
         }
     }
 
+    /// Sets how the data is encoded.
+    pub fn with_format(mut self, format: FrameFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Attaches a motion score.
+    pub fn with_motion_score(mut self, score: Option<f32>) -> Self {
+        self.motion_score = score;
+        self
+    }
+
     /// Returns the compressed data size in bytes.
     pub fn compressed_size(&self) -> usize {
         self.data.len()
@@ -80,7 +100,25 @@ impl CapturedFrame {
 
     /// Estimates the uncompressed size (width × height × 4 bytes).
     pub fn uncompressed_size(&self) -> usize {
-        (self.width * self.height * 4) as usize
+        self.width as usize * self.height as usize * 4
+    }
+
+    /// Checks that the dimensions and data are usable.
+    ///
+    /// Catches frames from a readback that failed partway: zero sizes,
+    /// no data, or raw data that doesn't fill the image.
+    pub fn validate(&self) -> Result<(), CompressionError> {
+        let rgba_size = rgba_len(self.width, self.height)?;
+        match self.format {
+            FrameFormat::Jpeg if self.data.is_empty() => Err(CompressionError::InvalidData),
+            FrameFormat::Rgb565 if self.data.len() != rgba_size / 2 => {
+                Err(CompressionError::InvalidSize {
+                    expected: rgba_size / 2,
+                    got: self.data.len(),
+                })
+            }
+            _ => Ok(()),
+        }
     }
 
     /// Returns the compression ratio.
@@ -92,19 +130,160 @@ impl CapturedFrame {
     }
 }
 
+// ============================================
+// RAW FRAME
+// ============================================
+
+/// An uncompressed frame waiting in the queue for a compression worker.
+///
+/// The timestamp is taken when the frame is captured, not when it is
+/// compressed, so queueing delay doesn't skew frame timing.
+#[derive(Clone, Debug)]
+pub struct RawFrame {
+    /// Raw RGBA pixel data (4 bytes per pixel)
+    pub rgba: Vec<u8>,
+
+    /// Timestamp in nanoseconds since Unix epoch
+    pub timestamp_ns: u64,
+
+    /// Which eye (0 = left, 1 = right)
+    pub eye_index: u32,
+
+    /// Image width in pixels
+    pub width: u32,
+
+    /// Image height in pixels
+    pub height: u32,
+}
+
+impl RawFrame {
+    /// Creates a raw frame timestamped now.
+    pub fn new(rgba: Vec<u8>, eye_index: u32, width: u32, height: u32) -> Self {
+        Self {
+            rgba,
+            timestamp_ns: now_ns(),
+            eye_index,
+            width,
+            height,
+        }
+    }
+
+    /// Creates a raw frame with the timestamp the source captured it at.
+    pub fn with_timestamp(
+        rgba: Vec<u8>,
+        eye_index: u32,
+        width: u32,
+        height: u32,
+        timestamp_ns: u64,
+    ) -> Self {
+        Self {
+            rgba,
+            timestamp_ns,
+            eye_index,
+            width,
+            height,
+        }
+    }
+}
+
+// ============================================
+// CAPTURE CLOCK
+// ============================================
+
+/// A monotonic clock for stamping frames, anchored to wall-clock time.
+///
+/// ## Plain English
+///
+/// The system clock can jump (NTP corrections, the user changing the
+/// time), which would make frames appear out of order or a clip seem
+/// minutes long. This clock reads the wall time once, then counts
+/// forward with a stopwatch that never jumps. Timestamps still look like
+/// "nanoseconds since 1970", so they line up with marks and file names.
+#[derive(Clone, Copy, Debug)]
+pub struct CaptureClock {
+    /// Wall-clock time when the clock was created
+    origin_ns: u64,
+
+    /// Stopwatch started at the same moment
+    origin: Instant,
+}
+
+impl CaptureClock {
+    /// Starts a clock at the current wall-clock time.
+    pub fn new() -> Self {
+        Self {
+            origin_ns: now_ns(),
+            origin: Instant::now(),
+        }
+    }
+
+    /// Returns the current time in nanoseconds since Unix epoch.
+    pub fn now_ns(&self) -> u64 {
+        self.at(Instant::now())
+    }
+
+    /// Converts a moment on the monotonic clock to a frame timestamp.
+    ///
+    /// Use this when the source reports when the image was taken (for
+    /// example a compositor display time), rather than when it arrived.
+    pub fn at(&self, instant: Instant) -> u64 {
+        let elapsed = instant.saturating_duration_since(self.origin);
+        self.origin_ns.saturating_add(elapsed.as_nanos() as u64)
+    }
+}
+
+impl Default for CaptureClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the current time in nanoseconds since Unix epoch.
+pub(crate) fn now_ns() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
 // ============================================
 // FRAME COMPRESSOR
 // ============================================
 
+/// Quality drop per retry when a frame is over the size cap
+pub const CAP_QUALITY_STEP: u8 = 10;
+
+/// Default lowest quality the size cap may use
+pub const DEFAULT_MIN_CAPPED_QUALITY: u8 = 30;
+
 /// Compresses raw frame data to JPEG format.
 ///
 /// ## Plain English
 ///
 /// Raw images are huge (~14MB for Quest 3 resolution).
 /// This compressor shrinks them to ~100KB using JPEG compression.
+///
+/// The quality can be changed while frames are being compressed, so one
+/// compressor can be shared (e.g. in an `Arc`) between the capture
+/// thread and a settings UI.
 pub struct FrameCompressor {
     /// JPEG quality (0-100)
-    quality: u8,
+    quality: AtomicU8,
+
+    /// Emit progressive (multi-scan) JPEGs instead of baseline
+    progressive: bool,
+
+    /// How alpha is removed (JPEG output is always RGB)
+    alpha: AlphaHandling,
+
+    /// Largest allowed compressed frame in bytes (0 = no cap)
+    max_frame_bytes: usize,
+
+    /// Lowest quality the size cap may drop a frame to
+    min_capped_quality: u8,
+
+    /// Frames re-compressed at lower quality to meet the cap
+    frames_capped: AtomicU64,
 }
 
 impl FrameCompressor {
@@ -116,12 +295,67 @@ impl FrameCompressor {
     /// - 50-70: Noticeable artifacts, small files
     pub fn new(quality: u8) -> Self {
         Self {
-            quality: quality.min(100),
+            quality: AtomicU8::new(quality.min(100)),
+            progressive: false,
+            alpha: AlphaHandling::Discard,
+            max_frame_bytes: 0,
+            min_capped_quality: DEFAULT_MIN_CAPPED_QUALITY,
+            frames_capped: AtomicU64::new(0),
+        }
+    }
+
+    /// Changes the quality used for the next frame (1-100).
+    ///
+    /// Frames already compressed keep their old quality, so a buffer
+    /// changes size gradually as old frames are pushed out.
+    pub fn set_quality(&self, quality: u8) -> Result<(), CompressionError> {
+        if !(1..=100).contains(&quality) {
+            return Err(CompressionError::InvalidQuality(quality));
         }
+        self.quality.store(quality, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Enables or disables progressive JPEG output.
+    ///
+    /// Progressive JPEGs can be rendered at low resolution before the
+    /// whole image has arrived, which helps previews of large frames.
+    /// They are slightly slower to encode.
+    pub fn set_progressive(&mut self, progressive: bool) {
+        self.progressive = progressive;
+    }
+
+    /// Sets how the alpha channel is removed before encoding.
+    pub fn set_alpha_handling(&mut self, alpha: AlphaHandling) {
+        self.alpha = alpha;
+    }
+
+    /// Caps the size of each compressed frame.
+    ///
+    /// A frame over `max_bytes` is re-compressed at lower quality, in
+    /// steps of [`CAP_QUALITY_STEP`], until it fits. If it still doesn't
+    /// fit at `min_quality` it is kept at that quality anyway. A cap of
+    /// 0 turns this off.
+    pub fn set_size_cap(&mut self, max_bytes: usize, min_quality: u8) {
+        self.max_frame_bytes = max_bytes;
+        self.min_capped_quality = min_quality.clamp(1, 100);
+    }
+
+    /// Returns the size cap in bytes (0 = no cap) and its quality floor.
+    pub fn size_cap(&self) -> (usize, u8) {
+        (self.max_frame_bytes, self.min_capped_quality)
+    }
+
+    /// Returns how many frames the size cap forced to a lower quality.
+    pub fn frames_capped(&self) -> u64 {
+        self.frames_capped.load(Ordering::Relaxed)
     }
 
     /// Compresses raw RGBA pixel data to JPEG.
     ///
+    /// The output is always a 3-channel (RGB) JPEG; alpha is removed as
+    /// set by [`FrameCompressor::set_alpha_handling`].
+    ///
     /// ## Parameters
     /// - `raw_rgba`: Raw pixel data in RGBA format (4 bytes per pixel)
     /// - `width`: Image width in pixels
@@ -135,7 +369,7 @@ impl FrameCompressor {
         width: u32,
         height: u32,
     ) -> Result<Vec<u8>, CompressionError> {
-        let expected_size = (width * height * 4) as usize;
+        let expected_size = rgba_len(width, height)?;
         if raw_rgba.len() != expected_size {
             return Err(CompressionError::InvalidSize {
                 expected: expected_size,
@@ -144,22 +378,82 @@ impl FrameCompressor {
         }
 
         // Convert RGBA to RGB (strip alpha channel) - do this in-place for speed
-        let pixel_count = (width * height) as usize;
+        let pixel_count = expected_size / 4;
         let mut rgb = Vec::with_capacity(pixel_count * 3);
-        for i in 0..pixel_count {
-            let base = i * 4;
-            rgb.push(raw_rgba[base]);     // R
-            rgb.push(raw_rgba[base + 1]); // G
-            rgb.push(raw_rgba[base + 2]); // B
-            // Skip alpha
+        match self.alpha {
+            AlphaHandling::Discard => {
+                for i in 0..pixel_count {
+                    let base = i * 4;
+                    rgb.push(raw_rgba[base]);     // R
+                    rgb.push(raw_rgba[base + 1]); // G
+                    rgb.push(raw_rgba[base + 2]); // B
+                    // Skip alpha
+                }
+            }
+            AlphaHandling::BlendOnBlack => {
+                for px in raw_rgba.chunks_exact(4) {
+                    let alpha = px[3] as u16;
+                    for &channel in &px[..3] {
+                        // Rounded channel * alpha / 255
+                        rgb.push(((channel as u16 * alpha + 127) / 255) as u8);
+                    }
+                }
+            }
+        }
+
+        let quality = self.quality();
+        let mut output = self.encode_rgb(&rgb, width, height, quality)?;
+
+        // Step quality down until the frame fits under the cap
+        if self.max_frame_bytes > 0 && output.len() > self.max_frame_bytes {
+            let original_len = output.len();
+            let mut capped_quality = quality;
+            while output.len() > self.max_frame_bytes && capped_quality > self.min_capped_quality {
+                capped_quality = capped_quality
+                    .saturating_sub(CAP_QUALITY_STEP)
+                    .max(self.min_capped_quality);
+                output = self.encode_rgb(&rgb, width, height, capped_quality)?;
+            }
+
+            if capped_quality < quality {
+                self.frames_capped.fetch_add(1, Ordering::Relaxed);
+                if output.len() > self.max_frame_bytes {
+                    log::warn!(
+                        "Frame still {} bytes at quality floor {} (cap {})",
+                        output.len(),
+                        capped_quality,
+                        self.max_frame_bytes
+                    );
+                } else {
+                    log::debug!(
+                        "Frame capped: {} -> {} bytes, quality {} -> {}",
+                        original_len,
+                        output.len(),
+                        quality,
+                        capped_quality
+                    );
+                }
+            }
         }
 
+        Ok(output)
+    }
+
+    /// Encodes RGB pixels to JPEG at `quality`.
+    fn encode_rgb(
+        &self,
+        rgb: &[u8],
+        width: u32,
+        height: u32,
+        quality: u8,
+    ) -> Result<Vec<u8>, CompressionError> {
         // Encode to JPEG using fast encoder with quality control
-        let mut output = Vec::with_capacity(pixel_count / 4); // Estimate ~4:1 compression
-        let encoder = Encoder::new(&mut output, self.quality);
-        
+        let mut output = Vec::with_capacity(rgb.len() / 12); // Estimate ~4:1 compression
+        let mut encoder = Encoder::new(&mut output, quality);
+        encoder.set_progressive(self.progressive);
+
         encoder
-            .encode(&rgb, width as u16, height as u16, ColorType::Rgb)
+            .encode(rgb, width as u16, height as u16, ColorType::Rgb)
             .map_err(|e| CompressionError::EncodingFailed(e.to_string()))?;
 
         Ok(output)
@@ -167,8 +461,49 @@ impl FrameCompressor {
 
     /// Returns the quality setting.
     pub fn quality(&self) -> u8 {
-        self.quality
+        self.quality.load(Ordering::Relaxed)
+    }
+
+    /// Returns how alpha is removed.
+    pub fn alpha_handling(&self) -> AlphaHandling {
+        self.alpha
     }
+
+    /// Returns whether progressive output is enabled.
+    pub fn is_progressive(&self) -> bool {
+        self.progressive
+    }
+}
+
+/// Returns true if the JPEG data uses progressive encoding (SOF2).
+///
+/// Walks the marker segments up to the first start-of-frame marker.
+pub fn is_progressive_jpeg(data: &[u8]) -> bool {
+    // Must start with SOI (FF D8)
+    if data.len() < 4 || data[0] != 0xFF || data[1] != 0xD8 {
+        return false;
+    }
+
+    let mut offset = 2;
+    while offset + 4 <= data.len() {
+        if data[offset] != 0xFF {
+            return false;
+        }
+
+        let marker = data[offset + 1];
+        match marker {
+            // Progressive DCT
+            0xC2 => return true,
+            // Any other start-of-frame, or start-of-scan: not progressive
+            0xC0 | 0xC1 | 0xC3 | 0xDA => return false,
+            _ => {}
+        }
+
+        let len = u16::from_be_bytes([data[offset + 2], data[offset + 3]]) as usize;
+        offset += 2 + len;
+    }
+
+    false
 }
 
 impl Default for FrameCompressor {
@@ -187,11 +522,17 @@ pub enum CompressionError {
     /// Input data size doesn't match dimensions
     InvalidSize { expected: usize, got: usize },
 
+    /// Width or height is zero, or the image is too big to address
+    InvalidDimensions { width: u32, height: u32 },
+
     /// Invalid input data
     InvalidData,
 
     /// JPEG encoding failed
     EncodingFailed(String),
+
+    /// Quality outside 1-100
+    InvalidQuality(u8),
 }
 
 impl std::fmt::Display for CompressionError {
@@ -200,14 +541,28 @@ impl std::fmt::Display for CompressionError {
             Self::InvalidSize { expected, got } => {
                 write!(f, "Invalid size: expected {} bytes, got {}", expected, got)
             }
+            Self::InvalidDimensions { width, height } => {
+                write!(f, "Invalid dimensions {}x{}", width, height)
+            }
             Self::InvalidData => write!(f, "Invalid image data"),
             Self::EncodingFailed(msg) => write!(f, "Encoding failed: {}", msg),
+            Self::InvalidQuality(q) => write!(f, "JPEG quality {} outside 1-100", q),
         }
     }
 }
 
 impl std::error::Error for CompressionError {}
 
+/// Returns the RGBA byte size of a `width` × `height` image, rejecting
+/// zero or overflowing dimensions.
+pub(crate) fn rgba_len(width: u32, height: u32) -> Result<usize, CompressionError> {
+    (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|pixels| pixels.checked_mul(4))
+        .filter(|&len| len > 0)
+        .ok_or(CompressionError::InvalidDimensions { width, height })
+}
+
 // ============================================
 // TESTS
 // ============================================
@@ -242,6 +597,20 @@ mod tests {
         assert_eq!(frame.compression_ratio(), 40.0);
     }
 
+    #[test]
+    fn test_capture_clock() {
+        let clock = CaptureClock::new();
+        let start = Instant::now();
+        let first = clock.now_ns();
+        let second = clock.now_ns();
+        assert!(second >= first);
+
+        // Instants map onto the clock by their distance from its origin
+        let later = start + std::time::Duration::from_millis(5);
+        assert_eq!(clock.at(later) - clock.at(start), 5_000_000);
+        assert!(first.abs_diff(now_ns()) < 1_000_000_000);
+    }
+
     #[test]
     fn test_compressor_quality() {
         let compressor = FrameCompressor::new(80);
@@ -252,6 +621,67 @@ mod tests {
         assert_eq!(high.quality(), 100);
     }
 
+    #[test]
+    fn test_alpha_handling() {
+        // White, fully transparent
+        let rgba = [255u8, 255, 255, 0].repeat(16 * 16);
+        let decode = |data: &[u8]| image::load_from_memory(data).unwrap().to_rgb8();
+
+        let discard = FrameCompressor::new(90);
+        let image = decode(&discard.compress(&rgba, 16, 16).unwrap());
+        assert!(image.get_pixel(8, 8).0.iter().all(|&c| c > 245));
+
+        let mut blend = FrameCompressor::new(90);
+        blend.set_alpha_handling(AlphaHandling::BlendOnBlack);
+        let image = decode(&blend.compress(&rgba, 16, 16).unwrap());
+        assert!(image.get_pixel(8, 8).0.iter().all(|&c| c < 10));
+
+        // Same input, same bytes
+        assert_eq!(
+            blend.compress(&rgba, 16, 16).unwrap(),
+            blend.compress(&rgba, 16, 16).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_set_quality_live() {
+        let compressor = FrameCompressor::new(90);
+        let rgba: Vec<u8> = (0..64 * 64 * 4).map(|i| (i * 7 % 251) as u8).collect();
+        let before = compressor.compress(&rgba, 64, 64).unwrap().len();
+
+        compressor.set_quality(20).unwrap();
+        assert_eq!(compressor.quality(), 20);
+        let after = compressor.compress(&rgba, 64, 64).unwrap().len();
+        assert!(after < before);
+
+        // Out of range leaves the quality unchanged
+        assert!(compressor.set_quality(0).is_err());
+        assert!(compressor.set_quality(101).is_err());
+        assert_eq!(compressor.quality(), 20);
+    }
+
+    #[test]
+    fn test_size_cap() {
+        let rgba: Vec<u8> = (0..64 * 64 * 4).map(|i| (i * 7 % 251) as u8).collect();
+        let at_quality = |q| FrameCompressor::new(q).compress(&rgba, 64, 64).unwrap();
+
+        // Cap between quality 70 and 80: stepped down from 90 to 70
+        let mut compressor = FrameCompressor::new(90);
+        compressor.set_size_cap(at_quality(70).len(), 30);
+        assert_eq!(compressor.compress(&rgba, 64, 64).unwrap(), at_quality(70));
+        assert_eq!(compressor.frames_capped(), 1);
+
+        // Impossible cap gives up at the floor
+        compressor.set_size_cap(10, 40);
+        assert_eq!(compressor.compress(&rgba, 64, 64).unwrap(), at_quality(40));
+        assert_eq!(compressor.frames_capped(), 2);
+
+        // Frames already under the cap are untouched
+        compressor.set_size_cap(usize::MAX, 40);
+        assert_eq!(compressor.compress(&rgba, 64, 64).unwrap(), at_quality(90));
+        assert_eq!(compressor.frames_capped(), 2);
+    }
+
     #[test]
     fn test_compression() {
         let compressor = FrameCompressor::new(80);
@@ -274,11 +704,49 @@ mod tests {
         assert!(compressed.len() < raw_rgba.len() / 2);
     }
 
+    #[test]
+    fn test_progressive_compression() {
+        let raw_rgba = vec![128u8; 64 * 64 * 4];
+
+        let baseline = FrameCompressor::new(80);
+        let data = baseline.compress(&raw_rgba, 64, 64).unwrap();
+        assert!(!is_progressive_jpeg(&data));
+
+        let mut progressive = FrameCompressor::new(80);
+        progressive.set_progressive(true);
+        assert!(progressive.is_progressive());
+        let data = progressive.compress(&raw_rgba, 64, 64).unwrap();
+        assert!(is_progressive_jpeg(&data));
+    }
+
+    #[test]
+    fn test_is_progressive_jpeg_garbage() {
+        assert!(!is_progressive_jpeg(&[]));
+        assert!(!is_progressive_jpeg(&[0u8; 100]));
+    }
+
     #[test]
     fn test_compression_invalid_size() {
         let compressor = FrameCompressor::new(80);
         let result = compressor.compress(&[0u8; 100], 100, 100);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_malformed_dimensions() {
+        let compressor = FrameCompressor::new(80);
+        assert!(matches!(
+            compressor.compress(&[0u8; 64], 0, 16),
+            Err(CompressionError::InvalidDimensions { width: 0, height: 16 })
+        ));
+        assert!(compressor.compress(&[], 0, 0).is_err());
+        assert!(compressor.compress(&[0u8; 64], u32::MAX, u32::MAX).is_err());
+
+        assert!(CapturedFrame::new(vec![0u8; 64], 0, 0, 16).validate().is_err());
+        assert!(CapturedFrame::new(Vec::new(), 0, 4, 4).validate().is_err());
+        assert!(CapturedFrame::new(vec![0u8; 8], 0, 4, 4).validate().is_ok());
+        let raw = CapturedFrame::new(vec![0u8; 31], 0, 4, 4).with_format(FrameFormat::Rgb565);
+        assert!(raw.validate().is_err());
+    }
 }
 
@@ -0,0 +1,113 @@
+//! # Idle Pause
+//!
+//! Pauses capture while nothing on screen is moving.
+//!
+//! ## Plain English
+//!
+//! A recorder that's always on spends battery compressing the same
+//! still menu or loading screen over and over. Each frame already gets
+//! a motion score (how different it is from the last one). Once the
+//! score has stayed under a floor for long enough, frames stop being
+//! compressed and stored; the first frame that moves again brings
+//! capture back, and is kept.
+
+use std::time::Duration;
+
+use parking_lot::Mutex;
+
+/// What the detector has seen so far.
+#[derive(Debug, Default)]
+struct IdleState {
+    /// Timestamp of the first frame in the current still run
+    still_since_ns: Option<u64>,
+
+    /// Is capture paused for idle?
+    paused: bool,
+}
+
+/// Decides from motion scores when capture should pause for idle.
+#[derive(Debug)]
+pub struct IdleDetector {
+    /// How long the picture must stay still before pausing
+    idle_after: Duration,
+
+    /// Motion score at or below which a frame counts as still
+    motion_floor: f32,
+
+    state: Mutex<IdleState>,
+}
+
+impl IdleDetector {
+    /// Creates a detector that pauses after `idle_after` of frames
+    /// scoring at most `motion_floor`.
+    pub fn new(idle_after: Duration, motion_floor: f32) -> Self {
+        Self {
+            idle_after,
+            motion_floor,
+            state: Mutex::new(IdleState::default()),
+        }
+    }
+
+    /// Records a frame's motion score and capture time.
+    ///
+    /// Returns true if the frame should be kept, false while paused.
+    pub fn observe(&self, motion_score: f32, timestamp_ns: u64) -> bool {
+        let mut state = self.state.lock();
+        if motion_score > self.motion_floor {
+            state.still_since_ns = None;
+            if state.paused {
+                state.paused = false;
+                log::info!("Motion detected, capture resumed");
+            }
+            return true;
+        }
+
+        let still_since = *state.still_since_ns.get_or_insert(timestamp_ns);
+        let still_for = Duration::from_nanos(timestamp_ns.saturating_sub(still_since));
+        if !state.paused && still_for >= self.idle_after {
+            state.paused = true;
+            log::info!("No motion for {:.0}s, capture paused", still_for.as_secs_f32());
+        }
+        !state.paused
+    }
+
+    /// Returns true while capture is paused for idle.
+    pub fn is_paused(&self) -> bool {
+        self.state.lock().paused
+    }
+
+    /// Forgets what was seen, e.g. after capture restarts.
+    pub fn reset(&self) {
+        *self.state.lock() = IdleState::default();
+    }
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pause_and_resume() {
+        let detector = IdleDetector::new(Duration::from_secs(2), 0.01);
+        let s = 1_000_000_000u64;
+
+        assert!(detector.observe(0.0, 0));
+        assert!(detector.observe(0.005, s));
+        assert!(!detector.observe(0.0, 2 * s));
+        assert!(detector.is_paused());
+        assert!(!detector.observe(0.01, 3 * s));
+
+        // Movement resumes at once and restarts the still clock
+        assert!(detector.observe(0.2, 4 * s));
+        assert!(!detector.is_paused());
+        assert!(detector.observe(0.0, 5 * s));
+
+        detector.observe(0.0, 9 * s);
+        detector.reset();
+        assert!(!detector.is_paused());
+    }
+}
@@ -0,0 +1,165 @@
+//! # Composition Layers
+//!
+//! Picks which of a frame's submitted layers ends up in the replay.
+//!
+//! ## Plain English
+//!
+//! A VR app doesn't always hand the headset one finished picture. It
+//! can send a stack of them: the game scene at the bottom, then a menu
+//! or HUD floating on top, and the headset lays them over each other.
+//! You might want the replay to show just the game (no HUD), the stack
+//! exactly as the player saw it, or only one particular sheet.
+
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+use super::EyeTexture;
+use crate::error::{ShadowplayError, ShadowplayResult};
+
+/// Which submitted composition layers are captured.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LayerSelect {
+    /// Only the bottom layer, usually the game's projection layer
+    #[default]
+    Base,
+
+    /// Every layer blended bottom to top, as the player saw them
+    All,
+
+    /// Only the layer at this position in submission order (0 = base)
+    Index(usize),
+}
+
+/// One layer of a frame, as submitted to the compositor.
+///
+/// Layers are listed in submission order, bottom first. Their pixels
+/// are RGBA with straight (non-premultiplied) alpha, already rendered
+/// at the eye's resolution.
+#[derive(Clone, Copy, Debug)]
+pub struct CompositionLayer<'a> {
+    /// Readback buffer holding the layer's pixels
+    pub pixels: &'a [u8],
+
+    /// How each eye is laid out in `pixels`
+    pub texture: EyeTexture,
+}
+
+impl<'a> CompositionLayer<'a> {
+    /// Creates a layer whose buffer holds only one eye's image.
+    pub fn new(pixels: &'a [u8]) -> Self {
+        Self {
+            pixels,
+            texture: EyeTexture::Separate,
+        }
+    }
+}
+
+impl LayerSelect {
+    /// Returns the RGBA pixels to capture for one eye of a frame.
+    ///
+    /// Fails if there are no layers, the chosen layer doesn't exist, or
+    /// a layer is the wrong size.
+    pub fn select<'a>(
+        &self,
+        layers: &[CompositionLayer<'a>],
+        width: u32,
+        height: u32,
+    ) -> ShadowplayResult<Cow<'a, [u8]>> {
+        let eye_pixels =
+            |layer: &CompositionLayer<'a>| layer.texture.eye_pixels(layer.pixels, width, height);
+
+        match *self {
+            Self::Base => layers
+                .first()
+                .ok_or_else(|| ShadowplayError::Capture("Frame has no layers".to_string()))
+                .and_then(eye_pixels)
+                .map(Cow::Borrowed),
+            Self::Index(index) => layers
+                .get(index)
+                .ok_or_else(|| {
+                    ShadowplayError::Capture(format!(
+                        "Layer {} not submitted ({} layers)",
+                        index,
+                        layers.len()
+                    ))
+                })
+                .and_then(eye_pixels)
+                .map(Cow::Borrowed),
+            Self::All => {
+                let pixels = layers.iter().map(eye_pixels).collect::<ShadowplayResult<Vec<_>>>()?;
+                composite_layers(&pixels, width, height).map(Cow::Owned)
+            }
+        }
+    }
+}
+
+/// Blends RGBA layers bottom to top ("over"), the way the compositor
+/// stacks them, and returns the result.
+pub fn composite_layers(layers: &[&[u8]], width: u32, height: u32) -> ShadowplayResult<Vec<u8>> {
+    let size = width as usize * height as usize * 4;
+    let (base, above) = layers
+        .split_first()
+        .ok_or_else(|| ShadowplayError::Capture("Frame has no layers".to_string()))?;
+    if let Some(layer) = layers.iter().find(|layer| layer.len() != size) {
+        return Err(ShadowplayError::Capture(format!(
+            "Layer is {} bytes, expected {} for {}x{}",
+            layer.len(),
+            size,
+            width,
+            height
+        )));
+    }
+
+    let mut out = base.to_vec();
+    for layer in above {
+        for (dst, src) in out.chunks_exact_mut(4).zip(layer.chunks_exact(4)) {
+            let src_a = src[3] as u32;
+            let dst_a = dst[3] as u32 * (255 - src_a) / 255;
+            let out_a = src_a + dst_a;
+            if out_a == 0 {
+                dst.copy_from_slice(&[0, 0, 0, 0]);
+                continue;
+            }
+            for c in 0..3 {
+                dst[c] = ((src[c] as u32 * src_a + dst[c] as u32 * dst_a) / out_a) as u8;
+            }
+            dst[3] = out_a as u8;
+        }
+    }
+    Ok(out)
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layer_select() {
+        // 1x1 eye: opaque red game, half-transparent white HUD
+        let game = [255, 0, 0, 255];
+        let hud = [255, 255, 255, 128];
+        let layers = [CompositionLayer::new(&game), CompositionLayer::new(&hud)];
+
+        assert_eq!(&*LayerSelect::Base.select(&layers, 1, 1).unwrap(), &game);
+        assert_eq!(&*LayerSelect::Index(1).select(&layers, 1, 1).unwrap(), &hud);
+        assert!(LayerSelect::Index(2).select(&layers, 1, 1).is_err());
+        assert!(LayerSelect::Base.select(&[], 1, 1).is_err());
+
+        let all = LayerSelect::All.select(&layers, 1, 1).unwrap();
+        assert_eq!(&*all, &[255, 128, 128, 255]);
+    }
+
+    #[test]
+    fn test_composite_transparent_and_mismatched() {
+        let base = [10, 20, 30, 255, 0, 0, 0, 0];
+        let clear = [200, 200, 200, 0, 200, 200, 200, 0];
+        assert_eq!(composite_layers(&[&base, &clear], 2, 1).unwrap(), base);
+        assert!(composite_layers(&[&base, &clear[..4]], 2, 1).is_err());
+    }
+}
@@ -9,13 +9,94 @@
 //! It's like a photocopier attached to a movie projector.
 
 mod frame;
+mod idle;
+mod layers;
+mod motion;
+mod pacer;
+mod pixel;
+mod queue;
+mod reorder;
+mod session;
+mod source;
+mod warmup;
 
-pub use frame::{CapturedFrame, FrameCompressor};
+pub use frame::{is_progressive_jpeg, CaptureClock, CapturedFrame, FrameCompressor, RawFrame};
+pub use idle::IdleDetector;
+pub use layers::{composite_layers, CompositionLayer, LayerSelect};
+pub use motion::{motion_score, motion_thumbnail, MotionTracker, MOTION_GRID};
+pub use pacer::FramePacer;
+pub use pixel::{
+    quantize_rgb565, rgb565_to_rgba, rgba_to_rgb565, AlphaHandling, ColorDepth, FrameFormat,
+};
+pub use queue::{BackpressurePolicy, FrameQueue, QueueStats};
+pub use reorder::ReorderBuffer;
+pub use session::SessionTimer;
+pub use source::{FrameSource, VecFrameSource};
+pub use warmup::WarmupFilter;
 
-use crate::buffer::SharedFrameBuffer;
-use crate::error::ShadowplayResult;
+use crate::buffer::CaptureBuffer;
+use crate::config::{Config, ConfigError};
+use crate::error::{ShadowplayError, ShadowplayResult};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// Default number of raw frames that can wait for compression
+pub const DEFAULT_QUEUE_DEPTH: usize = 4;
+
+// ============================================
+// EYE TEXTURES
+// ============================================
+
+/// How an eye's pixels are laid out in the readback buffer.
+///
+/// ## Plain English
+///
+/// Older apps render each eye into its own image. Most modern Quest apps
+/// use "multiview": both eyes live in one texture array, one layer per
+/// eye. Reading a multiview buffer as if it were a single image would
+/// only ever give us the left eye.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EyeTexture {
+    /// The buffer holds only this eye's image
+    Separate,
+
+    /// The buffer holds a texture array; this eye is at `layer`
+    TextureArray {
+        /// Array layer index (usually equals the eye index)
+        layer: u32,
+    },
+}
+
+impl EyeTexture {
+    /// Returns this eye's pixels from a readback buffer.
+    ///
+    /// For texture arrays, layers are assumed to be stored back to back,
+    /// each `width * height * 4` bytes.
+    pub fn eye_pixels<'a>(
+        &self,
+        pixels: &'a [u8],
+        width: u32,
+        height: u32,
+    ) -> ShadowplayResult<&'a [u8]> {
+        match *self {
+            Self::Separate => Ok(pixels),
+            Self::TextureArray { layer } => {
+                let layer_size = width as usize * height as usize * 4;
+                let start = layer as usize * layer_size;
+                pixels.get(start..start + layer_size).ok_or_else(|| {
+                    ShadowplayError::Capture(format!(
+                        "Texture array layer {} out of range ({} bytes for {}x{})",
+                        layer,
+                        pixels.len(),
+                        width,
+                        height
+                    ))
+                })
+            }
+        }
+    }
+}
 
 // ============================================
 // FRAME CAPTURER
@@ -30,19 +111,59 @@ use std::sync::Arc;
 /// frames before they're displayed.
 pub struct FrameCapturer {
     /// Where to store captured frames
-    buffer: Arc<SharedFrameBuffer>,
+    buffer: Arc<CaptureBuffer>,
 
     /// Compresses frames to save memory
     compressor: FrameCompressor,
 
+    /// Extra recording-profile buffers, each with its own quality
+    profiles: Vec<ProfileTarget>,
+
+    /// How pixels are stored
+    color_depth: ColorDepth,
+
+    /// Which submitted layers [`FrameCapturer::capture_layers`] keeps
+    capture_layer: LayerSelect,
+
+    /// Scores motion between frames, if enabled
+    motion: Option<parking_lot::Mutex<MotionTracker>>,
+
+    /// Whether motion scores are stored with frames (they may be worked
+    /// out only for idle detection)
+    store_motion: bool,
+
+    /// Pauses capture while nothing moves, if enabled
+    idle: Option<IdleDetector>,
+
+    /// Frames dropped while paused for idle
+    frames_idle_skipped: AtomicU64,
+
+    /// Raw frames waiting for a compression worker
+    queue: FrameQueue<RawFrame>,
+
+    /// Holds frames finished out of order by parallel workers
+    reorder: parking_lot::Mutex<ReorderBuffer<EncodedFrame>>,
+
+    /// Stamps frames that arrive without a source timestamp
+    clock: CaptureClock,
+
     /// Is capture enabled?
     enabled: AtomicBool,
 
+    /// Limits how long capture runs after each enable
+    session: SessionTimer,
+
+    /// Was capture last stopped by the session limit?
+    auto_stopped: AtomicBool,
+
     /// Frames captured successfully
     frames_captured: AtomicU64,
 
     /// Frames skipped (errors or performance)
     frames_skipped: AtomicU64,
+
+    /// Discards the first frames after each enable
+    warmup: WarmupFilter,
 }
 
 impl FrameCapturer {
@@ -51,17 +172,201 @@ impl FrameCapturer {
     /// ## Parameters
     /// - `buffer`: Where to store captured frames
     /// - `jpeg_quality`: Compression quality (0-100)
-    pub fn new(buffer: Arc<SharedFrameBuffer>, jpeg_quality: u8) -> Self {
+    pub fn new(buffer: Arc<CaptureBuffer>, jpeg_quality: u8) -> Self {
+        Self::with_queue(
+            buffer,
+            jpeg_quality,
+            DEFAULT_QUEUE_DEPTH,
+            BackpressurePolicy::default(),
+        )
+    }
+
+    /// Creates a frame capturer using the quality and queue settings
+    /// from the configuration.
+    pub fn with_config(buffer: Arc<CaptureBuffer>, config: &Config) -> Self {
+        let mut capturer = Self::with_queue(
+            buffer,
+            config.jpeg_quality,
+            config.capture_queue_depth,
+            config.backpressure,
+        );
+        capturer.set_warmup_frames(config.warmup_discard_frames);
+        capturer.set_motion_metric(config.motion_metric);
+        if config.auto_pause_on_idle {
+            capturer.set_idle_pause(
+                std::time::Duration::from_secs(config.idle_pause_seconds as u64),
+                config.idle_motion_floor,
+            );
+        }
+        capturer.set_color_depth(config.color_depth);
+        capturer.set_capture_layer(config.capture_layer);
+        capturer.compressor.set_alpha_handling(config.alpha_handling);
+        capturer
+            .compressor
+            .set_size_cap(config.max_frame_bytes, config.min_capped_jpeg_quality);
+        capturer.set_max_session_seconds(config.max_session_seconds);
+        capturer
+    }
+
+    /// Creates a frame capturer with an explicit compression queue.
+    ///
+    /// ## Parameters
+    /// - `queue_depth`: How many raw frames may wait for compression
+    /// - `policy`: What to do when the queue is full
+    pub fn with_queue(
+        buffer: Arc<CaptureBuffer>,
+        jpeg_quality: u8,
+        queue_depth: usize,
+        policy: BackpressurePolicy,
+    ) -> Self {
         Self {
             buffer,
             compressor: FrameCompressor::new(jpeg_quality),
+            profiles: Vec::new(),
+            color_depth: ColorDepth::default(),
+            capture_layer: LayerSelect::default(),
+            motion: None,
+            store_motion: false,
+            idle: None,
+            frames_idle_skipped: AtomicU64::new(0),
+            queue: FrameQueue::new(queue_depth, policy),
+            reorder: parking_lot::Mutex::new(ReorderBuffer::new()),
+            clock: CaptureClock::new(),
             enabled: AtomicBool::new(true),
+            session: SessionTimer::new(0),
+            auto_stopped: AtomicBool::new(false),
             frames_captured: AtomicU64::new(0),
             frames_skipped: AtomicU64::new(0),
+            warmup: WarmupFilter::new(0),
+        }
+    }
+
+    /// Sets how many frames to discard after capture is (re)enabled.
+    ///
+    /// Also applies right away, since a new capturer starts enabled.
+    pub fn set_warmup_frames(&mut self, frames: u32) {
+        self.warmup = WarmupFilter::new(frames);
+    }
+
+    /// Sets how long capture may run after each enable before it stops
+    /// itself (0 = unlimited).
+    ///
+    /// Also applies right away, since a new capturer starts enabled.
+    pub fn set_max_session_seconds(&mut self, seconds: u32) {
+        self.session = SessionTimer::new(seconds);
+        self.session.start();
+    }
+
+    /// Returns how long until capture auto-stops, or `None` if there is
+    /// no limit or capture is disabled.
+    pub fn remaining_session_time(&self) -> Option<std::time::Duration> {
+        self.session.remaining()
+    }
+
+    /// Returns true if capture was last stopped by the session limit
+    /// rather than by [`FrameCapturer::disable`].
+    pub fn was_auto_stopped(&self) -> bool {
+        self.auto_stopped.load(Ordering::Relaxed)
+    }
+
+    /// Disables capture if the session limit has passed.
+    ///
+    /// Returns true if capture should go on.
+    fn check_session_limit(&self) -> bool {
+        if !self.session.is_expired() {
+            return true;
+        }
+
+        if self.enabled.swap(false, Ordering::Relaxed) {
+            self.session.stop();
+            self.auto_stopped.store(true, Ordering::Relaxed);
+            log::warn!(
+                "Session limit of {}s reached, capture stopped",
+                self.session.limit().unwrap_or_default().as_secs()
+            );
+        }
+        false
+    }
+
+    /// Sets how captured pixels are stored (see [`ColorDepth`] for the
+    /// size/quality tradeoff).
+    pub fn set_color_depth(&mut self, color_depth: ColorDepth) {
+        self.color_depth = color_depth;
+    }
+
+    /// Sets which composition layers are captured when a frame arrives
+    /// as a stack of layers (see [`LayerSelect`]).
+    pub fn set_capture_layer(&mut self, select: LayerSelect) {
+        self.capture_layer = select;
+    }
+
+    /// Turns per-frame motion scoring on or off.
+    ///
+    /// Scoring runs wherever compression runs (the worker thread when
+    /// using [`FrameCapturer::submit_frame`]), never on the render thread.
+    pub fn set_motion_metric(&mut self, enabled: bool) {
+        self.store_motion = enabled;
+        self.motion = (enabled || self.idle.is_some())
+            .then(|| parking_lot::Mutex::new(MotionTracker::new()));
+    }
+
+    /// Pauses capture once frames have scored at most `motion_floor`
+    /// for `idle_after`, resuming on the first frame that moves (see
+    /// [`IdleDetector`]).
+    ///
+    /// Motion is scored for this even if the motion metric is off;
+    /// scores are then used only here, not stored with frames.
+    pub fn set_idle_pause(&mut self, idle_after: std::time::Duration, motion_floor: f32) {
+        self.idle = Some(IdleDetector::new(idle_after, motion_floor));
+        if self.motion.is_none() {
+            self.motion = Some(parking_lot::Mutex::new(MotionTracker::new()));
         }
     }
 
-    /// Processes a raw frame from the eye buffer.
+    /// Returns true while capture is paused because nothing is moving.
+    pub fn is_idle_paused(&self) -> bool {
+        self.idle.as_ref().is_some_and(|idle| idle.is_paused())
+    }
+
+    /// Changes the main buffer's JPEG quality (1-100) while capturing.
+    ///
+    /// Takes effect from the next compressed frame; profile buffers keep
+    /// their own quality.
+    pub fn set_jpeg_quality(&self, quality: u8) -> ShadowplayResult<()> {
+        self.compressor
+            .set_quality(quality)
+            .map_err(|_| ShadowplayError::Config(ConfigError::InvalidJpegQuality(quality)))?;
+        log::info!("JPEG quality set to {}", quality);
+        Ok(())
+    }
+
+    /// Returns the main buffer's current JPEG quality.
+    pub fn jpeg_quality(&self) -> u8 {
+        self.compressor.quality()
+    }
+
+    /// Also feeds every captured frame into `buffer`, compressed at
+    /// `jpeg_quality`.
+    ///
+    /// Used for recording profiles: one capture can fill a short
+    /// high-quality buffer and a long low-quality one at the same time.
+    pub fn add_profile_buffer(&mut self, buffer: Arc<CaptureBuffer>, jpeg_quality: u8) {
+        let mut compressor = FrameCompressor::new(jpeg_quality);
+        compressor.set_alpha_handling(self.compressor.alpha_handling());
+        let (max_bytes, min_quality) = self.compressor.size_cap();
+        compressor.set_size_cap(max_bytes, min_quality);
+        self.profiles.push(ProfileTarget { buffer, compressor });
+    }
+
+    /// Returns the clock used to stamp frames.
+    ///
+    /// Sources that build [`RawFrame`]s themselves should stamp them with
+    /// this clock so every frame in the buffer shares one time base.
+    pub fn clock(&self) -> &CaptureClock {
+        &self.clock
+    }
+
+    /// Processes a raw frame from the eye buffer, stamped now.
     ///
     /// ## Parameters
     /// - `raw_rgba`: Raw RGBA pixel data
@@ -78,28 +383,262 @@ impl FrameCapturer {
         height: u32,
         eye_index: u32,
     ) -> ShadowplayResult<()> {
-        if !self.enabled.load(Ordering::Relaxed) {
+        self.capture_frame_at(raw_rgba, width, height, eye_index, self.clock.now_ns())
+    }
+
+    /// Processes a raw frame captured at `timestamp_ns`.
+    ///
+    /// Use this when the source knows when the image was produced (for
+    /// example the compositor's display time, via [`CaptureClock::at`]);
+    /// the frame keeps that time however long compression takes.
+    pub fn capture_frame_at(
+        &self,
+        raw_rgba: &[u8],
+        width: u32,
+        height: u32,
+        eye_index: u32,
+        timestamp_ns: u64,
+    ) -> ShadowplayResult<()> {
+        if !self.enabled.load(Ordering::Relaxed)
+            || !self.check_session_limit()
+            || self.warmup.discard()
+        {
             return Ok(());
         }
 
-        // Compress the frame
-        match self.compressor.compress(raw_rgba, width, height) {
-            Ok(compressed) => {
-                let frame = CapturedFrame::new(compressed, eye_index, width, height);
-                self.buffer.push_frame(frame);
-                self.frames_captured.fetch_add(1, Ordering::Relaxed);
-                Ok(())
+        self.store_frame(raw_rgba, width, height, eye_index, timestamp_ns)
+    }
+
+    /// Compresses a frame into the main buffer and every profile buffer.
+    fn store_frame(
+        &self,
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        eye_index: u32,
+        timestamp_ns: u64,
+    ) -> ShadowplayResult<()> {
+        if let Some(encoded) = self.encode_frame(rgba, width, height, eye_index, timestamp_ns)? {
+            self.push_encoded(encoded);
+        }
+        Ok(())
+    }
+
+    /// Compresses a frame for the main buffer and every profile buffer,
+    /// without storing it.
+    ///
+    /// Returns `None` if the frame is dropped because capture is paused
+    /// for idle; it is scored for motion but never compressed.
+    fn encode_frame(
+        &self,
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        eye_index: u32,
+        timestamp_ns: u64,
+    ) -> ShadowplayResult<Option<EncodedFrame>> {
+        let motion_score = self
+            .motion
+            .as_ref()
+            .map(|tracker| tracker.lock().score(eye_index, rgba, width, height));
+        if let (Some(idle), Some(score)) = (&self.idle, motion_score) {
+            if !idle.observe(score, timestamp_ns) {
+                self.frames_idle_skipped.fetch_add(1, Ordering::Relaxed);
+                return Ok(None);
             }
+        }
+        let motion_score = motion_score.filter(|_| self.store_motion);
+
+        // Reduce color depth first so every buffer gets the same pixels
+        let quantized;
+        let pixels = if self.color_depth == ColorDepth::Rgb565Jpeg {
+            quantized = quantize_rgb565(rgba);
+            &quantized[..]
+        } else {
+            rgba
+        };
+
+        let (data, format) = match self.encode(&self.compressor, pixels, width, height) {
+            Ok(encoded) => encoded,
             Err(e) => {
                 self.frames_skipped.fetch_add(1, Ordering::Relaxed);
                 log::warn!("Frame compression failed: {}", e);
-                Err(crate::error::ShadowplayError::Capture(e.to_string()))
+                return Err(ShadowplayError::Capture(e));
+            }
+        };
+
+        let mut profiles = Vec::with_capacity(self.profiles.len());
+        for profile in &self.profiles {
+            // Reuse the main encode when the quality matches (or raw
+            // storage makes quality irrelevant)
+            let profile_data = if format == FrameFormat::Rgb565
+                || profile.compressor.quality() == self.compressor.quality()
+            {
+                data.clone()
+            } else {
+                match self.encode(&profile.compressor, pixels, width, height) {
+                    Ok((profile_data, _)) => profile_data,
+                    Err(e) => {
+                        log::warn!("Profile frame compression failed: {}", e);
+                        profiles.push(None);
+                        continue;
+                    }
+                }
+            };
+            profiles.push(Some(
+                CapturedFrame::with_timestamp(profile_data, eye_index, width, height, timestamp_ns)
+                    .with_format(format)
+                    .with_motion_score(motion_score),
+            ));
+        }
+
+        let main = CapturedFrame::with_timestamp(data, eye_index, width, height, timestamp_ns)
+            .with_format(format)
+            .with_motion_score(motion_score);
+        Ok(Some(EncodedFrame { main, profiles }))
+    }
+
+    /// Stores an encoded frame in its buffers.
+    fn push_encoded(&self, encoded: EncodedFrame) {
+        for (profile, frame) in self.profiles.iter().zip(encoded.profiles) {
+            if let Some(frame) = frame {
+                profile.buffer.push_frame(frame);
+            }
+        }
+        self.buffer.push_frame(encoded.main);
+        self.frames_captured.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Encodes pixels per the configured color depth.
+    fn encode(
+        &self,
+        compressor: &FrameCompressor,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<(Vec<u8>, FrameFormat), String> {
+        if self.color_depth == ColorDepth::Rgb565Raw {
+            let expected = frame::rgba_len(width, height).map_err(|e| e.to_string())?;
+            if pixels.len() != expected {
+                return Err(format!(
+                    "Invalid frame size: expected {} bytes, got {}",
+                    expected,
+                    pixels.len()
+                ));
             }
+            return Ok((rgba_to_rgb565(pixels), FrameFormat::Rgb565));
         }
+
+        compressor
+            .compress(pixels, width, height)
+            .map(|data| (data, FrameFormat::Jpeg))
+            .map_err(|e| e.to_string())
     }
 
-    /// Enables frame capture.
+    /// Captures one eye from a readback buffer that may hold several.
+    ///
+    /// Use this for multiview swapchains, where both eyes share a
+    /// texture array: the correct layer is selected before compression.
+    pub fn capture_eye_frame(
+        &self,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        eye_index: u32,
+        texture: EyeTexture,
+    ) -> ShadowplayResult<()> {
+        let eye_pixels = texture.eye_pixels(pixels, width, height)?;
+        self.capture_frame(eye_pixels, width, height, eye_index)
+    }
+
+    /// Captures one eye of a frame submitted as several composition
+    /// layers (game, then HUD or overlays), bottom first.
+    ///
+    /// The configured [`LayerSelect`] decides whether that is just the
+    /// base layer, one chosen layer, or all of them blended together.
+    pub fn capture_layers(
+        &self,
+        layers: &[CompositionLayer],
+        width: u32,
+        height: u32,
+        eye_index: u32,
+    ) -> ShadowplayResult<()> {
+        // Skip the blending work when the frame would be dropped anyway
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let pixels = self.capture_layer.select(layers, width, height)?;
+        self.capture_frame(&pixels, width, height, eye_index)
+    }
+
+    /// Queues a raw frame for compression on a worker thread.
+    ///
+    /// This is the cheap path for the render thread: it only moves the
+    /// frame into the queue. If the queue is full, the configured
+    /// backpressure policy decides what gets dropped.
+    ///
+    /// Returns `false` if the frame was not queued.
+    pub fn submit_frame(&self, frame: RawFrame) -> bool {
+        if !self.enabled.load(Ordering::Relaxed)
+            || !self.check_session_limit()
+            || self.warmup.discard()
+        {
+            return false;
+        }
+        self.queue.push(frame)
+    }
+
+    /// Compresses queued frames until the queue is closed and drained.
+    ///
+    /// Blocks the calling thread; see [`FrameCapturer::spawn_worker`].
+    /// Any number of workers may run at once: frames are numbered as
+    /// they leave the queue and stored strictly in that order, so the
+    /// buffer stays oldest-first however compression times vary.
+    pub fn run_worker(&self) {
+        while let Some((sequence, raw)) = self.queue.pop_sequenced() {
+            // Failures are counted and logged by encode_frame
+            let encoded = self
+                .encode_frame(
+                    &raw.rgba,
+                    raw.width,
+                    raw.height,
+                    raw.eye_index,
+                    raw.timestamp_ns,
+                )
+                .ok()
+                .flatten();
+
+            // Push while holding the lock so workers can't interleave
+            let mut reorder = self.reorder.lock();
+            for frame in reorder.insert(sequence, encoded) {
+                self.push_encoded(frame);
+            }
+        }
+    }
+
+    /// Spawns a background thread running [`FrameCapturer::run_worker`].
+    pub fn spawn_worker(self: &Arc<Self>) -> JoinHandle<()> {
+        let capturer = Arc::clone(self);
+        thread::spawn(move || capturer.run_worker())
+    }
+
+    /// Stops accepting frames; workers exit once the queue is drained.
+    pub fn close_queue(&self) {
+        self.queue.close();
+    }
+
+    /// Enables frame capture, restarting the warmup discard and the
+    /// session limit.
     pub fn enable(&self) {
+        self.session.start();
+        self.auto_stopped.store(false, Ordering::Relaxed);
+        self.warmup.restart();
+        if let Some(tracker) = &self.motion {
+            tracker.lock().reset();
+        }
+        if let Some(idle) = &self.idle {
+            idle.reset();
+        }
         self.enabled.store(true, Ordering::Relaxed);
         log::info!("Frame capture enabled");
     }
@@ -107,6 +646,7 @@ impl FrameCapturer {
     /// Disables frame capture.
     pub fn disable(&self) {
         self.enabled.store(false, Ordering::Relaxed);
+        self.session.stop();
         log::info!("Frame capture disabled");
     }
 
@@ -125,15 +665,61 @@ impl FrameCapturer {
         self.frames_skipped.load(Ordering::Relaxed)
     }
 
+    /// Returns the number of frames discarded during warmup.
+    pub fn frames_warmup_discarded(&self) -> u64 {
+        self.warmup.discarded()
+    }
+
+    /// Returns the number of frames dropped while paused for idle.
+    pub fn frames_idle_skipped(&self) -> u64 {
+        self.frames_idle_skipped.load(Ordering::Relaxed)
+    }
+
     /// Returns capture statistics.
     pub fn stats(&self) -> CaptureStats {
         CaptureStats {
             frames_captured: self.frames_captured(),
             frames_skipped: self.frames_skipped(),
+            frames_warmup_discarded: self.frames_warmup_discarded(),
             is_enabled: self.is_enabled(),
             buffer_fill: self.buffer.fill_percentage(),
+            queue: self.queue.stats(),
+            session_remaining: self.remaining_session_time(),
+            auto_stopped: self.was_auto_stopped(),
+            frames_size_capped: self.frames_size_capped(),
+            idle_paused: self.is_idle_paused(),
+            frames_idle_skipped: self.frames_idle_skipped(),
         }
     }
+
+    /// Returns how many frames (across all buffers) the frame size cap
+    /// forced down to a lower quality.
+    pub fn frames_size_capped(&self) -> u64 {
+        self.compressor.frames_capped()
+            + self
+                .profiles
+                .iter()
+                .map(|p| p.compressor.frames_capped())
+                .sum::<u64>()
+    }
+}
+
+/// A compressed frame ready for the main buffer and each profile buffer.
+struct EncodedFrame {
+    /// Frame for the main buffer
+    main: CapturedFrame,
+
+    /// Frame for each profile, in profile order (`None` = failed)
+    profiles: Vec<Option<CapturedFrame>>,
+}
+
+/// A recording-profile buffer fed alongside the main one.
+struct ProfileTarget {
+    /// Where this profile's frames go
+    buffer: Arc<CaptureBuffer>,
+
+    /// Compresses at this profile's quality
+    compressor: FrameCompressor,
 }
 
 /// Statistics about the capture process.
@@ -141,8 +727,15 @@ impl FrameCapturer {
 pub struct CaptureStats {
     pub frames_captured: u64,
     pub frames_skipped: u64,
+    pub frames_warmup_discarded: u64,
     pub is_enabled: bool,
     pub buffer_fill: f32,
+    pub queue: QueueStats,
+    pub session_remaining: Option<std::time::Duration>,
+    pub auto_stopped: bool,
+    pub frames_size_capped: u64,
+    pub idle_paused: bool,
+    pub frames_idle_skipped: u64,
 }
 
 // ============================================
@@ -153,9 +746,18 @@ pub struct CaptureStats {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_single_frame_types() {
+        // frame.rs holds the only definitions; every public path names them
+        let frame: frame::CapturedFrame = CapturedFrame::new(vec![0u8; 4], 0, 1, 1);
+        let frame: crate::CapturedFrame = frame;
+        let _: frame::FrameCompressor = FrameCompressor::default();
+        assert_eq!(frame.uncompressed_size(), 4);
+    }
+
     #[test]
     fn test_capturer_creation() {
-        let buffer = Arc::new(SharedFrameBuffer::new(1.0, 10));
+        let buffer = Arc::new(CaptureBuffer::new(1.0, 10));
         let capturer = FrameCapturer::new(buffer, 80);
 
         assert!(capturer.is_enabled());
@@ -165,7 +767,7 @@ mod tests {
 
     #[test]
     fn test_enable_disable() {
-        let buffer = Arc::new(SharedFrameBuffer::new(1.0, 10));
+        let buffer = Arc::new(CaptureBuffer::new(1.0, 10));
         let capturer = FrameCapturer::new(buffer, 80);
 
         assert!(capturer.is_enabled());
@@ -174,4 +776,269 @@ mod tests {
         capturer.enable();
         assert!(capturer.is_enabled());
     }
+
+    #[test]
+    fn test_capture_layers() {
+        let buffer = Arc::new(CaptureBuffer::new(10.0, 10));
+        let mut capturer = FrameCapturer::new(Arc::clone(&buffer), 80);
+        let game = vec![0u8; 8 * 8 * 4];
+        let hud = vec![255u8; 8 * 8 * 4];
+        let layers = [CompositionLayer::new(&game), CompositionLayer::new(&hud)];
+
+        capturer.capture_layers(&layers, 8, 8, 0).unwrap();
+        capturer.set_capture_layer(LayerSelect::Index(1));
+        capturer.capture_layers(&layers, 8, 8, 0).unwrap();
+        capturer.set_capture_layer(LayerSelect::Index(5));
+        assert!(capturer.capture_layers(&layers, 8, 8, 0).is_err());
+
+        // The base is black, the second layer an opaque white
+        let frames = buffer.snapshot();
+        assert_eq!(frames.len(), 2);
+        let brightness = |frame: &CapturedFrame| {
+            let image = image::load_from_memory(&frame.data).unwrap().to_rgba8();
+            image.as_raw()[0]
+        };
+        assert!(brightness(&frames[0]) < 10);
+        assert!(brightness(&frames[1]) > 245);
+    }
+
+    #[test]
+    fn test_texture_array_layers() {
+        // Two 2x1 layers: left is all 1s, right is all 2s
+        let mut pixels = vec![1u8; 2 * 4];
+        pixels.extend(vec![2u8; 2 * 4]);
+
+        let left = EyeTexture::TextureArray { layer: 0 };
+        let right = EyeTexture::TextureArray { layer: 1 };
+        let left_pixels = left.eye_pixels(&pixels, 2, 1).unwrap();
+        let right_pixels = right.eye_pixels(&pixels, 2, 1).unwrap();
+        assert!(left_pixels.iter().all(|&b| b == 1));
+        assert!(right_pixels.iter().all(|&b| b == 2));
+
+        let missing = EyeTexture::TextureArray { layer: 2 };
+        assert!(missing.eye_pixels(&pixels, 2, 1).is_err());
+
+        let whole = EyeTexture::Separate.eye_pixels(&pixels, 2, 1).unwrap();
+        assert_eq!(whole.len(), 16);
+    }
+
+    #[test]
+    fn test_capture_multiview_eyes() {
+        let buffer = Arc::new(CaptureBuffer::new(1.0, 10));
+        let capturer = FrameCapturer::new(Arc::clone(&buffer), 80);
+        let pixels = vec![200u8; 16 * 16 * 4 * 2];
+
+        for eye in 0..2 {
+            let texture = EyeTexture::TextureArray { layer: eye };
+            let result = capturer.capture_eye_frame(&pixels, 16, 16, eye, texture);
+            assert!(result.is_ok());
+        }
+
+        let eyes: Vec<_> = buffer.snapshot().iter().map(|f| f.eye_index).collect();
+        assert_eq!(eyes, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_warmup_discard() {
+        let buffer = Arc::new(CaptureBuffer::new(1.0, 10));
+        let mut capturer = FrameCapturer::new(Arc::clone(&buffer), 80);
+        capturer.set_warmup_frames(2);
+        let pixels = vec![128u8; 16 * 16 * 4];
+
+        for _ in 0..3 {
+            capturer.capture_frame(&pixels, 16, 16, 0).unwrap();
+        }
+        assert_eq!(buffer.frame_count(), 1);
+        assert_eq!(capturer.frames_warmup_discarded(), 2);
+
+        // Re-enabling starts a fresh warmup
+        capturer.disable();
+        capturer.enable();
+        capturer.capture_frame(&pixels, 16, 16, 0).unwrap();
+        assert_eq!(buffer.frame_count(), 1);
+        assert_eq!(capturer.stats().frames_warmup_discarded, 3);
+    }
+
+    #[test]
+    fn test_session_limit_auto_stops() {
+        let buffer = Arc::new(CaptureBuffer::new(1.0, 10));
+        let mut capturer = FrameCapturer::new(Arc::clone(&buffer), 80);
+        let pixels = vec![128u8; 16 * 16 * 4];
+
+        // No limit by default
+        assert!(capturer.remaining_session_time().is_none());
+
+        capturer.session = SessionTimer::with_limit(Some(std::time::Duration::from_millis(20)));
+        capturer.enable();
+        assert!(capturer.remaining_session_time().is_some());
+        capturer.capture_frame(&pixels, 16, 16, 0).unwrap();
+
+        thread::sleep(std::time::Duration::from_millis(30));
+        capturer.capture_frame(&pixels, 16, 16, 0).unwrap();
+        assert_eq!(buffer.frame_count(), 1);
+        assert!(!capturer.is_enabled());
+        assert!(capturer.was_auto_stopped());
+        assert!(capturer.remaining_session_time().is_none());
+
+        // Re-enabling starts a new session
+        capturer.enable();
+        assert!(!capturer.was_auto_stopped());
+        capturer.capture_frame(&pixels, 16, 16, 0).unwrap();
+        assert_eq!(buffer.frame_count(), 2);
+    }
+
+    #[test]
+    fn test_motion_metric() {
+        let buffer = Arc::new(CaptureBuffer::new(1.0, 10));
+        let mut capturer = FrameCapturer::new(Arc::clone(&buffer), 80);
+        let black = vec![0u8; 16 * 16 * 4];
+        let white = vec![255u8; 16 * 16 * 4];
+
+        capturer.capture_frame(&black, 16, 16, 0).unwrap();
+        capturer.set_motion_metric(true);
+        capturer.capture_frame(&black, 16, 16, 0).unwrap();
+        capturer.capture_frame(&white, 16, 16, 0).unwrap();
+
+        let scores: Vec<_> = buffer.snapshot().iter().map(|f| f.motion_score).collect();
+        assert_eq!(scores[0], None);
+        assert_eq!(scores[1], Some(0.0));
+        assert!(scores[2].unwrap() > 0.9);
+    }
+
+    #[test]
+    fn test_idle_pause() {
+        let buffer = Arc::new(CaptureBuffer::new(10.0, 10));
+        let mut capturer = FrameCapturer::new(Arc::clone(&buffer), 80);
+        capturer.set_idle_pause(std::time::Duration::from_millis(100), 0.01);
+        let black = vec![0u8; 16 * 16 * 4];
+        let white = vec![255u8; 16 * 16 * 4];
+        let ms = 1_000_000u64;
+
+        // Still for 100ms: paused from the third frame on
+        for i in 0..4 {
+            capturer.capture_frame_at(&black, 16, 16, 0, i * 50 * ms).unwrap();
+        }
+        assert!(capturer.is_idle_paused());
+        assert_eq!((buffer.frame_count(), capturer.frames_idle_skipped()), (2, 2));
+
+        // The frame that moves is kept, and scores aren't stored
+        capturer.capture_frame_at(&white, 16, 16, 0, 200 * ms).unwrap();
+        assert!(!capturer.stats().idle_paused);
+        let frames = buffer.snapshot();
+        assert_eq!(frames.len(), 3);
+        assert!(frames.iter().all(|f| f.motion_score.is_none()));
+    }
+
+    #[test]
+    fn test_rgb565_raw_capture() {
+        let buffer = Arc::new(CaptureBuffer::new(1.0, 10));
+        let mut capturer = FrameCapturer::new(Arc::clone(&buffer), 80);
+        capturer.set_color_depth(ColorDepth::Rgb565Raw);
+
+        let pixels = vec![255u8; 16 * 16 * 4];
+        capturer.capture_frame(&pixels, 16, 16, 0).unwrap();
+        assert!(capturer.capture_frame(&pixels[..10], 16, 16, 0).is_err());
+
+        let frame = &buffer.snapshot()[0];
+        assert_eq!(frame.format, FrameFormat::Rgb565);
+        assert_eq!(frame.data.len(), 16 * 16 * 2);
+        assert_eq!(rgb565_to_rgba(&frame.data), pixels);
+    }
+
+    #[test]
+    fn test_profile_buffers() {
+        let main = Arc::new(CaptureBuffer::new(1.0, 10));
+        let low = Arc::new(CaptureBuffer::new(5.0, 10));
+        let mut capturer = FrameCapturer::new(Arc::clone(&main), 95);
+        capturer.add_profile_buffer(Arc::clone(&low), 20);
+
+        // Noisy pixels so quality makes a visible size difference
+        let pixels: Vec<u8> = (0..32 * 32 * 4).map(|i| (i * 37 % 251) as u8).collect();
+        capturer.capture_frame(&pixels, 32, 32, 0).unwrap();
+
+        let main_frames = main.snapshot();
+        let low_frames = low.snapshot();
+        assert_eq!(low_frames.len(), 1);
+        assert_eq!(low_frames[0].timestamp_ns, main_frames[0].timestamp_ns);
+        assert!(low_frames[0].data.len() < main_frames[0].data.len());
+    }
+
+    #[test]
+    fn test_worker_drains_queue() {
+        let buffer = Arc::new(CaptureBuffer::new(1.0, 10));
+        let capturer = Arc::new(FrameCapturer::with_queue(
+            Arc::clone(&buffer),
+            80,
+            16,
+            BackpressurePolicy::Block,
+        ));
+        let worker = capturer.spawn_worker();
+
+        for i in 0..5 {
+            let raw = RawFrame::with_timestamp(vec![128u8; 16 * 16 * 4], 0, 16, 16, i);
+            assert!(capturer.submit_frame(raw));
+        }
+
+        capturer.close_queue();
+        worker.join().unwrap();
+
+        assert_eq!(capturer.frames_captured(), 5);
+        let timestamps: Vec<_> = buffer.snapshot().iter().map(|f| f.timestamp_ns).collect();
+        assert_eq!(timestamps, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_parallel_workers_keep_order() {
+        let buffer = Arc::new(CaptureBuffer::new(10.0, 30));
+        let capturer = Arc::new(FrameCapturer::with_queue(
+            Arc::clone(&buffer),
+            80,
+            8,
+            BackpressurePolicy::Block,
+        ));
+        let workers: Vec<_> = (0..4).map(|_| capturer.spawn_worker()).collect();
+
+        // Pseudo-random frame sizes so compression times vary widely
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        for i in 0..120 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let side = 8 + (state % 120) as u32;
+            let rgba: Vec<u8> = (0..side * side * 4).map(|p| (p as u64 ^ state) as u8).collect();
+            assert!(capturer.submit_frame(RawFrame::with_timestamp(rgba, 0, side, side, i)));
+        }
+
+        capturer.close_queue();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        let timestamps: Vec<_> = buffer.snapshot().iter().map(|f| f.timestamp_ns).collect();
+        assert_eq!(timestamps, (0..120).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_capture_frame_at_keeps_timestamp() {
+        let buffer = Arc::new(CaptureBuffer::new(1.0, 10));
+        let capturer = FrameCapturer::new(Arc::clone(&buffer), 80);
+        let pixels = vec![50u8; 16 * 16 * 4];
+
+        let before = capturer.clock().now_ns();
+        capturer.capture_frame_at(&pixels, 16, 16, 0, 42).unwrap();
+        capturer.capture_frame(&pixels, 16, 16, 0).unwrap();
+
+        let frames = buffer.snapshot();
+        assert_eq!(frames[0].timestamp_ns, 42);
+        assert!(frames[1].timestamp_ns >= before);
+    }
+
+    #[test]
+    fn test_submit_when_disabled() {
+        let buffer = Arc::new(CaptureBuffer::new(1.0, 10));
+        let capturer = FrameCapturer::new(buffer, 80);
+        capturer.disable();
+
+        assert!(!capturer.submit_frame(RawFrame::new(vec![0u8; 4], 0, 1, 1)));
+    }
 }
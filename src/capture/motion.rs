@@ -0,0 +1,122 @@
+//! # Motion Metric
+//!
+//! A cheap per-frame "how much changed" score.
+//!
+//! ## Plain English
+//!
+//! To find the exciting part of a clip later, we note how different each
+//! frame is from the one before. We shrink each frame to a tiny grayscale
+//! grid and add up how much every grid cell changed. Static menus score
+//! near 0.0; fast action scores higher (1.0 would be every pixel flipping
+//! from black to white).
+
+/// Side length of the grayscale grid frames are shrunk to.
+pub const MOTION_GRID: u32 = 32;
+
+/// Shrinks an RGBA frame to a `MOTION_GRID`×`MOTION_GRID` luma grid by
+/// sampling the center of each cell.
+pub fn motion_thumbnail(rgba: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let mut thumbnail = Vec::with_capacity((MOTION_GRID * MOTION_GRID) as usize);
+    if width == 0 || height == 0 {
+        return thumbnail;
+    }
+
+    for gy in 0..MOTION_GRID {
+        let y = ((gy * 2 + 1) * height / (MOTION_GRID * 2)).min(height - 1);
+        for gx in 0..MOTION_GRID {
+            let x = ((gx * 2 + 1) * width / (MOTION_GRID * 2)).min(width - 1);
+            let i = ((y * width + x) * 4) as usize;
+            let luma = match rgba.get(i..i + 3) {
+                // BT.601 luma with integer weights
+                Some(px) => (px[0] as u32 * 299 + px[1] as u32 * 587 + px[2] as u32 * 114) / 1000,
+                None => 0,
+            };
+            thumbnail.push(luma as u8);
+        }
+    }
+
+    thumbnail
+}
+
+/// Returns the mean absolute difference between two thumbnails, scaled
+/// to 0.0 (identical) to 1.0.
+pub fn motion_score(previous: &[u8], current: &[u8]) -> f32 {
+    if previous.is_empty() || previous.len() != current.len() {
+        return 0.0;
+    }
+
+    let sad: u64 = previous
+        .iter()
+        .zip(current)
+        .map(|(&a, &b)| a.abs_diff(b) as u64)
+        .sum();
+    sad as f32 / (previous.len() as f32 * 255.0)
+}
+
+/// Remembers the previous thumbnail per eye so each frame can be scored
+/// against the one before it.
+#[derive(Debug, Default)]
+pub struct MotionTracker {
+    previous: Vec<Option<Vec<u8>>>,
+}
+
+impl MotionTracker {
+    /// Creates a tracker with no history.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Scores a frame against the previous frame from the same eye.
+    ///
+    /// The first frame for each eye scores 0.0.
+    pub fn score(&mut self, eye_index: u32, rgba: &[u8], width: u32, height: u32) -> f32 {
+        let thumbnail = motion_thumbnail(rgba, width, height);
+        let eye = eye_index as usize;
+        if self.previous.len() <= eye {
+            self.previous.resize(eye + 1, None);
+        }
+
+        let score = self.previous[eye]
+            .as_deref()
+            .map(|prev| motion_score(prev, &thumbnail))
+            .unwrap_or(0.0);
+        self.previous[eye] = Some(thumbnail);
+        score
+    }
+
+    /// Forgets history, e.g. after capture restarts.
+    pub fn reset(&mut self) {
+        self.previous.clear();
+    }
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_static_frames_score_zero() {
+        let mut tracker = MotionTracker::new();
+        let frame = vec![90u8; 64 * 64 * 4];
+        assert_eq!(tracker.score(0, &frame, 64, 64), 0.0);
+        assert_eq!(tracker.score(0, &frame, 64, 64), 0.0);
+    }
+
+    #[test]
+    fn test_change_scores_higher() {
+        let mut tracker = MotionTracker::new();
+        let black = vec![0u8; 64 * 64 * 4];
+        let white = vec![255u8; 64 * 64 * 4];
+
+        tracker.score(0, &black, 64, 64);
+        let score = tracker.score(0, &white, 64, 64);
+        assert!(score > 0.99);
+
+        // Each eye has its own history
+        assert_eq!(tracker.score(1, &black, 64, 64), 0.0);
+    }
+}
@@ -0,0 +1,140 @@
+//! # Frame Pacer
+//!
+//! Keeps a capture loop on a steady frame rate.
+//!
+//! ## Plain English
+//!
+//! A capture thread that sleeps "one frame's worth" after every frame
+//! runs slow, because capturing the frame took time too. One that
+//! counts frames since it started tries to make up for every frame it
+//! ever missed, so after a hiccup it fires a burst of frames back to
+//! back. The pacer keeps a schedule of when each frame is due: a frame
+//! that's a little late makes the next wait shorter, and frames that
+//! were missed entirely are skipped rather than rushed.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Sleeps a capture loop to a fixed cadence without drifting.
+#[derive(Debug)]
+pub struct FramePacer {
+    /// Time between frames
+    interval: Duration,
+
+    /// When the next frame is due
+    next: Instant,
+
+    /// Frame slots skipped because the loop fell too far behind
+    skipped: u64,
+}
+
+impl FramePacer {
+    /// Creates a pacer for `fps` frames per second; the first frame is
+    /// due immediately.
+    pub fn new(fps: u32) -> Self {
+        Self::starting_at(fps, Instant::now())
+    }
+
+    /// Creates a pacer whose first frame is due at `start`.
+    fn starting_at(fps: u32, start: Instant) -> Self {
+        Self {
+            interval: Duration::from_nanos(1_000_000_000 / fps.max(1) as u64),
+            next: start,
+            skipped: 0,
+        }
+    }
+
+    /// Returns the time between frames.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Waits until the next frame is due.
+    ///
+    /// Call once per loop, before capturing. Returns how many frame slots
+    /// were missed since the last call (0 when on time).
+    pub fn wait(&mut self) -> u32 {
+        let (delay, missed) = self.advance_at(Instant::now());
+        if !delay.is_zero() {
+            thread::sleep(delay);
+        }
+        missed
+    }
+
+    /// Moves the schedule on one frame as of `now`.
+    ///
+    /// Returns how long to sleep before the frame and how many slots
+    /// were missed.
+    fn advance_at(&mut self, now: Instant) -> (Duration, u32) {
+        if now < self.next {
+            let delay = self.next - now;
+            self.next += self.interval;
+            return (delay, 0);
+        }
+
+        // Late: keep the schedule, but drop whole slots already passed so
+        // the loop doesn't fire them back to back
+        let missed = ((now - self.next).as_nanos() / self.interval.as_nanos()) as u32;
+        self.next += self.interval * (missed + 1);
+        self.skipped += missed as u64;
+        (Duration::ZERO, missed)
+    }
+
+    /// Returns how many frame slots have been skipped in total.
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Restarts the schedule from now, e.g. after a deliberate pause.
+    pub fn reset(&mut self) {
+        self.next = Instant::now();
+    }
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steady_cadence() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::starting_at(60, start);
+        let interval = pacer.interval();
+
+        let mut now = start;
+        for frame in 0..120u32 {
+            let (delay, missed) = pacer.advance_at(now);
+            assert_eq!(missed, 0);
+            now += delay;
+
+            // Frames land on the schedule, with no drift from the work time
+            assert_eq!(now, start + interval * frame);
+
+            // Uneven "capture" work, well under a frame
+            now += Duration::from_micros(2_000 * (frame as u64 % 3));
+        }
+    }
+
+    #[test]
+    fn test_stall_skips_instead_of_bursting() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::starting_at(100, start);
+        assert_eq!(pacer.advance_at(start), (Duration::ZERO, 0));
+
+        // Stall for about five frames
+        let now = start + Duration::from_millis(55);
+        let (delay, missed) = pacer.advance_at(now);
+        assert_eq!((delay, missed), (Duration::ZERO, 4));
+        assert_eq!(pacer.skipped(), 4);
+
+        // Back on schedule: the missed frames don't fire back to back
+        let (delay, missed) = pacer.advance_at(now);
+        assert_eq!((delay, missed), (Duration::from_millis(5), 0));
+        let (delay, _) = pacer.advance_at(now + delay);
+        assert_eq!(delay, pacer.interval());
+    }
+}
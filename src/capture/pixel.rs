@@ -0,0 +1,121 @@
+//! # Pixel Formats
+//!
+//! Reduced color depth (RGB565) for very long buffers.
+//!
+//! ## Plain English
+//!
+//! Normally each pixel uses 4 bytes (red, green, blue, alpha at 8 bits
+//! each). RGB565 squeezes a pixel into 2 bytes: 5 bits of red, 6 of green
+//! and 5 of blue, with no alpha. That halves the size of uncompressed
+//! frames, at the cost of visible banding in smooth gradients (skies,
+//! fog, menus with soft shadows).
+
+use serde::{Deserialize, Serialize};
+
+/// How captured pixels are stored in the buffer.
+///
+/// | Mode         | Encode cost | Size per frame        | Quality               |
+/// |--------------|-------------|-----------------------|-----------------------|
+/// | `Rgba8888`   | JPEG        | smallest              | best                  |
+/// | `Rgb565Jpeg` | JPEG        | slightly below 8888   | banding + JPEG loss   |
+/// | `Rgb565Raw`  | none        | half of raw RGBA      | banding, no JPEG loss |
+///
+/// `Rgb565Raw` skips JPEG entirely, so it is far larger than either JPEG
+/// mode. Use it only where JPEG encoding can't keep up; for the longest
+/// buffer per megabyte, stay with JPEG and lower `jpeg_quality`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorDepth {
+    /// Full 8-bit color, JPEG compressed
+    #[default]
+    Rgba8888,
+
+    /// Reduced to RGB565, then JPEG compressed
+    Rgb565Jpeg,
+
+    /// Stored as raw RGB565, 2 bytes per pixel
+    Rgb565Raw,
+}
+
+/// What to do with the alpha channel when encoding a JPEG.
+///
+/// JPEG has no alpha channel, so every JPEG is RGB. This decides what
+/// translucent pixels look like once alpha is gone.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlphaHandling {
+    /// Ignore alpha and keep the color as-is (eye buffers are opaque)
+    #[default]
+    Discard,
+
+    /// Blend each pixel over black by its alpha, as a compositor would
+    /// show a transparent layer over nothing
+    BlendOnBlack,
+}
+
+/// How a frame's `data` is encoded.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FrameFormat {
+    /// JPEG image
+    #[default]
+    Jpeg,
+
+    /// Raw RGB565, little-endian, row by row
+    Rgb565,
+}
+
+/// Packs RGBA pixels into little-endian RGB565 (alpha is dropped).
+pub fn rgba_to_rgb565(rgba: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(rgba.len() / 2);
+    for px in rgba.chunks_exact(4) {
+        let value = ((px[0] as u16 >> 3) << 11) | ((px[1] as u16 >> 2) << 5) | (px[2] as u16 >> 3);
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    out
+}
+
+/// Expands little-endian RGB565 back to opaque RGBA.
+///
+/// Low bits are filled by repeating the high bits, so full white stays
+/// full white.
+pub fn rgb565_to_rgba(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() * 2);
+    for px in data.chunks_exact(2) {
+        let value = u16::from_le_bytes([px[0], px[1]]);
+        let r = ((value >> 11) & 0x1F) as u8;
+        let g = ((value >> 5) & 0x3F) as u8;
+        let b = (value & 0x1F) as u8;
+        out.extend_from_slice(&[(r << 3) | (r >> 2), (g << 2) | (g >> 4), (b << 3) | (b >> 2), 255]);
+    }
+    out
+}
+
+/// Reduces RGBA pixels to RGB565 precision, keeping the RGBA layout.
+pub fn quantize_rgb565(rgba: &[u8]) -> Vec<u8> {
+    rgb565_to_rgba(&rgba_to_rgb565(rgba))
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rgb565_round_trip() {
+        let rgba = [255, 255, 255, 255, 0, 0, 0, 255, 200, 100, 50, 128];
+        let packed = rgba_to_rgb565(&rgba);
+        assert_eq!(packed.len(), 6);
+
+        let unpacked = rgb565_to_rgba(&packed);
+        assert_eq!(&unpacked[0..8], &[255, 255, 255, 255, 0, 0, 0, 255]);
+
+        // Within 565 precision, alpha forced opaque
+        assert!(unpacked[8].abs_diff(200) <= 8);
+        assert!(unpacked[9].abs_diff(100) <= 4);
+        assert!(unpacked[10].abs_diff(50) <= 8);
+        assert_eq!(unpacked[11], 255);
+    }
+}
@@ -0,0 +1,310 @@
+//! # Frame Queue
+//!
+//! Bounded hand-off between the capture thread and compression workers.
+//!
+//! ## Plain English
+//!
+//! The render thread drops raw frames into a small waiting area, and a
+//! worker picks them up to compress. If the worker falls behind, the
+//! waiting area fills up and we must decide what to give up - that's
+//! the backpressure policy.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use parking_lot::{Condvar, Mutex};
+use serde::{Deserialize, Serialize};
+
+// ============================================
+// BACKPRESSURE POLICY
+// ============================================
+
+/// What to do when a frame arrives and the queue is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackpressurePolicy {
+    /// Discard the oldest queued frame to make room (keeps the latest
+    /// footage - right for a replay buffer)
+    #[default]
+    DropOldest,
+
+    /// Discard the incoming frame
+    DropNewest,
+
+    /// Wait until there's room (stalls the caller - risky on the
+    /// render thread)
+    Block,
+}
+
+// ============================================
+// FRAME QUEUE
+// ============================================
+
+/// A bounded multi-producer, multi-consumer queue with a backpressure policy.
+pub struct FrameQueue<T> {
+    /// Queued items, oldest first
+    items: Mutex<VecDeque<T>>,
+
+    /// Signalled when an item is pushed or the queue closes
+    not_empty: Condvar,
+
+    /// Signalled when an item is popped or the queue closes
+    not_full: Condvar,
+
+    /// Maximum queued items
+    capacity: usize,
+
+    /// What to do when full
+    policy: BackpressurePolicy,
+
+    /// Set once no more items will be pushed
+    closed: AtomicBool,
+
+    /// Items discarded by `DropOldest`
+    dropped_oldest: AtomicU64,
+
+    /// Items discarded by `DropNewest`
+    dropped_newest: AtomicU64,
+
+    /// Pushes that had to wait under `Block`
+    blocked_pushes: AtomicU64,
+
+    /// Sequence number for the next `pop_sequenced`
+    next_sequence: AtomicU64,
+}
+
+impl<T> FrameQueue<T> {
+    /// Creates a queue holding at most `capacity` items (minimum 1).
+    pub fn new(capacity: usize, policy: BackpressurePolicy) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            items: Mutex::new(VecDeque::with_capacity(capacity)),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            capacity,
+            policy,
+            closed: AtomicBool::new(false),
+            dropped_oldest: AtomicU64::new(0),
+            dropped_newest: AtomicU64::new(0),
+            blocked_pushes: AtomicU64::new(0),
+            next_sequence: AtomicU64::new(0),
+        }
+    }
+
+    /// Adds an item, applying the backpressure policy if full.
+    ///
+    /// Returns `false` if the item was not queued (dropped by
+    /// `DropNewest`, or the queue is closed).
+    pub fn push(&self, item: T) -> bool {
+        if self.is_closed() {
+            return false;
+        }
+
+        let mut items = self.items.lock();
+
+        if items.len() >= self.capacity {
+            match self.policy {
+                BackpressurePolicy::DropOldest => {
+                    items.pop_front();
+                    self.dropped_oldest.fetch_add(1, Ordering::Relaxed);
+                }
+                BackpressurePolicy::DropNewest => {
+                    self.dropped_newest.fetch_add(1, Ordering::Relaxed);
+                    return false;
+                }
+                BackpressurePolicy::Block => {
+                    self.blocked_pushes.fetch_add(1, Ordering::Relaxed);
+                    while items.len() >= self.capacity && !self.is_closed() {
+                        self.not_full.wait(&mut items);
+                    }
+                    if self.is_closed() {
+                        return false;
+                    }
+                }
+            }
+        }
+
+        items.push_back(item);
+        self.not_empty.notify_one();
+        true
+    }
+
+    /// Removes the oldest item, waiting until one is available.
+    ///
+    /// Returns `None` once the queue is closed and empty.
+    pub fn pop(&self) -> Option<T> {
+        let mut items = self.items.lock();
+
+        loop {
+            if let Some(item) = items.pop_front() {
+                self.not_full.notify_one();
+                return Some(item);
+            }
+            if self.is_closed() {
+                return None;
+            }
+            self.not_empty.wait(&mut items);
+        }
+    }
+
+    /// Like [`FrameQueue::pop`], but also numbers the item.
+    ///
+    /// Numbers count up from 0 in the order items leave the queue,
+    /// which is the order they were pushed. Items dropped by
+    /// backpressure never get a number, so there are no gaps for a
+    /// [`super::ReorderBuffer`] to wait on.
+    pub fn pop_sequenced(&self) -> Option<(u64, T)> {
+        let mut items = self.items.lock();
+
+        loop {
+            if let Some(item) = items.pop_front() {
+                // Numbered under the lock so numbers follow pop order
+                let sequence = self.next_sequence.fetch_add(1, Ordering::Relaxed);
+                self.not_full.notify_one();
+                return Some((sequence, item));
+            }
+            if self.is_closed() {
+                return None;
+            }
+            self.not_empty.wait(&mut items);
+        }
+    }
+
+    /// Removes the oldest item without waiting.
+    pub fn try_pop(&self) -> Option<T> {
+        let item = self.items.lock().pop_front();
+        if item.is_some() {
+            self.not_full.notify_one();
+        }
+        item
+    }
+
+    /// Closes the queue: pushes fail, and `pop` returns `None` once drained.
+    pub fn close(&self) {
+        // Hold the lock so waiters can't miss the wakeup
+        let _items = self.items.lock();
+        self.closed.store(true, Ordering::SeqCst);
+        self.not_empty.notify_all();
+        self.not_full.notify_all();
+    }
+
+    /// Returns whether the queue has been closed.
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of queued items.
+    pub fn len(&self) -> usize {
+        self.items.lock().len()
+    }
+
+    /// Returns true if nothing is queued.
+    pub fn is_empty(&self) -> bool {
+        self.items.lock().is_empty()
+    }
+
+    /// Returns the maximum number of queued items.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the backpressure policy.
+    pub fn policy(&self) -> BackpressurePolicy {
+        self.policy
+    }
+
+    /// Returns drop counters.
+    pub fn stats(&self) -> QueueStats {
+        QueueStats {
+            dropped_oldest: self.dropped_oldest.load(Ordering::Relaxed),
+            dropped_newest: self.dropped_newest.load(Ordering::Relaxed),
+            blocked_pushes: self.blocked_pushes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Counters describing how often backpressure kicked in.
+#[derive(Debug, Clone, Default)]
+pub struct QueueStats {
+    /// Queued frames discarded to make room (`DropOldest`)
+    pub dropped_oldest: u64,
+    /// Incoming frames discarded (`DropNewest`)
+    pub dropped_newest: u64,
+    /// Pushes that had to wait (`Block`)
+    pub blocked_pushes: u64,
+}
+
+impl QueueStats {
+    /// Total frames lost to backpressure.
+    pub fn total_dropped(&self) -> u64 {
+        self.dropped_oldest + self.dropped_newest
+    }
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    fn drain<T>(queue: &FrameQueue<T>) -> Vec<T> {
+        std::iter::from_fn(|| queue.try_pop()).collect()
+    }
+
+    #[test]
+    fn test_drop_oldest() {
+        let queue = FrameQueue::new(3, BackpressurePolicy::DropOldest);
+        for i in 1..=5 {
+            assert!(queue.push(i));
+        }
+
+        assert_eq!(drain(&queue), vec![3, 4, 5]);
+        assert_eq!(queue.stats().dropped_oldest, 2);
+        assert_eq!(queue.stats().total_dropped(), 2);
+    }
+
+    #[test]
+    fn test_drop_newest() {
+        let queue = FrameQueue::new(3, BackpressurePolicy::DropNewest);
+        for i in 1..=5 {
+            queue.push(i);
+        }
+
+        assert_eq!(drain(&queue), vec![1, 2, 3]);
+        assert_eq!(queue.stats().dropped_newest, 2);
+    }
+
+    #[test]
+    fn test_block_waits_for_room() {
+        let queue = Arc::new(FrameQueue::new(1, BackpressurePolicy::Block));
+        queue.push(1);
+
+        let producer = {
+            let queue = Arc::clone(&queue);
+            thread::spawn(move || queue.push(2))
+        };
+
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(queue.pop(), Some(1));
+        assert!(producer.join().unwrap());
+        assert_eq!(queue.pop(), Some(2));
+        assert_eq!(queue.stats().blocked_pushes, 1);
+        assert_eq!(queue.stats().total_dropped(), 0);
+    }
+
+    #[test]
+    fn test_close_drains_then_ends() {
+        let queue = FrameQueue::new(4, BackpressurePolicy::DropOldest);
+        queue.push(1);
+        queue.close();
+
+        assert!(!queue.push(2));
+        assert_eq!(queue.pop(), Some(1));
+        assert_eq!(queue.pop(), None);
+    }
+}
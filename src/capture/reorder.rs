@@ -0,0 +1,127 @@
+//! # Reorder Buffer
+//!
+//! Puts results from parallel workers back into capture order.
+//!
+//! ## Plain English
+//!
+//! With several compression workers, a small frame can finish before a
+//! big one that was captured earlier. If each worker pushed its frame
+//! straight into the replay buffer, the buffer would end up shuffled and
+//! saved clips would stutter backwards. Each frame gets a number as it
+//! leaves the queue; finished frames wait here until every frame before
+//! them is done, then go out in number order.
+
+use std::collections::BTreeMap;
+
+/// Releases numbered items strictly in sequence order.
+#[derive(Debug)]
+pub struct ReorderBuffer<T> {
+    /// Sequence number of the next item to release
+    next_sequence: u64,
+
+    /// Finished items waiting for an earlier one (`None` = failed, skip)
+    pending: BTreeMap<u64, Option<T>>,
+}
+
+impl<T> ReorderBuffer<T> {
+    /// Creates an empty buffer expecting sequence 0 first.
+    pub fn new() -> Self {
+        Self {
+            next_sequence: 0,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Records the result for `sequence` and returns every item that is
+    /// now ready, in order.
+    ///
+    /// Pass `None` for a frame that failed, so later frames aren't held
+    /// back waiting for it.
+    pub fn insert(&mut self, sequence: u64, item: Option<T>) -> Vec<T> {
+        if sequence < self.next_sequence {
+            log::warn!("Dropping late frame {} (expected {})", sequence, self.next_sequence);
+            return Vec::new();
+        }
+        self.pending.insert(sequence, item);
+
+        let mut ready = Vec::new();
+        while let Some(item) = self.pending.remove(&self.next_sequence) {
+            ready.extend(item);
+            self.next_sequence += 1;
+        }
+        ready
+    }
+
+    /// Returns how many finished items are waiting for an earlier one.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+impl<T> Default for ReorderBuffer<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_releases_in_order() {
+        let mut reorder = ReorderBuffer::new();
+        assert!(reorder.insert(2, Some('c')).is_empty());
+        assert!(reorder.insert(1, None).is_empty());
+        assert_eq!(reorder.pending(), 2);
+
+        // 0 unblocks 1 (failed, skipped) and 2
+        assert_eq!(reorder.insert(0, Some('a')), vec!['a', 'c']);
+        assert_eq!(reorder.pending(), 0);
+        assert!(reorder.insert(0, Some('x')).is_empty());
+    }
+
+    #[test]
+    fn test_parallel_workers_stay_ordered() {
+        let reorder = Arc::new(Mutex::new(ReorderBuffer::new()));
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let next = Arc::new(std::sync::atomic::AtomicU64::new(0));
+
+        let workers: Vec<_> = (0..4u64)
+            .map(|worker| {
+                let (reorder, output, next) =
+                    (Arc::clone(&reorder), Arc::clone(&output), Arc::clone(&next));
+                thread::spawn(move || {
+                    // xorshift, seeded per worker, for uneven "compression" times
+                    let mut state = 0x9E37_79B9_7F4A_7C15u64 ^ (worker + 1);
+                    loop {
+                        let sequence = next.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                        if sequence >= 200 {
+                            break;
+                        }
+                        state ^= state << 13;
+                        state ^= state >> 7;
+                        state ^= state << 17;
+                        thread::sleep(Duration::from_micros(state % 500));
+
+                        let mut reorder = reorder.lock();
+                        output.lock().extend(reorder.insert(sequence, Some(sequence)));
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        assert_eq!(*output.lock(), (0..200).collect::<Vec<_>>());
+    }
+}
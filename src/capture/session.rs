@@ -0,0 +1,103 @@
+//! # Session Limit
+//!
+//! Stops a recording session after a maximum length.
+//!
+//! ## Plain English
+//!
+//! A replay buffer left running all night keeps the headset busy and
+//! warm. The session timer starts when capture is turned on; once the
+//! limit passes, capture turns itself off. A limit of zero means no limit.
+
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+/// Tracks how long the current capture session has been running.
+#[derive(Debug, Default)]
+pub struct SessionTimer {
+    /// Longest allowed session (None = unlimited)
+    limit: Option<Duration>,
+
+    /// When the current session started (None = not running)
+    started: Mutex<Option<Instant>>,
+}
+
+impl SessionTimer {
+    /// Creates a timer; `max_seconds` of 0 means unlimited.
+    pub fn new(max_seconds: u32) -> Self {
+        Self::with_limit((max_seconds > 0).then(|| Duration::from_secs(max_seconds as u64)))
+    }
+
+    /// Creates a timer with an exact limit (None = unlimited).
+    pub fn with_limit(limit: Option<Duration>) -> Self {
+        Self {
+            limit,
+            started: Mutex::new(None),
+        }
+    }
+
+    /// Starts (or restarts) the session clock.
+    pub fn start(&self) {
+        *self.started.lock() = Some(Instant::now());
+    }
+
+    /// Stops the session clock.
+    pub fn stop(&self) {
+        *self.started.lock() = None;
+    }
+
+    /// Returns the session limit, if any.
+    pub fn limit(&self) -> Option<Duration> {
+        self.limit
+    }
+
+    /// Returns how long the running session has left.
+    ///
+    /// `None` if there is no limit or no session running.
+    pub fn remaining(&self) -> Option<Duration> {
+        let limit = self.limit?;
+        let started = (*self.started.lock())?;
+        Some(limit.saturating_sub(started.elapsed()))
+    }
+
+    /// Returns true if a session is running and has used up its time.
+    pub fn is_expired(&self) -> bool {
+        self.remaining() == Some(Duration::ZERO)
+    }
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unlimited_session() {
+        let timer = SessionTimer::new(0);
+        timer.start();
+        assert_eq!(timer.remaining(), None);
+        assert!(!timer.is_expired());
+    }
+
+    #[test]
+    fn test_session_countdown() {
+        let timer = SessionTimer::new(60);
+        assert_eq!(timer.remaining(), None);
+
+        timer.start();
+        let remaining = timer.remaining().unwrap();
+        assert!(remaining > Duration::from_secs(59));
+        assert!(!timer.is_expired());
+
+        timer.stop();
+        assert_eq!(timer.remaining(), None);
+
+        let used_up = SessionTimer::with_limit(Some(Duration::ZERO));
+        assert!(!used_up.is_expired());
+        used_up.start();
+        assert!(used_up.is_expired());
+    }
+}
@@ -0,0 +1,104 @@
+//! # Frame Sources
+//!
+//! Something that produces frames (and controller input) one at a time.
+//!
+//! ## Plain English
+//!
+//! On the headset, frames arrive from the VR compositor. In tests we
+//! want the same app logic driven by a script: "here are 20 frames, and
+//! the save combo is pressed on frame 12". A `FrameSource` is that
+//! script; [`crate::QuestShadowplay::run_with_source`] plays it.
+
+use super::CapturedFrame;
+use crate::input::InputState;
+
+/// Produces captured frames for the app to consume.
+pub trait FrameSource {
+    /// Returns the next frame, or `None` when the source is exhausted.
+    fn next_frame(&mut self) -> Option<CapturedFrame>;
+
+    /// Returns the controller state that goes with the frame last
+    /// returned, if it changed.
+    fn input_state(&self) -> Option<InputState> {
+        None
+    }
+}
+
+/// A frame source that plays back a fixed list of frames.
+#[derive(Debug, Default)]
+pub struct VecFrameSource {
+    /// Frames with the input state to apply alongside each
+    frames: Vec<(CapturedFrame, Option<InputState>)>,
+
+    /// Index of the next frame to return
+    position: usize,
+}
+
+impl VecFrameSource {
+    /// Creates a source that yields `frames` in order.
+    pub fn new(frames: Vec<CapturedFrame>) -> Self {
+        Self {
+            frames: frames.into_iter().map(|frame| (frame, None)).collect(),
+            position: 0,
+        }
+    }
+
+    /// Applies `state` when the frame at `index` is delivered.
+    ///
+    /// The state sticks until another frame sets a new one, like a
+    /// button that stays held.
+    pub fn with_input_at(mut self, index: usize, state: InputState) -> Self {
+        if let Some((_, input)) = self.frames.get_mut(index) {
+            *input = Some(state);
+        }
+        self
+    }
+
+    /// Returns how many frames are left.
+    pub fn remaining(&self) -> usize {
+        self.frames.len() - self.position
+    }
+}
+
+impl FrameSource for VecFrameSource {
+    fn next_frame(&mut self) -> Option<CapturedFrame> {
+        let (frame, _) = self.frames.get(self.position)?;
+        self.position += 1;
+        Some(frame.clone())
+    }
+
+    fn input_state(&self) -> Option<InputState> {
+        let index = self.position.checked_sub(1)?;
+        self.frames.get(index).and_then(|(_, input)| input.clone())
+    }
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vec_source_order() {
+        let frames = (0..3)
+            .map(|i| CapturedFrame::with_timestamp(vec![0u8; 4], 0, 1, 1, i))
+            .collect();
+        let pressed = InputState {
+            left_trigger: 1.0,
+            ..InputState::default()
+        };
+        let mut source = VecFrameSource::new(frames).with_input_at(1, pressed);
+
+        assert_eq!(source.next_frame().unwrap().timestamp_ns, 0);
+        assert!(source.input_state().is_none());
+        assert_eq!(source.next_frame().unwrap().timestamp_ns, 1);
+        assert_eq!(source.input_state().unwrap().left_trigger, 1.0);
+        assert_eq!(source.remaining(), 1);
+
+        source.next_frame();
+        assert!(source.next_frame().is_none());
+    }
+}
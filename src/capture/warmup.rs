@@ -0,0 +1,58 @@
+//! # Warmup Discard
+//!
+//! Drops the first few frames after capture starts.
+//!
+//! ## Plain English
+//!
+//! The first frames after the compositor hands us its buffers are often
+//! black or half-drawn. Rather than saving them into a clip, we throw a
+//! fixed number away each time capture is turned on.
+
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+/// Counts down the frames to discard after each (re)start.
+#[derive(Debug, Default)]
+pub struct WarmupFilter {
+    /// Frames to discard after each restart
+    frames: u32,
+
+    /// Frames still to discard
+    remaining: AtomicU32,
+
+    /// Frames discarded so far
+    discarded: AtomicU64,
+}
+
+impl WarmupFilter {
+    /// Creates a filter that discards `frames` frames, starting now.
+    pub fn new(frames: u32) -> Self {
+        Self {
+            frames,
+            remaining: AtomicU32::new(frames),
+            discarded: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns true if this frame falls in the warmup window and should
+    /// be dropped.
+    pub fn discard(&self) -> bool {
+        let in_warmup = self
+            .remaining
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+            .is_ok();
+        if in_warmup {
+            self.discarded.fetch_add(1, Ordering::Relaxed);
+        }
+        in_warmup
+    }
+
+    /// Starts a new warmup window, e.g. when capture is re-enabled.
+    pub fn restart(&self) {
+        self.remaining.store(self.frames, Ordering::Relaxed);
+    }
+
+    /// Returns the number of frames discarded so far.
+    pub fn discarded(&self) -> u64 {
+        self.discarded.load(Ordering::Relaxed)
+    }
+}
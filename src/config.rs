@@ -8,6 +8,18 @@
 //! which buttons trigger saves, and where to save files.
 
 use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::ops::{RangeBounds, RangeInclusive};
+use std::str::FromStr;
+
+use image::imageops::FilterType;
+use serde::{Deserialize, Serialize};
+
+use crate::buffer::CaptureBuffer;
+use crate::capture::{AlphaHandling, BackpressurePolicy, ColorDepth, LayerSelect};
+use crate::encoder::StereoMode;
+use crate::input::MIN_PRESS_THRESHOLD;
 
 // ============================================
 // TRIGGER BUTTON OPTIONS
@@ -17,7 +29,8 @@ use std::fmt;
 ///
 /// We use button combinations (not single buttons) to prevent
 /// accidental saves during gameplay.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum TriggerButton {
     /// Hold left grip + left trigger together
     LeftGripAndTrigger,
@@ -27,6 +40,9 @@ pub enum TriggerButton {
 
     /// Press both grip buttons simultaneously
     BothGrips,
+
+    /// Hold every listed control together (two or more)
+    Custom(Vec<ButtonSpec>),
 }
 
 impl Default for TriggerButton {
@@ -35,12 +51,513 @@ impl Default for TriggerButton {
     }
 }
 
+impl TriggerButton {
+    /// Creates a custom combo of `controls`, each at the configured
+    /// press threshold.
+    pub fn custom(controls: &[ControllerAxis]) -> Self {
+        Self::Custom(controls.iter().copied().map(ButtonSpec::new).collect())
+    }
+
+    /// Returns the controls the combo holds, in a fixed order, so a
+    /// preset and the equivalent custom combo compare equal.
+    pub fn controls(&self) -> Vec<ControllerAxis> {
+        let mut controls = match self {
+            Self::LeftGripAndTrigger => vec![ControllerAxis::LeftGrip, ControllerAxis::LeftTrigger],
+            Self::RightGripAndTrigger => {
+                vec![ControllerAxis::RightGrip, ControllerAxis::RightTrigger]
+            }
+            Self::BothGrips => vec![ControllerAxis::LeftGrip, ControllerAxis::RightGrip],
+            Self::Custom(specs) => specs.iter().map(|spec| spec.control).collect(),
+        };
+        controls.sort_by_key(|control| *control as u8);
+        controls
+    }
+
+    /// Checks that the binding is usable.
+    ///
+    /// A custom binding needs two or more different controls, since one
+    /// control would be a single-button trigger, and its thresholds
+    /// must be between 0.3 and 1.0.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let Self::Custom(specs) = self else {
+            return Ok(());
+        };
+        if specs.len() < 2 {
+            return Err(ConfigError::InvalidTriggerSpec(self.to_string()));
+        }
+        if let Some(pair) = self.controls().windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(ConfigError::InvalidTriggerButton(pair[0]));
+        }
+        match specs.iter().find(|spec| {
+            spec.threshold
+                .is_some_and(|threshold| !(MIN_PRESS_THRESHOLD..=1.0).contains(&threshold))
+        }) {
+            Some(spec) => Err(ConfigError::InvalidTriggerSpec(spec.to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for TriggerButton {
+    /// Writes the combo the way [`TriggerButton::from_str`] reads it.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LeftGripAndTrigger => write!(f, "left_grip_and_trigger"),
+            Self::RightGripAndTrigger => write!(f, "right_grip_and_trigger"),
+            Self::BothGrips => write!(f, "both_grips"),
+            Self::Custom(specs) => {
+                let specs: Vec<String> = specs.iter().map(ToString::to_string).collect();
+                write!(f, "{}", specs.join("+"))
+            }
+        }
+    }
+}
+
+impl FromStr for TriggerButton {
+    type Err = ConfigError;
+
+    /// Parses a combo written as controls joined by `+`, like
+    /// `"left_grip+right_trigger"` or `"right_grip+a+b"`, or a preset
+    /// name like `"both_grips"`.
+    fn from_str(description: &str) -> Result<Self, Self::Err> {
+        let description = description.trim();
+        match description {
+            "left_grip_and_trigger" => return Ok(Self::LeftGripAndTrigger),
+            "right_grip_and_trigger" => return Ok(Self::RightGripAndTrigger),
+            "both_grips" => return Ok(Self::BothGrips),
+            _ => {}
+        }
+
+        let specs = description
+            .split('+')
+            .map(str::parse)
+            .collect::<Result<Vec<ButtonSpec>, _>>()
+            .map_err(|_| ConfigError::InvalidTriggerSpec(description.to_string()))?;
+        let button = Self::Custom(specs);
+        button.validate()?;
+        Ok(button)
+    }
+}
+
+/// One control of a custom trigger combo, written `right_trigger`, or
+/// `right_trigger@0.5` with a threshold of its own.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ButtonSpec {
+    /// Control that must be held
+    pub control: ControllerAxis,
+
+    /// How far it must be pressed, instead of the configured press
+    /// threshold (face buttons are simply on or off)
+    pub threshold: Option<f32>,
+}
+
+impl ButtonSpec {
+    /// Creates a spec for `control` at the configured press threshold.
+    pub fn new(control: ControllerAxis) -> Self {
+        Self {
+            control,
+            threshold: None,
+        }
+    }
+
+    /// Sets how far the control must be pressed (0.3-1.0).
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = Some(threshold);
+        self
+    }
+}
+
+impl fmt::Display for ButtonSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.threshold {
+            Some(threshold) => write!(f, "{}@{}", self.control, threshold),
+            None => write!(f, "{}", self.control),
+        }
+    }
+}
+
+impl FromStr for ButtonSpec {
+    type Err = ConfigError;
+
+    /// Parses `"left_grip"` or `"left_grip@0.5"`.
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let invalid = || ConfigError::InvalidTriggerSpec(spec.to_string());
+        match spec.trim().split_once('@') {
+            Some((control, threshold)) => Ok(Self::new(control.trim().parse()?)
+                .with_threshold(threshold.trim().parse().map_err(|_| invalid())?)),
+            None => Ok(Self::new(spec.trim().parse()?)),
+        }
+    }
+}
+
+impl TryFrom<String> for ButtonSpec {
+    type Error = ConfigError;
+
+    fn try_from(spec: String) -> Result<Self, Self::Error> {
+        spec.parse()
+    }
+}
+
+impl From<ButtonSpec> for String {
+    fn from(spec: ButtonSpec) -> Self {
+        spec.to_string()
+    }
+}
+
+/// A control that can be part of a custom trigger combo.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ControllerAxis {
+    /// Left grip button
+    LeftGrip,
+
+    /// Left index trigger
+    LeftTrigger,
+
+    /// Right grip button
+    RightGrip,
+
+    /// Right index trigger
+    RightTrigger,
+
+    /// Right A button
+    A,
+
+    /// Right B button
+    B,
+
+    /// Left X button
+    X,
+
+    /// Left Y button
+    Y,
+}
+
+/// How the save trigger is pressed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerMode {
+    /// Press (or hold) the trigger button combo
+    #[default]
+    Combo,
+
+    /// Tap one control twice within `window_ms`, like a double-click
+    DoubleTap {
+        /// Control that is tapped
+        button: ControllerAxis,
+
+        /// Longest gap between the two taps, in milliseconds
+        window_ms: u32,
+    },
+}
+
+impl FromStr for ControllerAxis {
+    type Err = ConfigError;
+
+    /// Parses a control name like `"left_grip"`, `"right_trigger"` or
+    /// `"a"`.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "left_grip" => Ok(Self::LeftGrip),
+            "left_trigger" => Ok(Self::LeftTrigger),
+            "right_grip" => Ok(Self::RightGrip),
+            "right_trigger" => Ok(Self::RightTrigger),
+            "a" => Ok(Self::A),
+            "b" => Ok(Self::B),
+            "x" => Ok(Self::X),
+            "y" => Ok(Self::Y),
+            _ => Err(ConfigError::InvalidTriggerSpec(name.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for ControllerAxis {
+    /// Writes the name [`ControllerAxis::from_str`] reads.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::LeftGrip => "left_grip",
+            Self::LeftTrigger => "left_trigger",
+            Self::RightGrip => "right_grip",
+            Self::RightTrigger => "right_trigger",
+            Self::A => "a",
+            Self::B => "b",
+            Self::X => "x",
+            Self::Y => "y",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Which button marks an interesting moment.
+///
+/// Marks inside a saved clip become subtitles and chapters on export.
+/// Off by default, since these buttons are also used by games.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarkButton {
+    /// No mark button
+    #[default]
+    Disabled,
+
+    /// Left X button
+    LeftX,
+
+    /// Left Y button
+    LeftY,
+
+    /// Right A button
+    RightA,
+
+    /// Right B button
+    RightB,
+}
+
+/// Something besides saving that the controller can do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputAction {
+    /// Stop or resume adding frames to the buffers
+    Pause,
+
+    /// Throw away everything buffered so far
+    ClearBuffer,
+
+    /// Mark the moment for chapters/subtitles
+    Mark,
+}
+
+/// What has to be pressed for an [`InputAction`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionBinding {
+    /// A combo of controls, like the save trigger
+    Combo(TriggerButton),
+
+    /// A single face button
+    Button(MarkButton),
+}
+
+impl ActionBinding {
+    /// Returns true if both bindings would fire from the same press.
+    ///
+    /// A disabled button never collides.
+    pub fn collides_with(&self, other: &ActionBinding) -> bool {
+        match (self, other) {
+            (Self::Combo(a), Self::Combo(b)) => a.controls() == b.controls(),
+            (Self::Button(a), Self::Button(b)) => a == b && *a != MarkButton::Disabled,
+            _ => false,
+        }
+    }
+}
+
+// ============================================
+// POST-SAVE BEHAVIOR
+// ============================================
+
+/// What happens to recording once a clip has been saved.
+///
+/// | Behavior        | After a save                   | Memory                       |
+/// |-----------------|--------------------------------|------------------------------|
+/// | `KeepRecording` | buffer keeps rolling           | stays full (normal DVR use)  |
+/// | `ClearBuffer`   | buffer emptied, keeps rolling  | freed, refills over duration |
+/// | `PauseUntilAck` | no new frames until resumed    | stays at saved size, frozen  |
+///
+/// `ClearBuffer` keeps two saves in a row from sharing footage.
+/// `PauseUntilAck` keeps the moment just saved in the buffer, but
+/// anything that happens while paused is never captured.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PostSaveBehavior {
+    /// Carry on recording as if nothing happened
+    #[default]
+    KeepRecording,
+
+    /// Empty the saved buffer, then carry on recording
+    ClearBuffer,
+
+    /// Stop taking frames until the user acknowledges the save
+    PauseUntilAck,
+}
+
+impl PostSaveBehavior {
+    /// Applies the behavior to the buffer a clip was just saved from.
+    ///
+    /// Returns true if capture should pause until acknowledged.
+    pub fn after_save(&self, buffer: &CaptureBuffer) -> bool {
+        match self {
+            Self::KeepRecording => false,
+            Self::ClearBuffer => {
+                buffer.clear();
+                log::info!("Buffer cleared after save");
+                false
+            }
+            Self::PauseUntilAck => {
+                log::info!("Recording paused after save");
+                true
+            }
+        }
+    }
+}
+
+// ============================================
+// ROTATION
+// ============================================
+
+/// How far clockwise exported video should be turned for viewing.
+///
+/// ## Plain English
+///
+/// When the headset is mirrored to a sideways phone or a rotated
+/// display, captured frames come out turned. Rather than rotating every
+/// pixel, the MP4 gets a note in its header telling players which way
+/// is up, so the picture is fixed without re-encoding.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rotation {
+    /// Frames are upright
+    #[default]
+    None,
+
+    /// Turn a quarter clockwise
+    Rot90,
+
+    /// Turn upside down
+    Rot180,
+
+    /// Turn a quarter counter-clockwise
+    Rot270,
+}
+
+impl Rotation {
+    /// Returns the clockwise angle in degrees.
+    pub fn degrees(&self) -> u32 {
+        match self {
+            Self::None => 0,
+            Self::Rot90 => 90,
+            Self::Rot180 => 180,
+            Self::Rot270 => 270,
+        }
+    }
+
+    /// Returns the MP4 `tkhd` display matrix `[a, b, u, c, d, v, x, y, w]`.
+    ///
+    /// `a`-`d`, `x` and `y` are 16.16 fixed point; `u`, `v` and `w` are
+    /// 2.30. Only the rotation part is set; players place the turned
+    /// picture themselves.
+    pub fn display_matrix(&self) -> [i32; 9] {
+        const ONE: i32 = 1 << 16;
+        const W: i32 = 1 << 30;
+        let (cos, sin) = match self {
+            Self::None => (ONE, 0),
+            Self::Rot90 => (0, ONE),
+            Self::Rot180 => (-ONE, 0),
+            Self::Rot270 => (0, -ONE),
+        };
+        [cos, sin, 0, -sin, cos, 0, 0, 0, W]
+    }
+
+    /// Returns the angle for ffmpeg's `-display_rotation`, which writes
+    /// the display matrix from a counter-clockwise angle.
+    ///
+    /// Read back from [`Rotation::display_matrix`], so the two agree.
+    pub fn display_rotation(&self) -> f64 {
+        let [cos, sin, ..] = self.display_matrix();
+        f64::from(-sin).atan2(f64::from(cos)).to_degrees()
+    }
+}
+
+// ============================================
+// SCALE FILTER
+// ============================================
+
+/// How pictures are resampled when they are scaled down.
+///
+/// ## Plain English
+///
+/// Shrinking a picture means working out each new pixel from several
+/// old ones. Copying the nearest old pixel is quickest but leaves jagged
+/// edges and shimmering fine detail; blending the neighbors looks
+/// smoother but takes longer. One setting covers every place frames are
+/// shrunk, so proxies, GIFs and exports all look alike.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScaleFilter {
+    /// Copy the nearest pixel: fastest, jagged edges
+    Nearest,
+
+    /// Blend the nearest pixels linearly: a fair balance
+    #[default]
+    Triangle,
+
+    /// Weigh three pixels each way: sharpest, slowest
+    Lanczos3,
+}
+
+impl ScaleFilter {
+    /// Returns the matching `image` resampling filter.
+    pub fn filter_type(&self) -> FilterType {
+        match self {
+            Self::Nearest => FilterType::Nearest,
+            Self::Triangle => FilterType::Triangle,
+            Self::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+// ============================================
+// RECORDING PROFILES
+// ============================================
+
+/// An extra named replay buffer kept alongside the main one.
+///
+/// ## Plain English
+///
+/// Lets you keep, say, a short sharp buffer for highlights and a long
+/// blurry one for context at the same time. Each profile gets its own
+/// buffer length and JPEG quality, and a save can pick which to use.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordingProfile {
+    /// Name used to pick the profile when saving (letters, digits, `-`, `_`)
+    pub name: String,
+
+    /// How many seconds of footage this profile keeps (5-60)
+    pub buffer_duration_seconds: f32,
+
+    /// JPEG quality for this profile's frames (0-100)
+    pub jpeg_quality: u8,
+}
+
+impl RecordingProfile {
+    /// Creates a profile.
+    pub fn new(name: impl Into<String>, buffer_duration_seconds: f32, jpeg_quality: u8) -> Self {
+        Self {
+            name: name.into(),
+            buffer_duration_seconds,
+            jpeg_quality,
+        }
+    }
+
+    /// Returns true if the name is safe to use in a clip filename.
+    pub fn has_valid_name(&self) -> bool {
+        !self.name.is_empty()
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    }
+}
+
 // ============================================
 // MAIN CONFIGURATION
 // ============================================
 
 /// All configuration options for Quest Shadowplay.
-#[derive(Clone, Debug)]
+///
+/// Can be read from and written to a TOML file; settings missing from
+/// the file keep their defaults.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     // ----------------------------------------
     // BUFFER SETTINGS
@@ -51,62 +568,284 @@ pub struct Config {
     /// Target frames per second to capture (72, 90, or 120)
     pub target_fps: u32,
 
+    /// Most bytes of compressed frames the main buffer may hold; busy
+    /// scenes then shorten the buffer instead of using more memory
+    /// (0 = limited by duration only)
+    pub max_buffer_bytes: usize,
+
     // ----------------------------------------
     // INPUT SETTINGS
     // ----------------------------------------
     /// Which button(s) trigger a save
     pub trigger_button: TriggerButton,
 
+    /// More combos that also save, e.g. one per hand (empty = only
+    /// `trigger_button`)
+    pub extra_trigger_buttons: Vec<TriggerButton>,
+
+    /// Press the combo, or double-tap a single control, to save
+    pub trigger_mode: TriggerMode,
+
+    /// How far (0.3-1.0) the trigger combo must be pressed to save;
+    /// lower it for triggers that no longer press all the way
+    pub trigger_press_threshold: f32,
+
+    /// How far (0.0-1.0) a held combo must be let go before it can save
+    /// again; below the press threshold so a wavering trigger can't
+    /// double-save
+    pub trigger_release_threshold: f32,
+
+    /// How long in milliseconds the combo must be held before it saves,
+    /// so a brief accidental press does nothing (0 = save on press)
+    pub trigger_hold_ms: u32,
+
+    /// Which button marks a moment for chapters/subtitles
+    pub mark_button: MarkButton,
+
+    /// Bindings for pause, clear and other actions besides saving; no
+    /// two actions (or an action and the save trigger) may share one
+    pub action_bindings: Vec<(InputAction, ActionBinding)>,
+
     /// Minimum time between saves in milliseconds
     pub save_cooldown_ms: u32,
 
+    /// Extra save triggers within this many milliseconds of a save are
+    /// folded into it, extending the clip to cover them, instead of
+    /// producing near-duplicate clips. The encode starts once this
+    /// window has passed.
+    pub save_coalesce_ms: u32,
+
+    /// Shift the saved window this many milliseconds before the save
+    /// press, to make up for reaction time (0 = save up to "now")
+    pub save_offset_ms: u32,
+
+    /// Keep recording, clear the buffer, or pause after a save
+    /// (see `PostSaveBehavior` for the memory trade-offs)
+    pub post_save: PostSaveBehavior,
+
     // ----------------------------------------
     // OUTPUT SETTINGS
     // ----------------------------------------
     /// Directory where clips are saved
     pub output_directory: String,
 
+    /// Directory for exports such as MP4s, e.g. a synced folder, while
+    /// raw clips stay in `output_directory` (None = same as clips)
+    pub export_directory: Option<String>,
+
+    /// When a clip won't fit on the drive, delete the oldest
+    /// non-favorite clips to make room instead of failing the save
+    pub auto_cleanup: bool,
+
+    /// Save clips into one `YYYY-MM-DD` subfolder per day instead of
+    /// all in `output_directory`
+    pub organize_by_date: bool,
+
     /// Video encoding bitrate in bits per second
     pub video_bitrate: u32,
 
     /// JPEG quality for buffered frames (0-100)
     pub jpeg_quality: u8,
 
+    /// Largest compressed frame in bytes; bigger frames are re-compressed
+    /// at lower quality so one busy frame can't balloon memory (0 = no cap)
+    pub max_frame_bytes: usize,
+
+    /// Lowest JPEG quality the frame size cap may drop to (1-100)
+    pub min_capped_jpeg_quality: u8,
+
+    /// Pixel storage: full color, or RGB565 for less memory at the cost
+    /// of banding (see `ColorDepth`)
+    pub color_depth: ColorDepth,
+
+    /// How translucent pixels are flattened, since JPEG has no alpha
+    pub alpha_handling: AlphaHandling,
+
+    /// Which composition layers are recorded when the app submits more
+    /// than one: the base game layer, one chosen layer, or all blended
+    pub capture_layer: LayerSelect,
+
+    /// Extra buffers fed from the same capture, each with its own
+    /// duration and quality (empty = main buffer only)
+    pub profiles: Vec<RecordingProfile>,
+
+    /// Motion score (0.0-1.0) above which an exported frame starts a
+    /// new keyframe, so cuts land on scene changes (0 = interval only)
+    pub scene_cut_threshold: f32,
+
+    /// Most frames between keyframes in exported video, whatever the
+    /// motion; keeps clips seekable
+    pub max_keyframe_interval: u32,
+
+    /// Most seconds of video between keyframes, by presentation time
+    /// (0 = frame count only). Lower values make scrubbing and seeking
+    /// snappier but the file bigger
+    pub keyframe_interval_seconds: f32,
+
+    /// Also save a low-resolution proxy next to each clip, for quick
+    /// previews and scrubbing while the full clip is kept for export
+    pub generate_proxy: bool,
+
+    /// Width in pixels of proxy frames (height keeps the aspect ratio)
+    pub proxy_width: u32,
+
+    /// Widest a GIF export may be, in pixels (height keeps the aspect
+    /// ratio); GIF size grows fast with resolution
+    pub gif_max_width: u32,
+
+    /// Most frames per second kept in a GIF export; extra frames are
+    /// dropped, trading smoothness for file size
+    pub gif_max_fps: u32,
+
+    /// How frames are resampled wherever they are scaled down: proxies,
+    /// GIFs, reframed exports and desktop capture
+    pub scale_filter: ScaleFilter,
+
+    /// Turn exported MP4s for sideways or upside-down sources; stored as
+    /// header metadata, so no pixels are re-encoded
+    pub rotation: Rotation,
+
+    /// Export both eyes as one 3D video (packed side by side or top and
+    /// bottom, and tagged so 3D players recognize it) instead of flat
+    pub stereo_mode: StereoMode,
+
     // ----------------------------------------
     // PERFORMANCE SETTINGS
     // ----------------------------------------
     /// Skip frames if processing takes too long
     pub skip_on_lag: bool,
 
+    /// Raw frames that may wait for compression before backpressure applies
+    pub capture_queue_depth: usize,
+
+    /// What to drop when the compression queue is full
+    pub backpressure: BackpressurePolicy,
+
+    /// Frames to throw away after capture starts, while the swapchain
+    /// is still settling and frames may be black or partial
+    pub warmup_discard_frames: u32,
+
+    /// Score how much each frame differs from the last, for finding
+    /// highlights later (costs a little CPU per frame)
+    pub motion_metric: bool,
+
+    /// Pause capture while nothing on screen moves, to save battery and
+    /// heat; resumes on the first frame that moves (scores motion even
+    /// with `motion_metric` off)
+    pub auto_pause_on_idle: bool,
+
+    /// Seconds without motion before an idle pause
+    pub idle_pause_seconds: u32,
+
+    /// Motion score (0.0-1.0) at or below which a frame counts as still
+    pub idle_motion_floor: f32,
+
+    /// Stop capturing after this many seconds of continuous recording,
+    /// to save battery if the recorder is forgotten (0 = unlimited)
+    pub max_session_seconds: u32,
+
+    /// Stop recording once this many clips have been saved in a
+    /// session, for bounded capture tasks (0 = never)
+    pub auto_stop_after_clips: u32,
+
+    /// Threads that decode frames in parallel when a save writes a
+    /// proxy or frame folder (0 = one per CPU core)
+    pub decode_workers: usize,
+
+    /// Nice value for the background save/encode thread (-20 to 19).
+    /// Higher = lower priority, leaving CPU to the VR render thread.
+    pub encode_thread_nice: i32,
+
     // ----------------------------------------
     // FEEDBACK SETTINGS
     // ----------------------------------------
     /// Enable haptic (vibration) feedback on save
     pub haptic_feedback: bool,
+
+    // ----------------------------------------
+    // DIAGNOSTICS SETTINGS
+    // ----------------------------------------
+    /// Also write logs to a rotating `shadowplay.log` in the output
+    /// directory, for attaching to bug reports
+    pub log_to_file: bool,
 }
 
 impl Config {
     /// Creates configuration with default values.
     pub fn default() -> Self {
         Self {
-            // Buffer: 10 seconds at 90 FPS
+            // Buffer: 10 seconds at 90 FPS, however many bytes that takes
             buffer_duration_seconds: 10.0,
             target_fps: 90,
+            max_buffer_bytes: 0,
 
-            // Input: Left grip + trigger, 500ms cooldown
+            // Input: Left grip + trigger combo only, press at 90% and release at 70%,
+            // no hold, no other actions bound, 500ms cooldown, 1s coalesce,
+            // no offset, keep recording after
             trigger_button: TriggerButton::default(),
+            extra_trigger_buttons: Vec::new(),
+            trigger_mode: TriggerMode::Combo,
+            trigger_press_threshold: 0.9,
+            trigger_release_threshold: 0.7,
+            trigger_hold_ms: 0,
+            mark_button: MarkButton::Disabled,
+            action_bindings: Vec::new(),
             save_cooldown_ms: 500,
+            save_coalesce_ms: 1000,
+            save_offset_ms: 0,
+            post_save: PostSaveBehavior::KeepRecording,
 
-            // Output: Standard location, 20 Mbps, 80% JPEG quality
+            // Output: Standard location (exports too), one flat folder never
+            // cleaned up automatically, 20 Mbps, 80% JPEG quality with
+            // no frame size cap, base layer only,
+            // keyframe on big scene changes and at least every 2s (180 frames),
+            // no proxy (480px wide when enabled), GIFs up to 480px at 15 FPS,
+            // bilinear scaling, upright, flat
             output_directory: default_output_directory(),
+            export_directory: None,
+            auto_cleanup: false,
+            organize_by_date: false,
             video_bitrate: 20_000_000,
             jpeg_quality: 80,
+            max_frame_bytes: 0,
+            min_capped_jpeg_quality: 30,
+            color_depth: ColorDepth::Rgba8888,
+            alpha_handling: AlphaHandling::Discard,
+            capture_layer: LayerSelect::Base,
+            profiles: Vec::new(),
+            scene_cut_threshold: 0.3,
+            max_keyframe_interval: 180,
+            keyframe_interval_seconds: 2.0,
+            generate_proxy: false,
+            proxy_width: 480,
+            gif_max_width: 480,
+            gif_max_fps: 15,
+            scale_filter: ScaleFilter::Triangle,
+            rotation: Rotation::None,
+            stereo_mode: StereoMode::Mono,
 
-            // Performance: Skip on lag
+            // Performance: Skip on lag, keep the newest frames under load,
+            // drop startup frames, no idle pause (30s of near-stillness when
+            // enabled), no session or clip limit, decode on every core,
+            // encode at low priority
             skip_on_lag: true,
+            capture_queue_depth: 4,
+            backpressure: BackpressurePolicy::DropOldest,
+            warmup_discard_frames: 3,
+            motion_metric: false,
+            auto_pause_on_idle: false,
+            idle_pause_seconds: 30,
+            idle_motion_floor: 0.01,
+            max_session_seconds: 0,
+            auto_stop_after_clips: 0,
+            decode_workers: 0,
+            encode_thread_nice: 10,
 
             // Feedback: Haptics enabled
             haptic_feedback: true,
+
+            // Diagnostics: Console logging only
+            log_to_file: false,
         }
     }
 
@@ -114,42 +853,252 @@ impl Config {
     pub fn validate(&self) -> Vec<ConfigError> {
         let mut errors = Vec::new();
 
-        // Buffer duration
-        if self.buffer_duration_seconds < 5.0 {
-            errors.push(ConfigError::BufferTooShort(self.buffer_duration_seconds));
+        // Buffer duration (NaN would make a zero-frame buffer)
+        let buffer = self.buffer_duration_seconds;
+        if buffer.is_nan() || buffer < *BUFFER_SECONDS_RANGE.start() {
+            errors.push(ConfigError::BufferTooShort(buffer));
         }
-        if self.buffer_duration_seconds > 60.0 {
-            errors.push(ConfigError::BufferTooLong(self.buffer_duration_seconds));
+        if buffer > *BUFFER_SECONDS_RANGE.end() {
+            errors.push(ConfigError::BufferTooLong(buffer));
         }
 
         // FPS
-        if self.target_fps < 30 || self.target_fps > 144 {
+        if !FPS_RANGE.contains(&self.target_fps) {
             errors.push(ConfigError::InvalidFps(self.target_fps));
         }
 
+        // Trigger bindings
+        for button in std::iter::once(&self.trigger_button).chain(&self.extra_trigger_buttons) {
+            if let Err(e) = button.validate() {
+                errors.push(e);
+            }
+        }
+        if let TriggerMode::DoubleTap { window_ms: 0, .. } = self.trigger_mode {
+            errors.push(ConfigError::InvalidDoubleTapWindow(0));
+        }
+
+        // Action bindings: each press must mean exactly one thing
+        let saves: Vec<_> = std::iter::once(&self.trigger_button)
+            .chain(&self.extra_trigger_buttons)
+            .map(|button| ActionBinding::Combo(button.clone()))
+            .collect();
+        let mark = ActionBinding::Button(self.mark_button.clone());
+        for (i, (action, binding)) in self.action_bindings.iter().enumerate() {
+            let taken = saves.iter().any(|save| binding.collides_with(save))
+                || (*action != InputAction::Mark && binding.collides_with(&mark))
+                || self.action_bindings[..i].iter().any(|(_, b)| binding.collides_with(b));
+            if taken {
+                errors.push(ConfigError::ConflictingBinding(*action));
+            }
+        }
+
+        // Trigger thresholds: release must sit at or below press
+        let thresholds = 0.0..=1.0;
+        if !(MIN_PRESS_THRESHOLD..=1.0).contains(&self.trigger_press_threshold)
+            || !thresholds.contains(&self.trigger_release_threshold)
+            || self.trigger_release_threshold > self.trigger_press_threshold
+        {
+            errors.push(ConfigError::InvalidTriggerThresholds(
+                self.trigger_press_threshold,
+                self.trigger_release_threshold,
+            ));
+        }
+
+        // Save offset must leave some footage to save
+        if self.save_offset_ms as f32 >= self.buffer_duration_seconds * 1000.0 {
+            errors.push(ConfigError::SaveOffsetTooLong(self.save_offset_ms));
+        }
+
+        // Thread priority
+        if !(-20..=19).contains(&self.encode_thread_nice) {
+            errors.push(ConfigError::InvalidNice(self.encode_thread_nice));
+        }
+
+        // Recording profiles
+        for (i, profile) in self.profiles.iter().enumerate() {
+            if !profile.has_valid_name() {
+                errors.push(ConfigError::InvalidProfileName(profile.name.clone()));
+            }
+            if self.profiles[..i].iter().any(|p| p.name == profile.name) {
+                errors.push(ConfigError::DuplicateProfile(profile.name.clone()));
+            }
+            let buffer = profile.buffer_duration_seconds;
+            if buffer.is_nan() || buffer < *BUFFER_SECONDS_RANGE.start() {
+                errors.push(ConfigError::BufferTooShort(buffer));
+            }
+            if buffer > *BUFFER_SECONDS_RANGE.end() {
+                errors.push(ConfigError::BufferTooLong(buffer));
+            }
+        }
+
+        // JPEG quality
+        if !JPEG_QUALITY_RANGE.contains(&self.jpeg_quality) {
+            errors.push(ConfigError::InvalidJpegQuality(self.jpeg_quality));
+        }
+        if !JPEG_QUALITY_RANGE.contains(&self.min_capped_jpeg_quality) {
+            errors.push(ConfigError::InvalidJpegQuality(self.min_capped_jpeg_quality));
+        }
+
+        // Keyframe placement
+        if !(0.0..=1.0).contains(&self.scene_cut_threshold) {
+            errors.push(ConfigError::InvalidSceneCutThreshold(self.scene_cut_threshold));
+        }
+        if self.max_keyframe_interval == 0 {
+            errors.push(ConfigError::InvalidKeyframeInterval(self.max_keyframe_interval));
+        }
+        if !(self.keyframe_interval_seconds >= 0.0 && self.keyframe_interval_seconds.is_finite()) {
+            errors.push(ConfigError::InvalidKeyframeSeconds(self.keyframe_interval_seconds));
+        }
+
+        // Idle pause compares motion scores against the floor
+        if !(0.0..=1.0).contains(&self.idle_motion_floor) {
+            errors.push(ConfigError::InvalidIdleMotionFloor(self.idle_motion_floor));
+        }
+
+        // Proxy frames need some width to scale to
+        if self.generate_proxy && self.proxy_width == 0 {
+            errors.push(ConfigError::InvalidProxyWidth(self.proxy_width));
+        }
+
         // Bitrate
-        if self.video_bitrate < 1_000_000 {
+        if self.video_bitrate < *BITRATE_RANGE.start() {
             errors.push(ConfigError::BitrateTooLow(self.video_bitrate));
         }
-        if self.video_bitrate > 100_000_000 {
+        if self.video_bitrate > *BITRATE_RANGE.end() {
             errors.push(ConfigError::BitrateTooHigh(self.video_bitrate));
         }
 
         errors
     }
 
+    /// Validates configuration, failing with every error at once.
+    ///
+    /// For startup paths that should refuse a bad config outright.
+    pub fn validate_strict(&self) -> Result<(), Vec<ConfigError>> {
+        let errors = self.validate();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Snaps out-of-range settings to the nearest valid value, logging
+    /// each change.
+    ///
+    /// Covers the numeric settings [`Config::validate`] checks (values
+    /// that aren't numbers go to the minimum). Conflicting bindings and
+    /// bad profile names have no nearest value and are left alone.
+    pub fn clamped(mut self) -> Config {
+        // Buffer: never a zero-frame buffer
+        let (min_buffer, max_buffer) = BUFFER_SECONDS_RANGE.into_inner();
+        snap("buffer_duration_seconds", &mut self.buffer_duration_seconds, min_buffer, max_buffer);
+        let (min_fps, max_fps) = FPS_RANGE.into_inner();
+        snap("target_fps", &mut self.target_fps, min_fps, max_fps);
+        for profile in &mut self.profiles {
+            snap("profile buffer", &mut profile.buffer_duration_seconds, min_buffer, max_buffer);
+        }
+
+        // Input: release at or below press, offset inside the buffer
+        let min_press = MIN_PRESS_THRESHOLD;
+        snap("trigger_press_threshold", &mut self.trigger_press_threshold, min_press, 1.0);
+        let press = self.trigger_press_threshold;
+        snap("trigger_release_threshold", &mut self.trigger_release_threshold, 0.0, press);
+        if self.save_offset_ms as f32 >= self.buffer_duration_seconds * 1000.0 {
+            let offset = self.save_offset_ms;
+            log::warn!("Config: save_offset_ms {} is not inside the buffer, using 0", offset);
+            self.save_offset_ms = 0;
+        }
+        snap("encode_thread_nice", &mut self.encode_thread_nice, -20, 19);
+
+        // Output
+        let (min_bitrate, max_bitrate) = BITRATE_RANGE.into_inner();
+        snap("video_bitrate", &mut self.video_bitrate, min_bitrate, max_bitrate);
+        let (min_quality, max_quality) = JPEG_QUALITY_RANGE.into_inner();
+        snap("jpeg_quality", &mut self.jpeg_quality, min_quality, max_quality);
+        let min_capped = &mut self.min_capped_jpeg_quality;
+        snap("min_capped_jpeg_quality", min_capped, min_quality, max_quality);
+        snap("scene_cut_threshold", &mut self.scene_cut_threshold, 0.0, 1.0);
+        snap("max_keyframe_interval", &mut self.max_keyframe_interval, 1, u32::MAX);
+        snap("keyframe_interval_seconds", &mut self.keyframe_interval_seconds, 0.0, f32::MAX);
+        snap("idle_motion_floor", &mut self.idle_motion_floor, 0.0, 1.0);
+        if self.generate_proxy {
+            snap("proxy_width", &mut self.proxy_width, 1, u32::MAX);
+        }
+
+        self
+    }
+
+    /// Checks for settings that are valid on their own but work badly
+    /// together, e.g. a long buffer at high quality that needs more
+    /// memory than the headset can spare.
+    ///
+    /// Unlike [`Config::validate`], these don't stop the recorder; they
+    /// are cautions to show the user.
+    pub fn warnings(&self) -> Vec<ConfigWarning> {
+        let mut warnings = Vec::new();
+
+        // Memory for all buffers at once
+        let memory_mb = self.estimated_memory_mb();
+        if memory_mb > MEMORY_WARNING_MB {
+            warnings.push(ConfigWarning::HighMemory(memory_mb));
+        }
+
+        // Compression at high frame rates
+        if self.target_fps > 90 && self.jpeg_quality > 90 {
+            warnings.push(ConfigWarning::FpsTooHighForQuality(
+                self.target_fps,
+                self.jpeg_quality,
+            ));
+        }
+
+        // A clip shorter than the keyframe intervals has one keyframe
+        let frames = self.buffer_frame_count();
+        let timed = self.keyframe_interval_seconds > 0.0
+            && self.keyframe_interval_seconds < self.buffer_duration_seconds;
+        let counted = self.scene_cut_threshold == 0.0 && !timed;
+        if counted && self.max_keyframe_interval as usize > frames {
+            warnings.push(ConfigWarning::KeyframeIntervalOverBuffer(
+                self.max_keyframe_interval,
+                frames,
+            ));
+        }
+
+        warnings
+    }
+
+    /// Returns where exports go: `export_directory` if set, otherwise
+    /// the clips directory.
+    pub fn export_directory_or_default(&self) -> &str {
+        self.export_directory.as_deref().unwrap_or(&self.output_directory)
+    }
+
     /// Calculates the number of frames the buffer will hold.
     pub fn buffer_frame_count(&self) -> usize {
         (self.buffer_duration_seconds * self.target_fps as f32).ceil() as usize
     }
 
-    /// Estimates memory usage in megabytes.
+    /// Calculates the number of frames a profile's buffer will hold.
+    pub fn profile_frame_count(&self, profile: &RecordingProfile) -> usize {
+        (profile.buffer_duration_seconds * self.target_fps as f32).ceil() as usize
+    }
+
+    /// Estimates memory usage in megabytes, summed over the main buffer
+    /// and every recording profile.
     ///
-    /// Assumes ~100KB per compressed frame at quality 80.
+    /// Assumes ~100KB per compressed frame at quality 80, and never more
+    /// than the main buffer's byte limit.
     pub fn estimated_memory_mb(&self) -> f32 {
-        let frames = self.buffer_frame_count();
-        let bytes_per_frame = 100_000.0 * (self.jpeg_quality as f32 / 80.0);
-        (frames as f32 * bytes_per_frame) / (1024.0 * 1024.0)
+        let mut main = estimate_buffer_mb(self.buffer_frame_count(), self.jpeg_quality);
+        if self.max_buffer_bytes > 0 {
+            main = main.min(self.max_buffer_bytes as f32 / (1024.0 * 1024.0));
+        }
+        let profiles: f32 = self
+            .profiles
+            .iter()
+            .map(|p| estimate_buffer_mb(self.profile_frame_count(p), p.jpeg_quality))
+            .sum();
+        main + profiles
     }
 }
 
@@ -159,11 +1108,50 @@ impl Default for Config {
     }
 }
 
+/// Buffer lengths allowed, in seconds (also for recording profiles).
+pub const BUFFER_SECONDS_RANGE: RangeInclusive<f32> = 5.0..=60.0;
+
+/// Capture frame rates allowed.
+pub const FPS_RANGE: RangeInclusive<u32> = 30..=144;
+
+/// Video bitrates allowed, in bits per second.
+pub const BITRATE_RANGE: RangeInclusive<u32> = 1_000_000..=100_000_000;
+
+/// JPEG qualities allowed.
+pub const JPEG_QUALITY_RANGE: RangeInclusive<u8> = 1..=100;
+
+/// Estimated buffer memory in megabytes above which a config is flagged;
+/// leaves room for the game itself on an 8GB headset.
+const MEMORY_WARNING_MB: f32 = 400.0;
+
+/// Estimates the size of a buffer of compressed frames in megabytes.
+fn estimate_buffer_mb(frames: usize, jpeg_quality: u8) -> f32 {
+    let bytes_per_frame = 100_000.0 * (jpeg_quality as f32 / 80.0);
+    (frames as f32 * bytes_per_frame) / (1024.0 * 1024.0)
+}
+
+/// Moves `value` into `min..=max` (to `min` if it isn't comparable, like
+/// NaN), logging the change.
+fn snap<T: PartialOrd + Copy + fmt::Debug>(name: &str, value: &mut T, min: T, max: T) {
+    let snapped = if *value > max {
+        max
+    } else if *value >= min {
+        return;
+    } else {
+        min
+    };
+    log::warn!("Config: {} {:?} out of range, using {:?}", name, value, snapped);
+    *value = snapped;
+}
+
 /// Returns the default output directory based on platform.
 fn default_output_directory() -> String {
     #[cfg(target_os = "android")]
     {
-        "/sdcard/QuestShadowplay/".to_string()
+        crate::storage::android_output_directory()
+            .unwrap_or_else(|| std::path::PathBuf::from(crate::storage::ANDROID_DEFAULT_DIRECTORY))
+            .to_string_lossy()
+            .to_string()
     }
 
     #[cfg(not(target_os = "android"))]
@@ -175,6 +1163,250 @@ fn default_output_directory() -> String {
     }
 }
 
+// ============================================
+// CONFIG BUILDER
+// ============================================
+
+/// Builds a [`Config`] from the defaults, one setting at a time.
+///
+/// ```
+/// use quest_shadowplay::Config;
+///
+/// let config = Config::builder().fps(72).buffer_seconds(20.0).build();
+/// assert_eq!(config.target_fps, 72);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ConfigBuilder {
+    /// Config being built
+    config: Config,
+}
+
+impl ConfigBuilder {
+    /// Starts from the default config.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how many seconds of footage to keep (5-60).
+    pub fn buffer_seconds(mut self, seconds: f32) -> Self {
+        self.config.buffer_duration_seconds = seconds;
+        self
+    }
+
+    /// Sets the capture frame rate (30-144).
+    pub fn fps(mut self, fps: u32) -> Self {
+        self.config.target_fps = fps;
+        self
+    }
+
+    /// Sets the video bitrate in bits per second (1-100 Mbps).
+    pub fn bitrate(mut self, bitrate: u32) -> Self {
+        self.config.video_bitrate = bitrate;
+        self
+    }
+
+    /// Sets the JPEG quality of buffered frames (1-100).
+    pub fn jpeg_quality(mut self, quality: u8) -> Self {
+        self.config.jpeg_quality = quality;
+        self
+    }
+
+    /// Sets the button combo that saves a clip.
+    pub fn trigger_button(mut self, button: TriggerButton) -> Self {
+        self.config.trigger_button = button;
+        self
+    }
+
+    /// Sets the directory clips are saved to.
+    pub fn output_directory(mut self, directory: impl Into<String>) -> Self {
+        self.config.output_directory = directory.into();
+        self
+    }
+
+    /// Returns the config, with out-of-range values snapped into range
+    /// (see [`Config::clamped`]).
+    pub fn build(self) -> Config {
+        self.config.clamped()
+    }
+}
+
+impl Config {
+    /// Starts building a config from the defaults.
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::new()
+    }
+
+    /// Settings for headsets short on memory: a 5 second buffer at
+    /// 72 FPS and lower JPEG quality.
+    pub fn low_memory() -> Config {
+        Self::builder()
+            .buffer_seconds(5.0)
+            .fps(72)
+            .jpeg_quality(60)
+            .bitrate(10_000_000)
+            .build()
+    }
+
+    /// Settings for the sharpest clips: 90 FPS at JPEG quality 95 and
+    /// a 50 Mbps export, at roughly 20% more memory than the defaults.
+    pub fn high_quality() -> Config {
+        Self::builder()
+            .fps(90)
+            .jpeg_quality(95)
+            .bitrate(50_000_000)
+            .build()
+    }
+}
+
+// ============================================
+// CONFIG FILE
+// ============================================
+
+/// Name of the config file looked for at startup
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+
+impl Config {
+    /// Reads a config from a TOML file.
+    ///
+    /// Settings missing from the file keep their defaults, and a file
+    /// that doesn't exist gives the default config. The result isn't
+    /// validated; call [`Config::validate`] before using it.
+    pub fn load_from_path(path: &Path) -> Result<Config, ConfigError> {
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+
+        let text = fs::read_to_string(path)
+            .map_err(|e| ConfigError::FileAccess(format!("{:?}: {}", path, e)))?;
+        toml::from_str(&text).map_err(|e| ConfigError::FileFormat(format!("{:?}: {}", path, e)))
+    }
+
+    /// Writes the config to a TOML file, creating its directory if needed.
+    pub fn save_to_path(&self, path: &Path) -> Result<(), ConfigError> {
+        let text =
+            toml::to_string_pretty(self).map_err(|e| ConfigError::FileFormat(e.to_string()))?;
+        let access = |e: std::io::Error| ConfigError::FileAccess(format!("{:?}: {}", path, e));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(access)?;
+        }
+        fs::write(path, text).map_err(access)
+    }
+
+    /// Returns where the config file lives on this platform, if there
+    /// is somewhere to look.
+    ///
+    /// - Android: next to the clips, e.g. `/sdcard/QuestShadowplay/config.toml`
+    /// - macOS: `~/Library/Application Support/QuestShadowplay/config.toml`
+    /// - Windows: `%APPDATA%\QuestShadowplay\config.toml`
+    /// - Linux: `$XDG_CONFIG_HOME/quest-shadowplay/config.toml`, or under
+    ///   `~/.config` if that isn't set
+    pub fn default_path() -> Option<PathBuf> {
+        #[cfg(target_os = "android")]
+        {
+            Some(crate::storage::android_output_directory()?.join(CONFIG_FILE_NAME))
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            let home = std::env::var_os("HOME")?;
+            let support = PathBuf::from(home).join("Library/Application Support");
+            Some(support.join("QuestShadowplay").join(CONFIG_FILE_NAME))
+        }
+
+        #[cfg(windows)]
+        {
+            let app_data = std::env::var_os("APPDATA")?;
+            Some(PathBuf::from(app_data).join("QuestShadowplay").join(CONFIG_FILE_NAME))
+        }
+
+        #[cfg(not(any(target_os = "android", target_os = "macos", windows)))]
+        {
+            let xdg = std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from);
+            let config_home = match xdg.filter(|dir| dir.is_absolute()) {
+                Some(dir) => dir,
+                None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+            };
+            Some(config_home.join("quest-shadowplay").join(CONFIG_FILE_NAME))
+        }
+    }
+}
+
+// ============================================
+// ENVIRONMENT OVERRIDES
+// ============================================
+
+/// Overrides `buffer_duration_seconds`
+pub const ENV_BUFFER_SECONDS: &str = "QSP_BUFFER_SECONDS";
+
+/// Overrides `target_fps`
+pub const ENV_TARGET_FPS: &str = "QSP_TARGET_FPS";
+
+/// Overrides `video_bitrate`
+pub const ENV_BITRATE: &str = "QSP_BITRATE";
+
+/// Overrides `output_directory`
+pub const ENV_OUTPUT_DIR: &str = "QSP_OUTPUT_DIR";
+
+/// Overrides `jpeg_quality`
+pub const ENV_JPEG_QUALITY: &str = "QSP_JPEG_QUALITY";
+
+impl Config {
+    /// Reads the config file at [`Config::default_path`] (or starts from
+    /// the defaults if there is none), then applies environment variable
+    /// overrides (see [`Config::with_env_overrides`]).
+    pub fn from_env() -> Result<Config, ConfigError> {
+        let config = match Config::default_path() {
+            Some(path) => Config::load_from_path(&path)?,
+            None => Config::default(),
+        };
+        config.with_env_overrides()
+    }
+
+    /// Overrides settings from `QSP_BUFFER_SECONDS`, `QSP_TARGET_FPS`,
+    /// `QSP_BITRATE`, `QSP_OUTPUT_DIR` and `QSP_JPEG_QUALITY`, for CI
+    /// runs and deployments that can't edit the config file.
+    ///
+    /// Unset variables leave the setting alone; a value that doesn't
+    /// parse, or is outside the range [`Config::clamped`] allows, is an
+    /// error rather than being ignored or quietly changed. Settings not
+    /// named by a variable aren't checked.
+    pub fn with_env_overrides(self) -> Result<Config, ConfigError> {
+        self.with_overrides(|name| std::env::var(name).ok())
+    }
+
+    /// Applies overrides from `lookup`, which maps a variable name to
+    /// its value.
+    fn with_overrides(
+        mut self,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Config, ConfigError> {
+        let buffer = &mut self.buffer_duration_seconds;
+        override_from(&lookup, ENV_BUFFER_SECONDS, buffer, BUFFER_SECONDS_RANGE)?;
+        override_from(&lookup, ENV_TARGET_FPS, &mut self.target_fps, FPS_RANGE)?;
+        override_from(&lookup, ENV_BITRATE, &mut self.video_bitrate, BITRATE_RANGE)?;
+        override_from(&lookup, ENV_OUTPUT_DIR, &mut self.output_directory, ..)?;
+        override_from(&lookup, ENV_JPEG_QUALITY, &mut self.jpeg_quality, JPEG_QUALITY_RANGE)?;
+        Ok(self)
+    }
+}
+
+/// Replaces `field` with the parsed value of variable `name`, if set,
+/// as long as it is inside `range`.
+fn override_from<T: FromStr + PartialOrd + fmt::Debug>(
+    lookup: &impl Fn(&str) -> Option<String>,
+    name: &str,
+    field: &mut T,
+    range: impl RangeBounds<T>,
+) -> Result<(), ConfigError> {
+    let Some(value) = lookup(name) else {
+        return Ok(());
+    };
+    let parsed = value.trim().parse().ok().filter(|parsed| range.contains(parsed));
+    *field = parsed.ok_or_else(|| ConfigError::InvalidEnvVar(name.to_string(), value.clone()))?;
+    log::info!("Config: {:?} from {}", field, name);
+    Ok(())
+}
+
 // ============================================
 // CONFIGURATION ERRORS
 // ============================================
@@ -196,6 +1428,60 @@ pub enum ConfigError {
 
     /// Bitrate too high
     BitrateTooHigh(u32),
+
+    /// Save offset not shorter than the buffer
+    SaveOffsetTooLong(u32),
+
+    /// Thread nice value outside valid range
+    InvalidNice(i32),
+
+    /// Recording profile name empty or not filename-safe
+    InvalidProfileName(String),
+
+    /// Two recording profiles share a name
+    DuplicateProfile(String),
+
+    /// JPEG quality outside 1-100
+    InvalidJpegQuality(u8),
+
+    /// Trigger press/release thresholds out of range or inverted
+    InvalidTriggerThresholds(f32, f32),
+
+    /// Custom trigger combo uses the same control twice
+    InvalidTriggerButton(ControllerAxis),
+
+    /// Trigger combo description that isn't two or more known controls
+    InvalidTriggerSpec(String),
+
+    /// Double-tap window of zero milliseconds
+    InvalidDoubleTapWindow(u32),
+
+    /// Scene cut threshold outside 0-1
+    InvalidSceneCutThreshold(f32),
+
+    /// Keyframe interval of zero frames
+    InvalidKeyframeInterval(u32),
+
+    /// Keyframe interval in seconds negative or not a number
+    InvalidKeyframeSeconds(f32),
+
+    /// Action bound to a press already used by another action or save
+    ConflictingBinding(InputAction),
+
+    /// Proxy width of zero pixels
+    InvalidProxyWidth(u32),
+
+    /// Idle motion floor outside 0-1
+    InvalidIdleMotionFloor(f32),
+
+    /// Config file couldn't be read or written
+    FileAccess(String),
+
+    /// Config file isn't valid TOML or has wrongly typed settings
+    FileFormat(String),
+
+    /// Environment variable override that doesn't parse (name, value)
+    InvalidEnvVar(String, String),
 }
 
 impl fmt::Display for ConfigError {
@@ -216,12 +1502,111 @@ impl fmt::Display for ConfigError {
             Self::BitrateTooHigh(val) => {
                 write!(f, "Bitrate {} too high", val)
             }
+            Self::SaveOffsetTooLong(val) => {
+                write!(f, "Save offset {} ms must be shorter than the buffer", val)
+            }
+            Self::InvalidNice(val) => {
+                write!(f, "Thread nice value {} outside valid range (-20 to 19)", val)
+            }
+            Self::InvalidProfileName(name) => {
+                write!(f, "Invalid profile name '{}' (letters, digits, - or _)", name)
+            }
+            Self::DuplicateProfile(name) => {
+                write!(f, "Duplicate profile name '{}'", name)
+            }
+            Self::InvalidJpegQuality(val) => {
+                write!(f, "JPEG quality {} outside valid range (1-100)", val)
+            }
+            Self::InvalidTriggerThresholds(press, release) => {
+                write!(
+                    f,
+                    "Trigger thresholds press {} / release {} invalid (0.3-1, release <= press)",
+                    press, release
+                )
+            }
+            Self::InvalidTriggerButton(axis) => {
+                write!(f, "Custom trigger combo uses {:?} twice", axis)
+            }
+            Self::InvalidTriggerSpec(description) => {
+                write!(
+                    f,
+                    "Trigger combo '{}' must be two or more controls like left_grip+a",
+                    description
+                )
+            }
+            Self::InvalidDoubleTapWindow(val) => {
+                write!(f, "Double-tap window {} ms must be at least 1 ms", val)
+            }
+            Self::InvalidSceneCutThreshold(val) => {
+                write!(f, "Scene cut threshold {} outside valid range (0-1)", val)
+            }
+            Self::InvalidKeyframeInterval(val) => {
+                write!(f, "Keyframe interval {} must be at least 1 frame", val)
+            }
+            Self::InvalidKeyframeSeconds(val) => {
+                write!(f, "Keyframe interval {}s must be 0 or more seconds", val)
+            }
+            Self::ConflictingBinding(action) => {
+                write!(f, "{:?} is bound to a press already in use", action)
+            }
+            Self::InvalidProxyWidth(val) => {
+                write!(f, "Proxy width {} must be at least 1 pixel", val)
+            }
+            Self::InvalidIdleMotionFloor(val) => {
+                write!(f, "Idle motion floor {} outside valid range (0-1)", val)
+            }
+            Self::FileAccess(reason) => {
+                write!(f, "Config file unavailable: {}", reason)
+            }
+            Self::FileFormat(reason) => {
+                write!(f, "Config file invalid: {}", reason)
+            }
+            Self::InvalidEnvVar(name, value) => {
+                write!(f, "Environment variable {}={:?} is not valid or out of range", name, value)
+            }
         }
     }
 }
 
 impl std::error::Error for ConfigError {}
 
+/// Settings combinations that are allowed but likely to perform badly.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigWarning {
+    /// Buffers are estimated to need this many megabytes
+    HighMemory(f32),
+
+    /// Frame rate and JPEG quality too high to compress every frame
+    FpsTooHighForQuality(u32, u8),
+
+    /// Keyframe interval longer than the buffer holds (interval, frames)
+    KeyframeIntervalOverBuffer(u32, usize),
+}
+
+impl fmt::Display for ConfigWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::HighMemory(mb) => {
+                write!(f, "This config needs ~{:.0} MB of RAM", mb)
+            }
+            Self::FpsTooHighForQuality(fps, quality) => {
+                write!(
+                    f,
+                    "{} FPS at JPEG quality {} may not be captured reliably",
+                    fps, quality
+                )
+            }
+            Self::KeyframeIntervalOverBuffer(interval, frames) => {
+                write!(
+                    f,
+                    "Keyframe interval {} is longer than the {}-frame buffer; clips won't seek",
+                    interval, frames
+                )
+            }
+        }
+    }
+}
+
 // ============================================
 // TESTS
 // ============================================
@@ -238,6 +1623,42 @@ mod tests {
         assert!(config.validate().is_empty());
     }
 
+    #[test]
+    fn test_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join(CONFIG_FILE_NAME);
+
+        // No file yet: defaults
+        let loaded = Config::load_from_path(&path).unwrap();
+        assert_eq!(loaded.target_fps, Config::default().target_fps);
+
+        let combo = TriggerButton::custom(&[ControllerAxis::RightGrip, ControllerAxis::A]);
+        let config = Config {
+            target_fps: 72,
+            trigger_button: combo,
+            mark_button: MarkButton::RightA,
+            action_bindings: vec![(InputAction::Pause, ActionBinding::Button(MarkButton::LeftX))],
+            capture_layer: LayerSelect::Index(1),
+            profiles: vec![RecordingProfile::new("long", 30.0, 50)],
+            rotation: Rotation::Rot90,
+            stereo_mode: StereoMode::SideBySide,
+            export_directory: Some("/sdcard/Exports".to_string()),
+            ..Config::default()
+        };
+        config.save_to_path(&path).unwrap();
+        let loaded = Config::load_from_path(&path).unwrap();
+        assert_eq!(format!("{:?}", loaded), format!("{:?}", config));
+
+        // Missing settings keep their defaults
+        fs::write(&path, "target_fps = 120\nrotation = \"rot180\"\n").unwrap();
+        let loaded = Config::load_from_path(&path).unwrap();
+        assert_eq!((loaded.target_fps, loaded.rotation), (120, Rotation::Rot180));
+        assert_eq!(loaded.jpeg_quality, 80);
+
+        fs::write(&path, "target_fps = \"fast\"").unwrap();
+        assert!(matches!(Config::load_from_path(&path), Err(ConfigError::FileFormat(_))));
+    }
+
     #[test]
     fn test_buffer_frame_count() {
         let config = Config::default();
@@ -253,11 +1674,221 @@ mod tests {
         assert!(!errors.is_empty());
     }
 
+    #[test]
+    fn test_validation_zero_frame_buffer() {
+        for (duration, fps) in [(0.0, 90), (f32::NAN, 90), (10.0, 0)] {
+            let config = Config {
+                buffer_duration_seconds: duration,
+                target_fps: fps,
+                ..Config::default()
+            };
+            assert!(!config.validate().is_empty(), "{}s at {} FPS", duration, fps);
+        }
+    }
+
+    #[test]
+    fn test_validation_save_offset() {
+        let config = Config {
+            save_offset_ms: 1_500,
+            ..Config::default()
+        };
+        assert!(config.validate().is_empty());
+
+        // Offset as long as the 10s buffer leaves nothing to save
+        let config = Config {
+            save_offset_ms: 10_000,
+            ..Config::default()
+        };
+        assert!(!config.validate().is_empty());
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let vars = |pairs: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                pairs.iter().find(|(n, _)| *n == name).map(|(_, v)| v.to_string())
+            }
+        };
+
+        let config = Config::default()
+            .with_overrides(vars(&[
+                (ENV_BUFFER_SECONDS, "10"),
+                (ENV_TARGET_FPS, "72"),
+                (ENV_BITRATE, "8000000"),
+                (ENV_OUTPUT_DIR, "/data/clips"),
+                (ENV_JPEG_QUALITY, " 65 "),
+            ]))
+            .unwrap();
+        assert_eq!((config.buffer_duration_seconds, config.target_fps), (10.0, 72));
+        assert_eq!((config.video_bitrate, config.jpeg_quality), (8_000_000, 65));
+        assert_eq!(config.output_directory, "/data/clips");
+
+        // Unset variables change nothing; bad values are errors
+        let config = Config::default().with_overrides(vars(&[])).unwrap();
+        assert_eq!(config.target_fps, 90);
+        let result = Config::default().with_overrides(vars(&[(ENV_TARGET_FPS, "fast")]));
+        let Err(ConfigError::InvalidEnvVar(name, _)) = result else {
+            panic!("expected an invalid variable error");
+        };
+        assert_eq!(name, ENV_TARGET_FPS);
+        assert!(Config::default().with_overrides(vars(&[(ENV_JPEG_QUALITY, "300")])).is_err());
+
+        // Out of range is an error too, never a quiet clamp or a zero-frame buffer
+        let out_of_range = [(ENV_TARGET_FPS, "0"), (ENV_BUFFER_SECONDS, "2"), (ENV_BITRATE, "5")];
+        for (name, value) in out_of_range {
+            let lookup = |n: &str| (n == name).then(|| value.to_string());
+            let result = Config::default().with_overrides(lookup);
+            assert!(matches!(result, Err(ConfigError::InvalidEnvVar(n, _)) if n == name));
+        }
+    }
+
+    #[test]
+    fn test_builder() {
+        let config = Config::builder()
+            .buffer_seconds(30.0)
+            .fps(120)
+            .bitrate(40_000_000)
+            .jpeg_quality(70)
+            .trigger_button(TriggerButton::RightGripAndTrigger)
+            .output_directory("/tmp/clips")
+            .build();
+        assert_eq!((config.buffer_duration_seconds, config.target_fps), (30.0, 120));
+        assert_eq!((config.video_bitrate, config.jpeg_quality), (40_000_000, 70));
+        assert_eq!(config.trigger_button, TriggerButton::RightGripAndTrigger);
+        assert_eq!(config.output_directory, "/tmp/clips");
+
+        // Out-of-range values are clamped on build
+        let config = Config::builder().fps(500).buffer_seconds(0.0).build();
+        assert_eq!((config.target_fps, config.buffer_duration_seconds), (144, 5.0));
+
+        for preset in [Config::low_memory(), Config::high_quality()] {
+            assert!(preset.validate().is_empty());
+            assert!(preset.warnings().is_empty());
+        }
+        let default_mb = Config::default().estimated_memory_mb();
+        assert!(Config::low_memory().estimated_memory_mb() < default_mb);
+    }
+
+    #[test]
+    fn test_clamped() {
+        let config = Config {
+            buffer_duration_seconds: f32::NAN,
+            target_fps: 0,
+            video_bitrate: 500_000_000,
+            jpeg_quality: 0,
+            trigger_press_threshold: 0.5,
+            trigger_release_threshold: 0.8,
+            save_offset_ms: 20_000,
+            keyframe_interval_seconds: -1.0,
+            ..Config::default()
+        };
+        let errors = config.validate_strict().unwrap_err();
+        assert_eq!(errors.len(), config.validate().len());
+
+        let clamped = config.clamped();
+        assert_eq!(clamped.validate_strict().map_err(|e| e.len()), Ok(()));
+        assert_eq!((clamped.buffer_duration_seconds, clamped.target_fps), (5.0, 30));
+        assert_eq!((clamped.video_bitrate, clamped.jpeg_quality), (100_000_000, 1));
+        assert_eq!(clamped.trigger_release_threshold, 0.5);
+        assert_eq!((clamped.save_offset_ms, clamped.keyframe_interval_seconds), (0, 0.0));
+        assert!(clamped.buffer_frame_count() > 0);
+
+        // Valid settings are left as they are
+        let config = Config::default().clamped();
+        assert_eq!(format!("{:?}", config), format!("{:?}", Config::default()));
+    }
+
+    #[test]
+    fn test_warnings() {
+        assert!(Config::default().warnings().is_empty());
+
+        // Valid, but 60s at 120 FPS and quality 95 is a lot of memory
+        let config = Config {
+            buffer_duration_seconds: 60.0,
+            target_fps: 120,
+            jpeg_quality: 95,
+            ..Config::default()
+        };
+        assert!(config.validate().is_empty());
+        let warnings = config.warnings();
+        assert!(matches!(warnings[0], ConfigWarning::HighMemory(mb) if mb > 400.0));
+        assert_eq!(warnings[1], ConfigWarning::FpsTooHighForQuality(120, 95));
+        assert_eq!(warnings.len(), 2);
+
+        let config = Config {
+            max_keyframe_interval: 1_000,
+            keyframe_interval_seconds: 0.0,
+            ..Config::default()
+        };
+        assert!(config.warnings().is_empty());
+        let config = Config {
+            scene_cut_threshold: 0.0,
+            ..config
+        };
+        assert_eq!(config.warnings(), vec![ConfigWarning::KeyframeIntervalOverBuffer(1_000, 900)]);
+
+        // A keyframe every few seconds keeps the clip seekable anyway
+        let config = Config {
+            keyframe_interval_seconds: 2.0,
+            ..config
+        };
+        assert!(config.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_rotation_matrix() {
+        const ONE: i32 = 1 << 16;
+        const W: i32 = 1 << 30;
+        assert_eq!(Rotation::None.display_matrix(), [ONE, 0, 0, 0, ONE, 0, 0, 0, W]);
+        assert_eq!(Rotation::Rot90.display_matrix(), [0, ONE, 0, -ONE, 0, 0, 0, 0, W]);
+        assert_eq!(Rotation::Rot180.display_matrix(), [-ONE, 0, 0, 0, -ONE, 0, 0, 0, W]);
+        assert_eq!(Rotation::Rot270.display_matrix(), [0, -ONE, 0, ONE, 0, 0, 0, 0, W]);
+        assert_eq!(Rotation::Rot270.degrees(), 270);
+
+        // ffmpeg's angle is counter-clockwise
+        assert_eq!(Rotation::None.display_rotation(), 0.0);
+        assert_eq!(Rotation::Rot90.display_rotation(), -90.0);
+        assert_eq!(Rotation::Rot180.display_rotation(), 180.0);
+        assert_eq!(Rotation::Rot270.display_rotation(), 90.0);
+    }
+
     #[test]
     fn test_memory_estimation() {
         let config = Config::default();
         let memory = config.estimated_memory_mb();
         // Should be around 90 MB for 900 frames at 100KB each
         assert!(memory > 50.0 && memory < 150.0);
+
+        let config = Config {
+            max_buffer_bytes: 32 * 1024 * 1024,
+            ..Config::default()
+        };
+        assert_eq!(config.estimated_memory_mb(), 32.0);
+    }
+
+    #[test]
+    fn test_profiles() {
+        let config = Config {
+            profiles: vec![
+                RecordingProfile::new("highlight", 5.0, 95),
+                RecordingProfile::new("context", 60.0, 40),
+            ],
+            ..Config::default()
+        };
+        assert!(config.validate().is_empty());
+        assert_eq!(config.profile_frame_count(&config.profiles[1]), 5400);
+
+        // Memory is summed across all buffers
+        assert!(config.estimated_memory_mb() > Config::default().estimated_memory_mb() * 3.0);
+
+        let config = Config {
+            profiles: vec![
+                RecordingProfile::new("a", 10.0, 80),
+                RecordingProfile::new("a", 10.0, 80),
+                RecordingProfile::new("../b", 10.0, 80),
+            ],
+            ..Config::default()
+        };
+        assert_eq!(config.validate().len(), 2);
     }
 }
@@ -0,0 +1,263 @@
+//! # GIF Export
+//!
+//! Turns a short clip into an animated GIF for sharing.
+//!
+//! ## Plain English
+//!
+//! A GIF plays anywhere (chats, forums) without a video player, which
+//! makes it handy for a 2-3 second highlight. The catch: GIFs are big
+//! and can only use 256 colors per picture. So the export keeps the
+//! file reasonable by shrinking the picture, showing fewer pictures per
+//! second (motion looks a bit choppier), and picking the best 256
+//! colors for each picture (smooth gradients may band).
+
+use std::borrow::Borrow;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use image::{imageops, RgbaImage};
+
+use super::VideoEncoder;
+use crate::capture::CapturedFrame;
+use crate::config::{Config, ScaleFilter};
+use crate::error::{ShadowplayError, ShadowplayResult};
+
+/// Quantizer speed (1 = best colors, 30 = fastest); 10 is a fair balance
+const QUANTIZE_SPEED: i32 = 10;
+
+/// Shortest delay players honor, in hundredths of a second; many treat
+/// anything lower as 10
+const MIN_DELAY_CS: u16 = 2;
+
+/// Picks which frames go in a GIF: only the first frame's eye, at most
+/// `max_fps` per second, using presentation times in microseconds.
+///
+/// Returns each kept frame's index and how long it shows, in
+/// hundredths of a second.
+fn select_frames<F: Borrow<CapturedFrame>>(
+    frames: &[F],
+    max_fps: u32,
+    fallback_fps: u32,
+) -> Vec<(usize, u16)> {
+    let Some(eye) = frames.first().map(|f| f.borrow().eye_index) else {
+        return Vec::new();
+    };
+    let min_gap_us = 1_000_000 / max_fps.max(1) as u64;
+    let times = VideoEncoder::presentation_times_us(frames, fallback_fps);
+
+    let mut kept: Vec<(usize, u64)> = Vec::new();
+    for (index, frame) in frames.iter().enumerate() {
+        if frame.borrow().eye_index != eye {
+            continue;
+        }
+        let pts_us = times[index];
+        if kept.last().is_none_or(|&(_, last_us)| pts_us >= last_us + min_gap_us) {
+            kept.push((index, pts_us));
+        }
+    }
+
+    // Delays come from rounded show times, so rounding never drifts;
+    // the last frame shows for one interval at the capped rate
+    let cs = |us: u64| (us + 5_000) / 10_000;
+    let end_us = kept.last().map_or(0, |&(_, last_us)| last_us + min_gap_us);
+    let mut delays: Vec<(usize, u16)> = Vec::with_capacity(kept.len());
+    for (i, &(index, pts_us)) in kept.iter().enumerate() {
+        let next_us = kept.get(i + 1).map_or(end_us, |&(_, next)| next);
+        let delay = cs(next_us).saturating_sub(cs(pts_us));
+        delays.push((index, (delay.min(u16::MAX as u64) as u16).max(MIN_DELAY_CS)));
+    }
+    delays
+}
+
+/// A looping animated GIF written one frame at a time, so it can be fed
+/// from a decode shared with other outputs (see
+/// [`crate::storage::write_outputs`]).
+pub struct GifWriter {
+    encoder: gif::Encoder<BufWriter<File>>,
+    width: u16,
+    height: u16,
+    filter: ScaleFilter,
+}
+
+impl GifWriter {
+    /// Creates a GIF at `output_path` for `width`x`height` frames, which
+    /// are shrunk to at most `config.gif_max_width` pixels wide.
+    pub fn create(
+        output_path: &Path,
+        width: u32,
+        height: u32,
+        config: &Config,
+    ) -> ShadowplayResult<Self> {
+        let out_w = width.min(config.gif_max_width.max(1));
+        let out_h = ((height as u64 * out_w as u64 / width.max(1) as u64) as u32).max(1);
+        let (gif_w, gif_h) = match (u16::try_from(out_w), u16::try_from(out_h)) {
+            (Ok(w), Ok(h)) => (w, h),
+            _ => {
+                return Err(ShadowplayError::Encoder(format!(
+                    "{}x{} is too large for a GIF",
+                    out_w, out_h
+                )))
+            }
+        };
+
+        if let Some(parent) = output_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = BufWriter::new(File::create(output_path)?);
+        let mut encoder = gif::Encoder::new(file, gif_w, gif_h, &[]).map_err(gif_error)?;
+        encoder.set_repeat(gif::Repeat::Infinite).map_err(gif_error)?;
+
+        Ok(Self {
+            encoder,
+            width: gif_w,
+            height: gif_h,
+            filter: config.scale_filter,
+        })
+    }
+
+    /// Returns the GIF's size in pixels.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width as u32, self.height as u32)
+    }
+
+    /// Adds a frame shown for `delay` hundredths of a second, resized to
+    /// the GIF's size if needed.
+    pub fn write_frame(&mut self, image: &RgbaImage, delay: u16) -> ShadowplayResult<()> {
+        let (width, height) = self.dimensions();
+        let mut image = if image.dimensions() == (width, height) {
+            image.clone()
+        } else {
+            imageops::resize(image, width, height, self.filter.filter_type())
+        };
+
+        let mut frame =
+            gif::Frame::from_rgba_speed(self.width, self.height, &mut image, QUANTIZE_SPEED);
+        frame.delay = delay;
+        self.encoder.write_frame(&frame).map_err(gif_error)
+    }
+
+    /// Ends the GIF and flushes it to disk.
+    pub fn finish(self) -> ShadowplayResult<()> {
+        self.encoder.into_inner()?.flush()?;
+        Ok(())
+    }
+}
+
+fn gif_error(e: gif::EncodingError) -> ShadowplayError {
+    ShadowplayError::Encoder(format!("GIF: {}", e))
+}
+
+impl VideoEncoder {
+    /// Picks the frames [`VideoEncoder::encode_gif`] would use: each
+    /// one's index and how long it shows, in hundredths of a second.
+    pub fn gif_frames<F: Borrow<CapturedFrame>>(
+        frames: &[F],
+        config: &Config,
+    ) -> Vec<(usize, u16)> {
+        select_frames(frames, config.gif_max_fps, config.target_fps)
+    }
+
+    /// Writes frames as a looping animated GIF.
+    ///
+    /// Frames are shrunk to at most `config.gif_max_width` pixels wide
+    /// and thinned to at most `config.gif_max_fps` (stereo clips use the
+    /// first frame's eye). Each shows for as long as the capture
+    /// timestamps say, so the GIF plays at real speed. Meant for short
+    /// highlights: a GIF is far bigger than an MP4 of the same clip.
+    ///
+    /// Returns the number of frames written.
+    pub fn encode_gif(
+        frames: &[CapturedFrame],
+        output_path: &str,
+        config: &Config,
+    ) -> ShadowplayResult<usize> {
+        let selected = Self::gif_frames(frames, config);
+        let Some(&(first, _)) = selected.first() else {
+            return Err(ShadowplayError::Encoder("No frames to encode".to_string()));
+        };
+
+        let (width, height) = (frames[first].width, frames[first].height);
+        let mut gif = GifWriter::create(Path::new(output_path), width, height, config)?;
+        let (out_w, out_h) = gif.dimensions();
+        log::info!(
+            "Encoding {} of {} frames as a {}x{} GIF to {}",
+            selected.len(),
+            frames.len(),
+            out_w,
+            out_h,
+            output_path
+        );
+
+        for &(index, delay) in &selected {
+            gif.write_frame(&Self::decode_frame_at(&frames[index], index)?, delay)?;
+        }
+        gif.finish()?;
+
+        Ok(selected.len())
+    }
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::FrameCompressor;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_frame_selection() {
+        // 90 fps for one second, capped to 15: every 6th frame, 7cs each
+        let ms = 1_000_000u64;
+        let frames: Vec<_> = (0..90u64)
+            .map(|i| CapturedFrame::with_timestamp(vec![0], 0, 1, 1, i * 1000 * ms / 90))
+            .collect();
+        let selected = select_frames(&frames, 15, 90);
+        assert_eq!(selected.len(), 15);
+        assert_eq!(selected[1].0, 6);
+        let total_cs: u32 = selected.iter().map(|&(_, d)| d as u32).sum();
+        assert_eq!(total_cs, 100);
+
+        // Right-eye frames are left out
+        let mut stereo = frames.clone();
+        stereo.iter_mut().skip(1).step_by(2).for_each(|f| f.eye_index = 1);
+        assert!(select_frames(&stereo, 90, 90).iter().all(|&(i, _)| i % 2 == 0));
+    }
+
+    #[test]
+    fn test_encode_gif() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("highlight.gif");
+        let compressor = FrameCompressor::new(80);
+        let frames: Vec<_> = (0..60u64)
+            .map(|i| {
+                let pixels = vec![(i * 4) as u8; 128 * 64 * 4];
+                let data = compressor.compress(&pixels, 128, 64).unwrap();
+                CapturedFrame::with_timestamp(data, 0, 128, 64, i * 1_000_000_000 / 30)
+            })
+            .collect();
+        let config = Config {
+            gif_max_width: 64,
+            gif_max_fps: 10,
+            ..Config::default()
+        };
+
+        let written = VideoEncoder::encode_gif(&frames, path.to_str().unwrap(), &config).unwrap();
+        assert_eq!(written, 20);
+
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = options.read_info(File::open(&path).unwrap()).unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (64, 32));
+        let mut count = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!(frame.delay, 10);
+            count += 1;
+        }
+        assert_eq!(count, 20);
+        assert_eq!(decoder.repeat(), gif::Repeat::Infinite);
+    }
+}
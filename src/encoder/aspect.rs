@@ -0,0 +1,211 @@
+//! # Aspect Ratio Conversion
+//!
+//! Reframes clips to a target aspect ratio on export.
+//!
+//! ## Plain English
+//!
+//! Quest eye buffers are almost square (1832×1920), but YouTube and TVs
+//! want 16:9. Played as-is, a clip gets black bars on both sides. These
+//! options decide how to fit the square picture into a wide frame:
+//! - **CropCenter**: cut off the top and bottom (fills the screen)
+//! - **Pad**: add black bars ourselves (keeps everything visible)
+//! - **Stretch**: pull the picture wider (fills, but distorts)
+
+use image::imageops;
+use image::{Rgba, RgbaImage};
+
+use crate::config::ScaleFilter;
+use crate::error::{ShadowplayError, ShadowplayResult};
+
+/// Widest (or tallest) ratio we accept, to catch swapped or typo'd values.
+const MAX_RATIO: f64 = 4.0;
+
+/// How a frame is fitted into the target aspect ratio.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum AspectMode {
+    /// Crop the center region with the target ratio
+    #[default]
+    CropCenter,
+
+    /// Letterbox/pillarbox with black bars
+    Pad,
+
+    /// Resize to the target ratio, ignoring the source ratio
+    Stretch,
+}
+
+/// Target aspect ratio and fit mode for an export.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExportAspect {
+    /// How to fit the frame
+    pub mode: AspectMode,
+    /// Ratio width (e.g. 16)
+    pub ratio_width: u32,
+    /// Ratio height (e.g. 9)
+    pub ratio_height: u32,
+    /// How frames are resampled when resized
+    pub filter: ScaleFilter,
+}
+
+impl ExportAspect {
+    /// Creates an aspect setting.
+    pub fn new(mode: AspectMode, ratio_width: u32, ratio_height: u32) -> Self {
+        Self {
+            mode,
+            ratio_width,
+            ratio_height,
+            filter: ScaleFilter::default(),
+        }
+    }
+
+    /// Sets how frames are resampled when resized.
+    pub fn with_filter(mut self, filter: ScaleFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// 16:9, the common video platform format.
+    pub fn widescreen(mode: AspectMode) -> Self {
+        Self::new(mode, 16, 9)
+    }
+
+    /// Checks the ratio is positive and not absurdly wide or tall.
+    pub fn validate(&self) -> ShadowplayResult<()> {
+        if self.ratio_width == 0 || self.ratio_height == 0 {
+            return Err(ShadowplayError::Encoder(format!(
+                "Invalid aspect ratio {}:{}",
+                self.ratio_width, self.ratio_height
+            )));
+        }
+        let ratio = self.ratio();
+        if !(1.0 / MAX_RATIO..=MAX_RATIO).contains(&ratio) {
+            return Err(ShadowplayError::Encoder(format!(
+                "Aspect ratio {}:{} outside supported range",
+                self.ratio_width, self.ratio_height
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns width divided by height.
+    pub fn ratio(&self) -> f64 {
+        self.ratio_width as f64 / self.ratio_height as f64
+    }
+
+    /// Returns the output size for a source frame.
+    ///
+    /// Cropping keeps the largest region that fits inside the source;
+    /// padding and stretching use the smallest canvas that holds it.
+    /// Sizes are rounded to even numbers, as H.264 encoders require.
+    pub fn output_dimensions(&self, width: u32, height: u32) -> ShadowplayResult<(u32, u32)> {
+        self.validate()?;
+        let ratio = self.ratio();
+        let source_ratio = width as f64 / height as f64;
+
+        let (w, h) = match self.mode {
+            AspectMode::CropCenter if source_ratio > ratio => {
+                ((height as f64 * ratio).round(), height as f64)
+            }
+            AspectMode::CropCenter => (width as f64, (width as f64 / ratio).round()),
+            AspectMode::Pad | AspectMode::Stretch if source_ratio > ratio => {
+                (width as f64, (width as f64 / ratio).round())
+            }
+            AspectMode::Pad | AspectMode::Stretch => {
+                ((height as f64 * ratio).round(), height as f64)
+            }
+        };
+
+        Ok((round_even(w as u32), round_even(h as u32)))
+    }
+
+    /// Reframes one image to the target aspect ratio.
+    pub fn apply(&self, image: &RgbaImage) -> ShadowplayResult<RgbaImage> {
+        let (width, height) = image.dimensions();
+        let (out_w, out_h) = self.output_dimensions(width, height)?;
+
+        let result = match self.mode {
+            AspectMode::CropCenter => {
+                let (crop_w, crop_h) = (out_w.min(width), out_h.min(height));
+                let x = (width - crop_w) / 2;
+                let y = (height - crop_h) / 2;
+                let cropped = imageops::crop_imm(image, x, y, crop_w, crop_h).to_image();
+                if cropped.dimensions() == (out_w, out_h) {
+                    cropped
+                } else {
+                    // Only differs by the even-size rounding
+                    imageops::resize(&cropped, out_w, out_h, self.filter.filter_type())
+                }
+            }
+            AspectMode::Pad => {
+                let mut canvas = RgbaImage::from_pixel(out_w, out_h, Rgba([0, 0, 0, 255]));
+                let x = out_w.saturating_sub(width) / 2;
+                let y = out_h.saturating_sub(height) / 2;
+                imageops::overlay(&mut canvas, image, x as i64, y as i64);
+                canvas
+            }
+            AspectMode::Stretch => {
+                imageops::resize(image, out_w, out_h, self.filter.filter_type())
+            }
+        };
+
+        Ok(result)
+    }
+}
+
+/// Rounds down to an even number (minimum 2).
+fn round_even(value: u32) -> u32 {
+    (value & !1).max(2)
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_square_to_widescreen_dimensions() {
+        let crop = ExportAspect::widescreen(AspectMode::CropCenter);
+        assert_eq!(crop.output_dimensions(1920, 1920).unwrap(), (1920, 1080));
+
+        let pad = ExportAspect::widescreen(AspectMode::Pad);
+        assert_eq!(pad.output_dimensions(1920, 1920).unwrap(), (3412, 1920));
+
+        // Quest eye buffer: odd results are rounded to even
+        let (w, h) = crop.output_dimensions(1832, 1920).unwrap();
+        assert_eq!((w % 2, h % 2), (0, 0));
+        assert!(w <= 1832 && h <= 1920);
+    }
+
+    #[test]
+    fn test_invalid_ratio() {
+        assert!(ExportAspect::new(AspectMode::Pad, 16, 0).validate().is_err());
+        assert!(ExportAspect::new(AspectMode::Pad, 100, 1).validate().is_err());
+        assert!(ExportAspect::new(AspectMode::Pad, 4, 3).validate().is_ok());
+    }
+
+    #[test]
+    fn test_apply_modes() {
+        let image = RgbaImage::from_pixel(64, 64, Rgba([255, 255, 255, 255]));
+
+        let padded = ExportAspect::widescreen(AspectMode::Pad)
+            .apply(&image)
+            .unwrap();
+        assert_eq!(padded.dimensions(), (114, 64));
+        assert_eq!(padded.get_pixel(0, 32), &Rgba([0, 0, 0, 255]));
+        assert_eq!(padded.get_pixel(56, 32), &Rgba([255, 255, 255, 255]));
+
+        let cropped = ExportAspect::widescreen(AspectMode::CropCenter)
+            .apply(&image)
+            .unwrap();
+        assert_eq!(cropped.dimensions(), (64, 36));
+
+        let stretched = ExportAspect::widescreen(AspectMode::Stretch)
+            .apply(&image)
+            .unwrap();
+        assert_eq!(stretched.dimensions(), (114, 64));
+        assert_eq!(stretched.get_pixel(0, 32), &Rgba([255, 255, 255, 255]));
+    }
+}
@@ -0,0 +1,215 @@
+//! # Clip Audio
+//!
+//! Sound recorded alongside a clip's frames.
+//!
+//! ## Plain English
+//!
+//! A replay without sound feels flat. When the app hands us the game's
+//! audio with the frames, it is stored in the clip as plain samples
+//! (PCM: a list of loudness values, 48,000 or so per second per
+//! channel). The audio starts at the clip's first frame, so both stay
+//! in step. On export to MP4, ffmpeg turns it into an AAC track.
+
+use crate::error::{ShadowplayError, ShadowplayResult};
+
+/// PCM audio stored with a clip, starting at its first frame.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClipAudio {
+    /// Samples per second per channel
+    pub sample_rate: u32,
+
+    /// Number of interleaved channels (2 = stereo)
+    pub channels: u16,
+
+    /// Interleaved signed 16-bit samples
+    pub samples: Vec<i16>,
+}
+
+impl ClipAudio {
+    /// Creates stereo audio from interleaved left/right samples.
+    pub fn stereo(samples: Vec<i16>, sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            channels: 2,
+            samples,
+        }
+    }
+
+    /// Returns how long the audio plays, in seconds.
+    pub fn duration_secs(&self) -> f64 {
+        let frames = self.samples.len() / self.channels.max(1) as usize;
+        frames as f64 / self.sample_rate.max(1) as f64
+    }
+
+    /// Returns the audio from `start_ns` into it up to `end_ns` (or the
+    /// end), for a clip cut down to the frames in that span.
+    pub fn slice(&self, start_ns: u64, end_ns: Option<u64>) -> Self {
+        let start = self.sample_index(start_ns);
+        let end = end_ns.map_or(self.samples.len(), |end_ns| self.sample_index(end_ns));
+        self.with_samples(self.samples[start..end.max(start)].to_vec())
+    }
+
+    /// Returns the audio with each `(start_ns, length_ns)` span cut
+    /// out, for when the frames it played under are removed. Spans are
+    /// offsets into the audio, in order.
+    pub fn without_spans(&self, spans: &[(u64, u64)]) -> Self {
+        let mut samples = Vec::with_capacity(self.samples.len());
+        let mut kept_from = 0;
+        for &(start_ns, length_ns) in spans {
+            let start = self.sample_index(start_ns).max(kept_from);
+            samples.extend_from_slice(&self.samples[kept_from..start]);
+            kept_from = self.sample_index(start_ns.saturating_add(length_ns)).max(start);
+        }
+        samples.extend_from_slice(&self.samples[kept_from..]);
+        self.with_samples(samples)
+    }
+
+    /// Returns the index of the first sample `offset_ns` into the audio,
+    /// on a whole frame of channels and clamped to the end.
+    fn sample_index(&self, offset_ns: u64) -> usize {
+        let frame = offset_ns as u128 * self.sample_rate as u128 / 1_000_000_000;
+        let index = frame.saturating_mul(self.channels as u128);
+        index.min(self.samples.len() as u128) as usize
+    }
+
+    /// Returns audio in the same format holding `samples`.
+    fn with_samples(&self, samples: Vec<i16>) -> Self {
+        Self {
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+            samples,
+        }
+    }
+
+    /// Checks the audio can be stored and played.
+    pub fn validate(&self) -> ShadowplayResult<()> {
+        if self.sample_rate == 0 || self.channels == 0 {
+            return Err(ShadowplayError::Encoder(format!(
+                "Invalid audio: {} Hz, {} channels",
+                self.sample_rate, self.channels
+            )));
+        }
+        if !self.samples.len().is_multiple_of(self.channels as usize) {
+            return Err(ShadowplayError::Encoder(format!(
+                "{} audio samples don't divide into {} channels",
+                self.samples.len(),
+                self.channels
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns the audio as a 16-bit PCM WAV file, which ffmpeg reads
+    /// when muxing it into an MP4.
+    pub fn to_wav(&self) -> Vec<u8> {
+        let data_len = (self.samples.len() * 2) as u32;
+        let block_align = self.channels * 2;
+
+        let mut wav = Vec::with_capacity(44 + data_len as usize);
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        wav.extend_from_slice(&self.channels.to_le_bytes());
+        wav.extend_from_slice(&self.sample_rate.to_le_bytes());
+        wav.extend_from_slice(&(self.sample_rate * block_align as u32).to_le_bytes());
+        wav.extend_from_slice(&block_align.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        for sample in &self.samples {
+            wav.extend_from_slice(&sample.to_le_bytes());
+        }
+        wav
+    }
+}
+
+// ============================================
+// CLIP FILE SECTION
+// ============================================
+
+/// Serializes audio for the end of a clip file: sample rate, channels,
+/// sample count, then the samples.
+pub(crate) fn encode_audio(audio: &ClipAudio) -> Vec<u8> {
+    let mut out = Vec::with_capacity(10 + audio.samples.len() * 2);
+    out.extend_from_slice(&audio.sample_rate.to_le_bytes());
+    out.extend_from_slice(&audio.channels.to_le_bytes());
+    out.extend_from_slice(&(audio.samples.len() as u32).to_le_bytes());
+    for sample in &audio.samples {
+        out.extend_from_slice(&sample.to_le_bytes());
+    }
+    out
+}
+
+/// Parses an audio section written by [`encode_audio`].
+pub(crate) fn decode_audio(data: &[u8]) -> ShadowplayResult<ClipAudio> {
+    let truncated = || ShadowplayError::Encoder("Truncated audio section".to_string());
+
+    let header = data.get(0..10).ok_or_else(truncated)?;
+    let sample_rate = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+    let channels = u16::from_le_bytes([header[4], header[5]]);
+    let count = u32::from_le_bytes([header[6], header[7], header[8], header[9]]) as usize;
+
+    let samples = data
+        .get(10..10 + count * 2)
+        .ok_or_else(truncated)?
+        .chunks_exact(2)
+        .map(|s| i16::from_le_bytes([s[0], s[1]]))
+        .collect();
+
+    let audio = ClipAudio {
+        sample_rate,
+        channels,
+        samples,
+    };
+    audio.validate()?;
+    Ok(audio)
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audio_section_round_trip() {
+        let audio = ClipAudio::stereo(vec![0, 1000, -1000, i16::MAX], 48_000);
+        assert_eq!(decode_audio(&encode_audio(&audio)).unwrap(), audio);
+        assert!(decode_audio(&encode_audio(&audio)[..12]).is_err());
+
+        let odd = ClipAudio::stereo(vec![1, 2, 3], 48_000);
+        assert!(odd.validate().is_err());
+    }
+
+    #[test]
+    fn test_slice_and_cut() {
+        // 10 stereo frames a second, so each frame is 100 ms
+        let audio = ClipAudio::stereo((0..40).collect(), 10);
+        let ms = 1_000_000;
+
+        assert_eq!(audio.slice(300 * ms, Some(500 * ms)).samples, vec![6, 7, 8, 9]);
+        assert_eq!(audio.slice(1_800 * ms, None).samples, vec![36, 37, 38, 39]);
+        assert!(audio.slice(5_000 * ms, None).samples.is_empty());
+
+        let cut = audio.without_spans(&[(100 * ms, 200 * ms), (1_500 * ms, 10_000 * ms)]);
+        let expected: Vec<i16> = [0, 1].into_iter().chain(6..30).collect();
+        assert_eq!(cut.samples, expected);
+        assert_eq!(cut.sample_rate, 10);
+    }
+
+    #[test]
+    fn test_wav_header() {
+        let audio = ClipAudio::stereo(vec![0; 96_000], 48_000);
+        assert_eq!(audio.duration_secs(), 1.0);
+
+        let wav = audio.to_wav();
+        assert_eq!(wav.len(), 44 + 192_000);
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(&wav[8..16], b"WAVEfmt ");
+        assert_eq!(u32::from_le_bytes([wav[28], wav[29], wav[30], wav[31]]), 192_000);
+    }
+}
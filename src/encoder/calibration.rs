@@ -0,0 +1,125 @@
+//! # Encoder Calibration
+//!
+//! Measures how fast this device saves clips, for save-time estimates.
+//!
+//! ## Plain English
+//!
+//! How long a save takes depends on the headset: its storage speed,
+//! its CPU, what else is running. Rather than guess, we save a short
+//! test clip once, time it, and remember how many seconds of saving
+//! each second of footage costs. Until that has run, estimates fall
+//! back to a typical figure.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use super::VideoEncoder;
+use crate::capture::{CapturedFrame, FrameCompressor};
+use crate::config::Config;
+use crate::error::{ShadowplayError, ShadowplayResult};
+
+/// Seconds of saving per second of footage assumed before calibration
+/// (saving runs about 3x faster than real time)
+pub const DEFAULT_REALTIME_RATIO: f64 = 1.0 / 3.0;
+
+/// Frames in the calibration clip (one second at 90 FPS)
+const SAMPLE_FRAMES: u32 = 90;
+
+/// Size of the calibration frames; busy enough to compress like a game
+const SAMPLE_SIZE: u32 = 512;
+
+/// Measured ratio as `f64` bits (0 = not calibrated yet)
+static MEASURED_RATIO: AtomicU64 = AtomicU64::new(0);
+
+/// Saves a short sample clip and records how long saving takes
+/// relative to real time.
+///
+/// Uses the frame rate and JPEG quality from `config`. The sample is
+/// written to the temp directory and deleted afterwards. Returns the
+/// measured ratio, which later estimates use.
+pub fn calibrate_encoder(config: &Config) -> ShadowplayResult<f64> {
+    let frames = sample_frames(config)?;
+    let path = std::env::temp_dir().join(format!("qsp_calibration_{}.qsp", std::process::id()));
+
+    let start = Instant::now();
+    let result = VideoEncoder::encode_frames(&frames, &path.to_string_lossy(), config);
+    let elapsed = start.elapsed().as_secs_f64();
+    if let Err(e) = std::fs::remove_file(&path) {
+        log::warn!("Failed to remove calibration clip {:?}: {}", path, e);
+    }
+    result?;
+
+    let footage = SAMPLE_FRAMES as f64 / config.target_fps.max(1) as f64;
+    // Keep it non-zero so it can't read as "not calibrated"
+    let ratio = (elapsed / footage).max(f64::MIN_POSITIVE);
+    MEASURED_RATIO.store(ratio.to_bits(), Ordering::Relaxed);
+    log::info!("Encoder calibrated: {:.3}s per second of footage", ratio);
+    Ok(ratio)
+}
+
+/// Returns the measured ratio, if [`calibrate_encoder`] has run.
+pub fn measured_realtime_ratio() -> Option<f64> {
+    match MEASURED_RATIO.load(Ordering::Relaxed) {
+        0 => None,
+        bits => Some(f64::from_bits(bits)),
+    }
+}
+
+/// Estimates how long saving `duration_secs` of footage takes on this
+/// device, using the measured ratio or [`DEFAULT_REALTIME_RATIO`].
+pub fn estimated_encoding_time(duration_secs: f32) -> Duration {
+    let ratio = measured_realtime_ratio().unwrap_or(DEFAULT_REALTIME_RATIO);
+    Duration::from_secs_f64((duration_secs.max(0.0) as f64 * ratio).min(u32::MAX as f64))
+}
+
+/// Builds compressed frames with varied content for the sample clip.
+fn sample_frames(config: &Config) -> ShadowplayResult<Vec<CapturedFrame>> {
+    let compressor = FrameCompressor::new(config.jpeg_quality);
+    let frame_ns = 1_000_000_000 / config.target_fps.max(1) as u64;
+    let mut rgba = vec![0u8; (SAMPLE_SIZE * SAMPLE_SIZE * 4) as usize];
+    let mut state = 0x2545_F491_4F6C_DD1Du64;
+
+    (0..SAMPLE_FRAMES)
+        .map(|i| {
+            // xorshift noise over a moving gradient, so frames differ
+            for (p, pixel) in rgba.chunks_exact_mut(4).enumerate() {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                let base = (p as u32 + i * 7) as u8;
+                pixel.copy_from_slice(&[base, base ^ (state as u8 & 0x3F), base / 2, 255]);
+            }
+            let data = compressor
+                .compress(&rgba, SAMPLE_SIZE, SAMPLE_SIZE)
+                .map_err(|e| ShadowplayError::Encoder(e.to_string()))?;
+            let timestamp_ns = i as u64 * frame_ns;
+            Ok(CapturedFrame::with_timestamp(data, 0, SAMPLE_SIZE, SAMPLE_SIZE, timestamp_ns))
+        })
+        .collect()
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calibration() {
+        // Before calibrating, the typical ratio is assumed
+        if measured_realtime_ratio().is_none() {
+            let estimate = estimated_encoding_time(9.0).as_secs_f64();
+            assert!((estimate - 3.0).abs() < 1e-6);
+        }
+
+        let ratio = calibrate_encoder(&Config::default()).unwrap();
+        assert!(ratio > 0.0);
+        assert_eq!(measured_realtime_ratio(), Some(ratio));
+
+        let estimate = estimated_encoding_time(10.0).as_secs_f64();
+        assert!((estimate - ratio * 10.0).abs() < 1e-6);
+        assert_eq!(estimated_encoding_time(-1.0), Duration::ZERO);
+    }
+}
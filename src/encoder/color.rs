@@ -0,0 +1,238 @@
+//! # Color Conversion
+//!
+//! Converts RGBA pixels to the YUV 4:2:0 layout video encoders take.
+//!
+//! ## Plain English
+//!
+//! Video doesn't store red, green and blue. It stores brightness (Y)
+//! plus two "color difference" values (U and V), and keeps only one U
+//! and V for every 2×2 block of pixels, because eyes notice detail in
+//! brightness far more than in color. There are two recipes for that
+//! split: BT.601 from standard-definition TV and BT.709 for HD. Using
+//! the wrong one, or not telling the player which one was used, makes
+//! colors come out slightly washed out or green. There are also two
+//! scales: "limited" (16-235, what TVs and players expect) and "full"
+//! (0-255).
+
+use crate::error::{ShadowplayError, ShadowplayResult};
+
+/// Smallest HD frame (1280x720); frames this wide or tall use BT.709
+const HD_MIN_SIZE: (u32, u32) = (1280, 720);
+
+/// Which recipe splits RGB into brightness and color.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ColorSpace {
+    /// Standard-definition TV (ITU-R BT.601)
+    Bt601,
+
+    /// HD video (ITU-R BT.709)
+    #[default]
+    Bt709,
+}
+
+/// Which numbers the Y, U and V values span.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ColorRange {
+    /// Y 16-235 and U/V 16-240, what players assume unless told
+    #[default]
+    Limited,
+
+    /// Y, U and V all 0-255
+    Full,
+}
+
+impl ColorSpace {
+    /// Picks the matrix players assume for a frame size: BT.709 for HD
+    /// (1280 wide or 720 tall and up), BT.601 below.
+    pub fn for_resolution(width: u32, height: u32) -> Self {
+        if width >= HD_MIN_SIZE.0 || height >= HD_MIN_SIZE.1 {
+            Self::Bt709
+        } else {
+            Self::Bt601
+        }
+    }
+
+    /// Returns the red and blue luma weights (Kr, Kb); green gets the rest.
+    pub fn luma_weights(&self) -> (f32, f32) {
+        match self {
+            Self::Bt601 => (0.299, 0.114),
+            Self::Bt709 => (0.2126, 0.0722),
+        }
+    }
+
+    /// Returns ffmpeg arguments that convert to this matrix and range
+    /// and tag the stream with them, so players decode the same way.
+    pub fn ffmpeg_args(&self, range: ColorRange) -> Vec<String> {
+        let (matrix, tag) = match self {
+            Self::Bt601 => ("bt601", "smpte170m"),
+            Self::Bt709 => ("bt709", "bt709"),
+        };
+        let range = match range {
+            ColorRange::Limited => "tv",
+            ColorRange::Full => "pc",
+        };
+        vec![
+            "-vf".to_string(),
+            format!("scale=out_color_matrix={}:out_range={}", matrix, range),
+            "-colorspace".to_string(),
+            tag.to_string(),
+            "-color_primaries".to_string(),
+            tag.to_string(),
+            "-color_trc".to_string(),
+            tag.to_string(),
+            "-color_range".to_string(),
+            range.to_string(),
+        ]
+    }
+
+    /// Converts one RGB pixel to Y, U and V.
+    pub fn rgb_to_yuv(&self, rgb: [u8; 3], range: ColorRange) -> [u8; 3] {
+        let (kr, kb) = self.luma_weights();
+        let [r, g, b] = rgb.map(|c| c as f32 / 255.0);
+        let y = kr * r + (1.0 - kr - kb) * g + kb * b;
+        let u = (b - y) / (2.0 * (1.0 - kb));
+        let v = (r - y) / (2.0 * (1.0 - kr));
+
+        let (y_scale, y_offset, uv_scale) = match range {
+            ColorRange::Limited => (219.0, 16.0, 224.0),
+            ColorRange::Full => (255.0, 0.0, 255.0),
+        };
+        let quantize = |value: f32| value.round().clamp(0.0, 255.0) as u8;
+        [
+            quantize(y_offset + y_scale * y),
+            quantize(128.0 + uv_scale * u),
+            quantize(128.0 + uv_scale * v),
+        ]
+    }
+}
+
+/// Converts RGBA pixels to planar YUV 4:2:0 (I420): the full-size Y
+/// plane, then the quarter-size U and V planes.
+///
+/// Each U and V value is the average over a 2×2 block. With an odd
+/// width or height, the chroma planes round up and the blocks on the
+/// last column or row reuse its pixels, as if the edge were repeated.
+/// Alpha is ignored.
+pub fn rgba_to_yuv420(
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    space: ColorSpace,
+    range: ColorRange,
+) -> ShadowplayResult<Vec<u8>> {
+    let (width, height) = (width as usize, height as usize);
+    if rgba.len() != width * height * 4 {
+        return Err(ShadowplayError::Encoder(format!(
+            "Expected {} bytes for {}x{} RGBA, got {}",
+            width * height * 4,
+            width,
+            height,
+            rgba.len()
+        )));
+    }
+
+    let (uv_width, uv_height) = (width.div_ceil(2), height.div_ceil(2));
+    let uv_size = uv_width * uv_height;
+    let mut out = vec![0u8; width * height + 2 * uv_size];
+    let (y_plane, uv_planes) = out.split_at_mut(width * height);
+    let (u_plane, v_plane) = uv_planes.split_at_mut(uv_size);
+
+    for (index, pixel) in rgba.chunks_exact(4).enumerate() {
+        y_plane[index] = space.rgb_to_yuv([pixel[0], pixel[1], pixel[2]], range)[0];
+    }
+
+    for uv_y in 0..uv_height {
+        for uv_x in 0..uv_width {
+            // Average the block's RGB first, then convert once
+            let mut sum = [0u32; 3];
+            for (dy, dx) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+                // Clamped, so odd edges sample their last pixel twice
+                let y = (uv_y * 2 + dy).min(height - 1);
+                let x = (uv_x * 2 + dx).min(width - 1);
+                let i = (y * width + x) * 4;
+                for c in 0..3 {
+                    sum[c] += rgba[i + c] as u32;
+                }
+            }
+            let [_, u, v] = space.rgb_to_yuv(sum.map(|s| ((s + 2) / 4) as u8), range);
+            u_plane[uv_y * uv_width + uv_x] = u;
+            v_plane[uv_y * uv_width + uv_x] = v;
+        }
+    }
+
+    Ok(out)
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pure_green() {
+        let green = [0, 255, 0];
+        let bt601 = ColorSpace::Bt601;
+        let bt709 = ColorSpace::Bt709;
+        assert_eq!(bt601.rgb_to_yuv(green, ColorRange::Limited), [145, 54, 34]);
+        assert_eq!(bt709.rgb_to_yuv(green, ColorRange::Limited), [173, 42, 26]);
+        assert_eq!(bt601.rgb_to_yuv(green, ColorRange::Full), [150, 44, 21]);
+        assert_eq!(bt709.rgb_to_yuv(green, ColorRange::Full), [182, 30, 12]);
+
+        // Black and white sit at the ends of the range, with neutral color
+        assert_eq!(bt709.rgb_to_yuv([0, 0, 0], ColorRange::Limited), [16, 128, 128]);
+        assert_eq!(bt709.rgb_to_yuv([255; 3], ColorRange::Limited), [235, 128, 128]);
+        assert_eq!(bt709.rgb_to_yuv([255; 3], ColorRange::Full), [255, 128, 128]);
+    }
+
+    #[test]
+    fn test_rgba_to_yuv420() {
+        // 4x2 RGBA: left block green, right block black
+        let mut rgba = Vec::new();
+        for _ in 0..2 {
+            rgba.extend_from_slice(&[0, 255, 0, 255, 0, 255, 0, 255, 0, 0, 0, 255, 0, 0, 0, 255]);
+        }
+        let yuv = rgba_to_yuv420(&rgba, 4, 2, ColorSpace::Bt709, ColorRange::Limited).unwrap();
+        assert_eq!(yuv.len(), 8 + 2 + 2);
+        assert_eq!(&yuv[..4], &[173, 173, 16, 16]);
+        assert_eq!(&yuv[8..], &[42, 128, 26, 128]);
+
+        // A buffer that doesn't match the size
+        assert!(rgba_to_yuv420(&rgba, 4, 1, ColorSpace::Bt709, ColorRange::Limited).is_err());
+        assert_eq!(ColorSpace::for_resolution(1832, 1920), ColorSpace::Bt709);
+        assert_eq!(ColorSpace::for_resolution(640, 480), ColorSpace::Bt601);
+    }
+
+    #[test]
+    fn test_odd_dimensions() {
+        for (width, height) in [(99u32, 99u32), (101, 100), (1, 1)] {
+            // Green everywhere except a white last column and row
+            let mut rgba = Vec::new();
+            for y in 0..height {
+                for x in 0..width {
+                    let edge = x == width - 1 || y == height - 1;
+                    rgba.extend_from_slice(if edge { &[255; 4] } else { &[0, 255, 0, 255] });
+                }
+            }
+            let yuv =
+                rgba_to_yuv420(&rgba, width, height, ColorSpace::Bt709, ColorRange::Limited)
+                    .unwrap();
+
+            let (w, h) = (width as usize, height as usize);
+            let (uv_w, uv_h) = (w.div_ceil(2), h.div_ceil(2));
+            assert_eq!(yuv.len(), w * h + 2 * uv_w * uv_h, "{}x{}", width, height);
+            assert_eq!(yuv[w * h - 1], 235);
+
+            // Odd edges only hold the white pixels, so their blocks are white
+            let u_plane = &yuv[w * h..w * h + uv_w * uv_h];
+            if w % 2 == 1 {
+                assert_eq!(u_plane[uv_w - 1], 128);
+            }
+            if w > 2 && h > 2 {
+                assert_eq!(u_plane[0], 42);
+            }
+        }
+    }
+}
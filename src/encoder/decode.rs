@@ -0,0 +1,211 @@
+//! # Parallel Decode
+//!
+//! Unpacks a clip's frames on several cores while they are used in order.
+//!
+//! ## Plain English
+//!
+//! Turning stored JPEGs back into pixels is the slow part of writing a
+//! proxy or a frame folder, and one frame doesn't depend on another. So
+//! a batch of frames is unpacked at once on a pool of workers while the
+//! previous batch is being written out. Frames still come out strictly
+//! in order, and only a couple of batches are held in memory at a time.
+
+use std::borrow::Borrow;
+use std::sync::mpsc::sync_channel;
+use std::thread;
+
+use image::RgbaImage;
+use rayon::prelude::*;
+
+use super::VideoEncoder;
+use crate::capture::CapturedFrame;
+use crate::error::{ShadowplayError, ShadowplayResult};
+
+/// Frames each worker decodes per batch
+const FRAMES_PER_WORKER: usize = 4;
+
+/// Decodes frames in parallel and hands them over in order.
+pub struct ParallelDecoder {
+    /// Workers that decode frames
+    pool: rayon::ThreadPool,
+
+    /// Frames decoded per batch
+    batch_size: usize,
+}
+
+impl ParallelDecoder {
+    /// Creates a decoder with `workers` threads (0 = one per CPU core).
+    pub fn new(workers: usize) -> ShadowplayResult<Self> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(workers)
+            .thread_name(|i| format!("shadowplay-decode-{}", i))
+            .build()
+            .map_err(|e| ShadowplayError::Internal(format!("Failed to start decoders: {}", e)))?;
+        let batch_size = pool.current_num_threads() * FRAMES_PER_WORKER;
+        Ok(Self { pool, batch_size })
+    }
+
+    /// Returns the number of decode threads.
+    pub fn workers(&self) -> usize {
+        self.pool.current_num_threads()
+    }
+
+    /// Decodes the frames `wanted` picks (given each frame's index) and
+    /// calls `consume` for every frame, in order, with its pixels
+    /// (`None` for frames not wanted).
+    ///
+    /// The next batch decodes while `consume` works through the current
+    /// one. Stops at the first decode error, after every earlier frame
+    /// has been consumed, or at the first error from `consume`.
+    pub fn decode_in_order<F, W, C>(
+        &self,
+        frames: &[F],
+        wanted: W,
+        mut consume: C,
+    ) -> ShadowplayResult<()>
+    where
+        F: Borrow<CapturedFrame> + Sync,
+        W: Fn(usize, &CapturedFrame) -> bool + Sync,
+        C: FnMut(usize, &CapturedFrame, Option<RgbaImage>) -> ShadowplayResult<()>,
+    {
+        type Batch = Vec<ShadowplayResult<Option<RgbaImage>>>;
+
+        // One batch waiting while another is consumed bounds memory
+        let (sender, receiver) = sync_channel::<Batch>(1);
+
+        let wanted = &wanted;
+        thread::scope(|scope| {
+            scope.spawn(move || {
+                for (batch_index, batch) in frames.chunks(self.batch_size).enumerate() {
+                    let first = batch_index * self.batch_size;
+                    let decoded: Batch = self.pool.install(|| {
+                        batch
+                            .par_iter()
+                            .enumerate()
+                            .map(|(offset, frame)| {
+                                let (index, frame) = (first + offset, frame.borrow());
+                                wanted(index, frame)
+                                    .then(|| VideoEncoder::decode_frame_at(frame, index))
+                                    .transpose()
+                            })
+                            .collect()
+                    });
+                    // The consumer stopped early: nothing left to do
+                    if sender.send(decoded).is_err() {
+                        return;
+                    }
+                }
+            });
+
+            let mut index = 0;
+            for batch in receiver {
+                for image in batch {
+                    consume(index, frames[index].borrow(), image?)?;
+                    index += 1;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Decodes every frame, in order.
+    pub fn decode_all(&self, frames: &[CapturedFrame]) -> ShadowplayResult<Vec<RgbaImage>> {
+        let mut images = Vec::with_capacity(frames.len());
+        self.decode_in_order(
+            frames,
+            |_, _| true,
+            |_, _, image| {
+                images.extend(image);
+                Ok(())
+            },
+        )?;
+        Ok(images)
+    }
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::FrameCompressor;
+    use std::time::Instant;
+
+    /// A clip whose frames are solid shades, so order is checkable.
+    fn shaded_clip(count: usize) -> Vec<CapturedFrame> {
+        let compressor = FrameCompressor::new(80);
+        (0..count)
+            .map(|i| {
+                let shade = (i % 16) as u8 * 16;
+                let data = compressor.compress(&vec![shade; 64 * 64 * 4], 64, 64).unwrap();
+                CapturedFrame::with_timestamp(data, 0, 64, 64, i as u64)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parallel_matches_serial() {
+        let frames = shaded_clip(300);
+
+        let serial: Vec<_> = frames
+            .iter()
+            .enumerate()
+            .map(|(i, frame)| VideoEncoder::decode_frame_at(frame, i).unwrap())
+            .collect();
+
+        let decoder = ParallelDecoder::new(4).unwrap();
+        let parallel = decoder.decode_all(&frames).unwrap();
+        assert_eq!(parallel, serial);
+    }
+
+    /// Benchmark: serial vs parallel decode of a 300-frame clip. Run with
+    /// `cargo test --release bench_parallel_decode -- --ignored --nocapture`.
+    #[test]
+    #[ignore = "benchmark; timings depend on the machine"]
+    fn bench_parallel_decode() {
+        let frames = shaded_clip(300);
+
+        let start = Instant::now();
+        for (i, frame) in frames.iter().enumerate() {
+            VideoEncoder::decode_frame_at(frame, i).unwrap();
+        }
+        let serial_time = start.elapsed();
+
+        let decoder = ParallelDecoder::new(0).unwrap();
+        let start = Instant::now();
+        decoder.decode_all(&frames).unwrap();
+        let parallel_time = start.elapsed();
+
+        println!(
+            "Decoded 300 frames: serial {:?}, parallel ({} workers) {:?}",
+            serial_time,
+            decoder.workers(),
+            parallel_time
+        );
+        if decoder.workers() > 1 {
+            assert!(parallel_time < serial_time, "parallel decode was no faster");
+        }
+    }
+
+    #[test]
+    fn test_skips_unwanted_and_stops_on_error() {
+        let mut frames = shaded_clip(20);
+        frames[13].data = vec![0xFF, 0xD8, 0x00];
+        let decoder = ParallelDecoder::new(2).unwrap();
+
+        let mut seen = Vec::new();
+        let result = decoder.decode_in_order(
+            &frames,
+            |_, frame| frame.timestamp_ns % 2 == 1,
+            |index, _, image| {
+                seen.push((index, image.is_some()));
+                Ok(())
+            },
+        );
+        assert!(result.unwrap_err().to_string().contains("Frame 13"));
+        assert_eq!(seen.len(), 13);
+        assert!(seen.iter().all(|&(i, decoded)| decoded == (i % 2 == 1)));
+    }
+}
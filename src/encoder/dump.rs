@@ -0,0 +1,135 @@
+//! # Frame Dump
+//!
+//! Writes frames out as numbered JPEG files with a JSON manifest.
+//!
+//! ## Plain English
+//!
+//! Sometimes you want to see exactly what was captured, with no video
+//! encoder in the way: wrong colors, frames out of order, gaps. This
+//! writes every frame as its own picture (`frame_00000.jpg`,
+//! `frame_00001.jpg`, ...) plus a `manifest.json` saying when each was
+//! captured and how big it is. The same numbered files are what ffmpeg
+//! reads when exporting to MP4.
+
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use super::{ExportAspect, VideoEncoder};
+use crate::capture::CapturedFrame;
+use crate::error::{ShadowplayError, ShadowplayResult};
+
+/// ffmpeg input pattern matching the dumped file names
+pub const FRAME_FILE_PATTERN: &str = "frame_%05d.jpg";
+
+/// Name of the manifest written next to the frames
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// One dumped frame, as listed in the manifest.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DumpedFrame {
+    /// JPEG file name inside the dump directory
+    pub file: String,
+
+    /// Capture time in nanoseconds
+    pub timestamp_ns: u64,
+
+    /// Which eye (0 = left, 1 = right)
+    pub eye_index: u32,
+
+    /// Width of the written image
+    pub width: u32,
+
+    /// Height of the written image
+    pub height: u32,
+}
+
+/// Returns the dump file name for frame number `index`.
+pub fn frame_file_name(index: usize) -> String {
+    format!("frame_{:05}.jpg", index)
+}
+
+/// Writes `frames` into `directory` as numbered JPEGs plus a manifest.
+///
+/// Raw frames are encoded at `jpeg_quality`; JPEG frames are written
+/// untouched unless `aspect` reframes them. Returns the manifest entries.
+pub fn dump_frames(
+    frames: &[CapturedFrame],
+    directory: &Path,
+    aspect: Option<&ExportAspect>,
+    jpeg_quality: u8,
+) -> ShadowplayResult<Vec<DumpedFrame>> {
+    fs::create_dir_all(directory)?;
+
+    let mut manifest = Vec::with_capacity(frames.len());
+    for (index, frame) in frames.iter().enumerate() {
+        let (data, width, height) = match aspect {
+            Some(aspect) => {
+                let reframed = VideoEncoder::reframe(frame, aspect, jpeg_quality)
+                    .map_err(|e| VideoEncoder::at_frame(index, e))?;
+                (reframed.data, reframed.width, reframed.height)
+            }
+            None => {
+                let jpeg = VideoEncoder::frame_to_jpeg(frame, jpeg_quality)
+                    .map_err(|e| VideoEncoder::at_frame(index, e))?;
+                (jpeg, frame.width, frame.height)
+            }
+        };
+
+        let file = frame_file_name(index);
+        fs::write(directory.join(&file), data)?;
+        manifest.push(DumpedFrame {
+            file,
+            timestamp_ns: frame.timestamp_ns,
+            eye_index: frame.eye_index,
+            width,
+            height,
+        });
+    }
+
+    write_manifest(directory, &manifest)?;
+    Ok(manifest)
+}
+
+/// Writes the manifest for frames already dumped into `directory`.
+pub fn write_manifest(directory: &Path, manifest: &[DumpedFrame]) -> ShadowplayResult<()> {
+    let json = serde_json::to_string_pretty(manifest)
+        .map_err(|e| ShadowplayError::Storage(format!("Failed to encode manifest: {}", e)))?;
+    fs::write(directory.join(MANIFEST_FILE_NAME), json)?;
+    Ok(())
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::FrameCompressor;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_dump_frames() {
+        let dir = tempdir().unwrap();
+        let compressor = FrameCompressor::new(80);
+        let frames: Vec<_> = (0..3u64)
+            .map(|i| {
+                let data = compressor.compress(&[i as u8 * 50; 8 * 4 * 4], 8, 4).unwrap();
+                CapturedFrame::with_timestamp(data, 0, 8, 4, i * 1_000)
+            })
+            .collect();
+
+        let manifest = dump_frames(&frames, dir.path(), None, 80).unwrap();
+        assert_eq!(manifest.len(), 3);
+        assert_eq!(manifest[2].file, "frame_00002.jpg");
+        assert_eq!(manifest[2].timestamp_ns, 2_000);
+        assert_eq!(fs::read(dir.path().join("frame_00001.jpg")).unwrap(), frames[1].data);
+
+        let json = fs::read_to_string(dir.path().join(MANIFEST_FILE_NAME)).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed[1]["timestamp_ns"], 1_000);
+        assert_eq!(parsed[1]["width"], 8);
+    }
+}
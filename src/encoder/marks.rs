@@ -0,0 +1,229 @@
+//! # Clip Marks
+//!
+//! Labelled moments inside a clip, exported as subtitles or chapters.
+//!
+//! ## Plain English
+//!
+//! While playing, you can tap a button to say "that was good". Each tap
+//! is a mark: a timestamp and a label. Marks that fall inside a saved
+//! clip are stored in the `.qsp` file and, on export, become a WebVTT
+//! subtitle file (a caption pops up at each mark) and chapter markers
+//! in the MP4 (so players can jump straight to them).
+
+use std::fmt::Write;
+
+use crate::error::{ShadowplayError, ShadowplayResult};
+
+/// How long each mark's caption stays on screen in WebVTT output
+pub const MARK_CUE_SECONDS: f64 = 2.0;
+
+/// Longest label stored in a clip file, in bytes
+pub const MAX_MARK_LABEL_BYTES: usize = 256;
+
+/// A labelled moment during capture.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClipMark {
+    /// Capture timestamp the mark refers to (same clock as frames)
+    pub timestamp_ns: u64,
+
+    /// Text shown for the mark
+    pub label: String,
+}
+
+impl ClipMark {
+    /// Creates a mark.
+    pub fn new(timestamp_ns: u64, label: impl Into<String>) -> Self {
+        Self {
+            timestamp_ns,
+            label: label.into(),
+        }
+    }
+}
+
+// ============================================
+// CLIP FILE SECTION
+// ============================================
+
+/// Serializes marks for the clip header: count, then per mark the
+/// timestamp, label length and UTF-8 label.
+///
+/// Labels longer than [`MAX_MARK_LABEL_BYTES`] are cut at a character
+/// boundary.
+pub(crate) fn encode_marks(marks: &[ClipMark]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&(marks.len() as u32).to_le_bytes());
+    for mark in marks {
+        let mut end = mark.label.len().min(MAX_MARK_LABEL_BYTES);
+        while !mark.label.is_char_boundary(end) {
+            end -= 1;
+        }
+        let label = &mark.label.as_bytes()[..end];
+
+        out.extend_from_slice(&mark.timestamp_ns.to_le_bytes());
+        out.extend_from_slice(&(label.len() as u16).to_le_bytes());
+        out.extend_from_slice(label);
+    }
+    out
+}
+
+/// Parses a marks section written by [`encode_marks`].
+pub(crate) fn decode_marks(data: &[u8]) -> ShadowplayResult<Vec<ClipMark>> {
+    let truncated = || ShadowplayError::Encoder("Truncated marks section".to_string());
+
+    let count_bytes = data.get(0..4).ok_or_else(truncated)?;
+    let count = u32::from_le_bytes([
+        count_bytes[0],
+        count_bytes[1],
+        count_bytes[2],
+        count_bytes[3],
+    ]);
+
+    let mut marks = Vec::new();
+    let mut offset = 4;
+    for _ in 0..count {
+        let record = data.get(offset..offset + 10).ok_or_else(truncated)?;
+        let timestamp_ns = u64::from_le_bytes([
+            record[0], record[1], record[2], record[3], record[4], record[5], record[6], record[7],
+        ]);
+        let label_len = u16::from_le_bytes([record[8], record[9]]) as usize;
+        offset += 10;
+
+        let label = data.get(offset..offset + label_len).ok_or_else(truncated)?;
+        offset += label_len;
+
+        marks.push(ClipMark::new(timestamp_ns, String::from_utf8_lossy(label)));
+    }
+
+    Ok(marks)
+}
+
+// ============================================
+// EXPORT FORMATS
+// ============================================
+
+/// Writes marks as a WebVTT subtitle file.
+///
+/// Times are relative to `clip_start_ns`; each caption lasts
+/// [`MARK_CUE_SECONDS`] or until the next mark, whichever is first,
+/// and never runs past `clip_end_ns`.
+pub fn marks_to_webvtt(marks: &[ClipMark], clip_start_ns: u64, clip_end_ns: u64) -> String {
+    let mut vtt = String::from("WEBVTT\n");
+    let cue_ns = (MARK_CUE_SECONDS * 1e9) as u64;
+
+    for (i, mark) in marks.iter().enumerate() {
+        let start = mark.timestamp_ns;
+        let next = marks.get(i + 1).map_or(u64::MAX, |m| m.timestamp_ns);
+        let end = start
+            .saturating_add(cue_ns)
+            .min(next)
+            .min(clip_end_ns)
+            .max(start);
+
+        let _ = write!(
+            vtt,
+            "\n{}\n{} --> {}\n{}\n",
+            i + 1,
+            vtt_time(start.saturating_sub(clip_start_ns)),
+            vtt_time(end.saturating_sub(clip_start_ns)),
+            mark.label
+        );
+    }
+
+    vtt
+}
+
+/// Writes marks as an ffmpeg metadata file with one chapter per mark.
+///
+/// Each chapter runs from its mark to the next one (or the clip end).
+/// Pass it to ffmpeg as an extra input with `-map_metadata`/`-map_chapters`.
+pub fn marks_to_ffmetadata(marks: &[ClipMark], clip_start_ns: u64, clip_end_ns: u64) -> String {
+    let mut meta = String::from(";FFMETADATA1\n");
+    let to_ms = |ns: u64| ns.saturating_sub(clip_start_ns) / 1_000_000;
+
+    for (i, mark) in marks.iter().enumerate() {
+        let end = marks.get(i + 1).map_or(clip_end_ns, |m| m.timestamp_ns);
+        let _ = write!(
+            meta,
+            "\n[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+            to_ms(mark.timestamp_ns),
+            to_ms(end.max(mark.timestamp_ns)),
+            escape_ffmetadata(&mark.label)
+        );
+    }
+
+    meta
+}
+
+/// Formats a duration as a WebVTT timestamp (`HH:MM:SS.mmm`).
+fn vtt_time(ns: u64) -> String {
+    let ms = ns / 1_000_000;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+/// Escapes the characters ffmetadata treats specially.
+fn escape_ffmetadata(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: u64 = 1_000_000_000;
+
+    #[test]
+    fn test_marks_round_trip() {
+        let marks = vec![ClipMark::new(5, "Clutch"), ClipMark::new(9, "")];
+        assert_eq!(decode_marks(&encode_marks(&marks)).unwrap(), marks);
+
+        // Long labels are cut on a character boundary
+        let long = ClipMark::new(1, "é".repeat(200));
+        let decoded = decode_marks(&encode_marks(&[long])).unwrap();
+        assert_eq!(decoded[0].label.len(), MAX_MARK_LABEL_BYTES);
+
+        assert!(decode_marks(&encode_marks(&marks)[..12]).is_err());
+    }
+
+    #[test]
+    fn test_webvtt_output() {
+        let start = 100 * SECOND;
+        let marks = vec![
+            ClipMark::new(start + SECOND, "First"),
+            ClipMark::new(start + SECOND + SECOND / 2, "Second"),
+        ];
+        let vtt = marks_to_webvtt(&marks, start, start + 2 * SECOND);
+
+        assert!(vtt.starts_with("WEBVTT\n"));
+        // First cue is cut short by the second mark
+        assert!(vtt.contains("1\n00:00:01.000 --> 00:00:01.500\nFirst\n"));
+        // Second cue is cut short by the clip end
+        assert!(vtt.contains("2\n00:00:01.500 --> 00:00:02.000\nSecond\n"));
+    }
+
+    #[test]
+    fn test_ffmetadata_chapters() {
+        let marks = vec![ClipMark::new(2 * SECOND, "Boss; phase=2")];
+        let meta = marks_to_ffmetadata(&marks, SECOND, 5 * SECOND);
+
+        assert!(meta.starts_with(";FFMETADATA1\n"));
+        assert!(meta.contains("START=1000\nEND=4000\n"));
+        assert!(meta.contains("title=Boss\\; phase\\=2\n"));
+    }
+}
@@ -11,14 +11,137 @@
 //!
 //! On Quest 3, this uses hardware encoding for speed.
 
+mod animation;
+mod aspect;
+mod audio;
+mod calibration;
+mod color;
+mod decode;
+mod dump;
+mod marks;
+mod quality;
+mod stereo;
+
+pub use animation::GifWriter;
+pub use aspect::{AspectMode, ExportAspect};
+pub use audio::ClipAudio;
+pub use calibration::{
+    calibrate_encoder, estimated_encoding_time, measured_realtime_ratio, DEFAULT_REALTIME_RATIO,
+};
+pub use color::{rgba_to_yuv420, ColorRange, ColorSpace};
+pub use decode::ParallelDecoder;
+pub use dump::{
+    dump_frames, frame_file_name, write_manifest, DumpedFrame, FRAME_FILE_PATTERN,
+    MANIFEST_FILE_NAME,
+};
+pub use marks::{marks_to_ffmetadata, marks_to_webvtt, ClipMark, MARK_CUE_SECONDS};
+pub use quality::{
+    estimate_quality, kush_bitrate, FrameQuality, QualityEstimate, QualityRating, QualityReport,
+};
+pub use stereo::{match_eyes, pair_eyes, EyeMatch, StereoLayout, StereoMode};
+
+use std::borrow::Borrow;
 use std::fs::File;
 use std::io::Write;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use crate::capture::CapturedFrame;
+use crate::capture::{
+    is_progressive_jpeg, rgb565_to_rgba, CapturedFrame, FrameCompressor, FrameFormat,
+    MotionTracker,
+};
 use crate::config::Config;
 use crate::error::{ShadowplayError, ShadowplayResult};
 
+// ============================================
+// CLIP FILE FORMAT
+// ============================================
+
+/// Magic bytes at the start of every clip file (followed by a 2-digit version)
+const MAGIC: &[u8; 6] = b"QSPLAY";
+
+/// Format version written by the encoder
+const FORMAT_VERSION: &[u8; 2] = b"03";
+
+/// Oldest and newest format versions the reader understands
+pub const SUPPORTED_VERSIONS: std::ops::RangeInclusive<u32> = 1..=3;
+
+/// Size of the v03 header (magic, version, frame count, width, height,
+/// fps, flags, header length). Later versions may append optional
+/// sections; readers skip to `header length` to find the frames.
+const HEADER_LEN_V03: u32 = 32;
+
+/// Byte offset of the fps field, the same in every version
+const FPS_OFFSET: u64 = 20;
+
+/// Bytes written per frame besides its data (timestamp, eye index,
+/// data length), plus 4 when motion scores are stored
+const FRAME_RECORD_LEN: u64 = 16;
+
+/// Header flag: frames are stored as progressive JPEGs
+pub const FLAG_PROGRESSIVE_JPEG: u32 = 1;
+
+/// Header flag: each frame record carries an f32 motion score after the
+/// eye index (NaN = not scored)
+pub const FLAG_MOTION_SCORES: u32 = 2;
+
+/// Header flag: frames are raw little-endian RGB565 instead of JPEG
+pub const FLAG_RAW_RGB565: u32 = 4;
+
+/// Header flag: a marks section follows the fixed header (optional, so
+/// readers that don't know it skip the section via the header length)
+pub const FLAG_MARKS: u32 = 1 << 16;
+
+/// Header flag: an audio section follows the last frame record (see
+/// [`ClipAudio`]; optional, since readers stop after the frames)
+pub const FLAG_AUDIO: u32 = 1 << 17;
+
+/// Flags in the low 16 bits change how frames are laid out, so a reader
+/// must understand them. Flags in the high 16 bits are hints that can be
+/// ignored safely.
+const REQUIRED_FLAGS_MASK: u32 = 0xFFFF;
+
+/// Every flag this reader understands
+const KNOWN_FLAGS: u32 =
+    FLAG_PROGRESSIVE_JPEG | FLAG_MOTION_SCORES | FLAG_RAW_RGB565 | FLAG_MARKS | FLAG_AUDIO;
+
+/// Motion score below which a frame counts as static when auto-trimming
+pub const DEFAULT_STATIC_THRESHOLD: f32 = 0.01;
+
+/// Longest gap between frames kept in presentation times; a longer
+/// stall counts as one frame interval, so it doesn't freeze playback
+pub const MAX_PRESENTATION_GAP_NS: u64 = 1_000_000_000;
+
+/// Frames written between progress reports
+const PROGRESS_INTERVAL: usize = 30;
+
+// ============================================
+// ENCODE PROGRESS
+// ============================================
+
+/// How far a long encode has got, reported while it runs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EncodeProgress {
+    /// Frames written so far
+    pub frames_done: usize,
+
+    /// Frames in the clip
+    pub total_frames: usize,
+
+    /// Time since encoding started
+    pub elapsed: std::time::Duration,
+}
+
+impl EncodeProgress {
+    /// Returns how much is done, from 0.0 to 1.0.
+    pub fn fraction(&self) -> f32 {
+        if self.total_frames == 0 {
+            return 1.0;
+        }
+        self.frames_done as f32 / self.total_frames as f32
+    }
+}
+
 // ============================================
 // VIDEO ENCODER
 // ============================================
@@ -62,21 +185,123 @@ impl VideoEncoder {
         frames: &[CapturedFrame],
         output_path: &str,
         config: &Config,
+    ) -> ShadowplayResult<()> {
+        Self::encode_frames_with_marks(frames, &[], output_path, config)
+    }
+
+    /// Encodes frames to a video file, storing the marks that fall
+    /// within the clip (see [`ClipMark`]).
+    ///
+    /// Takes owned frames or the buffer's shared ones alike (see
+    /// [`crate::SharedFrameBuffer::snapshot_shared`]), so a save can
+    /// encode without copying frame data.
+    pub fn encode_frames_with_marks<F: Borrow<CapturedFrame>>(
+        frames: &[F],
+        marks: &[ClipMark],
+        output_path: &str,
+        config: &Config,
+    ) -> ShadowplayResult<()> {
+        Self::encode_clip(frames, marks, None, output_path, config)
+    }
+
+    /// Encodes frames and marks to a video file with the audio captured
+    /// alongside them.
+    ///
+    /// `audio_samples` are interleaved stereo, starting at the first
+    /// frame, so sound and picture stay in sync across the clip. On
+    /// MP4 export they become an AAC track. With no samples, this is
+    /// the same as [`VideoEncoder::encode_frames_with_marks`].
+    pub fn encode_frames_with_audio(
+        frames: &[CapturedFrame],
+        marks: &[ClipMark],
+        audio_samples: &[i16],
+        sample_rate: u32,
+        output_path: &str,
+        config: &Config,
+    ) -> ShadowplayResult<()> {
+        if audio_samples.is_empty() {
+            return Self::encode_frames_with_marks(frames, marks, output_path, config);
+        }
+
+        let audio = ClipAudio::stereo(audio_samples.to_vec(), sample_rate);
+        audio.validate()?;
+        Self::encode_clip(frames, marks, Some(&audio), output_path, config)
+    }
+
+    /// Encodes frames to a video file, reporting progress and stopping
+    /// early if asked.
+    ///
+    /// `on_progress` is called every few frames and once at the end.
+    /// `cancel` is checked before each frame; once it is set, the
+    /// partial file is deleted and [`ShadowplayError::Cancelled`] is
+    /// returned.
+    pub fn encode_frames_with_progress(
+        frames: &[CapturedFrame],
+        output_path: &str,
+        config: &Config,
+        mut on_progress: impl FnMut(EncodeProgress),
+        cancel: &AtomicBool,
+    ) -> ShadowplayResult<()> {
+        Self::encode_clip_tracked(frames, &[], None, output_path, config, &mut on_progress, cancel)
+    }
+
+    /// Encodes frames into a clip held in memory, e.g. to stream it to
+    /// another device without writing a file first.
+    ///
+    /// The bytes are exactly what [`VideoEncoder::encode_frames`] would
+    /// write; [`FrameReader::from_bytes`] reads them back.
+    pub fn encode_frames_to_memory<F: Borrow<CapturedFrame>>(
+        frames: &[F],
+        config: &Config,
+    ) -> ShadowplayResult<Vec<u8>> {
+        if frames.is_empty() {
+            return Err(ShadowplayError::Encoder("No frames to encode".to_string()));
+        }
+        let frames: Vec<&CapturedFrame> = frames.iter().map(Borrow::borrow).collect();
+        let encoder = Self::for_clip(&frames, config);
+        Self::check_frames(&frames)?;
+
+        let mut buffer = Vec::new();
+        let never = AtomicBool::new(false);
+        encoder.write_clip(&mut buffer, &frames, &[], None, &mut |_| {}, &never)?;
+        log::info!("Encoded {} frames to {} bytes in memory", frames.len(), buffer.len());
+        Ok(buffer)
+    }
+
+    /// Encodes frames, marks and optional audio to a video file.
+    fn encode_clip<F: Borrow<CapturedFrame>>(
+        frames: &[F],
+        marks: &[ClipMark],
+        audio: Option<&ClipAudio>,
+        output_path: &str,
+        config: &Config,
+    ) -> ShadowplayResult<()> {
+        let never = AtomicBool::new(false);
+        Self::encode_clip_tracked(frames, marks, audio, output_path, config, &mut |_| {}, &never)
+    }
+
+    /// Encodes a clip, reporting progress and checking for cancellation.
+    fn encode_clip_tracked<F: Borrow<CapturedFrame>>(
+        frames: &[F],
+        marks: &[ClipMark],
+        audio: Option<&ClipAudio>,
+        output_path: &str,
+        config: &Config,
+        on_progress: &mut dyn FnMut(EncodeProgress),
+        cancel: &AtomicBool,
     ) -> ShadowplayResult<()> {
         if frames.is_empty() {
             return Err(ShadowplayError::Encoder("No frames to encode".to_string()));
         }
+        let frames: Vec<&CapturedFrame> = frames.iter().map(Borrow::borrow).collect();
 
         log::info!("Encoding {} frames to {}", frames.len(), output_path);
         let start = std::time::Instant::now();
-
-        // Get dimensions from first frame
-        let first = &frames[0];
-        let encoder = Self::new(first.width, first.height, config.target_fps, config.video_bitrate);
+        let encoder = Self::for_clip(&frames, config);
 
         // For now, we'll create a simple format that stores the frames
         // In production, this would use MediaCodec for H.264 encoding
-        encoder.write_frames(frames, output_path)?;
+        encoder.write_frames_tracked(&frames, marks, audio, output_path, on_progress, cancel)?;
 
         let elapsed = start.elapsed();
         log::info!(
@@ -89,11 +314,50 @@ impl VideoEncoder {
         Ok(())
     }
 
+    /// Creates the encoder for a clip: its size from the first frame,
+    /// and its rate from when the frames were really captured rather
+    /// than the target.
+    fn for_clip(frames: &[&CapturedFrame], config: &Config) -> Self {
+        let first = &frames[0];
+        let fps = Self::clip_fps(frames, config.target_fps);
+        if fps != config.target_fps {
+            log::info!("Captured at {} fps (target {})", fps, config.target_fps);
+        }
+        Self::new(first.width, first.height, fps, config.video_bitrate)
+    }
+
     /// Writes frames to file.
     ///
     /// This is a simplified implementation. Real implementation would
     /// use hardware H.264 encoding.
-    fn write_frames(&self, frames: &[CapturedFrame], output_path: &str) -> ShadowplayResult<()> {
+    fn write_frames(
+        &self,
+        frames: &[CapturedFrame],
+        marks: &[ClipMark],
+        audio: Option<&ClipAudio>,
+        output_path: &str,
+    ) -> ShadowplayResult<()> {
+        let never = AtomicBool::new(false);
+        let frames: Vec<&CapturedFrame> = frames.iter().collect();
+        self.write_frames_tracked(&frames, marks, audio, output_path, &mut |_| {}, &never)
+    }
+
+    /// Writes frames to file, reporting progress every
+    /// [`PROGRESS_INTERVAL`] frames and deleting the partial file if
+    /// `cancel` is set.
+    fn write_frames_tracked(
+        &self,
+        frames: &[&CapturedFrame],
+        marks: &[ClipMark],
+        audio: Option<&ClipAudio>,
+        output_path: &str,
+        on_progress: &mut dyn FnMut(EncodeProgress),
+        cancel: &AtomicBool,
+    ) -> ShadowplayResult<()> {
+        let start = std::time::Instant::now();
+        // Reject bad frames before creating the file
+        Self::check_frames(frames)?;
+
         // Ensure parent directory exists
         if let Some(parent) = Path::new(output_path).parent() {
             std::fs::create_dir_all(parent)?;
@@ -101,29 +365,804 @@ impl VideoEncoder {
 
         // Create output file
         let mut file = File::create(output_path)?;
+        let written = self.write_clip(&mut file, frames, marks, audio, on_progress, cancel);
+        if let Err(ShadowplayError::Cancelled) = written {
+            drop(file);
+            std::fs::remove_file(output_path)?;
+            log::info!("Removed partial clip {}", output_path);
+            return written;
+        }
+        written?;
+
+        file.sync_all()?;
+        on_progress(EncodeProgress {
+            frames_done: frames.len(),
+            total_frames: frames.len(),
+            elapsed: start.elapsed(),
+        });
+
+        log::debug!("Wrote {} bytes to {}", file.metadata()?.len(), output_path);
+        Ok(())
+    }
+
+    /// Checks frames can go in one clip.
+    fn check_frames(frames: &[&CapturedFrame]) -> ShadowplayResult<()> {
+        // Reject frames from a failed readback before writing anything
+        for (index, frame) in frames.iter().enumerate() {
+            frame
+                .validate()
+                .map_err(|e| Self::at_frame(index, ShadowplayError::Encoder(e.to_string())))?;
+        }
+
+        // The format is recorded once per file, so it can't vary
+        let format = frames[0].format;
+        if frames.iter().any(|f| f.format != format) {
+            return Err(ShadowplayError::Encoder(
+                "Cannot mix JPEG and raw frames in one clip".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Writes a clip to `sink`, a file or a buffer in memory.
+    ///
+    /// Stops with [`ShadowplayError::Cancelled`] once `cancel` is set,
+    /// leaving what was written so far in the sink. Frames must have
+    /// passed [`VideoEncoder::check_frames`].
+    fn write_clip<W: Write>(
+        &self,
+        sink: &mut W,
+        frames: &[&CapturedFrame],
+        marks: &[ClipMark],
+        audio: Option<&ClipAudio>,
+        on_progress: &mut dyn FnMut(EncodeProgress),
+        cancel: &AtomicBool,
+    ) -> ShadowplayResult<()> {
+        let start = std::time::Instant::now();
+
+        // Record how the frames were compressed
+        let mut flags = 0u32;
+        if frames[0].format == FrameFormat::Rgb565 {
+            flags |= FLAG_RAW_RGB565;
+        } else if is_progressive_jpeg(&frames[0].data) {
+            flags |= FLAG_PROGRESSIVE_JPEG;
+        }
+        let has_motion = frames.iter().any(|f| f.motion_score.is_some());
+        if has_motion {
+            flags |= FLAG_MOTION_SCORES;
+        }
+        if audio.is_some() {
+            flags |= FLAG_AUDIO;
+        }
+
+        // Only keep marks inside the clip
+        let first_ns = frames[0].timestamp_ns;
+        let last_ns = frames[frames.len() - 1].timestamp_ns;
+        let marks: Vec<ClipMark> = marks
+            .iter()
+            .filter(|m| (first_ns..=last_ns).contains(&m.timestamp_ns))
+            .cloned()
+            .collect();
+        let marks_section = if marks.is_empty() {
+            Vec::new()
+        } else {
+            flags |= FLAG_MARKS;
+            marks::encode_marks(&marks)
+        };
+        let header_len = HEADER_LEN_V03 + marks_section.len() as u32;
 
         // Write a simple container format
         // Header: magic + version + frame count + width + height + fps
-        file.write_all(b"QSPLAY01")?; // Magic + version
-        file.write_all(&(frames.len() as u32).to_le_bytes())?;
-        file.write_all(&self.width.to_le_bytes())?;
-        file.write_all(&self.height.to_le_bytes())?;
-        file.write_all(&self.fps.to_le_bytes())?;
+        //         + flags + header length + [marks]
+        sink.write_all(MAGIC)?;
+        sink.write_all(FORMAT_VERSION)?;
+        sink.write_all(&(frames.len() as u32).to_le_bytes())?;
+        sink.write_all(&self.width.to_le_bytes())?;
+        sink.write_all(&self.height.to_le_bytes())?;
+        sink.write_all(&self.fps.to_le_bytes())?;
+        sink.write_all(&flags.to_le_bytes())?;
+        sink.write_all(&header_len.to_le_bytes())?;
+        sink.write_all(&marks_section)?;
 
-        // Write each frame: timestamp + eye_index + data_len + data
-        for frame in frames {
-            file.write_all(&frame.timestamp_ns.to_le_bytes())?;
-            file.write_all(&frame.eye_index.to_le_bytes())?;
-            file.write_all(&(frame.data.len() as u32).to_le_bytes())?;
-            file.write_all(&frame.data)?;
+        // Write each frame: timestamp + eye_index + [motion] + data_len + data
+        for (index, frame) in frames.iter().enumerate() {
+            if cancel.load(Ordering::Relaxed) {
+                log::info!("Encoding cancelled after {} frames", index);
+                return Err(ShadowplayError::Cancelled);
+            }
+            if index % PROGRESS_INTERVAL == 0 && index > 0 {
+                on_progress(EncodeProgress {
+                    frames_done: index,
+                    total_frames: frames.len(),
+                    elapsed: start.elapsed(),
+                });
+            }
+
+            sink.write_all(&frame.timestamp_ns.to_le_bytes())?;
+            sink.write_all(&frame.eye_index.to_le_bytes())?;
+            if has_motion {
+                let score = frame.motion_score.unwrap_or(f32::NAN);
+                sink.write_all(&score.to_le_bytes())?;
+            }
+            sink.write_all(&(frame.data.len() as u32).to_le_bytes())?;
+            sink.write_all(&frame.data)?;
         }
 
-        file.sync_all()?;
-        
-        log::debug!("Wrote {} bytes to {}", file.metadata()?.len(), output_path);
+        // Audio trails the frames, where older readers never look
+        if let Some(audio) = audio {
+            sink.write_all(&audio::encode_audio(audio))?;
+        }
         Ok(())
     }
 
+    /// Decodes a frame to RGBA pixels, whatever its storage format.
+    pub fn decode_frame(frame: &CapturedFrame) -> ShadowplayResult<image::RgbaImage> {
+        match frame.format {
+            FrameFormat::Jpeg => Self::decode_jpeg(frame),
+            FrameFormat::Rgb565 => {
+                image::RgbaImage::from_raw(frame.width, frame.height, rgb565_to_rgba(&frame.data))
+                    .ok_or_else(|| {
+                        ShadowplayError::Encoder(format!(
+                            "Raw frame is {} bytes, too small for {}x{}",
+                            frame.data.len(),
+                            frame.width,
+                            frame.height
+                        ))
+                    })
+            }
+        }
+    }
+
+    /// Returns a frame as JPEG data, encoding raw frames at `jpeg_quality`.
+    ///
+    /// JPEG frames are returned as-is.
+    pub fn frame_to_jpeg(frame: &CapturedFrame, jpeg_quality: u8) -> ShadowplayResult<Vec<u8>> {
+        if frame.format == FrameFormat::Jpeg {
+            return Ok(frame.data.clone());
+        }
+
+        let image = Self::decode_frame(frame)?;
+        FrameCompressor::new(jpeg_quality)
+            .compress(image.as_raw(), frame.width, frame.height)
+            .map_err(|e| ShadowplayError::Encoder(e.to_string()))
+    }
+
+    /// Decodes a frame's JPEG data to RGBA pixels.
+    pub fn decode_jpeg(frame: &CapturedFrame) -> ShadowplayResult<image::RgbaImage> {
+        let image = image::load_from_memory_with_format(&frame.data, image::ImageFormat::Jpeg)
+            .map_err(|e| {
+                ShadowplayError::Encoder(format!(
+                    "Failed to decode {}-byte JPEG at {} ns: {}",
+                    frame.data.len(),
+                    frame.timestamp_ns,
+                    e
+                ))
+            })?;
+        Ok(image.to_rgba8())
+    }
+
+    /// Decodes frame number `index` of a clip.
+    ///
+    /// Same as [`decode_frame`](Self::decode_frame), but a failure names
+    /// the frame, so a log line says which frame broke and why.
+    pub fn decode_frame_at(
+        frame: &CapturedFrame,
+        index: usize,
+    ) -> ShadowplayResult<image::RgbaImage> {
+        Self::decode_frame(frame).map_err(|e| Self::at_frame(index, e))
+    }
+
+    /// Prefixes an encoder error with the frame it happened on.
+    fn at_frame(index: usize, error: ShadowplayError) -> ShadowplayError {
+        match error {
+            ShadowplayError::Encoder(msg) => {
+                ShadowplayError::Encoder(format!("Frame {}: {}", index, msg))
+            }
+            other => other,
+        }
+    }
+
+    /// Reframes one frame to a target aspect ratio, re-compressing it
+    /// at `jpeg_quality`.
+    pub fn reframe(
+        frame: &CapturedFrame,
+        aspect: &ExportAspect,
+        jpeg_quality: u8,
+    ) -> ShadowplayResult<CapturedFrame> {
+        let image = aspect.apply(&Self::decode_frame(frame)?)?;
+        let (width, height) = image.dimensions();
+        let data = FrameCompressor::new(jpeg_quality)
+            .compress(image.as_raw(), width, height)
+            .map_err(|e| ShadowplayError::Encoder(e.to_string()))?;
+        Ok(CapturedFrame::with_timestamp(
+            data,
+            frame.eye_index,
+            width,
+            height,
+            frame.timestamp_ns,
+        ))
+    }
+
+    /// Writes a copy of a clip reframed to a target aspect ratio.
+    ///
+    /// Every frame is cropped, padded or stretched per `aspect`, and the
+    /// output header carries the new dimensions.
+    ///
+    /// Returns the output dimensions.
+    pub fn export_with_aspect(
+        input_path: &str,
+        output_path: &str,
+        aspect: &ExportAspect,
+        jpeg_quality: u8,
+    ) -> ShadowplayResult<(u32, u32)> {
+        let reader = FrameReader::open(input_path)?;
+        if reader.frame_count() == 0 {
+            return Err(ShadowplayError::Encoder("No frames to encode".to_string()));
+        }
+
+        let (width, height) = reader.dimensions();
+        let (out_w, out_h) = aspect.output_dimensions(width, height)?;
+        log::info!(
+            "Reframing {} from {}x{} to {}x{} ({:?})",
+            input_path,
+            width,
+            height,
+            out_w,
+            out_h,
+            aspect.mode
+        );
+
+        let frames = reader
+            .frames()
+            .iter()
+            .enumerate()
+            .map(|(index, frame)| {
+                Self::reframe(frame, aspect, jpeg_quality).map_err(|e| Self::at_frame(index, e))
+            })
+            .collect::<ShadowplayResult<Vec<_>>>()?;
+
+        // Bitrate isn't stored in clip files
+        let encoder = Self::new(out_w, out_h, reader.fps(), 0);
+        encoder.write_frames(&frames, reader.marks(), reader.audio(), output_path)?;
+
+        Ok((out_w, out_h))
+    }
+
+    /// Picks which frames should be keyframes when exporting to video.
+    ///
+    /// The first frame always is. After that a frame becomes a keyframe
+    /// when its motion score is above `scene_cut_threshold` (a likely
+    /// scene change, so the cut lands where the picture changes anyway),
+    /// when `max_interval` frames have passed since the last one, or
+    /// when `max_interval_secs` of presentation time have (see
+    /// [`VideoEncoder::presentation_times_us`]; untimed frames are
+    /// spaced at `fps`). Frames without a score only count towards the
+    /// intervals; a threshold of 0 disables scene cuts and 0 seconds
+    /// leaves only the frame count.
+    pub fn keyframe_indices(
+        frames: &[CapturedFrame],
+        scene_cut_threshold: f32,
+        max_interval: u32,
+        max_interval_secs: f32,
+        fps: u32,
+    ) -> Vec<usize> {
+        let max_interval = max_interval.max(1) as usize;
+        let max_interval_us = (max_interval_secs * 1e6) as u64;
+        let times_us = Self::presentation_times_us(frames, fps);
+        let mut keyframes: Vec<usize> = Vec::new();
+
+        for (i, frame) in frames.iter().enumerate() {
+            let scene_cut = scene_cut_threshold > 0.0
+                && frame.motion_score.is_some_and(|s| s > scene_cut_threshold);
+            let due = keyframes.last().is_none_or(|&last| {
+                i - last >= max_interval
+                    || (max_interval_us > 0 && times_us[i] - times_us[last] >= max_interval_us)
+            });
+            if due || scene_cut {
+                keyframes.push(i);
+            }
+        }
+
+        keyframes
+    }
+
+    /// Writes a copy of a two-eye clip with each left/right pair packed
+    /// into one 3D frame per `layout`.
+    ///
+    /// Eyes are paired by timestamp (within half a frame interval). A
+    /// frame with no partner is left out, except with
+    /// [`StereoLayout::MonoAverage`], where it passes through as-is.
+    /// Output frames are compressed at `jpeg_quality` and keep the left
+    /// eye's timestamp.
+    ///
+    /// Returns the number of frames written and of unmatched frames.
+    pub fn export_stereo(
+        input_path: &str,
+        output_path: &str,
+        layout: StereoLayout,
+        jpeg_quality: u8,
+    ) -> ShadowplayResult<(usize, usize)> {
+        let reader = FrameReader::open(input_path)?;
+        let tolerance_ns = 500_000_000 / reader.fps().max(1) as u64;
+        let matches = match_eyes(reader.frames(), tolerance_ns);
+        let unmatched = matches
+            .iter()
+            .filter(|m| matches!(m, EyeMatch::Single(_)))
+            .count();
+        if unmatched == matches.len() {
+            return Err(ShadowplayError::Encoder(
+                "No left/right eye pairs to export".to_string(),
+            ));
+        }
+
+        let (eye_w, eye_h) = reader.dimensions();
+        let (out_w, out_h) = layout.output_dimensions(eye_w, eye_h);
+        log::info!(
+            "Stereo export {} ({:?}, {}x{}): {} pairs, {} unmatched",
+            input_path,
+            layout,
+            out_w,
+            out_h,
+            matches.len() - unmatched,
+            unmatched
+        );
+
+        let compressor = FrameCompressor::new(jpeg_quality);
+        let frames = matches
+            .iter()
+            .filter_map(|m| match *m {
+                EyeMatch::Pair(left, right) => Some((left, Some(right))),
+                EyeMatch::Single(frame) if layout.keeps_unmatched() => Some((frame, None)),
+                EyeMatch::Single(_) => None,
+            })
+            .map(|(left, right)| {
+                let data = match right {
+                    Some(right) => {
+                        let packed = layout
+                            .composite(&Self::decode_frame(left)?, &Self::decode_frame(right)?)?;
+                        compressor
+                            .compress(packed.as_raw(), out_w, out_h)
+                            .map_err(|e| ShadowplayError::Encoder(e.to_string()))?
+                    }
+                    None => Self::frame_to_jpeg(left, jpeg_quality)?,
+                };
+                Ok(CapturedFrame::with_timestamp(
+                    data,
+                    0,
+                    out_w,
+                    out_h,
+                    left.timestamp_ns,
+                ))
+            })
+            .collect::<ShadowplayResult<Vec<_>>>()?;
+
+        // Sound starts with the first packed frame
+        let audio = match (reader.audio(), reader.time_range_ns(), frames.first()) {
+            (Some(audio), Some((start_ns, _)), Some(first)) => {
+                Some(audio.slice(first.timestamp_ns.saturating_sub(start_ns), None))
+            }
+            _ => None,
+        };
+
+        let encoder = Self::new(out_w, out_h, reader.fps(), 0);
+        encoder.write_frames(&frames, reader.marks(), audio.as_ref(), output_path)?;
+
+        Ok((frames.len(), unmatched))
+    }
+
+    /// Packs a two-eye clip's frames into one 3D frame per moment, for
+    /// 3D export in a single file.
+    ///
+    /// Eyes are paired by timestamp (within half a frame interval at
+    /// `fps`). A left frame whose right eye is missing fills both halves,
+    /// so the moment stays in the clip, just flat for that frame; right
+    /// frames with no left eye are dropped. With
+    /// [`StereoLayout::MonoAverage`], a lone frame passes through as-is.
+    /// Packed frames are compressed at `jpeg_quality` and keep the left
+    /// eye's timestamp and motion score.
+    pub fn pack_stereo(
+        frames: &[CapturedFrame],
+        layout: StereoLayout,
+        fps: u32,
+        jpeg_quality: u8,
+    ) -> ShadowplayResult<Vec<CapturedFrame>> {
+        let tolerance_ns = 500_000_000 / fps.max(1) as u64;
+        let compressor = FrameCompressor::new(jpeg_quality);
+        let mut filled = 0;
+
+        let mut packed = Vec::new();
+        for m in match_eyes(frames, tolerance_ns) {
+            let (left, right) = match m {
+                EyeMatch::Pair(left, right) => (left, Some(right)),
+                EyeMatch::Single(frame) if layout.keeps_unmatched() => {
+                    packed.push(frame.clone());
+                    continue;
+                }
+                EyeMatch::Single(frame) if frame.eye_index == 0 => {
+                    filled += 1;
+                    (frame, None)
+                }
+                EyeMatch::Single(_) => continue,
+            };
+
+            let left_image = Self::decode_frame(left)?;
+            let right_image = match right {
+                Some(right) => Self::decode_frame(right)?,
+                None => left_image.clone(),
+            };
+            let image = layout.composite(&left_image, &right_image)?;
+            let data = compressor
+                .compress(image.as_raw(), image.width(), image.height())
+                .map_err(|e| ShadowplayError::Encoder(e.to_string()))?;
+            let mut frame = CapturedFrame::with_timestamp(
+                data,
+                0,
+                image.width(),
+                image.height(),
+                left.timestamp_ns,
+            );
+            frame.motion_score = left.motion_score;
+            packed.push(frame);
+        }
+
+        if filled > 0 {
+            log::warn!("{} frames had no right eye; showing the left eye in both", filled);
+        }
+        if packed.is_empty() {
+            return Err(ShadowplayError::Encoder("No left-eye frames to pack".to_string()));
+        }
+        Ok(packed)
+    }
+
+    /// Writes a copy of a clip with static frames trimmed from both ends.
+    ///
+    /// A frame is "active" if its motion score is above `threshold`.
+    /// Stored motion scores are used when the clip has them; otherwise
+    /// frames are decoded and compared. If the whole clip is static it
+    /// is written unchanged rather than trimmed to nothing.
+    ///
+    /// Returns the range of input frames that were kept.
+    pub fn auto_trim_static(
+        input_path: &str,
+        output_path: &str,
+        threshold: f32,
+    ) -> ShadowplayResult<std::ops::Range<usize>> {
+        let reader = FrameReader::open(input_path)?;
+        let frames = reader.frames();
+        if frames.is_empty() {
+            return Err(ShadowplayError::Encoder("No frames to encode".to_string()));
+        }
+
+        let scores = if reader.has_motion_scores() {
+            reader
+                .motion_scores()
+                .into_iter()
+                .map(|s| s.unwrap_or(0.0))
+                .collect()
+        } else {
+            Self::compute_motion_scores(frames)?
+        };
+
+        let first_active = scores.iter().position(|&s| s > threshold);
+        let last_active = scores.iter().rposition(|&s| s > threshold);
+
+        let kept = match (first_active, last_active) {
+            // A score measures change from the previous frame, so keep
+            // the frame before the first change too
+            (Some(first), Some(last)) => first.saturating_sub(1)..last + 1,
+            _ => {
+                log::warn!("{} is static throughout, leaving it untrimmed", input_path);
+                0..frames.len()
+            }
+        };
+
+        log::info!(
+            "Auto-trim {}: keeping frames {}..{} of {}",
+            input_path,
+            kept.start,
+            kept.end,
+            frames.len()
+        );
+
+        // Sound is cut to the frames kept
+        let start_ns = frames[0].timestamp_ns;
+        let offset = |index: usize| frames[index].timestamp_ns.saturating_sub(start_ns);
+        let audio = reader.audio().map(|audio| {
+            audio.slice(offset(kept.start), frames.get(kept.end).map(|_| offset(kept.end)))
+        });
+
+        let (width, height) = reader.dimensions();
+        let encoder = Self::new(width, height, reader.fps(), 0);
+        encoder.write_frames(&frames[kept.clone()], reader.marks(), audio.as_ref(), output_path)?;
+
+        Ok(kept)
+    }
+
+    /// Writes a copy of a clip with capture stalls cut out.
+    ///
+    /// Any gap between consecutive frames longer than `max_gap_ms` is
+    /// shortened to one frame interval, and every later timestamp (and
+    /// mark) is shifted back by the time removed, so playback runs on
+    /// without a frozen span.
+    ///
+    /// Returns the total time removed.
+    pub fn remove_gaps(
+        input_path: &str,
+        output_path: &str,
+        max_gap_ms: u32,
+    ) -> ShadowplayResult<std::time::Duration> {
+        let reader = FrameReader::open(input_path)?;
+        if reader.frame_count() == 0 {
+            return Err(ShadowplayError::Encoder("No frames to encode".to_string()));
+        }
+
+        // The header rate averages over the stalls, so use the usual
+        // interval between frames for what a stall shrinks to
+        let max_gap_ns = max_gap_ms as u64 * 1_000_000;
+        let interval_ns = Self::typical_interval_ns(reader.frames())
+            .unwrap_or(1_000_000_000 / reader.fps().max(1) as u64);
+
+        // (original timestamp, time removed up to there) per stall, and
+        // the span of sound removed with it
+        let mut cuts: Vec<(u64, u64)> = Vec::new();
+        let mut silenced: Vec<(u64, u64)> = Vec::new();
+        let mut removed_ns = 0u64;
+        let mut frames = reader.frames().to_vec();
+        let start_ns = frames[0].timestamp_ns;
+        let mut previous_ns = start_ns;
+
+        for frame in &mut frames {
+            let original_ns = frame.timestamp_ns;
+            let gap_ns = original_ns.saturating_sub(previous_ns);
+            if gap_ns > max_gap_ns {
+                let cut_ns = gap_ns - interval_ns.min(gap_ns);
+                removed_ns += cut_ns;
+                cuts.push((original_ns, removed_ns));
+                silenced.push((original_ns - cut_ns - start_ns, cut_ns));
+            }
+            previous_ns = original_ns;
+            frame.timestamp_ns = original_ns - removed_ns;
+        }
+
+        // Marks shift with the frames before them; marks inside a removed
+        // stall land on the frame that ended it
+        let marks: Vec<ClipMark> = reader
+            .marks()
+            .iter()
+            .map(|mark| {
+                let shift = cuts
+                    .iter()
+                    .rev()
+                    .find(|(end_ns, _)| *end_ns <= mark.timestamp_ns)
+                    .map_or(0, |(_, removed)| *removed);
+                let stall_end = cuts
+                    .iter()
+                    .find(|(end_ns, _)| *end_ns > mark.timestamp_ns)
+                    .map_or(u64::MAX, |(end_ns, removed)| end_ns - removed);
+                ClipMark::new((mark.timestamp_ns - shift).min(stall_end), mark.label.clone())
+            })
+            .collect();
+
+        let removed = std::time::Duration::from_nanos(removed_ns);
+        log::info!(
+            "Removed {} gap(s) totalling {:.2}s from {}",
+            cuts.len(),
+            removed.as_secs_f64(),
+            input_path
+        );
+
+        let audio = reader.audio().map(|audio| audio.without_spans(&silenced));
+
+        let (width, height) = reader.dimensions();
+        let encoder = Self::new(width, height, Self::clip_fps(&frames, reader.fps()), 0);
+        encoder.write_frames(&frames, &marks, audio.as_ref(), output_path)?;
+
+        Ok(removed)
+    }
+
+    /// Works out a clip's real frame rate from its timestamps, the same
+    /// way it plays back (see [`VideoEncoder::clip_fps`]).
+    ///
+    /// A pause counts as one frame at the typical rate, so it doesn't
+    /// drag the rate down. Returns `None` with fewer than two frames or
+    /// no time between them.
+    pub fn detect_fps(frames: &[CapturedFrame]) -> Option<u32> {
+        let typical_fps = (1e9 / Self::typical_interval_ns(frames)? as f64).round() as u32;
+        Some(Self::clip_fps(frames, typical_fps.max(1)))
+    }
+
+    /// Returns each frame's presentation time in microseconds, from its
+    /// capture timestamp, with the first frame at 0.
+    ///
+    /// Gaps longer than [`MAX_PRESENTATION_GAP_NS`] (a stall or a pause)
+    /// count as one frame at `fallback_fps`, so they don't freeze the
+    /// video. If every timestamp is the same (or missing, i.e. 0),
+    /// frames are spaced evenly at `fallback_fps` instead.
+    pub fn presentation_times_us<F: Borrow<CapturedFrame>>(
+        frames: &[F],
+        fallback_fps: u32,
+    ) -> Vec<u64> {
+        let frames: Vec<&CapturedFrame> = frames.iter().map(Borrow::borrow).collect();
+        let interval_ns = 1_000_000_000 / fallback_fps.max(1) as u64;
+        let Some(first) = frames.first() else {
+            return Vec::new();
+        };
+        if frames.iter().all(|f| f.timestamp_ns == first.timestamp_ns) {
+            return (0..frames.len() as u64).map(|i| i * interval_ns / 1000).collect();
+        }
+
+        let mut pts_ns = 0u64;
+        let mut previous_ns = first.timestamp_ns;
+        frames
+            .iter()
+            .map(|frame| {
+                // Out-of-order frames show at the same time as the last
+                let gap_ns = frame.timestamp_ns.saturating_sub(previous_ns);
+                pts_ns += if gap_ns > MAX_PRESENTATION_GAP_NS { interval_ns } else { gap_ns };
+                previous_ns = previous_ns.max(frame.timestamp_ns);
+                pts_ns / 1000
+            })
+            .collect()
+    }
+
+    /// Returns the frame rate frames were really captured at, from their
+    /// presentation times (see [`VideoEncoder::presentation_times_us`]).
+    ///
+    /// Counts only the first frame's eye, so stereo clips aren't counted
+    /// twice. Falls back to `fallback_fps` when the timestamps can't
+    /// tell (fewer than two frames, or all at the same time).
+    pub fn clip_fps<F: Borrow<CapturedFrame>>(frames: &[F], fallback_fps: u32) -> u32 {
+        let Some(eye) = frames.first().map(|f| f.borrow().eye_index) else {
+            return fallback_fps;
+        };
+        let times: Vec<u64> = frames
+            .iter()
+            .map(Borrow::borrow)
+            .zip(Self::presentation_times_us(frames, fallback_fps))
+            .filter(|(frame, _)| frame.eye_index == eye)
+            .map(|(_, pts_us)| pts_us)
+            .collect();
+
+        match times.last() {
+            Some(&last_us) if times.len() > 1 && last_us > 0 => {
+                let fps = ((times.len() - 1) as f64 * 1e6 / last_us as f64).round() as u32;
+                fps.max(1)
+            }
+            _ => fallback_fps,
+        }
+    }
+
+    /// Returns the median time between frames of the first frame's eye,
+    /// which unlike the average isn't thrown off by a few long stalls.
+    fn typical_interval_ns(frames: &[CapturedFrame]) -> Option<u64> {
+        let eye = frames.first()?.eye_index;
+        let times: Vec<u64> = frames
+            .iter()
+            .filter(|f| f.eye_index == eye)
+            .map(|f| f.timestamp_ns)
+            .collect();
+        let mut intervals: Vec<u64> = times
+            .windows(2)
+            .map(|pair| pair[1].saturating_sub(pair[0]))
+            .filter(|&interval| interval > 0)
+            .collect();
+        intervals.sort_unstable();
+        intervals.get(intervals.len() / 2).copied()
+    }
+
+    /// Rewrites a clip's declared frame rate to the one its timestamps
+    /// show (see [`VideoEncoder::detect_fps`]), in place.
+    ///
+    /// Fixes playback speed of clips saved with a wrong fps. Only the
+    /// header field changes; frames are not re-encoded.
+    ///
+    /// Returns the old and new fps (equal if nothing needed fixing).
+    pub fn repair_fps(path: &str) -> ShadowplayResult<(u32, u32)> {
+        use std::io::{Seek, SeekFrom};
+
+        let reader = FrameReader::open(path)?;
+        let old_fps = reader.fps();
+        let new_fps = Self::detect_fps(reader.frames()).ok_or_else(|| {
+            ShadowplayError::Encoder("Need two timed frames to detect the frame rate".to_string())
+        })?;
+
+        if new_fps != old_fps {
+            let mut file = std::fs::OpenOptions::new().write(true).open(path)?;
+            file.seek(SeekFrom::Start(FPS_OFFSET))?;
+            file.write_all(&new_fps.to_le_bytes())?;
+            file.sync_all()?;
+            log::info!("Repaired frame rate of {}: {} -> {} fps", path, old_fps, new_fps);
+        }
+        Ok((old_fps, new_fps))
+    }
+
+    /// Scores each frame's change from the previous one by decoding it.
+    fn compute_motion_scores(frames: &[CapturedFrame]) -> ShadowplayResult<Vec<f32>> {
+        let mut tracker = MotionTracker::new();
+        frames
+            .iter()
+            .enumerate()
+            .map(|(index, frame)| {
+                let image = Self::decode_frame_at(frame, index)?;
+                let (width, height) = image.dimensions();
+                Ok(tracker.score(frame.eye_index, image.as_raw(), width, height))
+            })
+            .collect()
+    }
+
+    /// Compares two clips of the same moment, frame by frame.
+    ///
+    /// Frames are matched by timestamp (within half a frame interval),
+    /// decoded, and scored with PSNR and SSIM. Useful for A/B testing
+    /// quality and bitrate settings on identical footage.
+    pub fn compare(clip_a: &str, clip_b: &str) -> ShadowplayResult<QualityReport> {
+        let reader_a = FrameReader::open(clip_a)?;
+        let reader_b = FrameReader::open(clip_b)?;
+        let (frames_a, frames_b) = (reader_a.frames(), reader_b.frames());
+
+        let tolerance_ns = 500_000_000 / reader_a.fps().max(1) as u64;
+
+        let mut results = Vec::new();
+        let mut unmatched = 0;
+        let (mut i, mut j) = (0, 0);
+
+        while i < frames_a.len() && j < frames_b.len() {
+            let (a, b) = (&frames_a[i], &frames_b[j]);
+
+            if a.timestamp_ns.abs_diff(b.timestamp_ns) <= tolerance_ns {
+                let image_a = Self::decode_frame_at(a, i)?;
+                let image_b = Self::decode_frame_at(b, j)?;
+
+                if image_a.dimensions() == image_b.dimensions() {
+                    let (width, height) = image_a.dimensions();
+                    results.push(FrameQuality {
+                        timestamp_ns: a.timestamp_ns,
+                        psnr_db: quality::psnr(&image_a, &image_b),
+                        ssim: quality::ssim(&image_a, &image_b, width, height),
+                    });
+                } else {
+                    log::warn!("Frame size mismatch at {} ns, skipping", a.timestamp_ns);
+                    unmatched += 2;
+                }
+
+                i += 1;
+                j += 1;
+            } else if a.timestamp_ns < b.timestamp_ns {
+                unmatched += 1;
+                i += 1;
+            } else {
+                unmatched += 1;
+                j += 1;
+            }
+        }
+        unmatched += (frames_a.len() - i) + (frames_b.len() - j);
+
+        let mut report = QualityReport::from_frames(results, unmatched);
+        report.size_a_bytes = std::fs::metadata(clip_a)?.len();
+        report.size_b_bytes = std::fs::metadata(clip_b)?.len();
+
+        log::info!(
+            "Compared clips: {} matched, PSNR {:.2} dB, SSIM {:.4}",
+            report.matched_frames,
+            report.mean_psnr_db,
+            report.mean_ssim
+        );
+
+        Ok(report)
+    }
+
+    /// Estimates the size of a clip of `frames` without marks or audio,
+    /// to check for disk space before writing it.
+    pub fn estimated_clip_bytes<F: Borrow<CapturedFrame>>(frames: &[F]) -> u64 {
+        let has_motion = frames.iter().any(|f| f.borrow().motion_score.is_some());
+        let record_len = FRAME_RECORD_LEN + if has_motion { 4 } else { 0 };
+        let frame_bytes: u64 = frames
+            .iter()
+            .map(|f| record_len + f.borrow().data.len() as u64)
+            .sum();
+        HEADER_LEN_V03 as u64 + frame_bytes
+    }
+
     /// Returns encoder info.
     pub fn info(&self) -> EncoderInfo {
         EncoderInfo {
@@ -162,6 +1201,9 @@ pub struct FrameReader {
     width: u32,
     height: u32,
     fps: u32,
+    flags: u32,
+    marks: Vec<ClipMark>,
+    audio: Option<ClipAudio>,
 }
 
 impl FrameReader {
@@ -169,34 +1211,153 @@ impl FrameReader {
     pub fn open(path: &str) -> ShadowplayResult<Self> {
         use std::io::Read;
 
-        let mut file = File::open(path)?;
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer)?;
+        let mut file = File::open(path)?;
+        let mut buffer = Vec::new();
+        file.read_to_end(&mut buffer)?;
+
+        Self::parse(&buffer)
+    }
+
+    /// Reads a clip held in memory, e.g. from
+    /// [`VideoEncoder::encode_frames_to_memory`].
+    pub fn from_bytes(data: &[u8]) -> ShadowplayResult<Self> {
+        Self::parse(data)
+    }
+
+    /// Checks that a clip can be read by this version, without loading
+    /// its frames.
+    ///
+    /// Returns the file's format version.
+    pub fn check_version(path: &str) -> ShadowplayResult<u32> {
+        use std::io::Read;
+
+        let mut header = Vec::with_capacity(HEADER_LEN_V03 as usize);
+        File::open(path)?
+            .take(HEADER_LEN_V03 as u64)
+            .read_to_end(&mut header)?;
 
-        Self::parse(&buffer)
+        let version = Self::parse_version(&header)?;
+        if header.len() < Self::fixed_header_len(version) {
+            return Err(ShadowplayError::Encoder("File too small".to_string()));
+        }
+        if version >= 2 {
+            Self::check_flags(u32::from_le_bytes([header[24], header[25], header[26], header[27]]))?;
+        }
+        Ok(version)
     }
 
-    /// Parses clip data.
-    fn parse(data: &[u8]) -> ShadowplayResult<Self> {
-        if data.len() < 24 {
+    /// Reads the magic and version, rejecting versions we can't read.
+    fn parse_version(data: &[u8]) -> ShadowplayResult<u32> {
+        if data.len() < 8 {
             return Err(ShadowplayError::Encoder("File too small".to_string()));
         }
 
         // Check magic
-        if &data[0..8] != b"QSPLAY01" {
+        if &data[0..6] != MAGIC {
             return Err(ShadowplayError::Encoder("Invalid file format".to_string()));
         }
 
+        let version = std::str::from_utf8(&data[6..8])
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .ok_or_else(|| ShadowplayError::Encoder("Invalid file version".to_string()))?;
+
+        if !SUPPORTED_VERSIONS.contains(&version) {
+            return Err(ShadowplayError::Encoder(format!(
+                "Unsupported file version {:02} (this build reads {:02} to {:02})",
+                version,
+                SUPPORTED_VERSIONS.start(),
+                SUPPORTED_VERSIONS.end()
+            )));
+        }
+
+        Ok(version)
+    }
+
+    /// Returns the size of a version's required header fields.
+    fn fixed_header_len(version: u32) -> usize {
+        match version {
+            1 => 24,
+            2 => 28,
+            _ => HEADER_LEN_V03 as usize,
+        }
+    }
+
+    /// Rejects files that use layout-changing flags we don't know.
+    fn check_flags(flags: u32) -> ShadowplayResult<()> {
+        let unknown_required = flags & REQUIRED_FLAGS_MASK & !KNOWN_FLAGS;
+        if unknown_required != 0 {
+            return Err(ShadowplayError::Encoder(format!(
+                "Clip uses unsupported features (flags {:#x}); update to read it",
+                unknown_required
+            )));
+        }
+        Ok(())
+    }
+
+    /// Parses clip data.
+    fn parse(data: &[u8]) -> ShadowplayResult<Self> {
+        let version = Self::parse_version(data)?;
+
+        let fixed_len = Self::fixed_header_len(version);
+        if data.len() < fixed_len {
+            return Err(ShadowplayError::Encoder("File too small".to_string()));
+        }
+
         let frame_count = u32::from_le_bytes([data[8], data[9], data[10], data[11]]) as usize;
         let width = u32::from_le_bytes([data[12], data[13], data[14], data[15]]);
         let height = u32::from_le_bytes([data[16], data[17], data[18], data[19]]);
         let fps = u32::from_le_bytes([data[20], data[21], data[22], data[23]]);
 
-        let mut frames = Vec::with_capacity(frame_count);
-        let mut offset = 24;
+        // Version 01 has no flags field
+        let flags = if version >= 2 {
+            u32::from_le_bytes([data[24], data[25], data[26], data[27]])
+        } else {
+            0
+        };
+        Self::check_flags(flags)?;
+
+        // Version 03+ records its header length, so optional sections
+        // added later are skipped
+        let header_len = if version >= 3 {
+            let len = u32::from_le_bytes([data[28], data[29], data[30], data[31]]) as usize;
+            if len < fixed_len || len > data.len() {
+                return Err(ShadowplayError::Encoder(format!(
+                    "Invalid header length {}",
+                    len
+                )));
+            }
+            len
+        } else {
+            fixed_len
+        };
+
+        // Marks are optional: a damaged section loses the marks, not the clip
+        let marks = if version >= 3 && flags & FLAG_MARKS != 0 {
+            marks::decode_marks(&data[fixed_len..header_len]).unwrap_or_else(|e| {
+                log::warn!("Ignoring clip marks: {}", e);
+                Vec::new()
+            })
+        } else {
+            Vec::new()
+        };
+
+        let has_motion = flags & FLAG_MOTION_SCORES != 0;
+        let format = if flags & FLAG_RAW_RGB565 != 0 {
+            FrameFormat::Rgb565
+        } else {
+            FrameFormat::Jpeg
+        };
+        let record_header_len = if has_motion { 20 } else { 16 };
+
+        // The count comes from the file, so don't trust it further than
+        // the records the file has room for
+        let room = (data.len() - header_len) / record_header_len;
+        let mut frames = Vec::with_capacity(frame_count.min(room));
+        let mut offset = header_len;
 
         for _ in 0..frame_count {
-            if offset + 16 > data.len() {
+            if offset + record_header_len > data.len() {
                 break;
             }
 
@@ -211,6 +1372,16 @@ impl FrameReader {
             ]);
             offset += 4;
 
+            let motion_score = if has_motion {
+                let score = f32::from_le_bytes([
+                    data[offset], data[offset + 1], data[offset + 2], data[offset + 3],
+                ]);
+                offset += 4;
+                (!score.is_nan()).then_some(score)
+            } else {
+                None
+            };
+
             let data_len = u32::from_le_bytes([
                 data[offset], data[offset + 1], data[offset + 2], data[offset + 3],
             ]) as usize;
@@ -223,20 +1394,38 @@ impl FrameReader {
             let frame_data = data[offset..offset + data_len].to_vec();
             offset += data_len;
 
-            frames.push(CapturedFrame::with_timestamp(
-                frame_data,
-                eye_index,
-                width,
-                height,
-                timestamp_ns,
-            ));
+            frames.push(
+                CapturedFrame::with_timestamp(frame_data, eye_index, width, height, timestamp_ns)
+                    .with_format(format)
+                    .with_motion_score(motion_score),
+            );
         }
 
+        if frames.len() < frame_count {
+            log::warn!(
+                "Clip is truncated: read {} of {} frames",
+                frames.len(),
+                frame_count
+            );
+        }
+
+        // Audio is optional too: a damaged section loses the sound only
+        let audio = if flags & FLAG_AUDIO != 0 && frames.len() == frame_count {
+            audio::decode_audio(&data[offset..])
+                .map_err(|e| log::warn!("Ignoring clip audio: {}", e))
+                .ok()
+        } else {
+            None
+        };
+
         Ok(Self {
             frames,
             width,
             height,
             fps,
+            flags,
+            marks,
+            audio,
         })
     }
 
@@ -245,6 +1434,19 @@ impl FrameReader {
         &self.frames
     }
 
+    /// Returns frame `index`, or `None` past the end.
+    pub fn frame(&self, index: usize) -> Option<&CapturedFrame> {
+        self.frames.get(index)
+    }
+
+    /// Decodes frame `index` to pixels, only when asked for.
+    pub fn decode(&self, index: usize) -> ShadowplayResult<image::RgbaImage> {
+        let frame = self.frame(index).ok_or_else(|| {
+            ShadowplayError::Encoder(format!("Frame {} is past the end of the clip", index))
+        })?;
+        VideoEncoder::decode_frame_at(frame, index)
+    }
+
     /// Returns frame count.
     pub fn frame_count(&self) -> usize {
         self.frames.len()
@@ -264,6 +1466,64 @@ impl FrameReader {
     pub fn duration_secs(&self) -> f32 {
         self.frames.len() as f32 / self.fps as f32
     }
+
+    /// Returns true if the frames are stored as progressive JPEGs.
+    pub fn is_progressive(&self) -> bool {
+        self.flags & FLAG_PROGRESSIVE_JPEG != 0
+    }
+
+    /// Returns the marks stored in the clip, oldest first.
+    pub fn marks(&self) -> &[ClipMark] {
+        &self.marks
+    }
+
+    /// Returns the audio stored with the clip, if any.
+    pub fn audio(&self) -> Option<&ClipAudio> {
+        self.audio.as_ref()
+    }
+
+    /// Returns the timestamps of the first and last frame.
+    pub fn time_range_ns(&self) -> Option<(u64, u64)> {
+        Some((self.frames.first()?.timestamp_ns, self.frames.last()?.timestamp_ns))
+    }
+
+    /// Returns whether frames carry motion scores.
+    pub fn has_motion_scores(&self) -> bool {
+        self.flags & FLAG_MOTION_SCORES != 0
+    }
+
+    /// Returns each frame's motion score, in frame order.
+    pub fn motion_scores(&self) -> Vec<Option<f32>> {
+        self.frames.iter().map(|f| f.motion_score).collect()
+    }
+
+    /// Finds the run of `window` frames with the most motion.
+    ///
+    /// Returns the index of the first frame and the window's mean score,
+    /// or `None` if the clip has no motion scores or is shorter than
+    /// `window`. Unscored frames count as 0.0.
+    pub fn most_active_segment(&self, window: usize) -> Option<(usize, f32)> {
+        if !self.has_motion_scores() || window == 0 || window > self.frames.len() {
+            return None;
+        }
+
+        let scores: Vec<f32> = self
+            .frames
+            .iter()
+            .map(|f| f.motion_score.unwrap_or(0.0))
+            .collect();
+
+        let mut sum: f32 = scores[..window].iter().sum();
+        let mut best = (0, sum);
+        for start in 1..=scores.len() - window {
+            sum += scores[start + window - 1] - scores[start - 1];
+            if sum > best.1 {
+                best = (start, sum);
+            }
+        }
+
+        Some((best.0, best.1 / window as f32))
+    }
 }
 
 // ============================================
@@ -323,5 +1583,672 @@ mod tests {
         let reader = FrameReader::open(path_str).unwrap();
         assert_eq!(reader.frame_count(), 10);
         assert_eq!(reader.dimensions(), (100, 100));
+        assert!(!reader.is_progressive());
+        for (read, written) in reader.frames().iter().zip(&frames) {
+            assert_eq!(read.data, written.data);
+            assert_eq!(read.timestamp_ns, written.timestamp_ns);
+        }
+    }
+
+    #[test]
+    fn test_frame_access() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("gradient.qsp");
+        write_gradient_clip(path.to_str().unwrap(), 90);
+
+        let reader = FrameReader::open(path.to_str().unwrap()).unwrap();
+        assert_eq!(reader.frame(2).unwrap().timestamp_ns, 2 * 11_111_111);
+        assert!(reader.frame(3).is_none());
+        assert_eq!(reader.decode(1).unwrap().dimensions(), (32, 32));
+        assert!(reader.decode(3).is_err());
+    }
+
+    /// Writes a clip of gradient frames compressed at the given quality.
+    fn write_gradient_clip(path: &str, quality: u8) {
+        let compressor = crate::capture::FrameCompressor::new(quality);
+        let frames: Vec<_> = (0..3u64)
+            .map(|i| {
+                let mut rgba = vec![0u8; 32 * 32 * 4];
+                for (p, px) in rgba.chunks_exact_mut(4).enumerate() {
+                    px[0] = (p as u64 * 7 + i * 20) as u8;
+                    px[1] = (p / 32) as u8 * 8;
+                    px[2] = 100;
+                    px[3] = 255;
+                }
+                let data = compressor.compress(&rgba, 32, 32).unwrap();
+                CapturedFrame::with_timestamp(data, 0, 32, 32, i * 11_111_111)
+            })
+            .collect();
+
+        VideoEncoder::encode_frames(&frames, path, &Config::default()).unwrap();
+    }
+
+    #[test]
+    fn test_compare_clips() {
+        let dir = tempdir().unwrap();
+        let high = dir.path().join("high.qsp");
+        let low = dir.path().join("low.qsp");
+        write_gradient_clip(high.to_str().unwrap(), 95);
+        write_gradient_clip(low.to_str().unwrap(), 20);
+
+        // A clip compared with itself is identical
+        let same = VideoEncoder::compare(high.to_str().unwrap(), high.to_str().unwrap()).unwrap();
+        assert_eq!(same.matched_frames, 3);
+        assert_eq!(same.mean_psnr_db, quality::MAX_PSNR_DB);
+
+        let report = VideoEncoder::compare(high.to_str().unwrap(), low.to_str().unwrap()).unwrap();
+        assert_eq!(report.matched_frames, 3);
+        assert_eq!(report.unmatched_frames, 0);
+        assert!(report.mean_psnr_db < quality::MAX_PSNR_DB);
+        assert!(report.mean_ssim < 1.0);
+        assert!(report.size_b_bytes < report.size_a_bytes);
+    }
+
+    #[test]
+    fn test_export_with_aspect() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("square.qsp");
+        let output = dir.path().join("wide.qsp");
+        write_gradient_clip(input.to_str().unwrap(), 80);
+
+        let aspect = ExportAspect::widescreen(AspectMode::Pad);
+        let dims = VideoEncoder::export_with_aspect(
+            input.to_str().unwrap(),
+            output.to_str().unwrap(),
+            &aspect,
+            80,
+        )
+        .unwrap();
+        assert_eq!(dims, (56, 32));
+
+        let reader = FrameReader::open(output.to_str().unwrap()).unwrap();
+        assert_eq!(reader.dimensions(), (56, 32));
+        assert_eq!(reader.frame_count(), 3);
+        let decoded = VideoEncoder::decode_jpeg(&reader.frames()[0]).unwrap();
+        assert_eq!(decoded.dimensions(), (56, 32));
+    }
+
+    #[test]
+    fn test_export_stereo_top_bottom() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("stereo.qsp");
+        let output = dir.path().join("tb.qsp");
+        let (input, output) = (input.to_str().unwrap(), output.to_str().unwrap());
+
+        // Left and right at each timestamp, plus a lone left eye at the end
+        let compressor = crate::capture::FrameCompressor::new(90);
+        let eyes = [(0, 0), (1, 1_000), (0, 11_111_111), (1, 11_112_000), (0, 22_222_222)];
+        let frames: Vec<_> = eyes
+            .iter()
+            .map(|&(eye, ts)| {
+                let level = if eye == 0 { 0 } else { 255 };
+                let data = compressor.compress(&vec![level; 16 * 8 * 4], 16, 8).unwrap();
+                CapturedFrame::with_timestamp(data, eye, 16, 8, ts)
+            })
+            .collect();
+        VideoEncoder::encode_frames(&frames, input, &Config::default()).unwrap();
+
+        let counts =
+            VideoEncoder::export_stereo(input, output, StereoLayout::TopBottom, 90).unwrap();
+        assert_eq!(counts, (2, 1));
+
+        let reader = FrameReader::open(output).unwrap();
+        assert_eq!(reader.dimensions(), (16, 16));
+        let packed = VideoEncoder::decode_jpeg(&reader.frames()[1]).unwrap();
+        assert!(packed.get_pixel(8, 2).0[0] < 20);
+        assert!(packed.get_pixel(8, 13).0[0] > 235);
+        assert_eq!(reader.frames()[1].timestamp_ns, 11_111_111);
+    }
+
+    #[test]
+    fn test_pack_stereo() {
+        // A pair, a lone left eye, then a lone right eye
+        let compressor = crate::capture::FrameCompressor::new(90);
+        let eyes = [(0, 0, 0u8), (1, 1_000, 255), (0, 11_111_111, 0), (1, 22_222_222, 255)];
+        let frames: Vec<_> = eyes
+            .iter()
+            .map(|&(eye, ts, level)| {
+                let data = compressor.compress(&vec![level; 16 * 8 * 4], 16, 8).unwrap();
+                CapturedFrame::with_timestamp(data, eye, 16, 8, ts)
+            })
+            .collect();
+
+        let packed = VideoEncoder::pack_stereo(&frames, StereoLayout::SideBySide, 90, 90).unwrap();
+        assert_eq!(packed.len(), 2);
+        assert!(packed.iter().all(|f| (f.width, f.height, f.eye_index) == (32, 8, 0)));
+        let pair = VideoEncoder::decode_jpeg(&packed[0]).unwrap();
+        assert!(pair.get_pixel(4, 4).0[0] < 20 && pair.get_pixel(28, 4).0[0] > 235);
+
+        // The lone left eye fills both halves
+        let filled = VideoEncoder::decode_jpeg(&packed[1]).unwrap();
+        assert!(filled.get_pixel(28, 4).0[0] < 20);
+        assert_eq!(packed[1].timestamp_ns, 11_111_111);
+
+        assert_eq!(StereoMode::TopBottom.layout().unwrap().frame_packing_type(), Some(4));
+        assert_eq!(StereoMode::default().layout(), None);
+    }
+
+    #[test]
+    fn test_export_mono_average() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("stereo.qsp");
+        let output = dir.path().join("mono.qsp");
+        let (input, output) = (input.to_str().unwrap(), output.to_str().unwrap());
+
+        // A dark left and bright right eye, then a lone right eye
+        let compressor = crate::capture::FrameCompressor::new(90);
+        let eyes = [(0, 0, 40u8), (1, 1_000, 200), (1, 11_111_111, 250)];
+        let frames: Vec<_> = eyes
+            .iter()
+            .map(|&(eye, ts, level)| {
+                let data = compressor.compress(&vec![level; 16 * 8 * 4], 16, 8).unwrap();
+                CapturedFrame::with_timestamp(data, eye, 16, 8, ts)
+            })
+            .collect();
+        VideoEncoder::encode_frames(&frames, input, &Config::default()).unwrap();
+
+        let counts =
+            VideoEncoder::export_stereo(input, output, StereoLayout::MonoAverage, 90).unwrap();
+        assert_eq!(counts, (2, 1));
+
+        let reader = FrameReader::open(output).unwrap();
+        assert_eq!(reader.dimensions(), (16, 8));
+        let averaged = VideoEncoder::decode_jpeg(&reader.frames()[0]).unwrap();
+        assert!(averaged.get_pixel(8, 4).0[0].abs_diff(120) < 5);
+
+        // The unmatched eye passes through untouched
+        assert_eq!(reader.frames()[1].data, frames[2].data);
+        assert_eq!(reader.frames()[1].timestamp_ns, 11_111_111);
+    }
+
+    #[test]
+    fn test_keyframe_indices() {
+        let scores = [None, Some(0.05), Some(0.8), Some(0.1), None, Some(0.1), Some(0.5)];
+        let frames: Vec<_> = scores
+            .iter()
+            .enumerate()
+            .map(|(i, &score)| dummy_frame(i as u64).with_motion_score(score))
+            .collect();
+
+        // Scene cuts at 2 and 6, interval cap at 5
+        assert_eq!(VideoEncoder::keyframe_indices(&frames, 0.3, 3, 0.0, 90), vec![0, 2, 5, 6]);
+
+        // Threshold 0 leaves only the interval
+        assert_eq!(VideoEncoder::keyframe_indices(&frames, 0.0, 3, 0.0, 90), vec![0, 3, 6]);
+        assert!(VideoEncoder::keyframe_indices(&[], 0.3, 3, 0.0, 90).is_empty());
+    }
+
+    #[test]
+    fn test_keyframe_interval_seconds() {
+        // 5s at 90 fps with jittery timestamps: a keyframe every second
+        let frames: Vec<_> = (0..450u64)
+            .map(|i| dummy_frame(i * 1_000_000_000 / 90 + (i % 3) * 1_000_000))
+            .collect();
+        let keyframes = VideoEncoder::keyframe_indices(&frames, 0.0, 1_000, 1.0, 90);
+        assert_eq!(keyframes.len(), 5);
+        let times_us = VideoEncoder::presentation_times_us(&frames, 90);
+        for pair in keyframes.windows(2) {
+            let spacing_us = times_us[pair[1]] - times_us[pair[0]];
+            assert!((1_000_000..1_020_000).contains(&spacing_us), "{}", spacing_us);
+        }
+
+        // Whichever limit comes first wins
+        let keyframes = VideoEncoder::keyframe_indices(&frames, 0.0, 30, 1.0, 90);
+        assert_eq!(keyframes[..4], [0, 30, 60, 90]);
+
+        // Untimed frames are spaced at the given rate
+        let untimed: Vec<_> = (0..100).map(|_| dummy_frame(0)).collect();
+        assert_eq!(VideoEncoder::keyframe_indices(&untimed, 0.0, 1_000, 0.5, 50), [0, 25, 50, 75]);
+    }
+
+    #[test]
+    fn test_auto_trim_static() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("input.qsp");
+        let output = dir.path().join("trimmed.qsp");
+        let (input, output) = (input.to_str().unwrap(), output.to_str().unwrap());
+
+        // Static, static, change, change, static, static (no stored scores)
+        let compressor = crate::capture::FrameCompressor::new(90);
+        let frames: Vec<_> = [0u8, 0, 100, 200, 200, 200]
+            .iter()
+            .enumerate()
+            .map(|(i, &level)| {
+                let data = compressor.compress(&vec![level; 16 * 16 * 4], 16, 16).unwrap();
+                CapturedFrame::with_timestamp(data, 0, 16, 16, i as u64 * 11_111_111)
+            })
+            .collect();
+        let samples = vec![0i16; 2 * 900 * 6 / 90];
+        let config = Config::default();
+        VideoEncoder::encode_frames_with_audio(&frames, &[], &samples, 900, input, &config)
+            .unwrap();
+
+        let kept = VideoEncoder::auto_trim_static(input, output, DEFAULT_STATIC_THRESHOLD).unwrap();
+        assert_eq!(kept, 1..4);
+        let trimmed = FrameReader::open(output).unwrap();
+        assert_eq!(trimmed.frame_count(), 3);
+        // Sound from the first kept frame up to the first cut one
+        assert_eq!(trimmed.audio().unwrap().samples.len(), 2 * 30);
+
+        // Fully static clip stays whole
+        VideoEncoder::encode_frames(&frames[..2], input, &Config::default()).unwrap();
+        let kept = VideoEncoder::auto_trim_static(input, output, DEFAULT_STATIC_THRESHOLD).unwrap();
+        assert_eq!(kept, 0..2);
+    }
+
+    #[test]
+    fn test_rejects_malformed_frames() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("bad.qsp");
+        let frames = vec![
+            CapturedFrame::with_timestamp(vec![0u8; 10], 0, 4, 4, 0),
+            CapturedFrame::with_timestamp(vec![0u8; 10], 0, 0, 4, 1),
+        ];
+
+        let err = VideoEncoder::encode_frames(&frames, path.to_str().unwrap(), &Config::default())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Frame 1: Invalid dimensions 0x4"), "{}", err);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_decode_error_names_frame() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("input.qsp");
+        let output = dir.path().join("trimmed.qsp");
+        let (input, output) = (input.to_str().unwrap(), output.to_str().unwrap());
+
+        let compressor = crate::capture::FrameCompressor::new(90);
+        let good = compressor.compress(&[0u8; 16 * 16 * 4], 16, 16).unwrap();
+        let frames = vec![
+            CapturedFrame::with_timestamp(good, 0, 16, 16, 0),
+            CapturedFrame::with_timestamp(vec![0xFF, 0xD8, 0x00], 0, 16, 16, 11_111_111),
+        ];
+        VideoEncoder::encode_frames(&frames, input, &Config::default()).unwrap();
+
+        let err = VideoEncoder::auto_trim_static(input, output, DEFAULT_STATIC_THRESHOLD)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Frame 1:"), "{}", err);
+        assert!(err.contains("3-byte JPEG at 11111111 ns"), "{}", err);
+    }
+
+    #[test]
+    fn test_remove_gaps() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("input.qsp");
+        let output = dir.path().join("smooth.qsp");
+        let (input, output) = (input.to_str().unwrap(), output.to_str().unwrap());
+
+        // 10 fps with a 1s stall after the third frame
+        let ms = 1_000_000u64;
+        let timestamps = [0, 100, 200, 1200, 1300];
+        let frames: Vec<_> = timestamps.iter().map(|&t| dummy_frame(t * ms)).collect();
+        let marks = vec![ClipMark::new(700 * ms, "mid-stall"), ClipMark::new(1300 * ms, "end")];
+        let config = Config {
+            target_fps: 10,
+            ..Config::default()
+        };
+        // 1 kHz stereo sound whose samples count the milliseconds
+        let samples: Vec<i16> = (0..1400).flat_map(|t| [t as i16, t as i16]).collect();
+        VideoEncoder::encode_frames_with_audio(&frames, &marks, &samples, 1_000, input, &config)
+            .unwrap();
+
+        let removed = VideoEncoder::remove_gaps(input, output, 250).unwrap();
+        assert_eq!(removed, std::time::Duration::from_millis(900));
+
+        let reader = FrameReader::open(output).unwrap();
+        let retimed: Vec<_> = reader.frames().iter().map(|f| f.timestamp_ns / ms).collect();
+        assert_eq!(retimed, vec![0, 100, 200, 300, 400]);
+        let mark_times: Vec<_> = reader.marks().iter().map(|m| m.timestamp_ns / ms).collect();
+        assert_eq!(mark_times, vec![300, 400]);
+
+        // The sound under the stall goes with it
+        let audio = reader.audio().unwrap();
+        assert_eq!(audio.samples.len(), 500 * 2);
+        assert_eq!(audio.samples[299 * 2..301 * 2], [299, 299, 1200, 1200]);
+
+        // Nothing above the threshold: timing untouched
+        let removed = VideoEncoder::remove_gaps(input, output, 2000).unwrap();
+        assert_eq!(removed, std::time::Duration::ZERO);
+    }
+
+    #[test]
+    fn test_encode_progress_and_cancel() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("clip.qsp");
+        let path = path.to_str().unwrap();
+        let frames: Vec<_> = (0..100).map(dummy_frame).collect();
+        let cancel = AtomicBool::new(false);
+
+        let mut reports = Vec::new();
+        VideoEncoder::encode_frames_with_progress(
+            &frames,
+            path,
+            &Config::default(),
+            |p| reports.push(p.frames_done),
+            &cancel,
+        )
+        .unwrap();
+        assert_eq!(reports, vec![30, 60, 90, 100]);
+
+        // Cancel on the second report: no partial file is left behind
+        std::fs::remove_file(path).unwrap();
+        let result = VideoEncoder::encode_frames_with_progress(
+            &frames,
+            path,
+            &Config::default(),
+            |p| cancel.store(p.frames_done >= 60, Ordering::Relaxed),
+            &cancel,
+        );
+        assert!(matches!(result, Err(ShadowplayError::Cancelled)));
+        assert!(!Path::new(path).exists());
+    }
+
+    #[test]
+    fn test_presentation_times() {
+        let ms = 1_000_000u64;
+        let at = |times: &[u64]| -> Vec<CapturedFrame> {
+            times.iter().map(|&t| dummy_frame(t)).collect()
+        };
+
+        // Start at 0 whatever the clock said; the 5s stall counts as
+        // one frame at the fallback rate
+        let frames = at(&[1000 * ms, 1020 * ms, 1040 * ms, 6040 * ms, 6060 * ms]);
+        assert_eq!(
+            VideoEncoder::presentation_times_us(&frames, 50),
+            vec![0, 20_000, 40_000, 60_000, 80_000]
+        );
+        assert_eq!(VideoEncoder::clip_fps(&frames, 50), 50);
+
+        // No usable timestamps: evenly spaced at the fallback rate
+        let untimed = at(&[0, 0, 0]);
+        assert_eq!(VideoEncoder::presentation_times_us(&untimed, 50), vec![0, 20_000, 40_000]);
+        assert_eq!(VideoEncoder::clip_fps(&untimed, 72), 72);
+        assert_eq!(VideoEncoder::clip_fps::<CapturedFrame>(&[], 72), 72);
+
+        // Saved clips declare the real rate, not the target
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("clip.qsp");
+        let path = path.to_str().unwrap();
+        let frames: Vec<_> = (0..12).map(|i| dummy_frame(i * 25 * ms)).collect();
+        VideoEncoder::encode_frames(&frames, path, &Config::default()).unwrap();
+        assert_eq!(FrameReader::open(path).unwrap().fps(), 40);
+    }
+
+    #[test]
+    fn test_encode_to_memory() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("clip.qsp");
+        let frames: Vec<_> = (0..5u64).map(|i| dummy_frame(i * 11_111_111)).collect();
+        let config = Config::default();
+
+        let bytes = VideoEncoder::encode_frames_to_memory(&frames, &config).unwrap();
+        VideoEncoder::encode_frames(&frames, path.to_str().unwrap(), &config).unwrap();
+        assert_eq!(bytes, std::fs::read(&path).unwrap());
+        assert_eq!(VideoEncoder::estimated_clip_bytes(&frames), bytes.len() as u64);
+
+        let reader = FrameReader::from_bytes(&bytes).unwrap();
+        assert_eq!(reader.frame_count(), 5);
+        assert_eq!(reader.frames()[4].timestamp_ns, frames[4].timestamp_ns);
+        assert!(VideoEncoder::encode_frames_to_memory::<CapturedFrame>(&[], &config).is_err());
+    }
+
+    #[test]
+    fn test_repair_fps() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("clip.qsp");
+        let path = path.to_str().unwrap();
+
+        // Captured at 30 fps, declared as 90; the right eye doesn't count twice
+        let ms = 1_000_000u64;
+        let frames: Vec<_> = (0..10u64)
+            .flat_map(|i| {
+                let left = dummy_frame(i * 100 * ms / 3);
+                let mut right = left.clone();
+                right.eye_index = 1;
+                [left, right]
+            })
+            .collect();
+        VideoEncoder::new(100, 100, 90, 0).write_frames(&frames, &[], None, path).unwrap();
+
+        assert_eq!(VideoEncoder::repair_fps(path).unwrap(), (90, 30));
+        let reader = FrameReader::open(path).unwrap();
+        assert_eq!(reader.fps(), 30);
+        assert_eq!(reader.frame_count(), 20);
+        assert_eq!(VideoEncoder::repair_fps(path).unwrap(), (30, 30));
+
+        // A 5 s pause halfway doesn't pull the rate down
+        let paused: Vec<_> = (0..20u64)
+            .map(|i| dummy_frame(i * 100 * ms / 3 + if i < 10 { 0 } else { 5_000 * ms }))
+            .collect();
+        assert_eq!(VideoEncoder::detect_fps(&paused), Some(30));
+
+        assert_eq!(VideoEncoder::detect_fps(&frames[..1]), None);
+    }
+
+    #[test]
+    fn test_audio_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("clip.qsp");
+        let path = path.to_str().unwrap();
+        let frames: Vec<_> = (0..3).map(dummy_frame).collect();
+        let samples: Vec<i16> = (0..960).map(|i| (i * 30) as i16).collect();
+
+        let marks = [ClipMark::new(1, "here")];
+        let config = Config::default();
+
+        VideoEncoder::encode_frames_with_audio(&frames, &marks, &samples, 48_000, path, &config)
+            .unwrap();
+        let reader = FrameReader::open(path).unwrap();
+        assert_eq!(reader.frame_count(), 3);
+        assert_eq!(reader.marks(), &marks);
+        let audio = reader.audio().unwrap();
+        assert_eq!((audio.sample_rate, audio.channels), (48_000, 2));
+        assert_eq!(audio.samples, samples);
+
+        // Reframing keeps the sound
+        let compressor = crate::capture::FrameCompressor::new(80);
+        let jpeg = compressor.compress(&[0u8; 16 * 16 * 4], 16, 16).unwrap();
+        let square: Vec<_> = (0..3)
+            .map(|i| CapturedFrame::with_timestamp(jpeg.clone(), 0, 16, 16, i))
+            .collect();
+        VideoEncoder::encode_frames_with_audio(&square, &[], &samples, 48_000, path, &config)
+            .unwrap();
+        let wide = dir.path().join("wide.qsp");
+        let aspect = ExportAspect::widescreen(AspectMode::Pad);
+        VideoEncoder::export_with_aspect(path, wide.to_str().unwrap(), &aspect, 80).unwrap();
+        let reframed = FrameReader::open(wide.to_str().unwrap()).unwrap();
+        assert_eq!(reframed.audio().unwrap().samples, samples);
+
+        // No samples: a plain video-only clip, marks and all
+        VideoEncoder::encode_frames_with_audio(&frames, &marks, &[], 48_000, path, &config)
+            .unwrap();
+        let reader = FrameReader::open(path).unwrap();
+        assert!(reader.audio().is_none());
+        assert_eq!(reader.marks(), &marks);
+
+        let odd = [1i16, 2, 3];
+        assert!(
+            VideoEncoder::encode_frames_with_audio(&frames, &[], &odd, 48_000, path, &config)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_raw_rgb565_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("raw.qsp");
+        let path_str = path.to_str().unwrap();
+
+        let rgba: Vec<u8> = (0..8 * 8).flat_map(|i| [i as u8 * 4, 128, 255, 255]).collect();
+        let frame = CapturedFrame::with_timestamp(crate::capture::rgba_to_rgb565(&rgba), 0, 8, 8, 1)
+            .with_format(FrameFormat::Rgb565);
+        VideoEncoder::encode_frames(std::slice::from_ref(&frame), path_str, &Config::default()).unwrap();
+
+        let reader = FrameReader::open(path_str).unwrap();
+        assert_eq!(reader.frames()[0].format, FrameFormat::Rgb565);
+
+        let decoded = VideoEncoder::decode_frame(&reader.frames()[0]).unwrap();
+        assert_eq!(decoded.dimensions(), (8, 8));
+        assert_eq!(decoded.get_pixel(0, 0).0, [0, 130, 255, 255]);
+
+        let jpeg = VideoEncoder::frame_to_jpeg(&reader.frames()[0], 80).unwrap();
+        assert!(image::load_from_memory_with_format(&jpeg, image::ImageFormat::Jpeg).is_ok());
+
+        // One format per file
+        let mixed = [frame, dummy_frame(2)];
+        assert!(VideoEncoder::encode_frames(&mixed, path_str, &Config::default()).is_err());
+    }
+
+    #[test]
+    fn test_motion_scores_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("motion.qsp");
+        let path_str = path.to_str().unwrap();
+
+        let scores = [None, Some(0.1), Some(0.9), Some(0.8), Some(0.0)];
+        let frames: Vec<_> = scores
+            .iter()
+            .enumerate()
+            .map(|(i, &score)| dummy_frame(i as u64).with_motion_score(score))
+            .collect();
+        VideoEncoder::encode_frames(&frames, path_str, &Config::default()).unwrap();
+
+        let reader = FrameReader::open(path_str).unwrap();
+        assert!(reader.has_motion_scores());
+        assert_eq!(reader.motion_scores(), scores.to_vec());
+        assert_eq!(reader.frames()[2].data, frames[2].data);
+
+        let (start, mean) = reader.most_active_segment(2).unwrap();
+        assert_eq!(start, 2);
+        assert!((mean - 0.85).abs() < 1e-6);
+        assert!(reader.most_active_segment(6).is_none());
+    }
+
+    #[test]
+    fn test_progressive_flag_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("progressive.qsp");
+        let path_str = path.to_str().unwrap();
+
+        let mut compressor = crate::capture::FrameCompressor::new(80);
+        compressor.set_progressive(true);
+        let data = compressor.compress(&vec![64u8; 32 * 32 * 4], 32, 32).unwrap();
+        let frames = vec![CapturedFrame::with_timestamp(data, 0, 32, 32, 0)];
+
+        VideoEncoder::encode_frames(&frames, path_str, &Config::default()).unwrap();
+
+        let reader = FrameReader::open(path_str).unwrap();
+        assert!(reader.is_progressive());
+    }
+
+    #[test]
+    fn test_read_version_01() {
+        // Version 01 header: no flags field
+        let mut data = b"QSPLAY01".to_vec();
+        data.extend_from_slice(&1u32.to_le_bytes()); // frame count
+        data.extend_from_slice(&100u32.to_le_bytes()); // width
+        data.extend_from_slice(&100u32.to_le_bytes()); // height
+        data.extend_from_slice(&90u32.to_le_bytes()); // fps
+        data.extend_from_slice(&7u64.to_le_bytes()); // timestamp
+        data.extend_from_slice(&0u32.to_le_bytes()); // eye index
+        data.extend_from_slice(&3u32.to_le_bytes()); // data len
+        data.extend_from_slice(&[1, 2, 3]);
+
+        let reader = FrameReader::parse(&data).unwrap();
+        assert_eq!(reader.frame_count(), 1);
+        assert_eq!(reader.frames()[0].data, vec![1, 2, 3]);
+        assert!(!reader.is_progressive());
+    }
+
+    /// Builds a v03 clip with one 3-byte frame and `extra` optional
+    /// header bytes.
+    fn v03_clip(flags: u32, extra: &[u8]) -> Vec<u8> {
+        let mut data = b"QSPLAY03".to_vec();
+        for value in [1u32, 100, 100, 90, flags, HEADER_LEN_V03 + extra.len() as u32] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(extra);
+        data.extend_from_slice(&7u64.to_le_bytes()); // timestamp
+        data.extend_from_slice(&0u32.to_le_bytes()); // eye index
+        data.extend_from_slice(&3u32.to_le_bytes()); // data len
+        data.extend_from_slice(&[1, 2, 3]);
+        data
+    }
+
+    #[test]
+    fn test_skips_optional_header_sections() {
+        // Unknown optional section and unknown optional (high) flag
+        let data = v03_clip(0x8000_0000, b"future section");
+        let reader = FrameReader::parse(&data).unwrap();
+        assert_eq!(reader.frames()[0].data, vec![1, 2, 3]);
+
+        // Unknown required flag: frames may be laid out differently
+        let data = v03_clip(0x80, &[]);
+        assert!(FrameReader::parse(&data).is_err());
+    }
+
+    #[test]
+    fn test_marks_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("clip.qsp");
+        let path_str = path.to_str().unwrap();
+
+        let frames: Vec<_> = (10..20).map(dummy_frame).collect();
+        let marks = vec![
+            ClipMark::new(5, "before clip"),
+            ClipMark::new(12, "Ace"),
+            ClipMark::new(19, "Wipe"),
+        ];
+        VideoEncoder::encode_frames_with_marks(&frames, &marks, path_str, &Config::default())
+            .unwrap();
+
+        let reader = FrameReader::open(path_str).unwrap();
+        assert_eq!(reader.frame_count(), 10);
+        assert_eq!(reader.marks(), &marks[1..]);
+        assert_eq!(reader.time_range_ns(), Some((10, 19)));
+
+        // A damaged marks section doesn't lose the frames
+        let data = v03_clip(FLAG_MARKS, b"xx");
+        let reader = FrameReader::parse(&data).unwrap();
+        assert!(reader.marks().is_empty());
+        assert_eq!(reader.frame_count(), 1);
+    }
+
+    #[test]
+    fn test_unsupported_and_truncated_files() {
+        let mut data = v03_clip(0, &[]);
+        data[6..8].copy_from_slice(b"99");
+        let err = FrameReader::parse(&data).err().unwrap().to_string();
+        assert!(err.contains("Unsupported file version 99"));
+
+        // Truncated header
+        assert!(FrameReader::parse(&v03_clip(0, &[])[..20]).is_err());
+
+        // Truncated frame data keeps what was complete
+        let data = v03_clip(0, &[]);
+        let reader = FrameReader::parse(&data[..data.len() - 1]).unwrap();
+        assert_eq!(reader.frame_count(), 0);
+
+        // A frame count the file has no room for isn't allocated up front
+        let mut data = v03_clip(0, &[]);
+        data[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(FrameReader::parse(&data).unwrap().frame_count(), 1);
+    }
+
+    #[test]
+    fn test_check_version() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("clip.qsp");
+        let path_str = path.to_str().unwrap();
+
+        let frames: Vec<_> = (0..2).map(dummy_frame).collect();
+        VideoEncoder::encode_frames(&frames, path_str, &Config::default()).unwrap();
+        assert_eq!(FrameReader::check_version(path_str).unwrap(), 3);
+
+        std::fs::write(&path, b"QSPLAY07").unwrap();
+        assert!(FrameReader::check_version(path_str).is_err());
     }
 }
@@ -0,0 +1,344 @@
+//! # Quality Metrics
+//!
+//! Compares two encodes of the same moment frame by frame.
+//!
+//! ## Plain English
+//!
+//! When tuning bitrate or JPEG quality, "looks fine to me" isn't enough.
+//! These metrics put a number on how close one encode is to another:
+//! - **PSNR**: how big the pixel errors are (higher = closer, in dB)
+//! - **SSIM**: how similar the structure looks to a human (1.0 = identical)
+//!
+//! Before anything is encoded, [`estimate_quality`] gives a rough idea of
+//! whether a bitrate is enough for a resolution and frame rate.
+
+/// PSNR reported for identical frames (true PSNR would be infinite).
+pub const MAX_PSNR_DB: f64 = 100.0;
+
+/// Side length of the blocks SSIM is computed over.
+const SSIM_BLOCK: u32 = 8;
+
+// ============================================
+// QUALITY REPORT
+// ============================================
+
+/// Quality of one matched frame pair.
+#[derive(Debug, Clone)]
+pub struct FrameQuality {
+    /// Timestamp of the frame in the first clip
+    pub timestamp_ns: u64,
+    /// Peak signal-to-noise ratio in dB
+    pub psnr_db: f64,
+    /// Structural similarity (0.0 to 1.0)
+    pub ssim: f64,
+}
+
+/// Result of comparing two clips.
+#[derive(Debug, Clone, Default)]
+pub struct QualityReport {
+    /// Frames found in both clips (matched by timestamp)
+    pub matched_frames: usize,
+    /// Frames in either clip with no counterpart
+    pub unmatched_frames: usize,
+    /// Average PSNR over matched frames in dB
+    pub mean_psnr_db: f64,
+    /// Worst PSNR over matched frames in dB
+    pub min_psnr_db: f64,
+    /// Average SSIM over matched frames
+    pub mean_ssim: f64,
+    /// Size of the first clip file
+    pub size_a_bytes: u64,
+    /// Size of the second clip file
+    pub size_b_bytes: u64,
+    /// Per-frame results (in order)
+    pub frames: Vec<FrameQuality>,
+}
+
+impl QualityReport {
+    /// Builds a report from per-frame results.
+    pub fn from_frames(frames: Vec<FrameQuality>, unmatched_frames: usize) -> Self {
+        let matched = frames.len();
+        if matched == 0 {
+            return Self {
+                unmatched_frames,
+                ..Self::default()
+            };
+        }
+
+        let mean_psnr_db = frames.iter().map(|f| f.psnr_db).sum::<f64>() / matched as f64;
+        let min_psnr_db = frames
+            .iter()
+            .map(|f| f.psnr_db)
+            .fold(f64::INFINITY, f64::min);
+        let mean_ssim = frames.iter().map(|f| f.ssim).sum::<f64>() / matched as f64;
+
+        Self {
+            matched_frames: matched,
+            unmatched_frames,
+            mean_psnr_db,
+            min_psnr_db,
+            mean_ssim,
+            size_a_bytes: 0,
+            size_b_bytes: 0,
+            frames,
+        }
+    }
+
+    /// Size of the second clip relative to the first (e.g. 0.5 = half).
+    pub fn size_ratio(&self) -> f64 {
+        if self.size_a_bytes == 0 {
+            return 0.0;
+        }
+        self.size_b_bytes as f64 / self.size_a_bytes as f64
+    }
+}
+
+// ============================================
+// BITRATE ESTIMATE
+// ============================================
+
+/// Bits per pixel per frame for low-motion H.264 in the Kush gauge
+pub const KUSH_CONSTANT: f64 = 0.07;
+
+/// How a bitrate is likely to look, from the Kush gauge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum QualityRating {
+    /// Below what low-motion footage needs: visible blocking
+    Poor,
+    /// Enough for low motion (menus, slow scenes)
+    Fair,
+    /// Enough for medium motion (most gameplay)
+    Good,
+    /// Enough for high motion (fast turns, action)
+    Excellent,
+}
+
+impl QualityRating {
+    /// Returns a lowercase name for display.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Poor => "poor",
+            Self::Fair => "fair",
+            Self::Good => "good",
+            Self::Excellent => "excellent",
+        }
+    }
+}
+
+/// Heuristic quality of a bitrate at a resolution and frame rate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityEstimate {
+    /// Bits available per pixel per frame
+    pub bits_per_pixel: f64,
+    /// Kush motion factor the bitrate covers (1 = low, 2 = medium,
+    /// 4 = high motion)
+    pub motion_factor: f64,
+    /// Rating derived from the motion factor
+    pub rating: QualityRating,
+}
+
+/// Estimates how good `bitrate` will look at a resolution and frame rate.
+///
+/// Uses the Kush gauge: H.264 needs about
+/// `width × height × fps × motion × 0.07` bits per second, where motion
+/// is 1 (low), 2 (medium) or 4 (high). Solving for motion says which
+/// kind of footage the bitrate is enough for. It's a rule of thumb, not
+/// a measurement; use [`super::VideoEncoder::compare`] for that.
+pub fn estimate_quality(bitrate: u32, width: u32, height: u32, fps: u32) -> QualityEstimate {
+    let pixel_rate = width as f64 * height as f64 * fps as f64;
+    if pixel_rate == 0.0 {
+        return QualityEstimate {
+            bits_per_pixel: 0.0,
+            motion_factor: 0.0,
+            rating: QualityRating::Poor,
+        };
+    }
+
+    let bits_per_pixel = bitrate as f64 / pixel_rate;
+    let motion_factor = bits_per_pixel / KUSH_CONSTANT;
+    let rating = if motion_factor >= 4.0 {
+        QualityRating::Excellent
+    } else if motion_factor >= 2.0 {
+        QualityRating::Good
+    } else if motion_factor >= 1.0 {
+        QualityRating::Fair
+    } else {
+        QualityRating::Poor
+    };
+
+    QualityEstimate {
+        bits_per_pixel,
+        motion_factor,
+        rating,
+    }
+}
+
+/// Returns the Kush gauge bitrate for a motion factor (1, 2 or 4).
+pub fn kush_bitrate(width: u32, height: u32, fps: u32, motion_factor: f64) -> u32 {
+    let bits = width as f64 * height as f64 * fps as f64 * motion_factor * KUSH_CONSTANT;
+    bits.min(u32::MAX as f64) as u32
+}
+
+// ============================================
+// METRICS
+// ============================================
+
+/// Computes PSNR between two RGBA images of equal size (alpha ignored).
+pub fn psnr(a: &[u8], b: &[u8]) -> f64 {
+    let mut squared_error = 0u64;
+    let mut samples = 0u64;
+
+    for (pa, pb) in a.chunks_exact(4).zip(b.chunks_exact(4)) {
+        for c in 0..3 {
+            let diff = pa[c] as i64 - pb[c] as i64;
+            squared_error += (diff * diff) as u64;
+        }
+        samples += 3;
+    }
+
+    if samples == 0 || squared_error == 0 {
+        return MAX_PSNR_DB;
+    }
+
+    let mse = squared_error as f64 / samples as f64;
+    (10.0 * (255.0 * 255.0 / mse).log10()).min(MAX_PSNR_DB)
+}
+
+/// Computes mean SSIM between two RGBA images on their luma channel.
+///
+/// Uses non-overlapping 8×8 blocks; images smaller than one block are
+/// treated as a single block.
+pub fn ssim(a: &[u8], b: &[u8], width: u32, height: u32) -> f64 {
+    let luma_a = to_luma(a);
+    let luma_b = to_luma(b);
+
+    let (block_w, block_h) = if width < SSIM_BLOCK || height < SSIM_BLOCK {
+        (width, height)
+    } else {
+        (SSIM_BLOCK, SSIM_BLOCK)
+    };
+
+    if block_w == 0 || block_h == 0 {
+        return 1.0;
+    }
+
+    let mut total = 0.0;
+    let mut blocks = 0u32;
+
+    for by in 0..height / block_h {
+        for bx in 0..width / block_w {
+            total += block_ssim(&luma_a, &luma_b, width, bx * block_w, by * block_h, block_w, block_h);
+            blocks += 1;
+        }
+    }
+
+    if blocks == 0 {
+        1.0
+    } else {
+        total / blocks as f64
+    }
+}
+
+/// SSIM of a single block.
+fn block_ssim(a: &[f64], b: &[f64], stride: u32, x0: u32, y0: u32, w: u32, h: u32) -> f64 {
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+    let n = (w * h) as f64;
+    let (mut sum_a, mut sum_b) = (0.0, 0.0);
+    for y in y0..y0 + h {
+        for x in x0..x0 + w {
+            let i = (y * stride + x) as usize;
+            sum_a += a[i];
+            sum_b += b[i];
+        }
+    }
+    let (mean_a, mean_b) = (sum_a / n, sum_b / n);
+
+    let (mut var_a, mut var_b, mut cov) = (0.0, 0.0, 0.0);
+    for y in y0..y0 + h {
+        for x in x0..x0 + w {
+            let i = (y * stride + x) as usize;
+            let (da, db) = (a[i] - mean_a, b[i] - mean_b);
+            var_a += da * da;
+            var_b += db * db;
+            cov += da * db;
+        }
+    }
+    let (var_a, var_b, cov) = (var_a / n, var_b / n, cov / n);
+
+    ((2.0 * mean_a * mean_b + C1) * (2.0 * cov + C2))
+        / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2))
+}
+
+/// Converts RGBA pixels to BT.601 luma.
+fn to_luma(rgba: &[u8]) -> Vec<f64> {
+    rgba.chunks_exact(4)
+        .map(|p| 0.299 * p[0] as f64 + 0.587 * p[1] as f64 + 0.114 * p[2] as f64)
+        .collect()
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(width: u32, height: u32, offset: u8) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                rgba.push((x as u8).wrapping_add(offset));
+                rgba.push(y as u8);
+                rgba.push(128);
+                rgba.push(255);
+            }
+        }
+        rgba
+    }
+
+    #[test]
+    fn test_identical_images() {
+        let img = gradient(16, 16, 0);
+        assert_eq!(psnr(&img, &img), MAX_PSNR_DB);
+        assert!((ssim(&img, &img, 16, 16) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_different_images() {
+        let a = gradient(16, 16, 0);
+        let b = gradient(16, 16, 40);
+        let p = psnr(&a, &b);
+        assert!(p > 0.0 && p < 30.0);
+        assert!(ssim(&a, &b, 16, 16) < 1.0);
+    }
+
+    #[test]
+    fn test_report_aggregates() {
+        let frames = vec![
+            FrameQuality { timestamp_ns: 0, psnr_db: 40.0, ssim: 0.9 },
+            FrameQuality { timestamp_ns: 1, psnr_db: 30.0, ssim: 0.8 },
+        ];
+        let report = QualityReport::from_frames(frames, 1);
+        assert_eq!(report.matched_frames, 2);
+        assert_eq!(report.unmatched_frames, 1);
+        assert_eq!(report.mean_psnr_db, 35.0);
+        assert_eq!(report.min_psnr_db, 30.0);
+        assert!((report.mean_ssim - 0.85).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_estimate_quality() {
+        // 1080p60 at 20 Mbps: ~0.16 bpp, medium motion covered
+        let estimate = estimate_quality(20_000_000, 1920, 1080, 60);
+        assert!((estimate.bits_per_pixel - 0.1608).abs() < 0.001);
+        assert_eq!(estimate.rating, QualityRating::Good);
+
+        let needed = kush_bitrate(1920, 1080, 60, 4.0);
+        assert_eq!(estimate_quality(needed, 1920, 1080, 60).rating, QualityRating::Excellent);
+        assert_eq!(estimate_quality(1_000_000, 1920, 1080, 60).rating, QualityRating::Poor);
+        assert_eq!(estimate_quality(1_000_000, 0, 0, 60).rating, QualityRating::Poor);
+    }
+}
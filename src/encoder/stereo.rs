@@ -0,0 +1,236 @@
+//! # Stereo Export
+//!
+//! Combines left and right eye frames into one 3D frame.
+//!
+//! ## Plain English
+//!
+//! When both eyes are captured, a clip holds two pictures for every
+//! moment. 3D video players want them packed into a single picture:
+//! - **SideBySide**: left eye on the left half, right eye on the right
+//! - **TopBottom**: left eye on top, right eye underneath
+//!
+//! Which one to use depends on the player; both hold the same pixels.
+//! For a flat video, **MonoAverage** blends the two eyes into one
+//! picture instead: a centered view with less per-eye noise than
+//! picking a single eye.
+
+use image::imageops;
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+
+use crate::capture::CapturedFrame;
+use crate::error::{ShadowplayError, ShadowplayResult};
+
+/// How the two eyes are packed into one frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum StereoLayout {
+    /// Left eye left, right eye right (double width)
+    #[default]
+    SideBySide,
+
+    /// Left eye top, right eye bottom (double height)
+    TopBottom,
+
+    /// Both eyes averaged into one flat frame (same size as an eye)
+    MonoAverage,
+}
+
+impl StereoLayout {
+    /// Returns the packed frame size for one eye's size.
+    pub fn output_dimensions(&self, eye_width: u32, eye_height: u32) -> (u32, u32) {
+        match self {
+            Self::SideBySide => (2 * eye_width, eye_height),
+            Self::TopBottom => (eye_width, 2 * eye_height),
+            Self::MonoAverage => (eye_width, eye_height),
+        }
+    }
+
+    /// Returns the H.264 frame packing arrangement type players read to
+    /// show the video in 3D (3 = side by side, 4 = top-bottom), or
+    /// `None` for a flat frame.
+    pub fn frame_packing_type(&self) -> Option<u8> {
+        match self {
+            Self::SideBySide => Some(3),
+            Self::TopBottom => Some(4),
+            Self::MonoAverage => None,
+        }
+    }
+
+    /// Returns true if frames without a partner eye are kept as they
+    /// are, rather than dropped.
+    pub fn keeps_unmatched(&self) -> bool {
+        *self == Self::MonoAverage
+    }
+
+    /// Packs a left and right eye image into one.
+    ///
+    /// Both images must be the same size.
+    pub fn composite(&self, left: &RgbaImage, right: &RgbaImage) -> ShadowplayResult<RgbaImage> {
+        if left.dimensions() != right.dimensions() {
+            return Err(ShadowplayError::Encoder(format!(
+                "Eye sizes differ: {:?} vs {:?}",
+                left.dimensions(),
+                right.dimensions()
+            )));
+        }
+
+        let (eye_w, eye_h) = left.dimensions();
+        let (out_w, out_h) = self.output_dimensions(eye_w, eye_h);
+        let (right_x, right_y) = match self {
+            Self::SideBySide => (eye_w as i64, 0),
+            Self::TopBottom => (0, eye_h as i64),
+            Self::MonoAverage => {
+                let mut mono = left.clone();
+                for (out, &r) in mono.iter_mut().zip(right.iter()) {
+                    *out = (*out as u16 + r as u16).div_ceil(2) as u8;
+                }
+                return Ok(mono);
+            }
+        };
+
+        let mut packed = RgbaImage::new(out_w, out_h);
+        imageops::replace(&mut packed, left, 0, 0);
+        imageops::replace(&mut packed, right, right_x, right_y);
+        Ok(packed)
+    }
+}
+
+/// Whether exported video is flat or 3D.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StereoMode {
+    /// Frames as captured, one picture at a time
+    #[default]
+    Mono,
+
+    /// Both eyes packed left and right in one double-width frame
+    SideBySide,
+
+    /// Both eyes packed top and bottom in one double-height frame
+    TopBottom,
+}
+
+impl StereoMode {
+    /// Returns how frames are packed, or `None` to leave them as they are.
+    pub fn layout(&self) -> Option<StereoLayout> {
+        match self {
+            Self::Mono => None,
+            Self::SideBySide => Some(StereoLayout::SideBySide),
+            Self::TopBottom => Some(StereoLayout::TopBottom),
+        }
+    }
+}
+
+/// One moment in a two-eye clip after matching eyes.
+#[derive(Clone, Copy, Debug)]
+pub enum EyeMatch<'a> {
+    /// Left and right frames from the same moment
+    Pair(&'a CapturedFrame, &'a CapturedFrame),
+
+    /// A frame whose other eye is missing
+    Single(&'a CapturedFrame),
+}
+
+/// Matches left-eye frames (eye 0) with right-eye frames (eye 1), in
+/// time order.
+///
+/// Frames pair up when their timestamps are within `tolerance_ns`;
+/// frames with no partner come back as [`EyeMatch::Single`].
+pub fn match_eyes(frames: &[CapturedFrame], tolerance_ns: u64) -> Vec<EyeMatch<'_>> {
+    let left: Vec<_> = frames.iter().filter(|f| f.eye_index == 0).collect();
+    let right: Vec<_> = frames.iter().filter(|f| f.eye_index == 1).collect();
+
+    let mut matches = Vec::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < left.len() && j < right.len() {
+        let (l, r) = (left[i], right[j]);
+        if l.timestamp_ns.abs_diff(r.timestamp_ns) <= tolerance_ns {
+            matches.push(EyeMatch::Pair(l, r));
+            i += 1;
+            j += 1;
+        } else if l.timestamp_ns < r.timestamp_ns {
+            matches.push(EyeMatch::Single(l));
+            i += 1;
+        } else {
+            matches.push(EyeMatch::Single(r));
+            j += 1;
+        }
+    }
+    matches.extend(left[i..].iter().chain(&right[j..]).map(|f| EyeMatch::Single(f)));
+    matches.sort_by_key(|m| match m {
+        EyeMatch::Pair(l, _) => l.timestamp_ns,
+        EyeMatch::Single(f) => f.timestamp_ns,
+    });
+
+    matches
+}
+
+/// Matches left-eye frames (eye 0) with right-eye frames (eye 1).
+///
+/// Frames pair up when their timestamps are within `tolerance_ns`.
+/// Frames with no partner are skipped; their count is returned with
+/// the pairs.
+pub fn pair_eyes(
+    frames: &[CapturedFrame],
+    tolerance_ns: u64,
+) -> (Vec<(&CapturedFrame, &CapturedFrame)>, usize) {
+    let mut pairs = Vec::new();
+    let mut unmatched = 0;
+    for m in match_eyes(frames, tolerance_ns) {
+        match m {
+            EyeMatch::Pair(l, r) => pairs.push((l, r)),
+            EyeMatch::Single(_) => unmatched += 1,
+        }
+    }
+    (pairs, unmatched)
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn eye(timestamp_ns: u64, eye_index: u32) -> CapturedFrame {
+        CapturedFrame::with_timestamp(Vec::new(), eye_index, 4, 4, timestamp_ns)
+    }
+
+    #[test]
+    fn test_pair_eyes() {
+        let frames = vec![eye(0, 0), eye(1, 1), eye(100, 0), eye(200, 0), eye(203, 1)];
+        let (pairs, unmatched) = pair_eyes(&frames, 5);
+
+        let times: Vec<_> = pairs
+            .iter()
+            .map(|(l, r)| (l.timestamp_ns, r.timestamp_ns))
+            .collect();
+        assert_eq!(times, vec![(0, 1), (200, 203)]);
+        assert_eq!(unmatched, 1);
+    }
+
+    #[test]
+    fn test_layouts() {
+        let left = RgbaImage::from_pixel(2, 3, Rgba([255, 0, 0, 255]));
+        let right = RgbaImage::from_pixel(2, 3, Rgba([0, 0, 255, 255]));
+
+        let tb = StereoLayout::TopBottom.composite(&left, &right).unwrap();
+        assert_eq!(tb.dimensions(), (2, 6));
+        assert_eq!(tb.get_pixel(1, 2).0, [255, 0, 0, 255]);
+        assert_eq!(tb.get_pixel(1, 3).0, [0, 0, 255, 255]);
+
+        let sbs = StereoLayout::SideBySide.composite(&left, &right).unwrap();
+        assert_eq!(sbs.dimensions(), (4, 3));
+        assert_eq!(sbs.get_pixel(2, 0).0, [0, 0, 255, 255]);
+
+        let mono = StereoLayout::MonoAverage.composite(&left, &right).unwrap();
+        assert_eq!(mono.dimensions(), (2, 3));
+        assert_eq!(mono.get_pixel(1, 1).0, [128, 0, 128, 255]);
+
+        let small = RgbaImage::new(1, 1);
+        assert!(StereoLayout::TopBottom.composite(&left, &small).is_err());
+    }
+}
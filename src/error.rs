@@ -31,11 +31,17 @@ pub enum ShadowplayError {
     /// Storage/file system error
     Storage(String),
 
+    /// Storage error callers can react to (see [`StorageErrorKind`])
+    StorageKind(StorageErrorKind, String),
+
     /// I/O error
     Io(io::Error),
 
     /// Internal error
     Internal(String),
+
+    /// Stopped on request before finishing (e.g. a cancelled save)
+    Cancelled,
 }
 
 impl fmt::Display for ShadowplayError {
@@ -44,9 +50,29 @@ impl fmt::Display for ShadowplayError {
             Self::Config(e) => write!(f, "Configuration error: {}", e),
             Self::Capture(msg) => write!(f, "Capture error: {}", msg),
             Self::Encoder(msg) => write!(f, "Encoder error: {}", msg),
-            Self::Storage(msg) => write!(f, "Storage error: {}", msg),
+            Self::Storage(msg) | Self::StorageKind(_, msg) => write!(f, "Storage error: {}", msg),
             Self::Io(e) => write!(f, "I/O error: {}", e),
             Self::Internal(msg) => write!(f, "Internal error: {}", msg),
+            Self::Cancelled => write!(f, "Cancelled"),
+        }
+    }
+}
+
+impl ShadowplayError {
+    /// Returns what kind of storage problem this is, if it is one.
+    ///
+    /// I/O errors are classified too, so a failed delete reports
+    /// [`StorageErrorKind::PermissionDenied`] like a refused one.
+    pub fn storage_kind(&self) -> Option<StorageErrorKind> {
+        match self {
+            Self::StorageKind(kind, _) => Some(*kind),
+            Self::Storage(_) => Some(StorageErrorKind::Other),
+            Self::Io(e) => Some(match e.kind() {
+                io::ErrorKind::StorageFull => StorageErrorKind::DiskFull,
+                io::ErrorKind::PermissionDenied => StorageErrorKind::PermissionDenied,
+                _ => StorageErrorKind::Other,
+            }),
+            _ => None,
         }
     }
 }
@@ -72,6 +98,23 @@ impl From<ConfigError> for ShadowplayError {
     }
 }
 
+// ============================================
+// STORAGE ERROR KINDS
+// ============================================
+
+/// What went wrong with storage, so the UI can say what to do about it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageErrorKind {
+    /// Not enough free space; the user should delete some clips
+    DiskFull,
+
+    /// Not allowed, e.g. a path outside the clips folder
+    PermissionDenied,
+
+    /// Anything else
+    Other,
+}
+
 // ============================================
 // RESULT TYPE ALIAS
 // ============================================
@@ -104,4 +147,15 @@ mod tests {
             _ => panic!("Expected Io variant"),
         }
     }
+
+    #[test]
+    fn test_storage_kind() {
+        let full = ShadowplayError::StorageKind(StorageErrorKind::DiskFull, "full".to_string());
+        assert_eq!(full.storage_kind(), Some(StorageErrorKind::DiskFull));
+        assert_eq!(full.to_string(), "Storage error: full");
+
+        let denied: ShadowplayError = io::Error::from(io::ErrorKind::PermissionDenied).into();
+        assert_eq!(denied.storage_kind(), Some(StorageErrorKind::PermissionDenied));
+        assert_eq!(ShadowplayError::Cancelled.storage_kind(), None);
+    }
 }
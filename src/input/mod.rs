@@ -6,10 +6,26 @@
 //!
 //! Watches controller buttons and detects when you want to save.
 //! Uses button COMBINATIONS (not single buttons) to prevent accidents.
+//! Other actions (pause, clear, mark) can get their own buttons, as
+//! long as no two share one.
 
 use std::time::{Duration, Instant};
 
-use crate::config::TriggerButton;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{
+    ActionBinding, Config, ConfigError, ControllerAxis, InputAction, MarkButton, TriggerButton,
+    TriggerMode,
+};
+
+/// Analog value above which a trigger or grip counts as pressed
+pub const PRESS_THRESHOLD: f32 = 0.9;
+
+/// Analog value a held combo must drop below before it counts as released
+pub const RELEASE_THRESHOLD: f32 = 0.7;
+
+/// Lowest press threshold allowed; below this, resting fingers save
+pub const MIN_PRESS_THRESHOLD: f32 = 0.3;
 
 // ============================================
 // INPUT STATE
@@ -17,8 +33,10 @@ use crate::config::TriggerButton;
 
 /// Current state of all controller inputs.
 ///
-/// Updated every frame with the latest controller data.
-#[derive(Clone, Debug, Default)]
+/// Updated every frame with the latest controller data. Missing fields
+/// deserialize as at rest, so a UI only has to send what it reads.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct InputState {
     // Left controller
     /// Left trigger value (0.0 to 1.0)
@@ -58,23 +76,110 @@ impl InputState {
     }
 
     /// Returns true if left trigger is fully pressed (>90%).
+    ///
+    /// These checks use the default threshold; see
+    /// [`InputHandler::is_pressed`] for the configured one.
     pub fn left_trigger_pressed(&self) -> bool {
-        self.left_trigger > 0.9
+        self.left_trigger_pressed_with(PRESS_THRESHOLD)
     }
 
     /// Returns true if left grip is fully pressed (>90%).
     pub fn left_grip_pressed(&self) -> bool {
-        self.left_grip > 0.9
+        self.left_grip_pressed_with(PRESS_THRESHOLD)
     }
 
     /// Returns true if right trigger is fully pressed (>90%).
     pub fn right_trigger_pressed(&self) -> bool {
-        self.right_trigger > 0.9
+        self.right_trigger_pressed_with(PRESS_THRESHOLD)
     }
 
     /// Returns true if right grip is fully pressed (>90%).
     pub fn right_grip_pressed(&self) -> bool {
-        self.right_grip > 0.9
+        self.right_grip_pressed_with(PRESS_THRESHOLD)
+    }
+
+    /// Returns true if left trigger is pressed past `threshold`, such as
+    /// [`InputHandler::press_threshold`].
+    pub fn left_trigger_pressed_with(&self, threshold: f32) -> bool {
+        self.left_trigger > threshold
+    }
+
+    /// Returns true if left grip is pressed past `threshold`.
+    pub fn left_grip_pressed_with(&self, threshold: f32) -> bool {
+        self.left_grip > threshold
+    }
+
+    /// Returns true if right trigger is pressed past `threshold`.
+    pub fn right_trigger_pressed_with(&self, threshold: f32) -> bool {
+        self.right_trigger > threshold
+    }
+
+    /// Returns true if right grip is pressed past `threshold`.
+    pub fn right_grip_pressed_with(&self, threshold: f32) -> bool {
+        self.right_grip > threshold
+    }
+
+    /// Returns true if the given mark button is pressed.
+    pub fn mark_pressed(&self, button: &MarkButton) -> bool {
+        match button {
+            MarkButton::Disabled => false,
+            MarkButton::LeftX => self.left_x,
+            MarkButton::LeftY => self.left_y,
+            MarkButton::RightA => self.right_a,
+            MarkButton::RightB => self.right_b,
+        }
+    }
+
+    /// Returns true if an action's binding is pressed past `threshold`
+    /// (face buttons are simply on or off).
+    pub fn binding_pressed(&self, binding: &ActionBinding, threshold: f32) -> bool {
+        match binding {
+            ActionBinding::Combo(combo) => self.combo_value(combo) > threshold,
+            ActionBinding::Button(button) => self.mark_pressed(button),
+        }
+    }
+
+    /// Returns how far a button combo is pressed: the lowest of its
+    /// controls' values, so the combo is only as pressed as its weakest
+    /// part. A custom control with a threshold of its own counts as
+    /// fully pressed once past it, and not at all before.
+    pub fn combo_value(&self, button: &TriggerButton) -> f32 {
+        match button {
+            TriggerButton::LeftGripAndTrigger => self.left_grip.min(self.left_trigger),
+            TriggerButton::RightGripAndTrigger => self.right_grip.min(self.right_trigger),
+            TriggerButton::BothGrips => self.left_grip.min(self.right_grip),
+            TriggerButton::Custom(specs) => specs
+                .iter()
+                .map(|spec| match spec.threshold {
+                    Some(threshold) => on_off(self.axis(spec.control) > threshold),
+                    None => self.axis(spec.control),
+                })
+                .fold(1.0, f32::min),
+        }
+    }
+
+    /// Returns the value of one control: analog for grips and triggers,
+    /// 0.0 or 1.0 for face buttons.
+    pub fn axis(&self, axis: ControllerAxis) -> f32 {
+        match axis {
+            ControllerAxis::LeftGrip => self.left_grip,
+            ControllerAxis::LeftTrigger => self.left_trigger,
+            ControllerAxis::RightGrip => self.right_grip,
+            ControllerAxis::RightTrigger => self.right_trigger,
+            ControllerAxis::A => on_off(self.right_a),
+            ControllerAxis::B => on_off(self.right_b),
+            ControllerAxis::X => on_off(self.left_x),
+            ControllerAxis::Y => on_off(self.left_y),
+        }
+    }
+}
+
+/// Returns a face button's value as if it were analog.
+fn on_off(pressed: bool) -> f32 {
+    if pressed {
+        1.0
+    } else {
+        0.0
     }
 }
 
@@ -85,22 +190,57 @@ impl InputState {
 /// Handles input detection and save triggering.
 ///
 /// ## Features
-/// - Watches for configured button combination
+/// - Watches for any of the configured button combinations
 /// - Debouncing prevents accidental double-triggers
 /// - Edge detection catches the moment of press
+/// - Hysteresis stops a trigger hovering near the threshold from
+///   chattering between pressed and released
+/// - An optional hold time makes the combo save only once it has been
+///   held that long, so a brief accidental press does nothing
+/// - Alternatively, a double-tap of one control saves
 pub struct InputHandler {
-    /// Which buttons trigger a save
-    trigger_button: TriggerButton,
+    /// Combos that trigger a save, primary first; never empty
+    trigger_buttons: Vec<TriggerButton>,
+
+    /// Press the combo or double-tap a control
+    trigger_mode: TriggerMode,
+
+    /// When the last single tap landed, in double-tap mode
+    last_tap: Option<Instant>,
 
     /// Minimum time between saves
     debounce_duration: Duration,
 
+    /// Minimum number of checks (frames) between saves (0 = disabled)
+    debounce_frames: u32,
+
     /// When we last triggered
     last_trigger_time: Option<Instant>,
 
+    /// Checks since we last triggered (None = never triggered)
+    frames_since_trigger: Option<u32>,
+
+    /// Combo value above which an idle combo becomes pressed
+    press_threshold: f32,
+
+    /// Combo value below which a held combo becomes released
+    release_threshold: f32,
+
     /// Was pressed last frame?
     was_pressed: bool,
 
+    /// How long the combo must be held before it saves (zero = on press)
+    hold_duration: Duration,
+
+    /// When the current press started, while the combo is held
+    held_since: Option<Instant>,
+
+    /// Has the current hold already saved (or been debounced)?
+    hold_handled: bool,
+
+    /// Bindings for actions besides saving
+    actions: Vec<BoundAction>,
+
     /// Current input state
     current_state: InputState,
 }
@@ -109,14 +249,47 @@ impl InputHandler {
     /// Creates a new input handler.
     pub fn new(trigger_button: TriggerButton) -> Self {
         Self {
-            trigger_button,
+            trigger_buttons: vec![trigger_button],
+            trigger_mode: TriggerMode::Combo,
+            last_tap: None,
             debounce_duration: Duration::from_millis(500),
+            debounce_frames: 0,
             last_trigger_time: None,
+            frames_since_trigger: None,
+            press_threshold: PRESS_THRESHOLD,
+            release_threshold: RELEASE_THRESHOLD,
             was_pressed: false,
+            hold_duration: Duration::ZERO,
+            held_since: None,
+            hold_handled: false,
+            actions: Vec::new(),
             current_state: InputState::new(),
         }
     }
 
+    /// Creates an input handler with the bindings, thresholds, hold time
+    /// and trigger mode from `config`.
+    ///
+    /// Fails if a trigger combo is invalid, or two actions (or an action
+    /// and the save trigger) share a binding.
+    pub fn from_config(config: &Config) -> Result<Self, ConfigError> {
+        let mut handler = Self::new(config.trigger_button.clone());
+        for button in &config.extra_trigger_buttons {
+            handler.add_trigger_button(button.clone())?;
+        }
+        handler.set_trigger_thresholds(
+            config.trigger_press_threshold,
+            config.trigger_release_threshold,
+        );
+        handler.set_hold_duration(Duration::from_millis(config.trigger_hold_ms as u64));
+        handler.set_trigger_mode(config.trigger_mode);
+        handler.set_mark_button(config.mark_button.clone());
+        for (action, binding) in &config.action_bindings {
+            handler.set_action_binding(*action, binding.clone())?;
+        }
+        Ok(handler)
+    }
+
     /// Updates the input state.
     pub fn update(&mut self, state: InputState) {
         self.current_state = state;
@@ -125,52 +298,288 @@ impl InputHandler {
     /// Checks if a save should be triggered.
     ///
     /// Returns `true` only on the rising edge of the button press
-    /// (the moment it's first pressed) and respects debouncing.
+    /// (the moment it's first pressed) and respects debouncing. With a
+    /// hold duration set, it instead returns `true` once the combo has
+    /// been held that long without a break, once per hold. In
+    /// double-tap mode, it returns `true` on the second of two taps.
+    ///
+    /// Call this once per frame: the frame-based debounce counts calls.
     pub fn check_save_triggered(&mut self) -> bool {
+        self.check_save_triggered_at(Instant::now())
+    }
+
+    /// Checks for a save as of `now`.
+    fn check_save_triggered_at(&mut self, now: Instant) -> bool {
+        if let Some(frames) = self.frames_since_trigger.as_mut() {
+            *frames = frames.saturating_add(1);
+        }
+
         let is_pressed = self.is_combo_pressed();
 
         // Edge detection: only trigger when first pressed
         let just_pressed = is_pressed && !self.was_pressed;
         self.was_pressed = is_pressed;
 
-        if !just_pressed {
+        // A release ends the hold; an interrupted hold starts over
+        if just_pressed {
+            self.held_since = Some(now);
+            self.hold_handled = false;
+        } else if !is_pressed {
+            self.held_since = None;
+        }
+
+        let ready = match self.trigger_mode {
+            TriggerMode::DoubleTap { window_ms, .. } => {
+                just_pressed && self.is_second_tap(now, Duration::from_millis(window_ms as u64))
+            }
+            TriggerMode::Combo if self.hold_duration.is_zero() => just_pressed,
+            TriggerMode::Combo => {
+                let held_long_enough = self.held_since.is_some_and(|since| {
+                    now.saturating_duration_since(since) >= self.hold_duration
+                });
+                held_long_enough && !self.hold_handled
+            }
+        };
+        if !ready {
             return false;
         }
+        self.hold_handled = true;
 
         // Debounce check
         if let Some(last) = self.last_trigger_time {
-            if last.elapsed() < self.debounce_duration {
+            if now.saturating_duration_since(last) < self.debounce_duration {
                 log::debug!("Save trigger debounced");
                 return false;
             }
         }
 
-        self.last_trigger_time = Some(Instant::now());
+        // Frame-based debounce check (independent of wall-clock time)
+        if let Some(frames) = self.frames_since_trigger {
+            if frames < self.debounce_frames {
+                log::debug!("Save trigger debounced ({} frames since last)", frames);
+                return false;
+            }
+        }
+
+        self.last_trigger_time = Some(now);
+        self.frames_since_trigger = Some(0);
         log::info!("Save triggered!");
         true
     }
 
-    /// Checks if the trigger combo is currently pressed.
-    fn is_combo_pressed(&self) -> bool {
-        match &self.trigger_button {
-            TriggerButton::LeftGripAndTrigger => {
-                self.current_state.left_grip_pressed()
-                    && self.current_state.left_trigger_pressed()
-            }
-            TriggerButton::RightGripAndTrigger => {
-                self.current_state.right_grip_pressed()
-                    && self.current_state.right_trigger_pressed()
+    /// Records a tap at `now`, returning true if it completes a double
+    /// tap. A completed pair is forgotten, so a third tap starts over.
+    fn is_second_tap(&mut self, now: Instant, window: Duration) -> bool {
+        match self.last_tap.take() {
+            Some(first) if now.saturating_duration_since(first) <= window => true,
+            _ => {
+                self.last_tap = Some(now);
+                false
             }
-            TriggerButton::BothGrips => {
-                self.current_state.left_grip_pressed()
-                    && self.current_state.right_grip_pressed()
+        }
+    }
+
+    /// Checks if a mark should be dropped.
+    ///
+    /// Returns `true` on the rising edge of the mark button; holding it
+    /// marks once. Call once per frame.
+    pub fn check_mark_triggered(&mut self) -> bool {
+        self.check_action_triggered(InputAction::Mark)
+    }
+
+    /// Checks one action for the rising edge of its binding.
+    ///
+    /// Returns `false` if the action isn't bound. Combos use the same
+    /// press/release hysteresis as the save trigger.
+    pub fn check_action_triggered(&mut self, action: InputAction) -> bool {
+        let (press, release) = (self.press_threshold, self.release_threshold);
+        let state = &self.current_state;
+        match self.actions.iter_mut().find(|a| a.action == action) {
+            Some(bound) => {
+                let threshold = if bound.was_pressed { release } else { press };
+                let is_pressed = state.binding_pressed(&bound.binding, threshold);
+                let just_pressed = is_pressed && !bound.was_pressed;
+                bound.was_pressed = is_pressed;
+                just_pressed
             }
+            None => false,
+        }
+    }
+
+    /// Checks every bound action, returning those just pressed.
+    ///
+    /// Call once per frame, instead of checking actions one by one.
+    pub fn check_actions(&mut self) -> Vec<InputAction> {
+        let bound: Vec<_> = self.actions.iter().map(|a| a.action).collect();
+        bound
+            .into_iter()
+            .filter(|&action| self.check_action_triggered(action))
+            .collect()
+    }
+
+    /// Checks if the trigger combo is currently pressed.
+    ///
+    /// Once pressed, the combo stays pressed until it drops below the
+    /// (lower) release threshold, so it has to be clearly let go before
+    /// it can trigger again.
+    fn is_combo_pressed(&self) -> bool {
+        let threshold = if self.was_pressed {
+            self.release_threshold
+        } else {
+            self.press_threshold
+        };
+        let value = match self.trigger_mode {
+            TriggerMode::Combo => self
+                .trigger_buttons
+                .iter()
+                .map(|button| self.current_state.combo_value(button))
+                .fold(0.0, f32::max),
+            TriggerMode::DoubleTap { button, .. } => self.current_state.axis(button),
+        };
+        value > threshold
+    }
+
+    /// Changes the trigger button, replacing any others.
+    ///
+    /// A combo held under the old binding doesn't carry over: the new
+    /// one must be pressed fresh before it can save. Fails, keeping the
+    /// old binding, if the combo is invalid or bound to an action.
+    pub fn set_trigger_button(&mut self, button: TriggerButton) -> Result<(), ConfigError> {
+        self.set_trigger_buttons(vec![button])
+    }
+
+    /// Changes the primary trigger button, keeping any others.
+    ///
+    /// Fails, keeping the old bindings, if the combo is invalid or
+    /// bound to an action.
+    pub fn set_primary_trigger_button(&mut self, button: TriggerButton) -> Result<(), ConfigError> {
+        let extras = self.trigger_buttons[1..].iter().filter(|b| **b != button).cloned();
+        let buttons = std::iter::once(button.clone()).chain(extras).collect();
+        self.set_trigger_buttons(buttons)
+    }
+
+    /// Sets every combo that saves; pressing any one of them saves.
+    ///
+    /// The combos share one press: holding one while pressing another
+    /// doesn't save twice. An empty list is ignored, so there is always
+    /// a way to save. Fails, changing nothing, if any combo is invalid
+    /// or bound to an action.
+    pub fn set_trigger_buttons(&mut self, buttons: Vec<TriggerButton>) -> Result<(), ConfigError> {
+        if buttons.is_empty() {
+            log::warn!("Ignoring empty trigger button list");
+            return Ok(());
+        }
+        for button in &buttons {
+            self.check_trigger_button(button)?;
+        }
+        self.trigger_buttons = buttons;
+        self.was_pressed = true;
+        Ok(())
+    }
+
+    /// Checks that `button` is a valid combo that no action is bound to.
+    pub fn check_trigger_button(&self, button: &TriggerButton) -> Result<(), ConfigError> {
+        button.validate()?;
+        let combo = ActionBinding::Combo(button.clone());
+        match self.actions.iter().find(|a| a.binding.collides_with(&combo)) {
+            Some(taken) => Err(ConfigError::ConflictingBinding(taken.action)),
+            None => Ok(()),
+        }
+    }
+
+    /// Adds another combo that saves, alongside the existing ones.
+    ///
+    /// A combo that already saves is left as is. Fails, changing
+    /// nothing, if the combo is invalid or bound to an action.
+    pub fn add_trigger_button(&mut self, button: TriggerButton) -> Result<(), ConfigError> {
+        self.check_trigger_button(&button)?;
+        if !self.trigger_buttons.contains(&button) {
+            self.trigger_buttons.push(button);
+            self.was_pressed = true;
+        }
+        Ok(())
+    }
+
+    /// Returns the primary trigger button.
+    pub fn trigger_button(&self) -> &TriggerButton {
+        &self.trigger_buttons[0]
+    }
+
+    /// Returns every combo that saves, primary first.
+    pub fn trigger_buttons(&self) -> &[TriggerButton] {
+        &self.trigger_buttons
+    }
+
+    /// Switches between pressing the combo and double-tapping a control.
+    ///
+    /// Like changing the button, a control held while switching must be
+    /// let go first.
+    pub fn set_trigger_mode(&mut self, mode: TriggerMode) {
+        self.trigger_mode = mode;
+        self.last_tap = None;
+        self.was_pressed = true;
+    }
+
+    /// Returns the active trigger mode.
+    pub fn trigger_mode(&self) -> TriggerMode {
+        self.trigger_mode
+    }
+
+    /// Returns the hand that saves, for haptic feedback.
+    pub fn trigger_hand(&self) -> Hand {
+        match self.trigger_mode {
+            TriggerMode::Combo => Hand::holding(&self.trigger_button().controls()),
+            TriggerMode::DoubleTap { button, .. } => Hand::holding(&[button]),
         }
     }
 
-    /// Changes the trigger button.
-    pub fn set_trigger_button(&mut self, button: TriggerButton) {
-        self.trigger_button = button;
+    /// Changes the mark button.
+    pub fn set_mark_button(&mut self, button: MarkButton) {
+        if let Err(e) = self.set_action_binding(InputAction::Mark, ActionBinding::Button(button)) {
+            log::warn!("Mark button not bound: {}", e);
+        }
+    }
+
+    /// Binds an action, replacing its old binding.
+    ///
+    /// Fails if the save trigger or another action already uses the
+    /// press. Binding a disabled button unbinds the action. Like the
+    /// save trigger, a binding held while it's set must be pressed
+    /// fresh before it fires.
+    pub fn set_action_binding(
+        &mut self,
+        action: InputAction,
+        binding: ActionBinding,
+    ) -> Result<(), ConfigError> {
+        let taken = self
+            .trigger_buttons
+            .iter()
+            .any(|button| binding.collides_with(&ActionBinding::Combo(button.clone())))
+            || self
+                .actions
+                .iter()
+                .any(|a| a.action != action && binding.collides_with(&a.binding));
+        if taken {
+            return Err(ConfigError::ConflictingBinding(action));
+        }
+
+        self.actions.retain(|a| a.action != action);
+        if binding != ActionBinding::Button(MarkButton::Disabled) {
+            self.actions.push(BoundAction {
+                action,
+                binding,
+                was_pressed: true,
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns an action's binding, if it has one.
+    pub fn action_binding(&self, action: InputAction) -> Option<&ActionBinding> {
+        self.actions
+            .iter()
+            .find(|a| a.action == action)
+            .map(|a| &a.binding)
     }
 
     /// Changes the debounce duration.
@@ -178,6 +587,57 @@ impl InputHandler {
         self.debounce_duration = Duration::from_millis(ms);
     }
 
+    /// Changes the frame-based debounce.
+    ///
+    /// A save can only trigger once `frames` calls to
+    /// `check_save_triggered` have passed since the last one.
+    /// Applies in addition to the time-based debounce; 0 disables it.
+    pub fn set_debounce_frames(&mut self, frames: u32) {
+        self.debounce_frames = frames;
+    }
+
+    /// Changes how long the combo must be held before it saves.
+    ///
+    /// Zero saves the moment the combo is pressed. A hold that is let
+    /// go early doesn't count towards the next one.
+    pub fn set_hold_duration(&mut self, duration: Duration) {
+        self.hold_duration = duration;
+    }
+
+    /// Changes the press and release thresholds (0.0 to 1.0).
+    ///
+    /// `press` is clamped to 0.3-1.0 and `release` to at most `press`;
+    /// equal values disable hysteresis.
+    pub fn set_trigger_thresholds(&mut self, press: f32, release: f32) {
+        let clamped = press.clamp(MIN_PRESS_THRESHOLD, 1.0);
+        if clamped != press {
+            log::warn!("Press threshold {} out of range, using {}", press, clamped);
+        }
+        self.press_threshold = clamped;
+        self.release_threshold = release.clamp(0.0, clamped);
+    }
+
+    /// Changes how far a control must be pressed to count, for
+    /// controllers whose triggers no longer bottom out.
+    ///
+    /// Clamped to 0.3-1.0. The release threshold moves with it, keeping
+    /// the same gap below.
+    pub fn set_press_threshold(&mut self, press: f32) {
+        let gap = self.press_threshold - self.release_threshold;
+        let clamped = press.clamp(MIN_PRESS_THRESHOLD, 1.0);
+        self.set_trigger_thresholds(clamped, clamped - gap);
+    }
+
+    /// Returns how far a control must be pressed to count.
+    pub fn press_threshold(&self) -> f32 {
+        self.press_threshold
+    }
+
+    /// Returns true if `axis` is pressed past the press threshold.
+    pub fn is_pressed(&self, axis: ControllerAxis) -> bool {
+        self.current_state.axis(axis) > self.press_threshold
+    }
+
     /// Returns the current input state.
     pub fn current_state(&self) -> &InputState {
         &self.current_state
@@ -189,6 +649,19 @@ impl InputHandler {
     }
 }
 
+/// An action's binding and its edge-detection state.
+#[derive(Debug)]
+struct BoundAction {
+    /// What happens on press
+    action: InputAction,
+
+    /// What has to be pressed
+    binding: ActionBinding,
+
+    /// Was the binding pressed last frame?
+    was_pressed: bool,
+}
+
 impl Default for InputHandler {
     fn default() -> Self {
         Self::new(TriggerButton::default())
@@ -211,6 +684,15 @@ pub struct HapticParams {
 }
 
 impl HapticParams {
+    /// Faint pulse: a save has started.
+    pub fn working() -> Self {
+        Self {
+            duration_ms: 30,
+            amplitude: 0.3,
+            frequency: Some(160.0),
+        }
+    }
+
     /// Short click feedback.
     pub fn click() -> Self {
         Self {
@@ -239,6 +721,85 @@ impl HapticParams {
     }
 }
 
+/// Which controller vibrates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hand {
+    /// Left controller
+    Left,
+
+    /// Right controller
+    Right,
+}
+
+impl Hand {
+    /// Returns the hand that presses `controls`: right only if every
+    /// one is on the right controller.
+    pub fn holding(controls: &[ControllerAxis]) -> Self {
+        let right = [
+            ControllerAxis::RightGrip,
+            ControllerAxis::RightTrigger,
+            ControllerAxis::A,
+            ControllerAxis::B,
+        ];
+        if controls.iter().all(|axis| right.contains(axis)) {
+            Self::Right
+        } else {
+            Self::Left
+        }
+    }
+}
+
+/// Something that can vibrate a controller.
+///
+/// The platform layer supplies one; the library only decides when to
+/// buzz and how.
+pub trait HapticOutput: Send + Sync {
+    /// Plays `feedback` on `hand`'s controller.
+    fn play(&self, hand: Hand, feedback: &HapticParams);
+}
+
+/// Haptics for platforms without controllers: does nothing.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoHaptics;
+
+impl HapticOutput for NoHaptics {
+    fn play(&self, _hand: Hand, _feedback: &HapticParams) {}
+}
+
+/// Haptics through the OpenXR haptic action.
+///
+/// Stub: the OpenXR session and action live in the native layer, which
+/// isn't wired up yet, so this only logs what it would play.
+#[cfg(target_os = "android")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OpenXrHaptics;
+
+#[cfg(target_os = "android")]
+impl HapticOutput for OpenXrHaptics {
+    fn play(&self, hand: Hand, feedback: &HapticParams) {
+        log::debug!(
+            "Haptic {:?}: {}ms at {:.1} ({:?} Hz)",
+            hand,
+            feedback.duration_ms,
+            feedback.amplitude,
+            feedback.frequency
+        );
+    }
+}
+
+/// Returns the platform's haptic output.
+pub fn platform_haptics() -> std::sync::Arc<dyn HapticOutput> {
+    #[cfg(target_os = "android")]
+    {
+        std::sync::Arc::new(OpenXrHaptics)
+    }
+
+    #[cfg(not(target_os = "android"))]
+    {
+        std::sync::Arc::new(NoHaptics)
+    }
+}
+
 // ============================================
 // TESTS
 // ============================================
@@ -246,6 +807,7 @@ impl HapticParams {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::ButtonSpec;
 
     #[test]
     fn test_input_state_default() {
@@ -254,6 +816,23 @@ mod tests {
         assert!(!state.left_trigger_pressed());
     }
 
+    #[test]
+    fn test_from_config() {
+        let mut config = Config {
+            extra_trigger_buttons: vec![TriggerButton::BothGrips],
+            trigger_press_threshold: 0.5,
+            ..Config::default()
+        };
+        let handler = InputHandler::from_config(&config).unwrap();
+        assert_eq!(handler.trigger_buttons().len(), 2);
+        assert_eq!(handler.press_threshold(), 0.5);
+
+        // An action can't share the save combo
+        config.action_bindings =
+            vec![(InputAction::Pause, ActionBinding::Combo(TriggerButton::BothGrips))];
+        assert!(InputHandler::from_config(&config).is_err());
+    }
+
     #[test]
     fn test_input_pressed() {
         let mut state = InputState::new();
@@ -264,6 +843,20 @@ mod tests {
         assert!(state.left_grip_pressed());
     }
 
+    #[test]
+    fn test_pressed_with_threshold() {
+        let mut state = InputState::new();
+        state.left_grip = 0.95;
+        state.right_trigger = 0.5;
+
+        // A configured threshold above the press doesn't count it
+        assert!(state.left_grip_pressed_with(PRESS_THRESHOLD));
+        assert!(!state.left_grip_pressed_with(0.97));
+        assert!(!state.right_trigger_pressed());
+        assert!(state.right_trigger_pressed_with(MIN_PRESS_THRESHOLD));
+        assert!(!state.right_grip_pressed_with(MIN_PRESS_THRESHOLD));
+    }
+
     #[test]
     fn test_trigger_detection() {
         let mut handler = InputHandler::new(TriggerButton::LeftGripAndTrigger);
@@ -303,4 +896,420 @@ mod tests {
         handler.update(pressed);
         assert!(!handler.check_save_triggered());
     }
+
+    #[test]
+    fn test_debounce_frames() {
+        let mut handler = InputHandler::new(TriggerButton::LeftGripAndTrigger);
+        handler.set_debounce_ms(0);
+        handler.set_debounce_frames(4);
+
+        let mut pressed = InputState::new();
+        pressed.left_trigger = 1.0;
+        pressed.left_grip = 1.0;
+
+        // Frame 0: first press triggers
+        handler.update(pressed.clone());
+        assert!(handler.check_save_triggered());
+
+        // Frame 1: release, frame 2: press again - only 2 frames later
+        handler.update(InputState::new());
+        assert!(!handler.check_save_triggered());
+        handler.update(pressed.clone());
+        assert!(!handler.check_save_triggered());
+
+        // Frame 3: release, frame 4: press - 4 frames later, allowed
+        handler.update(InputState::new());
+        assert!(!handler.check_save_triggered());
+        handler.update(pressed);
+        assert!(handler.check_save_triggered());
+    }
+
+    #[test]
+    fn test_hysteresis() {
+        let mut handler = InputHandler::new(TriggerButton::LeftGripAndTrigger);
+        handler.set_debounce_ms(0);
+
+        let held = |trigger: f32| InputState {
+            left_trigger: trigger,
+            left_grip: 1.0,
+            ..InputState::default()
+        };
+
+        handler.update(held(0.95));
+        assert!(handler.check_save_triggered());
+
+        // Chatter around the press threshold is not a release
+        for value in [0.85, 0.92, 0.8, 0.95] {
+            handler.update(held(value));
+            assert!(!handler.check_save_triggered());
+            assert!(handler.is_combo_held());
+        }
+
+        // Dropping below the release threshold re-arms
+        handler.update(held(0.5));
+        assert!(!handler.check_save_triggered());
+        handler.update(held(0.8));
+        assert!(!handler.check_save_triggered());
+        handler.update(held(0.95));
+        assert!(handler.check_save_triggered());
+    }
+
+    #[test]
+    fn test_hold_duration() {
+        let mut handler = InputHandler::new(TriggerButton::LeftGripAndTrigger);
+        handler.set_debounce_ms(0);
+        handler.set_hold_duration(Duration::from_millis(750));
+        let held = InputState {
+            left_trigger: 1.0,
+            left_grip: 1.0,
+            ..InputState::default()
+        };
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+
+        // Held 500ms, let go, held again: the first hold doesn't count
+        for ms in (0..=500).step_by(100) {
+            handler.update(held.clone());
+            assert!(!handler.check_save_triggered_at(at(ms)));
+        }
+        handler.update(InputState::new());
+        assert!(!handler.check_save_triggered_at(at(600)));
+        handler.update(held.clone());
+        for ms in (700..1450).step_by(50) {
+            assert!(!handler.check_save_triggered_at(at(ms)), "{}ms", ms);
+        }
+
+        // 750ms into the second hold it saves, once
+        assert!(handler.check_save_triggered_at(at(1450)));
+        assert!(!handler.check_save_triggered_at(at(1500)));
+        assert!(!handler.check_save_triggered_at(at(3000)));
+
+        // Let go and hold again for another save
+        handler.update(InputState::new());
+        assert!(!handler.check_save_triggered_at(at(3100)));
+        handler.update(held);
+        assert!(!handler.check_save_triggered_at(at(3200)));
+        assert!(handler.check_save_triggered_at(at(3950)));
+    }
+
+    #[test]
+    fn test_double_tap() {
+        let mut handler = InputHandler::default();
+        handler.set_debounce_ms(500);
+        handler.set_trigger_mode(TriggerMode::DoubleTap {
+            button: ControllerAxis::RightTrigger,
+            window_ms: 300,
+        });
+        let trigger = |value: f32| InputState {
+            right_trigger: value,
+            ..InputState::default()
+        };
+        let start = Instant::now();
+        let mut tap = |ms: u64| {
+            handler.update(trigger(1.0));
+            let saved = handler.check_save_triggered_at(start + Duration::from_millis(ms));
+            handler.update(trigger(0.0));
+            assert!(!handler.check_save_triggered_at(start + Duration::from_millis(ms + 50)));
+            saved
+        };
+
+        // Taps too far apart never pair up
+        assert!(!tap(0));
+        assert!(!tap(400));
+        assert!(!tap(800));
+
+        // A second tap within the window saves; a third right after
+        // doesn't save again, and a fourth pairs with it but is debounced
+        assert!(tap(1000));
+        assert!(!tap(1100));
+        assert!(!tap(1200));
+
+        // Once the debounce has passed, a new double tap saves
+        assert!(!tap(2000));
+        assert!(tap(2250));
+    }
+
+    #[test]
+    fn test_haptic_hand() {
+        let hand = |button: TriggerButton| InputHandler::new(button).trigger_hand();
+        assert_eq!(hand(TriggerButton::LeftGripAndTrigger), Hand::Left);
+        assert_eq!(hand(TriggerButton::RightGripAndTrigger), Hand::Right);
+        assert_eq!(hand(TriggerButton::BothGrips), Hand::Left);
+
+        let mut handler = InputHandler::default();
+        handler.set_trigger_mode(TriggerMode::DoubleTap {
+            button: ControllerAxis::RightTrigger,
+            window_ms: 300,
+        });
+        assert_eq!(handler.trigger_hand(), Hand::Right);
+    }
+
+    #[test]
+    fn test_mark_button() {
+        let mut handler = InputHandler::default();
+        let b_pressed = InputState {
+            right_b: true,
+            ..InputState::default()
+        };
+
+        // Disabled by default
+        handler.update(b_pressed.clone());
+        assert!(!handler.check_mark_triggered());
+
+        handler.set_mark_button(MarkButton::RightB);
+        handler.update(InputState::new());
+        assert!(!handler.check_mark_triggered());
+        handler.update(b_pressed);
+        assert!(handler.check_mark_triggered());
+        assert!(!handler.check_mark_triggered());
+    }
+
+    #[test]
+    fn test_action_bindings() {
+        let mut handler = InputHandler::new(TriggerButton::LeftGripAndTrigger);
+        let pause = ActionBinding::Combo(TriggerButton::BothGrips);
+        let clear = ActionBinding::Button(MarkButton::RightA);
+        handler
+            .set_action_binding(InputAction::Pause, pause.clone())
+            .unwrap();
+        handler
+            .set_action_binding(InputAction::ClearBuffer, clear.clone())
+            .unwrap();
+
+        // Collisions with the save combo (in any order) or another action
+        let save_swapped =
+            TriggerButton::custom(&[ControllerAxis::LeftTrigger, ControllerAxis::LeftGrip]);
+        assert!(handler
+            .set_action_binding(InputAction::Mark, ActionBinding::Combo(save_swapped))
+            .is_err());
+        assert!(handler
+            .set_action_binding(InputAction::Mark, clear.clone())
+            .is_err());
+        // Rebinding an action to its own press is fine
+        assert!(handler
+            .set_action_binding(InputAction::ClearBuffer, clear)
+            .is_ok());
+
+        let grips = InputState {
+            left_grip: 1.0,
+            right_grip: 1.0,
+            right_a: true,
+            ..InputState::default()
+        };
+        handler.update(InputState::new());
+        assert!(handler.check_actions().is_empty());
+        handler.update(grips.clone());
+        assert_eq!(
+            handler.check_actions(),
+            vec![InputAction::Pause, InputAction::ClearBuffer]
+        );
+        handler.update(grips);
+        assert!(handler.check_actions().is_empty());
+        assert!(!handler.check_save_triggered());
+
+        handler
+            .set_action_binding(
+                InputAction::Pause,
+                ActionBinding::Button(MarkButton::Disabled),
+            )
+            .unwrap();
+        assert_eq!(handler.action_binding(InputAction::Pause), None);
+    }
+
+    #[test]
+    fn test_custom_thresholds() {
+        let mut handler = InputHandler::new(TriggerButton::BothGrips);
+        handler.set_debounce_ms(0);
+        handler.set_trigger_thresholds(0.6, 0.8);
+
+        let grips = |value: f32| InputState {
+            left_grip: value,
+            right_grip: value,
+            ..InputState::default()
+        };
+
+        handler.update(grips(0.7));
+        assert!(handler.check_save_triggered());
+
+        // Release clamped to the press threshold: 0.65 still held
+        handler.update(grips(0.65));
+        assert!(!handler.check_save_triggered());
+        assert!(handler.is_combo_held());
+    }
+
+    #[test]
+    fn test_press_threshold() {
+        let mut handler = InputHandler::new(TriggerButton::LeftGripAndTrigger);
+        handler.set_debounce_ms(0);
+        let worn = InputState {
+            left_trigger: 0.8,
+            left_grip: 0.8,
+            ..InputState::default()
+        };
+
+        // A trigger that stops at 80% never saves at the default
+        handler.update(worn.clone());
+        assert!(!handler.check_save_triggered());
+        assert!(!handler.is_pressed(ControllerAxis::LeftTrigger));
+
+        handler.set_press_threshold(0.75);
+        assert!(handler.is_pressed(ControllerAxis::LeftTrigger));
+        assert!(handler.check_save_triggered());
+
+        // Hysteresis keeps its gap: 0.6 is still held, 0.5 lets go
+        handler.update(InputState { left_trigger: 0.6, ..worn.clone() });
+        assert!(handler.is_combo_held());
+        handler.update(InputState { left_trigger: 0.5, ..worn });
+        assert!(!handler.is_combo_held());
+
+        // Out-of-range values are clamped
+        handler.set_press_threshold(0.05);
+        assert_eq!(handler.press_threshold(), MIN_PRESS_THRESHOLD);
+        handler.set_press_threshold(3.0);
+        assert_eq!(handler.press_threshold(), 1.0);
+    }
+
+    #[test]
+    fn test_multiple_trigger_buttons() {
+        let mut handler = InputHandler::new(TriggerButton::LeftGripAndTrigger);
+        handler.set_debounce_ms(0);
+        handler.add_trigger_button(TriggerButton::BothGrips).unwrap();
+        assert_eq!(handler.trigger_buttons().len(), 2);
+
+        // Invalid combos and combos bound to an action aren't added
+        let same = TriggerButton::custom(&[ControllerAxis::LeftGrip, ControllerAxis::LeftGrip]);
+        assert!(handler.add_trigger_button(same).is_err());
+        let right = ActionBinding::Combo(TriggerButton::RightGripAndTrigger);
+        handler.set_action_binding(InputAction::Mark, right).unwrap();
+        assert!(matches!(
+            handler.add_trigger_button(TriggerButton::RightGripAndTrigger),
+            Err(ConfigError::ConflictingBinding(InputAction::Mark))
+        ));
+        assert_eq!(handler.trigger_buttons().len(), 2);
+        let press = |left_trigger: f32, left_grip: f32, right_grip: f32| InputState {
+            left_trigger,
+            left_grip,
+            right_grip,
+            ..InputState::default()
+        };
+
+        // Either combo saves on its own
+        handler.update(InputState::new());
+        assert!(!handler.check_save_triggered());
+        handler.update(press(1.0, 1.0, 0.0));
+        assert!(handler.check_save_triggered());
+        handler.update(InputState::new());
+        assert!(!handler.check_save_triggered());
+        handler.update(press(0.0, 1.0, 1.0));
+        assert!(handler.check_save_triggered());
+
+        // Adding the second combo while the first is held is one press
+        handler.update(press(1.0, 1.0, 1.0));
+        assert!(!handler.check_save_triggered());
+        handler.update(press(1.0, 1.0, 0.0));
+        assert!(!handler.check_save_triggered());
+
+        // Both combos are taken for actions; an empty list changes nothing
+        let grips = ActionBinding::Combo(TriggerButton::BothGrips);
+        assert!(handler.set_action_binding(InputAction::Pause, grips).is_err());
+        handler.set_trigger_buttons(Vec::new()).unwrap();
+        assert_eq!(handler.trigger_buttons().len(), 2);
+    }
+
+    #[test]
+    fn test_trigger_button_collides_with_action() {
+        let mut handler = InputHandler::new(TriggerButton::default());
+        let grips = ActionBinding::Combo(TriggerButton::BothGrips);
+        handler.set_action_binding(InputAction::Pause, grips).unwrap();
+
+        assert!(matches!(
+            handler.set_trigger_button(TriggerButton::BothGrips),
+            Err(ConfigError::ConflictingBinding(InputAction::Pause))
+        ));
+        assert_eq!(handler.trigger_button(), &TriggerButton::default());
+        handler.set_trigger_button(TriggerButton::RightGripAndTrigger).unwrap();
+    }
+
+    #[test]
+    fn test_custom_trigger_button() {
+        let mut handler = InputHandler::new(TriggerButton::default());
+        handler.set_debounce_ms(0);
+        let combo =
+            TriggerButton::custom(&[ControllerAxis::LeftGrip, ControllerAxis::RightTrigger]);
+        let held = InputState {
+            left_grip: 1.0,
+            right_trigger: 1.0,
+            ..InputState::default()
+        };
+
+        // Held while switching: must be let go before it saves
+        handler.update(held.clone());
+        assert!(!handler.check_save_triggered());
+        handler.set_trigger_button(combo.clone()).unwrap();
+        assert_eq!(handler.trigger_button(), &combo);
+        assert!(!handler.check_save_triggered());
+
+        handler.update(InputState::default());
+        assert!(!handler.check_save_triggered());
+        handler.update(held);
+        assert!(handler.check_save_triggered());
+    }
+
+    #[test]
+    fn test_parsed_custom_combo() {
+        let combo: TriggerButton = "left_grip + right_trigger".parse().unwrap();
+        assert_eq!(
+            combo,
+            TriggerButton::custom(&[ControllerAxis::LeftGrip, ControllerAxis::RightTrigger])
+        );
+        assert_eq!("both_grips".parse::<TriggerButton>().unwrap(), TriggerButton::BothGrips);
+        for bad in ["left_grip", "left_grip+left_grip", "left_grip+z", "a+b@0.1", "a+b+a", ""] {
+            assert!(bad.parse::<TriggerButton>().is_err(), "{}", bad);
+        }
+
+        let mut handler = InputHandler::new(combo);
+        handler.set_debounce_ms(0);
+        let press = |left_grip: f32, right_trigger: f32| InputState {
+            left_grip,
+            right_trigger,
+            ..InputState::default()
+        };
+
+        // Either control alone doesn't save; both together do
+        for (grip, trigger) in [(1.0, 0.0), (0.0, 1.0), (0.0, 0.0)] {
+            handler.update(press(grip, trigger));
+            assert!(!handler.check_save_triggered());
+        }
+        handler.update(press(1.0, 1.0));
+        assert!(handler.check_save_triggered());
+    }
+
+    #[test]
+    fn test_face_button_combo() {
+        let combo: TriggerButton = "right_grip + a + b@0.5".parse().unwrap();
+        let TriggerButton::Custom(specs) = &combo else {
+            panic!("expected a custom combo");
+        };
+        assert_eq!(specs.len(), 3);
+        assert_eq!(specs[2], ButtonSpec::new(ControllerAxis::B).with_threshold(0.5));
+        assert_eq!(combo.to_string(), "right_grip+a+b@0.5");
+
+        let mut handler = InputHandler::new(combo);
+        handler.set_debounce_ms(0);
+        assert_eq!(handler.trigger_hand(), Hand::Right);
+        let press = |right_a: bool, right_b: bool| InputState {
+            right_grip: 1.0,
+            right_a,
+            right_b,
+            ..InputState::default()
+        };
+
+        // Every listed control is needed
+        for (a, b) in [(true, false), (false, true), (false, false)] {
+            handler.update(press(a, b));
+            assert!(!handler.check_save_triggered());
+        }
+        handler.update(press(true, true));
+        assert!(handler.check_save_triggered());
+    }
 }
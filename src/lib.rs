@@ -14,6 +14,7 @@
 //! - `storage`: File system operations
 //! - `config`: Application configuration
 //! - `error`: Error types
+//! - `logging`: Optional log file for bug reports
 
 // ============================================
 // MODULE DECLARATIONS
@@ -25,16 +26,17 @@ pub mod config;
 pub mod encoder;
 pub mod error;
 pub mod input;
+pub mod logging;
 pub mod storage;
 
 // ============================================
 // RE-EXPORTS
 // ============================================
 
-pub use buffer::SharedFrameBuffer;
+pub use buffer::{CaptureBuffer, SharedFrameBuffer};
 pub use capture::CapturedFrame;
-pub use config::Config;
-pub use error::{ShadowplayError, ShadowplayResult};
+pub use config::{Config, ConfigBuilder, RecordingProfile};
+pub use error::{ShadowplayError, ShadowplayResult, StorageErrorKind};
 pub use input::InputHandler;
 
 // ============================================
@@ -44,8 +46,9 @@ pub use input::InputHandler;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use chrono::Local;
 use log::{error, info, warn};
 use parking_lot::Mutex;
 
@@ -63,22 +66,58 @@ use parking_lot::Mutex;
 /// - Triggers video encoding when saving
 pub struct QuestShadowplay {
     /// The circular buffer storing recent frames
-    buffer: Arc<SharedFrameBuffer>,
+    buffer: Arc<CaptureBuffer>,
+
+    /// Extra buffers for the configured recording profiles
+    profile_buffers: Vec<(RecordingProfile, Arc<CaptureBuffer>)>,
 
     /// Handles controller input
     input_handler: Arc<Mutex<InputHandler>>,
 
+    /// Marked moments still covered by a buffer, oldest first
+    marks: Mutex<Vec<encoder::ClipMark>>,
+
     /// Application configuration
     config: Config,
 
     /// Is a save currently in progress?
     is_saving: Arc<AtomicBool>,
 
+    /// The running save while it still takes in triggers (coalescing)
+    open_save: Arc<Mutex<Option<OpenSave>>>,
+
+    /// Discards the first frames of each session
+    warmup: capture::WarmupFilter,
+
+    /// Is capture paused until a save is acknowledged?
+    paused_after_save: Arc<AtomicBool>,
+
+    /// Is capture paused by the pause action?
+    paused: AtomicBool,
+
+    /// Did the session stop after saving `auto_stop_after_clips` clips?
+    auto_stopped: Arc<AtomicBool>,
+
     /// Is the application running?
     is_running: Arc<AtomicBool>,
 
     /// Statistics about operation
     stats: Arc<Mutex<AppStats>>,
+
+    /// Vibrates the controller to confirm saves
+    haptics: Mutex<Arc<dyn input::HapticOutput>>,
+
+    /// App or game being recorded, as reported by the platform layer
+    recorded_app: Mutex<Option<String>>,
+}
+
+/// A save that later triggers can still be folded into.
+#[derive(Debug, Clone, Copy)]
+struct OpenSave {
+    /// When the save was triggered
+    started: Instant,
+    /// Did another trigger arrive within the coalesce window?
+    extended: bool,
 }
 
 /// Runtime statistics for monitoring
@@ -86,16 +125,121 @@ pub struct QuestShadowplay {
 pub struct AppStats {
     /// Total frames received
     pub frames_received: u64,
+    /// Frames discarded at the start of a session
+    pub frames_warmup_discarded: u64,
     /// Total clips saved
     pub clips_saved: u64,
+    /// Clips saved since the current session started
+    pub session_clips_saved: u64,
     /// Total save errors
     pub save_errors: u64,
+    /// Save triggers folded into an earlier save
+    pub saves_coalesced: u64,
+    /// Frames decoded to re-encode them for recording profiles
+    pub profile_decodes: u64,
+    /// JPEG encodes made for recording profiles
+    pub profile_encodes: u64,
+    /// Moments marked (button or API)
+    pub marks_added: u64,
+}
+
+/// One captured frame at each recording profile's JPEG quality.
+///
+/// Incoming frames are taken to be at the configured quality, so they
+/// are shared as-is when a profile matches; raw frames are always
+/// shared, the same as [`capture::FrameCapturer`] does. Otherwise the
+/// frame is decoded once, and encoded once per quality however many
+/// profiles use it.
+struct ProfileFrames<'a> {
+    /// The frame as captured
+    source: &'a CapturedFrame,
+    /// Quality the source was encoded at
+    source_quality: u8,
+    /// The decoded source, once a profile needed it (`None` inside if
+    /// decoding failed)
+    decoded: Option<Option<image::RgbaImage>>,
+    /// Frames encoded so far, by quality
+    encoded: Vec<(u8, CapturedFrame)>,
+}
+
+impl<'a> ProfileFrames<'a> {
+    fn new(source: &'a CapturedFrame, source_quality: u8) -> Self {
+        Self {
+            source,
+            source_quality,
+            decoded: None,
+            encoded: Vec::new(),
+        }
+    }
+
+    /// Returns the frame at `profile`'s quality, or `None` (skipping the
+    /// profile for this frame) if re-encoding fails.
+    fn frame_for(&mut self, profile: &RecordingProfile) -> Option<CapturedFrame> {
+        let frame = self.source;
+        if frame.format != capture::FrameFormat::Jpeg
+            || profile.jpeg_quality == self.source_quality
+        {
+            return Some(frame.clone());
+        }
+        if let Some((_, encoded)) = self.encoded.iter().find(|(q, _)| *q == profile.jpeg_quality) {
+            return Some(encoded.clone());
+        }
+
+        let image = self
+            .decoded
+            .get_or_insert_with(|| match encoder::VideoEncoder::decode_jpeg(frame) {
+                Ok(image) => Some(image),
+                Err(e) => {
+                    warn!("Can't re-encode a frame for profiles: {}", e);
+                    None
+                }
+            })
+            .as_ref()?;
+        let compressed = capture::FrameCompressor::new(profile.jpeg_quality).compress(
+            image.as_raw(),
+            frame.width,
+            frame.height,
+        );
+        match compressed {
+            Ok(data) => {
+                let mut profile_frame = CapturedFrame::with_timestamp(
+                    data,
+                    frame.eye_index,
+                    frame.width,
+                    frame.height,
+                    frame.timestamp_ns,
+                );
+                profile_frame.motion_score = frame.motion_score;
+                self.encoded.push((profile.jpeg_quality, profile_frame.clone()));
+                Some(profile_frame)
+            }
+            Err(e) => {
+                warn!("Profile '{}' skipped a frame: {}", profile.name, e);
+                None
+            }
+        }
+    }
 }
 
 impl QuestShadowplay {
-    /// Creates a new application instance with default configuration.
+    /// Creates a new application instance, configured from the config
+    /// file at [`Config::default_path`] if there is one.
+    ///
+    /// Falls back to the default configuration when there is no file or
+    /// it can't be read. `QSP_*` environment variables override the
+    /// file (see [`Config::with_env_overrides`]); a bad variable is an
+    /// error. Out-of-range settings in the file are clamped rather than
+    /// rejected, so a hand-edited file can't stop the app from starting.
     pub fn new() -> ShadowplayResult<Self> {
-        Self::with_config(Config::default())
+        let config = match Config::default_path() {
+            Some(path) => Config::load_from_path(&path).unwrap_or_else(|e| {
+                warn!("Using default config: {}", e);
+                Config::default()
+            }),
+            None => Config::default(),
+        };
+        let config = config.with_env_overrides().map_err(ShadowplayError::Config)?;
+        Self::with_config(config.clamped())
     }
 
     /// Creates a new application instance with custom configuration.
@@ -107,10 +251,12 @@ impl QuestShadowplay {
     /// A new `QuestShadowplay` instance or an error
     pub fn with_config(config: Config) -> ShadowplayResult<Self> {
         // Validate configuration
-        let errors = config.validate();
-        if !errors.is_empty() {
+        if let Err(errors) = config.validate_strict() {
             return Err(ShadowplayError::Config(errors[0].clone()));
         }
+        for warning in config.warnings() {
+            warn!("Config: {}", warning);
+        }
 
         info!(
             "Initializing Quest Shadowplay: {}s buffer at {} FPS",
@@ -118,25 +264,52 @@ impl QuestShadowplay {
         );
 
         // Create the frame buffer
-        let buffer = Arc::new(SharedFrameBuffer::new(
+        let buffer = Arc::new(CaptureBuffer::new_with_byte_limit(
             config.buffer_duration_seconds,
             config.target_fps,
+            config.max_buffer_bytes,
         ));
 
+        // One extra buffer per recording profile
+        let profile_buffers = config
+            .profiles
+            .iter()
+            .map(|profile| {
+                info!(
+                    "Recording profile '{}': {}s buffer at quality {}",
+                    profile.name, profile.buffer_duration_seconds, profile.jpeg_quality
+                );
+                let buffer = Arc::new(CaptureBuffer::new(
+                    profile.buffer_duration_seconds,
+                    config.target_fps,
+                ));
+                (profile.clone(), buffer)
+            })
+            .collect();
+
         // Create input handler
-        let input_handler = Arc::new(Mutex::new(InputHandler::new(
-            config.trigger_button.clone(),
-        )));
+        let input_handler = InputHandler::from_config(&config)?;
+        let input_handler = Arc::new(Mutex::new(input_handler));
+        let warmup = capture::WarmupFilter::new(config.warmup_discard_frames);
 
         info!("Quest Shadowplay initialized successfully");
 
         Ok(Self {
             buffer,
+            profile_buffers,
             input_handler,
+            marks: Mutex::new(Vec::new()),
             config,
             is_saving: Arc::new(AtomicBool::new(false)),
+            open_save: Arc::new(Mutex::new(None)),
+            warmup,
+            paused_after_save: Arc::new(AtomicBool::new(false)),
+            paused: AtomicBool::new(false),
+            auto_stopped: Arc::new(AtomicBool::new(false)),
             is_running: Arc::new(AtomicBool::new(true)),
             stats: Arc::new(Mutex::new(AppStats::default())),
+            haptics: Mutex::new(input::platform_haptics()),
+            recorded_app: Mutex::new(None),
         })
     }
 
@@ -152,16 +325,147 @@ impl QuestShadowplay {
             stats.frames_received += 1;
         }
 
-        // Add to buffer
-        self.buffer.push_frame(frame);
+        if self.paused_after_save.load(Ordering::SeqCst) || self.is_auto_stopped() {
+            return;
+        }
+
+        // The first frames of a session are often black or half-drawn
+        if self.warmup.discard() {
+            self.stats.lock().frames_warmup_discarded += 1;
+            return;
+        }
+
+        let timestamp_ns = frame.timestamp_ns;
 
-        // Check for save trigger (only if not already saving)
-        if !self.is_saving.load(Ordering::SeqCst) {
+        // Add to buffers, re-encoding for profiles with their own quality.
+        // While paused, input is still checked so the pause can be undone.
+        if !self.is_paused() {
+            let mut profile_frames = ProfileFrames::new(&frame, self.config.jpeg_quality);
+            for (profile, buffer) in &self.profile_buffers {
+                if let Some(profile_frame) = profile_frames.frame_for(profile) {
+                    buffer.push_frame(profile_frame);
+                }
+            }
+            if profile_frames.decoded.is_some() {
+                let mut stats = self.stats.lock();
+                stats.profile_decodes += 1;
+                stats.profile_encodes += profile_frames.encoded.len() as u64;
+            }
+            self.buffer.push_frame(frame);
+        }
+
+        let (actions, save) = {
             let mut input = self.input_handler.lock();
-            if input.check_save_triggered() {
-                self.trigger_save();
+            let actions = input.check_actions();
+            // Check for save trigger (only if not already saving)
+            let save = !self.is_saving.load(Ordering::SeqCst) && input.check_save_triggered();
+            (actions, save)
+        };
+
+        for action in actions {
+            match action {
+                config::InputAction::Mark => {
+                    let label = format!("Mark {}", self.stats.lock().marks_added + 1);
+                    self.add_mark(timestamp_ns, label);
+                }
+                config::InputAction::Pause => self.set_paused(!self.is_paused()),
+                config::InputAction::ClearBuffer => self.clear_buffers(),
+            }
+        }
+        if save {
+            self.trigger_save();
+        }
+    }
+
+    /// Pauses or resumes adding frames to the buffers.
+    ///
+    /// What's already buffered is kept and can still be saved.
+    pub fn set_paused(&self, paused: bool) {
+        if self.paused.swap(paused, Ordering::SeqCst) != paused {
+            info!("Recording {}", if paused { "paused" } else { "resumed" });
+        }
+    }
+
+    /// Returns whether recording is paused by [`QuestShadowplay::set_paused`]
+    /// or the pause action.
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Empties the main buffer, every profile buffer and their marks.
+    pub fn clear_buffers(&self) {
+        self.buffer.clear();
+        for (_, buffer) in &self.profile_buffers {
+            buffer.clear();
+        }
+        self.marks.lock().clear();
+        info!("Buffers cleared");
+    }
+
+    /// Marks a moment so clips covering it get a chapter and subtitle
+    /// there on export.
+    ///
+    /// `timestamp_ns` uses the same clock as frame timestamps. Marks
+    /// older than every buffer are dropped, since no clip can hold them.
+    pub fn add_mark(&self, timestamp_ns: u64, label: impl Into<String>) {
+        let label = label.into();
+        let oldest_kept = std::iter::once(&self.buffer)
+            .chain(self.profile_buffers.iter().map(|(_, buffer)| buffer))
+            .filter_map(|buffer| buffer.oldest_timestamp_ns())
+            .min()
+            .unwrap_or(0);
+        if timestamp_ns < oldest_kept {
+            warn!("Ignoring mark '{}': older than every buffer", label);
+            return;
+        }
+        info!("Marked moment: {}", label);
+
+        let mut marks = self.marks.lock();
+        marks.retain(|m| m.timestamp_ns >= oldest_kept);
+        let index = marks.partition_point(|m| m.timestamp_ns <= timestamp_ns);
+        marks.insert(index, encoder::ClipMark::new(timestamp_ns, label));
+        self.stats.lock().marks_added += 1;
+    }
+
+    /// Returns the marks still covered by a buffer, oldest first.
+    pub fn marks(&self) -> Vec<encoder::ClipMark> {
+        self.marks.lock().clone()
+    }
+
+    /// Feeds frames from `source` through [`QuestShadowplay::on_frame_captured`],
+    /// applying any controller input it supplies along the way.
+    ///
+    /// Stops after `max_frames` or when the source runs out, and returns
+    /// how many frames were delivered. Used to drive the whole
+    /// trigger → snapshot → encode flow from a script in tests.
+    pub fn run_with_source(
+        &self,
+        source: &mut dyn capture::FrameSource,
+        max_frames: usize,
+    ) -> usize {
+        let mut delivered = 0;
+        while delivered < max_frames {
+            let Some(frame) = source.next_frame() else {
+                break;
+            };
+            if let Some(state) = source.input_state() {
+                self.update_input(state);
             }
+            self.on_frame_captured(frame);
+            delivered += 1;
+        }
+        delivered
+    }
+
+    /// Creates a frame capturer that feeds the main buffer and every
+    /// recording profile, each at its own JPEG quality.
+    pub fn create_capturer(&self) -> capture::FrameCapturer {
+        let mut capturer =
+            capture::FrameCapturer::with_config(Arc::clone(&self.buffer), &self.config);
+        for (profile, buffer) in &self.profile_buffers {
+            capturer.add_profile_buffer(Arc::clone(buffer), profile.jpeg_quality);
         }
+        capturer
     }
 
     /// Updates the input state from controller data.
@@ -172,40 +476,192 @@ impl QuestShadowplay {
         handler.update(state);
     }
 
+    /// Changes which button combo saves a clip, effective immediately.
+    ///
+    /// Only the primary combo changes; extra combos from
+    /// `extra_trigger_buttons` keep saving too. Invalid custom combos,
+    /// and combos bound to an action, are rejected and the old binding
+    /// kept. The change is not persisted; see [`storage::SavedSettings`].
+    pub fn set_trigger_button(&self, button: config::TriggerButton) -> ShadowplayResult<()> {
+        let mut input = self.input_handler.lock();
+        input.set_primary_trigger_button(button)?;
+        info!("Trigger button set to {:?}", input.trigger_button());
+        Ok(())
+    }
+
+    /// Returns the button combo that currently saves a clip.
+    pub fn trigger_button(&self) -> config::TriggerButton {
+        self.input_handler.lock().trigger_button().clone()
+    }
+
+    /// Replaces what vibrates the controller when a save starts, finishes
+    /// or fails (by default the platform's own, if it has one).
+    pub fn set_haptic_output(&self, output: Arc<dyn input::HapticOutput>) {
+        *self.haptics.lock() = output;
+    }
+
+    /// Sets which app or game is being recorded, stored in the metadata
+    /// of clips saved from now on.
+    ///
+    /// The platform layer calls this when the foreground app changes;
+    /// while it's `None`, clips are saved without one.
+    pub fn set_recorded_app(&self, app: Option<String>) {
+        *self.recorded_app.lock() = app;
+    }
+
     /// Manually triggers a save operation.
     ///
-    /// Returns `true` if save was started, `false` if already saving.
+    /// Triggers arriving within `save_coalesce_ms` of the last started
+    /// save are folded into it, so mashing the button yields one clip:
+    /// the save waits out the window before encoding, and if another
+    /// trigger came in, the clip runs on to cover it too.
+    ///
+    /// Returns `true` if save was started, `false` if already saving
+    /// or the trigger was coalesced.
     pub fn trigger_save(&self) -> bool {
-        // Check if already saving
-        if self.is_saving.swap(true, Ordering::SeqCst) {
-            warn!("Save already in progress");
-            return false;
+        self.trigger_save_with_outputs(storage::SaveOutput::defaults(&self.config))
+    }
+
+    /// Triggers a save that also writes `outputs`, such as a frame
+    /// folder for MP4 export, decoding each frame only once. The clip
+    /// itself is always written.
+    ///
+    /// Returns `false` for the same reasons as
+    /// [`QuestShadowplay::trigger_save`].
+    pub fn trigger_save_with_outputs(&self, mut outputs: Vec<storage::SaveOutput>) -> bool {
+        if !outputs.contains(&storage::SaveOutput::Master) {
+            outputs.insert(0, storage::SaveOutput::Master);
+        }
+        self.start_save(Arc::clone(&self.buffer), None, outputs)
+    }
+
+    /// Triggers a save from a recording profile's buffer instead of the
+    /// main one.
+    ///
+    /// Returns `false` if the profile doesn't exist, or for the same
+    /// reasons as [`QuestShadowplay::trigger_save`].
+    pub fn trigger_save_profile(&self, name: &str) -> bool {
+        match self.profile_buffer(name) {
+            Some(buffer) => {
+                let outputs = storage::SaveOutput::defaults(&self.config);
+                self.start_save(buffer, Some(name.to_string()), outputs)
+            }
+            None => {
+                warn!("Unknown recording profile: {}", name);
+                false
+            }
+        }
+    }
+
+    /// Starts a background save of `buffer`.
+    fn start_save(
+        &self,
+        buffer: Arc<CaptureBuffer>,
+        profile: Option<String>,
+        outputs: Vec<storage::SaveOutput>,
+    ) -> bool {
+        let coalesce_window = Duration::from_millis(self.config.save_coalesce_ms as u64);
+
+        {
+            let mut open_save = self.open_save.lock();
+
+            if let Some(open) = open_save.as_mut() {
+                if open.started.elapsed() < coalesce_window {
+                    info!("Save trigger coalesced into the running save");
+                    open.extended = true;
+                    self.stats.lock().saves_coalesced += 1;
+                    return false;
+                }
+            }
+
+            // Check if already saving
+            if self.is_saving.swap(true, Ordering::SeqCst) {
+                warn!("Save already in progress");
+                return false;
+            }
+
+            *open_save = Some(OpenSave {
+                started: Instant::now(),
+                extended: false,
+            });
         }
 
+        // Name the clip after the moment the user reacted, not when the
+        // background encode gets going
+        let triggered_at = Local::now();
+
         info!("Save triggered - starting background encode");
 
+        // Buzz now, and again once the save succeeds or fails
+        let haptics = self.config.haptic_feedback.then(|| {
+            let haptics = Arc::clone(&*self.haptics.lock());
+            let hand = self.input_handler.lock().trigger_hand();
+            haptics.play(hand, &input::HapticParams::working());
+            (haptics, hand)
+        });
+
         // Clone references for the background thread
-        let buffer = Arc::clone(&self.buffer);
         let is_saving = Arc::clone(&self.is_saving);
+        let paused_after_save = Arc::clone(&self.paused_after_save);
+        let auto_stopped = Arc::clone(&self.auto_stopped);
         let config = self.config.clone();
+        let marks = self.marks();
+        let app = self.recorded_app.lock().clone();
         let stats = Arc::clone(&self.stats);
+        let open_save = Arc::clone(&self.open_save);
 
         // Spawn background thread for encoding
         thread::spawn(move || {
-            let result = Self::do_save(&buffer, &config);
+            // Keep the encode from competing with the VR render thread
+            set_current_thread_nice(config.encode_thread_nice);
+
+            let result =
+                Self::coalesced_snapshot(&buffer, &config, &open_save).and_then(|frames| {
+                    Self::do_save(
+                        frames,
+                        &config,
+                        &marks,
+                        &outputs,
+                        profile.as_deref(),
+                        app.as_deref(),
+                        triggered_at,
+                    )
+                });
 
             // Update stats
             {
                 let mut s = stats.lock();
                 match &result {
-                    Ok(_) => s.clips_saved += 1,
+                    Ok(_) => {
+                        s.clips_saved += 1;
+                        s.session_clips_saved += 1;
+                    }
                     Err(_) => s.save_errors += 1,
                 }
+
+                let limit = config.auto_stop_after_clips as u64;
+                if result.is_ok() && limit > 0 && s.session_clips_saved >= limit {
+                    auto_stopped.store(true, Ordering::SeqCst);
+                    info!("Saved {} clips this session, recording stopped", limit);
+                }
+            }
+
+            if let Some((haptics, hand)) = &haptics {
+                let feedback = match &result {
+                    Ok(_) => input::HapticParams::success(),
+                    Err(_) => input::HapticParams::error(),
+                };
+                haptics.play(*hand, &feedback);
             }
 
             // Log result
             match result {
-                Ok(path) => info!("Clip saved to: {}", path),
+                Ok(path) => {
+                    info!("Clip saved to: {}", path);
+                    if config.post_save.after_save(&buffer) {
+                        paused_after_save.store(true, Ordering::SeqCst);
+                    }
+                }
                 Err(e) => error!("Failed to save clip: {}", e),
             }
 
@@ -216,30 +672,149 @@ impl QuestShadowplay {
         true
     }
 
-    /// Performs the actual save operation (runs in background thread).
-    fn do_save(buffer: &SharedFrameBuffer, config: &Config) -> ShadowplayResult<String> {
-        // Snapshot the buffer
-        let frames = buffer.snapshot();
-        let frame_count = frames.len();
+    /// Snapshots the buffer for a save, then waits out the coalesce
+    /// window; if another trigger came in meanwhile, frames captured
+    /// since are appended so the clip covers both (runs in background
+    /// thread).
+    fn coalesced_snapshot(
+        buffer: &CaptureBuffer,
+        config: &Config,
+        open_save: &Mutex<Option<OpenSave>>,
+    ) -> ShadowplayResult<Vec<Arc<CapturedFrame>>> {
+        let first = Self::snapshot_for_save(buffer, config);
+
+        let window = Duration::from_millis(config.save_coalesce_ms as u64);
+        let started = open_save.lock().map(|open| open.started);
+        if let Some(started) = started {
+            thread::sleep(window.saturating_sub(started.elapsed()));
+        }
+        // Closing the window here means no trigger is folded in too late
+        let extended = open_save.lock().take().is_some_and(|open| open.extended);
+        if !extended {
+            return first;
+        }
+
+        let mut frames = first.unwrap_or_default();
+        let end_ns = frames.last().map(|frame| frame.timestamp_ns);
+        let later = Self::snapshot_for_save(buffer, config)?;
+        frames.extend(later.into_iter().filter(|frame| Some(frame.timestamp_ns) > end_ns));
+        info!("Save extended to {} frames by a coalesced trigger", frames.len());
+        Ok(frames)
+    }
+
+    /// Snapshots the buffer, shifted back by the configured offset.
+    ///
+    /// Only pointers are copied, so capture carries on into the buffer
+    /// while this snapshot is encoded (a "hot save").
+    fn snapshot_for_save(
+        buffer: &CaptureBuffer,
+        config: &Config,
+    ) -> ShadowplayResult<Vec<Arc<CapturedFrame>>> {
+        let offset_ns = config.save_offset_ms as u64 * 1_000_000;
+        let frames = buffer.snapshot_shared_ending_before(offset_ns);
 
-        if frame_count == 0 {
+        if frames.is_empty() {
+            if offset_ns > 0 && buffer.frame_count() > 0 {
+                return Err(ShadowplayError::Internal(format!(
+                    "Buffer holds less than the {} ms save offset",
+                    config.save_offset_ms
+                )));
+            }
             return Err(ShadowplayError::Internal("No frames to save".to_string()));
         }
+        Ok(frames)
+    }
 
+    /// Performs the actual save operation (runs in background thread).
+    fn do_save(
+        frames: Vec<Arc<CapturedFrame>>,
+        config: &Config,
+        marks: &[encoder::ClipMark],
+        outputs: &[storage::SaveOutput],
+        profile: Option<&str>,
+        app: Option<&str>,
+        triggered_at: chrono::DateTime<Local>,
+    ) -> ShadowplayResult<String> {
+        let frame_count = frames.len();
         info!("Encoding {} frames...", frame_count);
 
+        // Ensure output directory (and the day's folder, if organizing
+        // by date) exists, even if deleted since startup
+        let clip_directory = storage::StorageManager::clip_directory_at(
+            &config.output_directory,
+            triggered_at,
+            config.organize_by_date,
+        );
+        storage::recreate_if_missing(std::path::Path::new(&clip_directory))?;
+
         // Generate output path
-        let output_path = storage::StorageManager::generate_filename(&config.output_directory);
+        let output_path = match profile {
+            Some(name) => storage::StorageManager::generate_profile_filename_at(
+                &clip_directory,
+                name,
+                triggered_at,
+            ),
+            None => storage::StorageManager::generate_filename_at(&clip_directory, triggered_at),
+        };
 
-        // Ensure output directory exists
-        storage::ensure_directory(&config.output_directory)?;
+        // Fail before writing anything rather than leave a truncated clip,
+        // deleting old clips first if the user allows it
+        let needed = encoder::VideoEncoder::estimated_clip_bytes(&frames);
+        storage::StorageManager::new(&config.output_directory)?
+            .make_room_for(needed, config.auto_cleanup)?;
 
-        // Encode frames to video
-        encoder::VideoEncoder::encode_frames(&frames, &output_path, config)?;
+        // Encode frames to video, keeping marks that fall inside it, and
+        // any extra outputs from the same decode
+        let clip_path = std::path::Path::new(&output_path);
+        storage::write_outputs(&frames, marks, clip_path, outputs, config)?;
+
+        // Record when it happened alongside the clip
+        let metadata = storage::ClipMetadata {
+            triggered_at: Some(triggered_at),
+            saved_at: Some(Local::now()),
+            app: app.map(str::to_string),
+            ..storage::ClipMetadata::for_frames(&frames, config.target_fps)
+        };
+        if let Err(e) = metadata.save(std::path::Path::new(&output_path)) {
+            warn!("Failed to write clip metadata: {}", e);
+        }
 
         Ok(output_path)
     }
 
+    /// Resumes capture paused by [`config::PostSaveBehavior::PauseUntilAck`].
+    ///
+    /// Returns `true` if capture was paused.
+    pub fn acknowledge_save(&self) -> bool {
+        let was_paused = self.paused_after_save.swap(false, Ordering::SeqCst);
+        if was_paused {
+            info!("Save acknowledged, recording resumed");
+        }
+        was_paused
+    }
+
+    /// Returns whether capture is paused waiting for a save to be
+    /// acknowledged.
+    pub fn is_paused_after_save(&self) -> bool {
+        self.paused_after_save.load(Ordering::SeqCst)
+    }
+
+    /// Returns whether recording stopped because the session saved
+    /// `auto_stop_after_clips` clips.
+    pub fn is_auto_stopped(&self) -> bool {
+        self.auto_stopped.load(Ordering::SeqCst)
+    }
+
+    /// Starts a new session: resets the session clip count, restarts the
+    /// warmup discard and resumes recording if it was auto-stopped.
+    pub fn start_session(&self) {
+        self.stats.lock().session_clips_saved = 0;
+        self.warmup.restart();
+        if self.auto_stopped.swap(false, Ordering::SeqCst) {
+            info!("New session started, recording resumed");
+        }
+    }
+
     /// Returns whether a save is currently in progress.
     pub fn is_saving(&self) -> bool {
         self.is_saving.load(Ordering::SeqCst)
@@ -255,11 +830,50 @@ impl QuestShadowplay {
         self.buffer.frame_count()
     }
 
+    /// Returns the overall compression ratio of the buffered frames.
+    pub fn buffer_compression_ratio(&self) -> f32 {
+        self.buffer.average_compression_ratio()
+    }
+
+    /// Times a sample save on this device so save-time estimates match
+    /// its speed. Slow (about a second), so run it off the render thread.
+    pub fn calibrate_encoder(&self) -> ShadowplayResult<f64> {
+        encoder::calibrate_encoder(&self.config)
+    }
+
+    /// Estimates how long saving the current buffer would take.
+    pub fn estimated_save_time(&self) -> Duration {
+        let footage = self.buffer.frame_count() as f32 / self.config.target_fps.max(1) as f32;
+        encoder::estimated_encoding_time(footage)
+    }
+
+    /// Returns the buffer for a recording profile, if configured.
+    pub fn profile_buffer(&self, name: &str) -> Option<Arc<CaptureBuffer>> {
+        self.profile_buffers
+            .iter()
+            .find(|(profile, _)| profile.name == name)
+            .map(|(_, buffer)| Arc::clone(buffer))
+    }
+
+    /// Returns the names of the configured recording profiles.
+    pub fn profile_names(&self) -> Vec<String> {
+        self.profile_buffers
+            .iter()
+            .map(|(profile, _)| profile.name.clone())
+            .collect()
+    }
+
     /// Returns a copy of the current statistics.
     pub fn stats(&self) -> AppStats {
         self.stats.lock().clone()
     }
 
+    /// Checks that the buffer's left and right eye frames still line up,
+    /// so capture asymmetry shows before a misaligned stereo export.
+    pub fn stereo_sync_report(&self) -> buffer::StereoSyncReport {
+        self.buffer.stereo_sync_report(self.config.target_fps)
+    }
+
     /// Returns a reference to the configuration.
     pub fn config(&self) -> &Config {
         &self.config
@@ -280,9 +894,11 @@ impl QuestShadowplay {
     }
 }
 
+/// Uses [`Config::default`], without reading a config file or the
+/// environment (see [`QuestShadowplay::new`] for that).
 impl Default for QuestShadowplay {
     fn default() -> Self {
-        Self::new().expect("Failed to create default QuestShadowplay")
+        Self::with_config(Config::default()).expect("Default config is valid")
     }
 }
 
@@ -309,6 +925,91 @@ pub fn init_logging() {
     }
 }
 
+/// Initialize logging, also writing to a rotating log file in the
+/// output directory if `config.log_to_file` is set.
+pub fn init_logging_with_config(config: &Config) {
+    if !config.log_to_file {
+        init_logging();
+        return;
+    }
+
+    let path = std::path::Path::new(&config.output_directory).join(logging::LOG_FILE_NAME);
+    let file = match logging::RotatingFile::open(
+        &path,
+        logging::LOG_FILE_MAX_BYTES,
+        logging::LOG_FILE_KEEP,
+    ) {
+        Ok(file) => file,
+        Err(e) => {
+            init_logging();
+            warn!("Failed to open log file {:?}: {}", path, e);
+            return;
+        }
+    };
+
+    let logger = logging::FileTeeLogger::new(platform_logger(), file);
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(log::LevelFilter::Info);
+        info!("Logging to {:?}", path);
+    }
+}
+
+/// Returns the platform's console logger (logcat on Android, stderr elsewhere).
+fn platform_logger() -> Box<dyn log::Log> {
+    #[cfg(target_os = "android")]
+    {
+        Box::new(android_logger::AndroidLogger::new(
+            android_logger::Config::default()
+                .with_max_level(log::LevelFilter::Info)
+                .with_tag("QuestShadowplay"),
+        ))
+    }
+
+    #[cfg(not(target_os = "android"))]
+    {
+        Box::new(
+            env_logger::Builder::new()
+                .filter_level(log::LevelFilter::Info)
+                .build(),
+        )
+    }
+}
+
+// ============================================
+// THREAD PRIORITY
+// ============================================
+
+/// Sets the scheduling priority (nice value) of the calling thread.
+///
+/// Higher values mean lower priority: 10 lets a background encode
+/// yield to the render thread, negative values (which usually need
+/// extra privileges) favor latency-sensitive work like capture.
+///
+/// Returns `true` if the priority was applied. Only supported on
+/// Linux/Android, where each thread has its own nice value.
+pub fn set_current_thread_nice(nice: i32) -> bool {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        let tid = unsafe { libc::gettid() };
+        let result = unsafe { libc::setpriority(libc::PRIO_PROCESS, tid as libc::id_t, nice) };
+        if result != 0 {
+            warn!(
+                "Failed to set thread nice to {}: {}",
+                nice,
+                std::io::Error::last_os_error()
+            );
+            return false;
+        }
+        true
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        log::debug!("Thread priority not supported on this platform (nice {})", nice);
+        false
+    }
+}
+
 // ============================================
 // TESTS
 // ============================================
@@ -320,7 +1021,7 @@ mod tests {
     #[test]
     fn test_app_creation() {
         init_logging();
-        let app = QuestShadowplay::new();
+        let app = QuestShadowplay::with_config(Config::builder().build());
         assert!(app.is_ok());
     }
 
@@ -331,18 +1032,513 @@ mod tests {
         assert!(app.is_ok());
     }
 
+    #[test]
+    fn test_set_trigger_button() {
+        use config::{ControllerAxis, TriggerButton};
+
+        let app = QuestShadowplay::with_config(Config::default()).unwrap();
+        assert_eq!(app.trigger_button(), TriggerButton::default());
+
+        app.set_trigger_button(TriggerButton::BothGrips).unwrap();
+        assert_eq!(app.trigger_button(), TriggerButton::BothGrips);
+
+        let same = TriggerButton::custom(&[ControllerAxis::LeftGrip, ControllerAxis::LeftGrip]);
+        assert!(app.set_trigger_button(same).is_err());
+        assert_eq!(app.trigger_button(), TriggerButton::BothGrips);
+
+        // Extra combos survive a change of the primary one
+        let config = Config {
+            extra_trigger_buttons: vec![TriggerButton::RightGripAndTrigger],
+            ..Config::default()
+        };
+        let app = QuestShadowplay::with_config(config).unwrap();
+        app.set_trigger_button(TriggerButton::BothGrips).unwrap();
+        let buttons = app.input_handler.lock().trigger_buttons().to_vec();
+        assert_eq!(buttons, [TriggerButton::BothGrips, TriggerButton::RightGripAndTrigger]);
+    }
+
+    #[test]
+    fn test_save_haptics() {
+        /// Records the amplitude of every buzz.
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<(input::Hand, f32)>>);
+
+        impl input::HapticOutput for Recorder {
+            fn play(&self, hand: input::Hand, feedback: &input::HapticParams) {
+                self.0.lock().push((hand, feedback.amplitude));
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config {
+            output_directory: dir.path().to_string_lossy().to_string(),
+            ..Config::default()
+        };
+        let app = QuestShadowplay::with_config(config).unwrap();
+        let recorder = Arc::new(Recorder::default());
+        app.set_haptic_output(recorder.clone());
+
+        // Nothing buffered: the save starts, then fails
+        assert!(app.trigger_save());
+        while app.is_saving() {
+            thread::sleep(Duration::from_millis(5));
+        }
+        let played = recorder.0.lock().clone();
+        let (working, error) = (input::HapticParams::working(), input::HapticParams::error());
+        assert_eq!(
+            played,
+            vec![(input::Hand::Left, working.amplitude), (input::Hand::Left, error.amplitude)]
+        );
+    }
+
     #[test]
     fn test_buffer_starts_empty() {
-        let app = QuestShadowplay::new().unwrap();
+        let app = QuestShadowplay::with_config(Config::default()).unwrap();
         assert_eq!(app.buffer_frame_count(), 0);
         assert_eq!(app.buffer_fill(), 0.0);
     }
 
     #[test]
     fn test_stats_default() {
-        let app = QuestShadowplay::new().unwrap();
+        let app = QuestShadowplay::with_config(Config::default()).unwrap();
         let stats = app.stats();
         assert_eq!(stats.frames_received, 0);
         assert_eq!(stats.clips_saved, 0);
     }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn test_set_thread_nice() {
+        // Lowering our own priority never needs privileges
+        let applied = thread::spawn(|| set_current_thread_nice(5)).join().unwrap();
+        assert!(applied);
+    }
+
+    #[test]
+    fn test_rapid_saves_coalesce() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config {
+            output_directory: dir.path().to_string_lossy().to_string(),
+            save_coalesce_ms: 300,
+            ..Config::default()
+        };
+        let app = QuestShadowplay::with_config(config).unwrap();
+        let frame =
+            |timestamp_ns| CapturedFrame::with_timestamp(vec![1, 2, 3], 0, 16, 16, timestamp_ns);
+        app.buffer.push_frame(frame(0));
+
+        // A frame captured between the presses ends up in the one clip
+        assert!(app.trigger_save());
+        thread::sleep(Duration::from_millis(100));
+        app.buffer.push_frame(frame(33_000_000));
+        assert!(!app.trigger_save());
+        assert!(!app.trigger_save());
+        app.shutdown();
+
+        assert_eq!(app.stats().saves_coalesced, 2);
+        assert_eq!(app.stats().clips_saved, 1);
+        let manager = storage::StorageManager::new(&dir.path().to_string_lossy()).unwrap();
+        let clips = manager.list_clips().unwrap();
+        assert_eq!(clips.len(), 1);
+        let reader = encoder::FrameReader::open(&clips[0].path.to_string_lossy()).unwrap();
+        let timestamps: Vec<_> = reader.frames().iter().map(|f| f.timestamp_ns).collect();
+        assert_eq!(timestamps, vec![0, 33_000_000]);
+    }
+
+    #[test]
+    fn test_coalesce_disabled() {
+        let config = Config {
+            save_coalesce_ms: 0,
+            ..Config::default()
+        };
+        let app = QuestShadowplay::with_config(config).unwrap();
+
+        assert!(app.trigger_save());
+        app.shutdown();
+        assert!(app.trigger_save());
+        app.shutdown();
+
+        assert_eq!(app.stats().saves_coalesced, 0);
+    }
+
+    #[test]
+    fn test_save_from_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config {
+            output_directory: dir.path().to_string_lossy().to_string(),
+            profiles: vec![RecordingProfile::new("context", 30.0, 40)],
+            warmup_discard_frames: 0,
+            ..Config::default()
+        };
+        let app = QuestShadowplay::with_config(config).unwrap();
+        assert_eq!(app.profile_names(), vec!["context".to_string()]);
+
+        let capturer = app.create_capturer();
+        let pixels = vec![128u8; 16 * 16 * 4];
+        capturer.capture_frame(&pixels, 16, 16, 0).unwrap();
+        assert_eq!(app.profile_buffer("context").unwrap().frame_count(), 1);
+
+        assert!(!app.trigger_save_profile("missing"));
+        assert!(app.trigger_save_profile("context"));
+        app.shutdown();
+
+        let saved: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .collect();
+        assert!(saved.iter().any(|name| name.ends_with("_context.qsp")));
+    }
+
+    #[test]
+    fn test_profile_frames_use_profile_quality() {
+        let config = Config {
+            jpeg_quality: 90,
+            profiles: vec![
+                RecordingProfile::new("context", 30.0, 10),
+                RecordingProfile::new("archive", 60.0, 10),
+                RecordingProfile::new("preview", 10.0, 50),
+                RecordingProfile::new("highlight", 10.0, 90),
+            ],
+            warmup_discard_frames: 0,
+            ..Config::default()
+        };
+        let app = QuestShadowplay::with_config(config).unwrap();
+
+        // Noisy pixels, so quality makes a visible difference in size
+        let pixels: Vec<u8> = (0..32 * 32 * 4).map(|i| (i * 7919 % 251) as u8).collect();
+        let jpeg = capture::FrameCompressor::new(90).compress(&pixels, 32, 32).unwrap();
+        app.on_frame_captured(CapturedFrame::with_timestamp(jpeg.clone(), 0, 32, 32, 1));
+
+        let main = app.buffer.snapshot();
+        let profile = app.profile_buffer("context").unwrap().snapshot();
+        assert_eq!(main[0].data, jpeg);
+        assert_eq!(profile[0].timestamp_ns, 1);
+        assert!(profile[0].data.len() < jpeg.len());
+        let archive = app.profile_buffer("archive").unwrap().snapshot();
+        assert_eq!(archive[0].data, profile[0].data);
+        assert_eq!(app.profile_buffer("highlight").unwrap().snapshot()[0].data, jpeg);
+
+        // One decode for the frame, one encode per quality it's needed at
+        let stats = app.stats();
+        assert_eq!((stats.profile_decodes, stats.profile_encodes), (1, 2));
+    }
+
+    #[test]
+    fn test_warmup_discard() {
+        let config = Config {
+            warmup_discard_frames: 2,
+            ..Config::default()
+        };
+        let app = QuestShadowplay::with_config(config).unwrap();
+        let frame = |i: u64| CapturedFrame::with_timestamp(vec![0u8; 10], 0, 1, 1, i * 100);
+
+        for i in 0..3 {
+            app.on_frame_captured(frame(i));
+        }
+        assert_eq!(app.buffer_frame_count(), 1);
+        assert_eq!(app.stats().frames_warmup_discarded, 2);
+
+        // A new session warms up again
+        app.start_session();
+        app.on_frame_captured(frame(3));
+        assert_eq!(app.buffer_frame_count(), 1);
+        assert_eq!(app.stats().frames_warmup_discarded, 3);
+    }
+
+    #[test]
+    fn test_save_recreates_deleted_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("clips");
+        let config = Config {
+            output_directory: output.to_string_lossy().to_string(),
+            warmup_discard_frames: 0,
+            ..Config::default()
+        };
+        let app = QuestShadowplay::with_config(config).unwrap();
+        let capturer = app.create_capturer();
+        capturer.capture_frame(&vec![128u8; 16 * 16 * 4], 16, 16, 0).unwrap();
+
+        std::fs::remove_dir_all(&output).ok();
+        assert!(app.trigger_save());
+        app.shutdown();
+
+        assert_eq!(app.stats().clips_saved, 1);
+        assert!(output.is_dir());
+    }
+
+    #[test]
+    fn test_hot_save_drops_no_frames() {
+        const FRAMES: u64 = 3_000;
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config {
+            output_directory: dir.path().to_string_lossy().to_string(),
+            buffer_duration_seconds: 5.0,
+            target_fps: 90,
+            save_coalesce_ms: 0,
+            save_cooldown_ms: 0,
+            warmup_discard_frames: 0,
+            ..Config::default()
+        };
+        let app = Arc::new(QuestShadowplay::with_config(config).unwrap());
+        let data = capture::FrameCompressor::new(80)
+            .compress(&vec![90u8; 64 * 64 * 4], 64, 64)
+            .unwrap();
+
+        // Capture runs flat out while saves encode alongside it
+        let producer = {
+            let app = Arc::clone(&app);
+            thread::spawn(move || {
+                for n in 0..FRAMES {
+                    let frame = CapturedFrame::with_timestamp(data.clone(), 0, 64, 64, n);
+                    app.on_frame_captured(frame);
+                }
+            })
+        };
+        // Saving before the first frame arrives would count as an error
+        while app.buffer.frame_count() == 0 {
+            thread::yield_now();
+        }
+        let mut saves = 0;
+        loop {
+            if app.trigger_save() {
+                saves += 1;
+            }
+            if producer.is_finished() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        producer.join().unwrap();
+        app.shutdown();
+
+        // Every frame went in: the buffer holds the newest ones, unbroken
+        let frames = app.buffer.snapshot_shared();
+        assert_eq!(frames.len(), app.buffer.capacity());
+        assert_eq!(frames.last().unwrap().timestamp_ns, FRAMES - 1);
+        assert!(frames.windows(2).all(|w| w[1].timestamp_ns == w[0].timestamp_ns + 1));
+        assert_eq!(app.buffer.evicted_count() as usize, FRAMES as usize - frames.len());
+        assert_eq!(app.stats().frames_received, FRAMES);
+        assert!(saves > 0);
+        assert_eq!(app.stats().save_errors, 0);
+    }
+
+    #[test]
+    fn test_run_with_source_saves_on_trigger() {
+        use capture::{FrameCompressor, VecFrameSource};
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config {
+            output_directory: dir.path().to_string_lossy().to_string(),
+            warmup_discard_frames: 0,
+            ..Config::default()
+        };
+        let app = QuestShadowplay::with_config(config).unwrap();
+
+        let compressor = FrameCompressor::new(80);
+        let frames = (0..10u64)
+            .map(|i| {
+                let data = compressor
+                    .compress(&vec![i as u8 * 20; 16 * 16 * 4], 16, 16)
+                    .unwrap();
+                CapturedFrame::with_timestamp(data, 0, 16, 16, i * 11_111_111)
+            })
+            .collect();
+        let pressed = input::InputState {
+            left_trigger: 1.0,
+            left_grip: 1.0,
+            ..Default::default()
+        };
+        let mut source = VecFrameSource::new(frames).with_input_at(6, pressed);
+
+        // Stop right after the press so the save sees frames 0-6
+        assert_eq!(app.run_with_source(&mut source, 7), 7);
+        app.shutdown();
+        assert_eq!(app.run_with_source(&mut source, 100), 3);
+        assert_eq!(app.buffer_frame_count(), 10);
+
+        // One save despite the combo staying held
+        assert_eq!(app.stats().clips_saved, 1);
+        let clip = std::fs::read_dir(dir.path())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .find(|p| p.extension().is_some_and(|ext| ext == "qsp"))
+            .unwrap();
+        let reader = encoder::FrameReader::open(&clip.to_string_lossy()).unwrap();
+        assert_eq!(reader.frame_count(), 7);
+    }
+
+    #[test]
+    fn test_marks_saved_with_clip() {
+        use capture::VecFrameSource;
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config {
+            output_directory: dir.path().to_string_lossy().to_string(),
+            mark_button: config::MarkButton::RightB,
+            warmup_discard_frames: 0,
+            ..Config::default()
+        };
+        let app = QuestShadowplay::with_config(config).unwrap();
+
+        let frames = (1..=5u64)
+            .map(|i| CapturedFrame::with_timestamp(vec![0u8; 10], 0, 1, 1, i * 100))
+            .collect();
+        let marked = input::InputState {
+            right_b: true,
+            ..Default::default()
+        };
+        let mut source = VecFrameSource::new(frames)
+            .with_input_at(1, marked)
+            .with_input_at(2, input::InputState::new());
+        app.run_with_source(&mut source, 5);
+
+        // Button mark on frame 1, API mark later, one outside any clip
+        app.add_mark(450, "Clutch");
+        app.add_mark(50, "Too old");
+        let labels: Vec<_> = app.marks().into_iter().map(|m| m.label).collect();
+        assert_eq!(labels, vec!["Mark 1", "Clutch"]);
+        assert_eq!(app.stats().marks_added, 2);
+
+        app.set_recorded_app(Some("Beat Saber".to_string()));
+        assert!(app.trigger_save());
+        app.shutdown();
+
+        let clip = std::fs::read_dir(dir.path())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .find(|p| p.extension().is_some_and(|ext| ext == "qsp"))
+            .unwrap();
+        let reader = encoder::FrameReader::open(&clip.to_string_lossy()).unwrap();
+        let saved: Vec<_> = reader.marks().iter().map(|m| m.timestamp_ns).collect();
+        assert_eq!(saved, vec![200, 450]);
+        let metadata = storage::ClipMetadata::load(&clip).unwrap().unwrap();
+        assert_eq!(metadata.app.as_deref(), Some("Beat Saber"));
+    }
+
+    #[test]
+    fn test_auto_stop_after_clips() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config {
+            output_directory: dir.path().to_string_lossy().to_string(),
+            save_coalesce_ms: 0,
+            auto_stop_after_clips: 2,
+            warmup_discard_frames: 0,
+            ..Config::default()
+        };
+        let app = QuestShadowplay::with_config(config).unwrap();
+        let frame = |i: u64| CapturedFrame::with_timestamp(vec![0u8; 10], 0, 1, 1, i * 100);
+
+        for i in 0..2 {
+            app.on_frame_captured(frame(i));
+            assert!(app.trigger_save());
+            app.shutdown();
+        }
+        assert_eq!(app.stats().session_clips_saved, 2);
+        assert!(app.is_auto_stopped());
+
+        // Frames are dropped until a new session starts
+        app.on_frame_captured(frame(2));
+        assert_eq!(app.buffer_frame_count(), 2);
+        app.start_session();
+        assert!(!app.is_auto_stopped());
+        assert_eq!(app.stats().session_clips_saved, 0);
+        app.on_frame_captured(frame(3));
+        assert_eq!(app.buffer_frame_count(), 3);
+    }
+
+    #[test]
+    fn test_post_save_behavior() {
+        use config::PostSaveBehavior;
+
+        let dir = tempfile::tempdir().unwrap();
+        let frame = |i: u64| CapturedFrame::with_timestamp(vec![0u8; 10], 0, 1, 1, i * 100);
+        let app_with = |post_save| {
+            let config = Config {
+                output_directory: dir.path().to_string_lossy().to_string(),
+                save_coalesce_ms: 0,
+                post_save,
+                warmup_discard_frames: 0,
+                ..Config::default()
+            };
+            let app = QuestShadowplay::with_config(config).unwrap();
+            for i in 0..3 {
+                app.on_frame_captured(frame(i));
+            }
+            assert!(app.trigger_save());
+            app.shutdown();
+            app
+        };
+
+        let app = app_with(PostSaveBehavior::KeepRecording);
+        assert_eq!(app.buffer_frame_count(), 3);
+        assert!(!app.is_paused_after_save());
+
+        let app = app_with(PostSaveBehavior::ClearBuffer);
+        assert_eq!(app.buffer_frame_count(), 0);
+        app.on_frame_captured(frame(3));
+        assert_eq!(app.buffer_frame_count(), 1);
+
+        // Frames are dropped until the save is acknowledged
+        let app = app_with(PostSaveBehavior::PauseUntilAck);
+        assert!(app.is_paused_after_save());
+        app.on_frame_captured(frame(3));
+        assert_eq!(app.buffer_frame_count(), 3);
+        assert!(app.acknowledge_save());
+        assert!(!app.acknowledge_save());
+        app.on_frame_captured(frame(4));
+        assert_eq!(app.buffer_frame_count(), 4);
+    }
+
+    #[test]
+    fn test_pause_and_clear_actions() {
+        use capture::VecFrameSource;
+        use config::{ActionBinding, InputAction, MarkButton};
+
+        let config = Config {
+            action_bindings: vec![
+                (InputAction::Pause, ActionBinding::Button(MarkButton::RightA)),
+                (InputAction::ClearBuffer, ActionBinding::Button(MarkButton::RightB)),
+            ],
+            warmup_discard_frames: 0,
+            ..Config::default()
+        };
+        let app = QuestShadowplay::with_config(config).unwrap();
+
+        let press = |right_a, right_b| input::InputState {
+            right_a,
+            right_b,
+            ..Default::default()
+        };
+        let frames = (0..8u64)
+            .map(|i| CapturedFrame::with_timestamp(vec![0u8; 10], 0, 1, 1, i * 100))
+            .collect();
+        // Pause after frame 2, resume on frame 5 (frames 3-5 dropped)
+        let mut source = VecFrameSource::new(frames)
+            .with_input_at(0, press(false, false))
+            .with_input_at(2, press(true, false))
+            .with_input_at(3, press(false, false))
+            .with_input_at(5, press(true, false))
+            .with_input_at(6, press(false, false));
+        app.run_with_source(&mut source, 8);
+        assert!(!app.is_paused());
+        assert_eq!(app.buffer_frame_count(), 5);
+
+        app.update_input(press(false, true));
+        app.on_frame_captured(CapturedFrame::with_timestamp(vec![0u8; 10], 0, 1, 1, 900));
+        assert_eq!(app.buffer_frame_count(), 0);
+
+        // An action may not reuse the save combo
+        let config = Config {
+            action_bindings: vec![(
+                InputAction::Pause,
+                ActionBinding::Combo(config::TriggerButton::default()),
+            )],
+            ..Config::default()
+        };
+        assert_eq!(config.validate().len(), 1);
+        assert!(QuestShadowplay::with_config(config).is_err());
+    }
 }
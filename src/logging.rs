@@ -0,0 +1,196 @@
+//! # Log File Sink
+//!
+//! Optionally copies log output into a size-limited file.
+//!
+//! ## Plain English
+//!
+//! On a headset there's no terminal to read logs from, and asking users
+//! to run `adb logcat` is a lot. With `log_to_file` on, every log line is
+//! also written to `shadowplay.log` in the clips folder, so it can be
+//! attached to a bug report. When the file gets too big it is renamed to
+//! `shadowplay.log.1` (pushing older ones down) and a fresh one started.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use log::{Log, Metadata, Record};
+use parking_lot::Mutex;
+
+use crate::error::ShadowplayResult;
+
+/// Name of the active log file
+pub const LOG_FILE_NAME: &str = "shadowplay.log";
+
+/// Size at which the log file is rotated
+pub const LOG_FILE_MAX_BYTES: u64 = 1024 * 1024;
+
+/// How many rotated files to keep (`.1` is the newest)
+pub const LOG_FILE_KEEP: usize = 3;
+
+// ============================================
+// ROTATING FILE
+// ============================================
+
+/// A log file that rotates once it reaches a size limit.
+pub struct RotatingFile {
+    /// Path of the active file
+    path: PathBuf,
+
+    /// Open handle to the active file
+    file: File,
+
+    /// Bytes in the active file
+    size: u64,
+
+    /// Size at which to rotate
+    max_bytes: u64,
+
+    /// Rotated files to keep
+    keep: usize,
+}
+
+impl RotatingFile {
+    /// Opens (or creates) a log file, appending to existing content.
+    pub fn open(path: &Path, max_bytes: u64, keep: usize) -> ShadowplayResult<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            size,
+            max_bytes,
+            keep,
+        })
+    }
+
+    /// Appends a line, rotating first if it would exceed the limit.
+    pub fn write_line(&mut self, line: &str) -> ShadowplayResult<()> {
+        let len = line.len() as u64 + 1;
+        if self.size > 0 && self.size + len > self.max_bytes {
+            self.rotate()?;
+        }
+
+        writeln!(self.file, "{}", line)?;
+        self.size += len;
+        Ok(())
+    }
+
+    /// Shifts `log` → `log.1` → `log.2` ..., dropping the oldest.
+    fn rotate(&mut self) -> ShadowplayResult<()> {
+        self.file.flush()?;
+
+        if self.keep == 0 {
+            self.file = File::create(&self.path)?;
+        } else {
+            let _ = fs::remove_file(self.rotated_path(self.keep));
+            for i in (1..self.keep).rev() {
+                let from = self.rotated_path(i);
+                if from.exists() {
+                    fs::rename(&from, self.rotated_path(i + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+            self.file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+        }
+
+        self.size = 0;
+        Ok(())
+    }
+
+    /// Returns the path of the `index`th rotated file.
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+}
+
+// ============================================
+// TEE LOGGER
+// ============================================
+
+/// Sends records to the platform logger and to a log file.
+pub struct FileTeeLogger {
+    /// The usual logger (env_logger / android_logger)
+    inner: Box<dyn Log>,
+
+    /// The file copy
+    file: Mutex<RotatingFile>,
+}
+
+impl FileTeeLogger {
+    /// Wraps a logger so its output is also written to `file`.
+    pub fn new(inner: Box<dyn Log>, file: RotatingFile) -> Self {
+        Self {
+            inner,
+            file: Mutex::new(file),
+        }
+    }
+}
+
+impl Log for FileTeeLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        self.inner.log(record);
+
+        let line = format!(
+            "{} {:<5} {}: {}",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        // Nowhere to report a failure to log
+        let _ = self.file.lock().write_line(&line);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+        let _ = self.file.lock().file.flush();
+    }
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_rotation_bounds_size() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(LOG_FILE_NAME);
+        let mut file = RotatingFile::open(&path, 100, 2).unwrap();
+
+        for i in 0..20 {
+            file.write_line(&format!("line {:02} of the log", i)).unwrap();
+        }
+        file.file.flush().unwrap();
+
+        assert!(fs::metadata(&path).unwrap().len() <= 100);
+        assert!(dir.path().join("shadowplay.log.1").exists());
+        assert!(dir.path().join("shadowplay.log.2").exists());
+        assert!(!dir.path().join("shadowplay.log.3").exists());
+
+        // Newest line is in the active file
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains("line 19"));
+    }
+}
@@ -0,0 +1,120 @@
+//! # Android Storage Paths
+//!
+//! Asks the Android framework where we're allowed to write, via JNI.
+//!
+//! ## Plain English
+//!
+//! Not every device mounts shared storage at `/sdcard`, and newer
+//! Android versions restrict which folders an app may write to.
+//! Instead of guessing, we ask the system.
+
+use std::ffi::c_void;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use jni::objects::{JObject, JString, JValue};
+use jni::{JNIEnv, JavaVM};
+
+/// Returns `Environment.getExternalStorageDirectory()` (usually `/sdcard`).
+pub fn external_storage_directory() -> Option<PathBuf> {
+    with_env(|env, _context| {
+        let file = env
+            .call_static_method(
+                "android/os/Environment",
+                "getExternalStorageDirectory",
+                "()Ljava/io/File;",
+                &[],
+            )?
+            .l()?;
+        file_path(env, &file)
+    })
+}
+
+/// Returns `Context.getExternalFilesDir(null)`.
+///
+/// This app-specific folder is always writable without permissions,
+/// even under scoped storage.
+pub fn external_files_directory() -> Option<PathBuf> {
+    with_env(|env, context| {
+        let file = env
+            .call_method(
+                context,
+                "getExternalFilesDir",
+                "(Ljava/lang/String;)Ljava/io/File;",
+                &[JValue::Object(&JObject::null())],
+            )?
+            .l()?;
+        file_path(env, &file)
+    })
+}
+
+/// Converts a `java.io.File` into a path.
+fn file_path(env: &mut JNIEnv, file: &JObject) -> jni::errors::Result<Option<PathBuf>> {
+    if file.is_null() {
+        return Ok(None);
+    }
+
+    let path = JString::from(
+        env.call_method(file, "getAbsolutePath", "()Ljava/lang/String;", &[])?
+            .l()?,
+    );
+    let path: String = env.get_string(&path)?.into();
+    Ok(Some(PathBuf::from(path)))
+}
+
+/// Set once `ndk_context` holds the app's JVM and Context.
+///
+/// `ndk_context` only offers a getter that panics when unset (and
+/// release builds abort on panic), so it is never called before this.
+static CONTEXT: OnceLock<()> = OnceLock::new();
+
+/// Hands the app's JVM and Context to the library, e.g. from
+/// `JNI_OnLoad`. Later calls are ignored.
+///
+/// # Safety
+///
+/// `vm` must be a valid `JavaVM*` and `context` a global reference to an
+/// `android.content.Context`, both alive for as long as the app.
+pub unsafe fn register_android_context(vm: *mut c_void, context: *mut c_void) {
+    CONTEXT.get_or_init(|| ndk_context::initialize_android_context(vm, context));
+}
+
+/// Records that the app framework (e.g. `android-activity`) has already
+/// initialized `ndk_context` itself.
+pub fn mark_android_context_registered() {
+    CONTEXT.get_or_init(|| ());
+}
+
+/// Returns true once the app has registered its JVM and Context.
+///
+/// Until then (unit tests, tools linking the library) there is nobody
+/// to ask, and lookups return `None`.
+pub fn has_context() -> bool {
+    CONTEXT.get().is_some()
+}
+
+/// Returns the app's JVM and Context, if they have been registered.
+fn android_context() -> Option<ndk_context::AndroidContext> {
+    has_context().then(ndk_context::android_context)
+}
+
+/// Runs a JNI lookup against the app's Context.
+fn with_env<F>(lookup: F) -> Option<PathBuf>
+where
+    F: FnOnce(&mut JNIEnv, &JObject) -> jni::errors::Result<Option<PathBuf>>,
+{
+    let ctx = android_context()?;
+    let vm = unsafe { JavaVM::from_raw(ctx.vm().cast()) }.ok()?;
+    let mut env = vm.attach_current_thread().ok()?;
+    let context = unsafe { JObject::from_raw(ctx.context().cast()) };
+
+    match lookup(&mut env, &context) {
+        Ok(path) => path,
+        Err(e) => {
+            // A thrown Java exception must be cleared before further JNI calls
+            let _ = env.exception_clear();
+            log::warn!("Storage path lookup failed: {}", e);
+            None
+        }
+    }
+}
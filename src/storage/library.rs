@@ -0,0 +1,305 @@
+//! # Library Repair
+//!
+//! Checks every saved clip and fixes what can be fixed in one pass.
+//!
+//! ## Plain English
+//!
+//! After months of use, a clips folder collects small problems: clips
+//! saved by an older version with the wrong playback speed, missing
+//! thumbnails or info files, and leftovers (thumbnails, proxies, info
+//! files) of clips that were deleted. This walks the whole folder and
+//! tidies it up. Running it twice does nothing the second time, and a
+//! dry run reports what would change without touching anything.
+//! Clips that can't be read are reported, never deleted.
+
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+
+use super::thumbnail::{generate_thumbnail, is_fresh, thumbnail_path};
+use super::{clip_folders, ClipMetadata, StorageManager, SETTINGS_FILE_NAME};
+use crate::config::ScaleFilter;
+use crate::encoder::{FrameReader, VideoEncoder, MANIFEST_FILE_NAME};
+use crate::error::ShadowplayResult;
+
+/// Endings of files that only belong next to a clip
+const CLIP_COMPANIONS: [&str; 4] = [".thumb.jpg", ".proxy.qsp", ".json", ".frames"];
+
+/// What a library repair found and fixed (or would fix, in a dry run).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LibraryReport {
+    /// Nothing was changed; counts are what a real run would do
+    pub dry_run: bool,
+
+    /// Clips looked at
+    pub clips_checked: usize,
+
+    /// Clips that can't be read; left in place
+    pub damaged: Vec<PathBuf>,
+
+    /// Leftover files and folders of clips that no longer exist
+    pub orphans_removed: Vec<PathBuf>,
+
+    /// Clips given a missing metadata sidecar
+    pub sidecars_written: usize,
+
+    /// Clips given a missing or out-of-date thumbnail
+    pub thumbnails_written: usize,
+
+    /// Clips whose declared frame rate was corrected
+    pub fps_repaired: usize,
+}
+
+impl LibraryReport {
+    /// Returns the number of fixes made (or due, in a dry run).
+    pub fn changes(&self) -> usize {
+        self.orphans_removed.len()
+            + self.sidecars_written
+            + self.thumbnails_written
+            + self.fps_repaired
+    }
+
+    /// Returns a one-line summary for display.
+    pub fn summary(&self) -> String {
+        format!(
+            "{}{} clips checked, {} damaged: {} leftovers removed, {} sidecars and {} \
+             thumbnails written, {} frame rates fixed",
+            if self.dry_run { "Dry run: " } else { "" },
+            self.clips_checked,
+            self.damaged.len(),
+            self.orphans_removed.len(),
+            self.sidecars_written,
+            self.thumbnails_written,
+            self.fps_repaired
+        )
+    }
+}
+
+impl StorageManager {
+    /// Checks every clip and repairs the library.
+    ///
+    /// Removes leftovers of deleted clips, writes missing sidecars and
+    /// thumbnails, and fixes declared frame rates that don't match the
+    /// timestamps. Clips that can't be opened are only reported. With
+    /// `dry_run`, nothing is changed. Safe to run repeatedly.
+    pub fn repair_library(&self, dry_run: bool) -> ShadowplayResult<LibraryReport> {
+        let mut report = LibraryReport {
+            dry_run,
+            ..LibraryReport::default()
+        };
+
+        for path in self.orphaned_files()? {
+            if !dry_run {
+                let removed = if path.is_dir() {
+                    fs::remove_dir_all(&path)
+                } else {
+                    fs::remove_file(&path)
+                };
+                if let Err(e) = removed {
+                    log::warn!("Failed to remove leftover {:?}: {}", path, e);
+                    continue;
+                }
+            }
+            report.orphans_removed.push(path);
+        }
+
+        for clip in self.list_clips()? {
+            report.clips_checked += 1;
+            let repaired =
+                repair_clip(&clip.path, clip.modified, self.scale_filter, dry_run, &mut report);
+            if let Err(e) = repaired {
+                log::warn!("Clip {:?} is damaged: {}", clip.path, e);
+                report.damaged.push(clip.path);
+            }
+        }
+
+        log::info!("{}", report.summary());
+        Ok(report)
+    }
+
+    /// Returns companion files and folders whose clip is gone.
+    ///
+    /// Only ones this app wrote count (see [`is_own_companion`]); a
+    /// user's file that merely shares an ending is left alone.
+    fn orphaned_files(&self) -> ShadowplayResult<Vec<PathBuf>> {
+        let mut orphans = Vec::new();
+        for folder in clip_folders(&self.output_directory)? {
+            for entry in fs::read_dir(&folder)? {
+                let path = entry?.path();
+                let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                if name == SETTINGS_FILE_NAME {
+                    continue;
+                }
+                let Some((stem, ending)) = CLIP_COMPANIONS
+                    .iter()
+                    .find_map(|end| name.strip_suffix(end).map(|stem| (stem, *end)))
+                else {
+                    continue;
+                };
+                let clip = folder.join(format!("{}.qsp", stem));
+                if !clip.exists() && is_own_companion(&path, ending) {
+                    orphans.push(path);
+                }
+            }
+        }
+        orphans.sort();
+        Ok(orphans)
+    }
+}
+
+/// Returns true if `path`, ending in `ending`, has the contents this
+/// app writes there: a JPEG thumbnail, a readable proxy clip, a frame
+/// folder with its manifest, or a metadata sidecar.
+fn is_own_companion(path: &Path, ending: &str) -> bool {
+    match ending {
+        ".thumb.jpg" => {
+            let mut magic = [0u8; 3];
+            let read = fs::File::open(path).and_then(|mut file| file.read_exact(&mut magic));
+            read.is_ok() && magic == [0xFF, 0xD8, 0xFF]
+        }
+        ".proxy.qsp" => FrameReader::check_version(&path.to_string_lossy()).is_ok(),
+        ".frames" => path.join(MANIFEST_FILE_NAME).is_file(),
+        ".json" => is_metadata_sidecar(path),
+        _ => false,
+    }
+}
+
+/// Returns true if `path` holds a [`ClipMetadata`] sidecar: a JSON
+/// object with only metadata fields, at least one of them.
+fn is_metadata_sidecar(path: &Path) -> bool {
+    let Ok(serde_json::Value::Object(known)) = serde_json::to_value(ClipMetadata::default())
+    else {
+        return false;
+    };
+    let value = fs::read_to_string(path)
+        .ok()
+        .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok());
+    let Some(serde_json::Value::Object(fields)) = value else {
+        return false;
+    };
+    !fields.is_empty()
+        && fields.keys().all(|key| known.contains_key(key))
+        && serde_json::from_value::<ClipMetadata>(serde_json::Value::Object(fields)).is_ok()
+}
+
+/// Checks one clip, fixing what's needed unless `dry_run`.
+///
+/// Fails only if the clip itself can't be read.
+fn repair_clip(
+    clip_path: &Path,
+    modified: Option<std::time::SystemTime>,
+    filter: ScaleFilter,
+    dry_run: bool,
+    report: &mut LibraryReport,
+) -> ShadowplayResult<()> {
+    let reader = FrameReader::open(&clip_path.to_string_lossy())?;
+
+    let detected = VideoEncoder::detect_fps(reader.frames());
+    if detected.is_some_and(|fps| fps != reader.fps()) {
+        if !dry_run {
+            VideoEncoder::repair_fps(&clip_path.to_string_lossy())?;
+        }
+        report.fps_repaired += 1;
+    }
+
+    if !ClipMetadata::sidecar_path(clip_path).exists() {
+        if !dry_run {
+            // The file's time is the best guess at when it was saved
+            let metadata = ClipMetadata {
+                saved_at: modified.map(DateTime::<Local>::from),
+                ..ClipMetadata::default()
+            };
+            if let Err(e) = metadata.save(clip_path) {
+                log::warn!("Failed to write sidecar for {:?}: {}", clip_path, e);
+            }
+        }
+        report.sidecars_written += 1;
+    }
+
+    // Checked after the fps fix, which makes an older cache stale
+    if !is_fresh(&thumbnail_path(clip_path), clip_path) {
+        if !dry_run {
+            let jpeg = generate_thumbnail(clip_path, filter)?;
+            fs::write(thumbnail_path(clip_path), jpeg)?;
+        }
+        report.thumbnails_written += 1;
+    }
+    Ok(())
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::{CapturedFrame, FrameCompressor};
+    use crate::config::Config;
+    use crate::storage::frames_directory;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_repair_library() {
+        let dir = tempdir().unwrap();
+        let manager = StorageManager::new(dir.path().to_str().unwrap()).unwrap();
+
+        // A 30 fps clip saved with a 90 fps header
+        let clip = dir.path().join("clip_1.qsp");
+        let data = FrameCompressor::new(80).compress(&[128; 8 * 8 * 4], 8, 8).unwrap();
+        let frames: Vec<_> = (0..10u64)
+            .map(|i| CapturedFrame::with_timestamp(data.clone(), 0, 8, 8, i * 1_000_000_000 / 30))
+            .collect();
+        let path = clip.to_string_lossy();
+        VideoEncoder::encode_frames_with_marks(&frames, &[], &path, &Config::default()).unwrap();
+        let mut bytes = fs::read(&clip).unwrap();
+        bytes[20..24].copy_from_slice(&90u32.to_le_bytes());
+        fs::write(&clip, bytes).unwrap();
+        fs::write(dir.path().join("clip_2.qsp"), b"not a clip").unwrap();
+        let gone = dir.path().join("clip_gone.qsp");
+        fs::write(thumbnail_path(&gone), &data).unwrap();
+        ClipMetadata::default().save(&gone).unwrap();
+        fs::create_dir(frames_directory(&gone)).unwrap();
+        fs::write(frames_directory(&gone).join(MANIFEST_FILE_NAME), b"[]").unwrap();
+        fs::write(dir.path().join(SETTINGS_FILE_NAME), b"{}").unwrap();
+
+        // Files of the user's that only share an ending are kept
+        let keep = [
+            dir.path().join("notes.json"),
+            dir.path().join("empty.json"),
+            dir.path().join("photo.thumb.jpg"),
+            dir.path().join("other.proxy.qsp"),
+            dir.path().join("album.frames"),
+        ];
+        fs::write(&keep[0], br#"{"note": "mine", "author": "me"}"#).unwrap();
+        fs::write(&keep[1], b"{}").unwrap();
+        fs::write(&keep[2], b"GIF89a").unwrap();
+        fs::write(&keep[3], b"not a clip").unwrap();
+        fs::create_dir(&keep[4]).unwrap();
+
+        let planned = manager.repair_library(true).unwrap();
+        assert_eq!(planned.clips_checked, 2);
+        assert_eq!(planned.damaged, vec![dir.path().join("clip_2.qsp")]);
+        assert_eq!(planned.orphans_removed.len(), 3);
+        assert_eq!((planned.sidecars_written, planned.thumbnails_written), (1, 1));
+        assert_eq!(planned.fps_repaired, 1);
+        assert!(dir.path().join("clip_gone.json").exists());
+        assert_eq!(FrameReader::open(&path).unwrap().fps(), 90);
+
+        let report = manager.repair_library(false).unwrap();
+        assert_eq!(report.changes(), planned.changes());
+        assert!(!dir.path().join("clip_gone.json").exists());
+        assert!(dir.path().join(SETTINGS_FILE_NAME).exists());
+        assert!(keep.iter().all(|path| path.exists()));
+        assert!(ClipMetadata::load(&clip).unwrap().unwrap().saved_at.is_some());
+        assert!(thumbnail_path(&clip).exists());
+        assert_eq!(FrameReader::open(&path).unwrap().fps(), 30);
+
+        // Nothing left to do, and the damaged clip is still there
+        let again = manager.repair_library(false).unwrap();
+        assert_eq!(again.changes(), 0);
+        assert_eq!(again.damaged.len(), 1);
+    }
+}
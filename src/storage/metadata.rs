@@ -0,0 +1,203 @@
+//! # Clip Metadata
+//!
+//! Extra information about a clip, stored in a JSON "sidecar" file
+//! next to it (`clip_20240101_120000.qsp` → `clip_20240101_120000.json`).
+//!
+//! ## Plain English
+//!
+//! Like a sticky note attached to each video: when you pressed save,
+//! when the file was written, what was recorded, the picture size and
+//! real frame rate, and a note you can add later. None of it is baked
+//! into the video itself.
+
+use std::borrow::Borrow;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::capture::CapturedFrame;
+use crate::encoder::VideoEncoder;
+use crate::error::{ShadowplayError, ShadowplayResult};
+
+/// Metadata stored alongside a saved clip.
+///
+/// Unknown or missing fields are tolerated so sidecars written by other
+/// versions still load.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClipMetadata {
+    /// When the user pressed save
+    pub triggered_at: Option<DateTime<Local>>,
+
+    /// When the clip finished writing
+    pub saved_at: Option<DateTime<Local>>,
+
+    /// Favorited clips are never removed by automatic cleanup
+    pub favorite: bool,
+
+    /// App or game that was being recorded, if the platform reported it
+    pub app: Option<String>,
+
+    /// Width and height of one eye's frames
+    pub resolution: Option<(u32, u32)>,
+
+    /// Frame rate the clip was really captured at
+    pub fps: Option<u32>,
+
+    /// Length of the clip in seconds
+    pub duration_secs: Option<f64>,
+
+    /// Note added by the user
+    pub note: Option<String>,
+
+    /// Labels added by the user, for finding clips later
+    pub tags: Vec<String>,
+}
+
+impl ClipMetadata {
+    /// Describes a clip about to be saved from `frames`: resolution,
+    /// real frame rate and duration. Times, app and note are left empty.
+    pub fn for_frames<F: Borrow<CapturedFrame>>(frames: &[F], fallback_fps: u32) -> Self {
+        let Some(first) = frames.first().map(Borrow::borrow) else {
+            return Self::default();
+        };
+        let fps = VideoEncoder::clip_fps(frames, fallback_fps);
+
+        // The last frame shows for one interval too
+        let times = VideoEncoder::presentation_times_us(frames, fallback_fps);
+        let end_us = times.last().copied().unwrap_or(0) + 1_000_000 / fps.max(1) as u64;
+
+        Self {
+            resolution: Some((first.width, first.height)),
+            fps: Some(fps),
+            duration_secs: Some(end_us as f64 / 1e6),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the sidecar path for a clip.
+    pub fn sidecar_path(clip_path: &Path) -> PathBuf {
+        clip_path.with_extension("json")
+    }
+
+    /// Writes this metadata next to the clip.
+    pub fn save(&self, clip_path: &Path) -> ShadowplayResult<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| ShadowplayError::Storage(format!("Failed to encode metadata: {}", e)))?;
+        fs::write(Self::sidecar_path(clip_path), json)?;
+        Ok(())
+    }
+
+    /// Loads the metadata for a clip, if it has a sidecar.
+    pub fn load(clip_path: &Path) -> ShadowplayResult<Option<Self>> {
+        let path = Self::sidecar_path(clip_path);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let json = fs::read_to_string(&path)?;
+        let metadata = serde_json::from_str(&json)
+            .map_err(|e| ShadowplayError::Storage(format!("Invalid metadata {:?}: {}", path, e)))?;
+        Ok(Some(metadata))
+    }
+
+    /// Returns true if the clip is marked as a favorite.
+    ///
+    /// Missing or unreadable sidecars count as not favorited.
+    pub fn is_favorite(clip_path: &Path) -> bool {
+        matches!(Self::load(clip_path), Ok(Some(metadata)) if metadata.favorite)
+    }
+
+    /// Returns true if the clip has `tag`, ignoring case.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    /// Marks or unmarks a clip as a favorite, keeping other metadata.
+    pub fn set_favorite(clip_path: &Path, favorite: bool) -> ShadowplayResult<()> {
+        let mut metadata = Self::load(clip_path)?.unwrap_or_default();
+        metadata.favorite = favorite;
+        metadata.save(clip_path)
+    }
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_sidecar_path() {
+        let path = ClipMetadata::sidecar_path(Path::new("/clips/clip_1.qsp"));
+        assert_eq!(path, PathBuf::from("/clips/clip_1.json"));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = tempdir().unwrap();
+        let clip = dir.path().join("clip.qsp");
+
+        assert_eq!(ClipMetadata::load(&clip).unwrap(), None);
+
+        let metadata = ClipMetadata {
+            triggered_at: Some(Local::now()),
+            saved_at: Some(Local::now()),
+            ..Default::default()
+        };
+        metadata.save(&clip).unwrap();
+
+        assert_eq!(ClipMetadata::load(&clip).unwrap(), Some(metadata));
+    }
+
+    #[test]
+    fn test_for_frames() {
+        // Two eyes at 30 fps for one second
+        let frames: Vec<_> = (0..60u64)
+            .map(|i| {
+                let timestamp_ns = (i / 2) * 1_000_000_000 / 30;
+                CapturedFrame::with_timestamp(vec![0], (i % 2) as u32, 64, 32, timestamp_ns)
+            })
+            .collect();
+
+        let metadata = ClipMetadata::for_frames(&frames, 90);
+        assert_eq!(metadata.resolution, Some((64, 32)));
+        assert_eq!(metadata.fps, Some(30));
+        assert!((metadata.duration_secs.unwrap() - 1.0).abs() < 0.01);
+        assert_eq!(metadata.saved_at, None);
+        assert_eq!(ClipMetadata::for_frames::<CapturedFrame>(&[], 90), ClipMetadata::default());
+    }
+
+    #[test]
+    fn test_missing_fields_default() {
+        let dir = tempdir().unwrap();
+        let clip = dir.path().join("clip.qsp");
+        fs::write(ClipMetadata::sidecar_path(&clip), "{}").unwrap();
+
+        assert_eq!(ClipMetadata::load(&clip).unwrap(), Some(ClipMetadata::default()));
+    }
+
+    #[test]
+    fn test_set_favorite_keeps_times() {
+        let dir = tempdir().unwrap();
+        let clip = dir.path().join("clip.qsp");
+        assert!(!ClipMetadata::is_favorite(&clip));
+
+        let triggered_at = Some(Local::now());
+        ClipMetadata {
+            triggered_at,
+            ..Default::default()
+        }
+        .save(&clip)
+        .unwrap();
+
+        ClipMetadata::set_favorite(&clip, true).unwrap();
+        assert!(ClipMetadata::is_favorite(&clip));
+        assert_eq!(ClipMetadata::load(&clip).unwrap().unwrap().triggered_at, triggered_at);
+    }
+}
@@ -10,12 +10,33 @@
 //! 3. Writes files to storage
 //! 4. Manages storage space
 
+#[cfg(target_os = "android")]
+mod android;
+mod library;
+mod metadata;
+mod outputs;
+mod proxy;
+mod settings;
+mod thumbnail;
+
+pub use library::LibraryReport;
+pub use metadata::ClipMetadata;
+pub use outputs::{frames_directory, gif_path, write_outputs, SaveOutput};
+pub use proxy::{downscale_frame, existing_proxy, is_proxy, proxy_path, write_proxy};
+pub use settings::{SavedSettings, SETTINGS_FILE_NAME};
+pub use thumbnail::{cached_thumbnail, generate_thumbnail, thumbnail_path, THUMBNAIL_WIDTH};
+
+#[cfg(target_os = "android")]
+pub use android::{mark_android_context_registered, register_android_context};
+
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
 
-use chrono::Local;
+use chrono::{DateTime, Local, NaiveDate};
 
-use crate::error::{ShadowplayError, ShadowplayResult};
+use crate::config::ScaleFilter;
+use crate::error::{ShadowplayError, ShadowplayResult, StorageErrorKind};
 
 // ============================================
 // STORAGE MANAGER
@@ -25,6 +46,12 @@ use crate::error::{ShadowplayError, ShadowplayResult};
 pub struct StorageManager {
     /// Root directory for clips
     output_directory: PathBuf,
+
+    /// New clips go in a `YYYY-MM-DD` subfolder
+    organize_by_date: bool,
+
+    /// How repaired thumbnails are resampled
+    scale_filter: ScaleFilter,
 }
 
 impl StorageManager {
@@ -40,30 +67,110 @@ impl StorageManager {
 
         Ok(Self {
             output_directory: path,
+            organize_by_date: false,
+            scale_filter: ScaleFilter::default(),
         })
     }
 
-    /// Generates a unique filename for a new clip.
+    /// Sets whether new clips go in one subfolder per day.
+    pub fn with_date_folders(mut self, organize_by_date: bool) -> Self {
+        self.organize_by_date = organize_by_date;
+        self
+    }
+
+    /// Sets how thumbnails written by library repair are resampled.
+    pub fn with_scale_filter(mut self, scale_filter: ScaleFilter) -> Self {
+        self.scale_filter = scale_filter;
+        self
+    }
+
+    /// Generates a unique path for a new clip in this manager's folder,
+    /// creating today's subfolder if clips are organized by date.
     ///
-    /// Format: `clip_YYYYMMDD_HHMMSS.qsp`
-    pub fn generate_filename(output_directory: &str) -> String {
+    /// Same naming as [`StorageManager::generate_filename`].
+    pub fn next_clip_path(&self) -> ShadowplayResult<PathBuf> {
         let now = Local::now();
-        let filename = format!("clip_{}.qsp", now.format("%Y%m%d_%H%M%S"));
-        let path = PathBuf::from(output_directory).join(&filename);
+        let directory = Self::clip_directory_at(
+            &self.output_directory.to_string_lossy(),
+            now,
+            self.organize_by_date,
+        );
+        fs::create_dir_all(&directory)?;
+        Ok(PathBuf::from(Self::generate_filename_at(&directory, now)))
+    }
+
+    /// Returns the folder a clip saved at `time` belongs in: the output
+    /// directory itself, or its `YYYY-MM-DD` subfolder when
+    /// `organize_by_date` is set. Doesn't create it.
+    pub fn clip_directory_at(
+        output_directory: &str,
+        time: DateTime<Local>,
+        organize_by_date: bool,
+    ) -> String {
+        let mut path = PathBuf::from(output_directory);
+        if organize_by_date {
+            path.push(time.format(DATE_FOLDER_FORMAT).to_string());
+        }
         path.to_string_lossy().to_string()
     }
 
-    /// Returns all saved clips, newest first.
+    /// Generates a unique filename for a new clip.
+    ///
+    /// Format: `clip_YYYYMMDD_HHMMSS.qsp`, plus `_1`, `_2`, ... if a clip
+    /// saved in the same second already has that name.
+    pub fn generate_filename(output_directory: &str) -> String {
+        Self::generate_filename_at(output_directory, Local::now())
+    }
+
+    /// Generates a filename for a clip named after the given time.
+    ///
+    /// Pass the moment the user pressed save, so the name reflects when
+    /// it happened rather than when the background encode got to it.
+    pub fn generate_filename_at(output_directory: &str, time: DateTime<Local>) -> String {
+        let stem = format!("clip_{}", time.format("%Y%m%d_%H%M%S"));
+        unused_clip_path(Path::new(output_directory), &stem)
+    }
+
+    /// Generates a filename for a clip saved from a recording profile,
+    /// e.g. `clip_20240101_120000_highlight.qsp`.
+    pub fn generate_profile_filename_at(
+        output_directory: &str,
+        profile: &str,
+        time: DateTime<Local>,
+    ) -> String {
+        let stem = format!("clip_{}_{}", time.format("%Y%m%d_%H%M%S"), profile);
+        unused_clip_path(Path::new(output_directory), &stem)
+    }
+
+    /// Returns all saved clips, newest first, including those in date
+    /// subfolders.
+    ///
+    /// If the output directory was deleted while running, it is
+    /// recreated (and holds no clips).
     pub fn list_clips(&self) -> ShadowplayResult<Vec<ClipInfo>> {
         let mut clips = Vec::new();
+        recreate_if_missing(&self.output_directory)?;
 
-        for entry in fs::read_dir(&self.output_directory)? {
+        let entries = clip_folders(&self.output_directory)?
+            .into_iter()
+            .map(fs::read_dir)
+            .collect::<Result<Vec<_>, _>>()?;
+        for entry in entries.into_iter().flatten() {
             let entry = entry?;
             let path = entry.path();
 
-            if path.extension().map(|e| e == "qsp").unwrap_or(false) {
+            // Proxies are listed with their master, not as clips
+            if path.extension().map(|e| e == "qsp").unwrap_or(false) && !is_proxy(&path) {
                 if let Ok(metadata) = entry.metadata() {
+                    let sidecar = ClipMetadata::load(&path).ok().flatten();
+                    let proxy = existing_proxy(&path);
+                    let proxy_bytes = proxy
+                        .as_ref()
+                        .and_then(|p| fs::metadata(p).ok())
+                        .map_or(0, |m| m.len());
                     clips.push(ClipInfo {
+                        proxy,
+                        proxy_bytes,
                         path: path.clone(),
                         filename: path
                             .file_name()
@@ -72,6 +179,8 @@ impl StorageManager {
                             .to_string(),
                         size_bytes: metadata.len(),
                         modified: metadata.modified().ok(),
+                        favorite: sidecar.as_ref().is_some_and(|m| m.favorite),
+                        metadata: sidecar,
                     });
                 }
             }
@@ -82,37 +191,366 @@ impl StorageManager {
         Ok(clips)
     }
 
-    /// Returns total storage used in bytes.
+    /// Tags a clip, keeping its other metadata. Tags are trimmed and
+    /// compared ignoring case, so adding one twice does nothing.
+    pub fn add_tag(&self, clip: &Path, tag: &str) -> ShadowplayResult<()> {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            return Err(ShadowplayError::Storage("Tags can't be empty".to_string()));
+        }
+        let mut metadata = ClipMetadata::load(clip)?.unwrap_or_default();
+        if !metadata.has_tag(tag) {
+            metadata.tags.push(tag.to_string());
+            metadata.save(clip)?;
+        }
+        Ok(())
+    }
+
+    /// Removes a tag from a clip, ignoring case. Returns true if the clip
+    /// had it.
+    pub fn remove_tag(&self, clip: &Path, tag: &str) -> ShadowplayResult<bool> {
+        let Some(mut metadata) = ClipMetadata::load(clip)? else {
+            return Ok(false);
+        };
+        let before = metadata.tags.len();
+        metadata.tags.retain(|t| !t.eq_ignore_ascii_case(tag.trim()));
+        if metadata.tags.len() == before {
+            return Ok(false);
+        }
+        metadata.save(clip)?;
+        Ok(true)
+    }
+
+    /// Returns clips with `tag` (ignoring case), newest first.
+    pub fn find_clips_by_tag(&self, tag: &str) -> ShadowplayResult<Vec<ClipInfo>> {
+        let tag = tag.trim();
+        Ok(self
+            .list_clips()?
+            .into_iter()
+            .filter(|clip| clip.metadata.as_ref().is_some_and(|m| m.has_tag(tag)))
+            .collect())
+    }
+
+    /// Returns clips whose file name or a tag contains `text`, ignoring
+    /// case, newest first.
+    pub fn search_clips(&self, text: &str) -> ShadowplayResult<Vec<ClipInfo>> {
+        let text = text.trim().to_lowercase();
+        let matches = |value: &str| value.to_lowercase().contains(&text);
+        Ok(self
+            .list_clips()?
+            .into_iter()
+            .filter(|clip| {
+                matches(&clip.filename)
+                    || clip.metadata.as_ref().is_some_and(|m| m.tags.iter().any(|t| matches(t)))
+            })
+            .collect())
+    }
+
+    /// Returns total storage used in bytes, proxies included.
     pub fn total_storage_used(&self) -> ShadowplayResult<u64> {
-        Ok(self.list_clips()?.iter().map(|c| c.size_bytes).sum())
+        Ok(self.list_clips()?.iter().map(ClipInfo::total_bytes).sum())
     }
 
     /// Deletes a clip.
+    ///
+    /// The path must be inside the output directory; one that climbs
+    /// out with `..` is refused.
     pub fn delete_clip(&self, path: &Path) -> ShadowplayResult<()> {
-        if !path.starts_with(&self.output_directory) {
-            return Err(ShadowplayError::Storage("Path outside directory".to_string()));
+        let climbs = path.components().any(|c| c == Component::ParentDir);
+        if climbs || !path.starts_with(&self.output_directory) {
+            return Err(ShadowplayError::StorageKind(
+                StorageErrorKind::PermissionDenied,
+                format!("Permission denied: {:?} is outside the clips folder", path),
+            ));
         }
         fs::remove_file(path)?;
         log::info!("Deleted clip: {:?}", path);
+
+        // A proxy or frame folder is only useful with its master
+        if let Some(proxy) = existing_proxy(path) {
+            if let Err(e) = fs::remove_file(&proxy) {
+                log::warn!("Failed to delete proxy {:?}: {}", proxy, e);
+            }
+        }
+        for sidecar in [thumbnail_path(path), ClipMetadata::sidecar_path(path)] {
+            if sidecar.exists() {
+                if let Err(e) = fs::remove_file(&sidecar) {
+                    log::warn!("Failed to delete {:?}: {}", sidecar, e);
+                }
+            }
+        }
+        let frames = frames_directory(path);
+        if frames.is_dir() {
+            if let Err(e) = fs::remove_dir_all(&frames) {
+                log::warn!("Failed to delete frames {:?}: {}", frames, e);
+            }
+        }
+
+        // Drop a date folder once its last clip is gone; this fails,
+        // harmlessly, while anything else is left in it
+        if let Some(folder) = path.parent().filter(|p| *p != self.output_directory) {
+            if folder.file_name().is_some_and(|name| is_date_folder(&name.to_string_lossy())) {
+                let _ = fs::remove_dir(folder);
+            }
+        }
         Ok(())
     }
 
+    /// Finds the file for a clip name from outside, e.g. the UI.
+    ///
+    /// The name may start with a date folder (`2024-03-05/clip.qsp`);
+    /// absolute paths, any other path separators, or `..`, are refused
+    /// with [`StorageErrorKind::PermissionDenied`]. The resolved file
+    /// (following symlinks) must be inside the output directory.
+    pub fn resolve_clip_name(&self, name: &str) -> ShadowplayResult<PathBuf> {
+        let refused = || {
+            ShadowplayError::StorageKind(
+                StorageErrorKind::PermissionDenied,
+                format!("Permission denied: {:?} is not a clip name", name),
+            )
+        };
+        let components: Vec<_> = Path::new(name).components().collect();
+        let plain = match components.as_slice() {
+            [Component::Normal(_)] => true,
+            [Component::Normal(folder), Component::Normal(_)] => {
+                is_date_folder(&folder.to_string_lossy())
+            }
+            _ => false,
+        };
+        if !plain || name.contains('\\') {
+            return Err(refused());
+        }
+
+        let path = self.output_directory.join(name);
+        let resolved = path
+            .canonicalize()
+            .map_err(|_| ShadowplayError::Storage(format!("Clip not found: {}", name)))?;
+        if !resolved.starts_with(self.output_directory.canonicalize()?) {
+            log::warn!("Refusing {:?}: outside {:?}", resolved, self.output_directory);
+            return Err(refused());
+        }
+        Ok(path)
+    }
+
+    /// Deletes a clip given only its file name, e.g. from the UI.
+    ///
+    /// The name is checked as in [`StorageManager::resolve_clip_name`].
+    pub fn delete_clip_by_name(&self, name: &str) -> ShadowplayResult<()> {
+        self.delete_clip(&self.resolve_clip_name(name)?)
+    }
+
+    /// Deletes the oldest clips until they use at most `max_bytes`.
+    ///
+    /// The folder is read once; each deletion subtracts the clip's known
+    /// size, proxy included. Favorited clips are never deleted. Returns
+    /// how many clips were removed.
+    pub fn cleanup_to_limit(&self, max_bytes: u64) -> ShadowplayResult<usize> {
+        self.cleanup_listed(self.list_clips()?, max_bytes)
+    }
+
+    /// Does [`StorageManager::cleanup_to_limit`] from `clips`, a listing
+    /// of the folder (newest first) taken by the caller, without reading
+    /// the folder again.
+    fn cleanup_listed(&self, clips: Vec<ClipInfo>, max_bytes: u64) -> ShadowplayResult<usize> {
+        let mut total: u64 = clips.iter().map(ClipInfo::total_bytes).sum();
+        let mut deleted = 0;
+
+        // Oldest first
+        for clip in clips.iter().rev() {
+            if total <= max_bytes {
+                break;
+            }
+            if clip.favorite {
+                continue;
+            }
+            self.delete_clip(&clip.path)?;
+            total -= clip.total_bytes();
+            deleted += 1;
+        }
+
+        if total > max_bytes {
+            log::warn!(
+                "Clips still use {} bytes (limit {}), but only favorites remain",
+                total,
+                max_bytes
+            );
+        }
+
+        Ok(deleted)
+    }
+
+    /// Deletes the oldest clips until at most `max_clips` remain.
+    ///
+    /// Favorited clips are never deleted. Returns how many clips were
+    /// removed.
+    pub fn cleanup_to_count(&self, max_clips: usize) -> ShadowplayResult<usize> {
+        let clips = self.list_clips()?;
+        let mut remaining = clips.len();
+        let mut deleted = 0;
+
+        // Oldest first
+        for clip in clips.iter().rev() {
+            if remaining <= max_clips {
+                break;
+            }
+            if clip.favorite {
+                continue;
+            }
+            self.delete_clip(&clip.path)?;
+            remaining -= 1;
+            deleted += 1;
+        }
+
+        if remaining > max_clips {
+            log::warn!(
+                "{} clips remain (limit {}), but only favorites are left",
+                remaining,
+                max_clips
+            );
+        }
+
+        Ok(deleted)
+    }
+
+    /// Deletes clips last modified more than `max_age` ago.
+    ///
+    /// Favorited clips, and clips whose age can't be read, are never
+    /// deleted. Returns how many clips were removed.
+    pub fn cleanup_older_than(&self, max_age: Duration) -> ShadowplayResult<usize> {
+        let mut deleted = 0;
+        for clip in self.list_clips()? {
+            if clip.favorite || clip.age().is_none_or(|age| age <= max_age) {
+                continue;
+            }
+            self.delete_clip(&clip.path)?;
+            deleted += 1;
+        }
+        Ok(deleted)
+    }
+
+    /// Deletes old clips as `policy` says. Favorites are always kept.
+    ///
+    /// Returns how many clips were removed.
+    pub fn enforce_retention(&self, policy: RetentionPolicy) -> ShadowplayResult<usize> {
+        let deleted = match policy {
+            RetentionPolicy::MaxBytes(max_bytes) => self.cleanup_to_limit(max_bytes)?,
+            RetentionPolicy::MaxCount(max_clips) => self.cleanup_to_count(max_clips)?,
+            RetentionPolicy::MaxAge(max_age) => self.cleanup_older_than(max_age)?,
+        };
+        if deleted > 0 {
+            log::info!("Retention {:?}: deleted {} clips", policy, deleted);
+        }
+        Ok(deleted)
+    }
+
+    /// Returns the bytes free on the drive holding the clips.
+    pub fn available_storage(&self) -> ShadowplayResult<u64> {
+        available_storage(&self.output_directory)
+    }
+
+    /// Returns true if a file of `bytes` fits on the drive with
+    /// [`RESERVED_BYTES`] to spare.
+    pub fn has_space_for(&self, bytes: u64) -> ShadowplayResult<bool> {
+        Ok(bytes.saturating_add(RESERVED_BYTES) <= self.available_storage()?)
+    }
+
+    /// Fails with a disk full error unless a file of `bytes` fits.
+    pub fn ensure_space_for(&self, bytes: u64) -> ShadowplayResult<()> {
+        let available = self.available_storage()?;
+        if bytes.saturating_add(RESERVED_BYTES) <= available {
+            return Ok(());
+        }
+        let message = format!(
+            "Disk full: clip needs ~{} MB but only {} MB is free; delete some clips",
+            bytes.div_ceil(1024 * 1024),
+            available / (1024 * 1024)
+        );
+        Err(ShadowplayError::StorageKind(StorageErrorKind::DiskFull, message))
+    }
+
+    /// Checks that a clip of `bytes` fits before saving it.
+    ///
+    /// If it doesn't and `auto_cleanup` is on, the oldest non-favorite
+    /// clips are deleted (see [`StorageManager::cleanup_to_limit`]) until
+    /// it does. Fails with a disk full error if there still isn't room;
+    /// when deleting every non-favorite clip wouldn't be enough, none
+    /// are deleted.
+    pub fn make_room_for(&self, bytes: u64, auto_cleanup: bool) -> ShadowplayResult<()> {
+        let available = self.available_storage()?;
+        let needed = bytes.saturating_add(RESERVED_BYTES);
+        if needed <= available {
+            return Ok(());
+        }
+
+        if !auto_cleanup {
+            log::warn!(
+                "Not enough space for a {} MB clip; auto cleanup is off",
+                bytes.div_ceil(1024 * 1024)
+            );
+            return self.ensure_space_for(bytes);
+        }
+
+        let shortfall = needed - available;
+        let clips = self.list_clips()?;
+        let deletable: u64 = clips.iter().filter(|c| !c.favorite).map(ClipInfo::total_bytes).sum();
+        if deletable < shortfall {
+            log::warn!(
+                "Not enough space for a {} MB clip even without old clips; deleting none",
+                bytes.div_ceil(1024 * 1024)
+            );
+        } else {
+            let used: u64 = clips.iter().map(ClipInfo::total_bytes).sum();
+            let deleted = self.cleanup_listed(clips, used - shortfall)?;
+            log::warn!(
+                "Low on space: deleted {} old clips to free {} MB for a new one",
+                deleted,
+                shortfall.div_ceil(1024 * 1024)
+            );
+        }
+        self.ensure_space_for(bytes)
+    }
+
     /// Returns the output directory.
     pub fn output_directory(&self) -> &Path {
         &self.output_directory
     }
 }
 
+/// Which old clips to delete when tidying the clips folder.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RetentionPolicy {
+    /// Keep the newest clips that fit in this many bytes
+    MaxBytes(u64),
+
+    /// Keep at most this many clips
+    MaxCount(usize),
+
+    /// Keep clips younger than this
+    MaxAge(Duration),
+}
+
 /// Information about a saved clip.
 #[derive(Debug, Clone)]
 pub struct ClipInfo {
     pub path: PathBuf,
+    /// Low-resolution copy for previews, if one was saved
+    pub proxy: Option<PathBuf>,
+    /// Size of the proxy, or 0 without one
+    pub proxy_bytes: u64,
     pub filename: String,
     pub size_bytes: u64,
     pub modified: Option<std::time::SystemTime>,
+    pub favorite: bool,
+    /// Contents of the clip's sidecar when it was listed, if it has one
+    pub metadata: Option<ClipMetadata>,
 }
 
 impl ClipInfo {
+    /// Returns the bytes the clip takes up, proxy included.
+    pub fn total_bytes(&self) -> u64 {
+        self.size_bytes + self.proxy_bytes
+    }
+
     /// Returns human-readable size.
     pub fn size_human(&self) -> String {
         const KB: u64 = 1024;
@@ -129,12 +567,62 @@ impl ClipInfo {
             format!("{} bytes", self.size_bytes)
         }
     }
+
+    /// Reads the clip's sidecar again, picking up changes made since it
+    /// was listed. Returns `None` if it has none or it can't be read.
+    pub fn load_metadata(&self) -> Option<ClipMetadata> {
+        ClipMetadata::load(&self.path).ok().flatten()
+    }
+
+    /// Returns how long ago the clip was last modified, if known.
+    ///
+    /// Clips dated in the future count as brand new.
+    pub fn age(&self) -> Option<Duration> {
+        let modified = self.modified?;
+        Some(modified.elapsed().unwrap_or_default())
+    }
 }
 
 // ============================================
 // UTILITY FUNCTIONS
 // ============================================
 
+/// Date folder names, e.g. `2024-03-05`
+const DATE_FOLDER_FORMAT: &str = "%Y-%m-%d";
+
+/// Returns true if `name` is a `YYYY-MM-DD` date folder name.
+fn is_date_folder(name: &str) -> bool {
+    name.len() == 10 && NaiveDate::parse_from_str(name, DATE_FOLDER_FORMAT).is_ok()
+}
+
+/// Returns the folders clips can be in: the output directory, then its
+/// date subfolders, oldest first.
+pub fn clip_folders(output_directory: &Path) -> ShadowplayResult<Vec<PathBuf>> {
+    let mut dated = Vec::new();
+    for entry in fs::read_dir(output_directory)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        if path.is_dir() && is_date_folder(&name) {
+            dated.push(path);
+        }
+    }
+    dated.sort();
+    dated.insert(0, output_directory.to_path_buf());
+    Ok(dated)
+}
+
+/// Returns `<stem>.qsp` in `directory`, or `<stem>_1.qsp`, `<stem>_2.qsp`,
+/// ... if that clip already exists, so a save never overwrites another.
+fn unused_clip_path(directory: &Path, stem: &str) -> String {
+    let mut path = directory.join(format!("{}.qsp", stem));
+    let mut suffix = 0;
+    while path.exists() {
+        suffix += 1;
+        path = directory.join(format!("{}_{}.qsp", stem, suffix));
+    }
+    path.to_string_lossy().to_string()
+}
+
 /// Ensures a directory exists.
 pub fn ensure_directory(path: &str) -> ShadowplayResult<()> {
     let path = Path::new(path);
@@ -144,10 +632,140 @@ pub fn ensure_directory(path: &str) -> ShadowplayResult<()> {
     Ok(())
 }
 
-/// Returns available storage in bytes (placeholder).
-pub fn available_storage() -> u64 {
-    // In real implementation, use statvfs or Android API
-    10 * 1024 * 1024 * 1024 // 10 GB placeholder
+/// Recreates a directory that has disappeared, e.g. deleted by the
+/// user or a cleanup tool while the app was running.
+///
+/// Returns true if it had to be recreated, which is logged as a warning.
+pub fn recreate_if_missing(path: &Path) -> ShadowplayResult<bool> {
+    if path.is_dir() {
+        return Ok(false);
+    }
+    log::warn!("Directory {:?} is missing, recreating it", path);
+    fs::create_dir_all(path)?;
+    Ok(true)
+}
+
+/// Shared-storage folder used when the system can't be asked.
+#[cfg(target_os = "android")]
+pub const ANDROID_DEFAULT_DIRECTORY: &str = "/sdcard/QuestShadowplay";
+
+/// Returns a writable clips directory on Android.
+///
+/// Prefers `QuestShadowplay/` on shared storage (easy to find over USB),
+/// then the app-specific external files folder (always writable under
+/// scoped storage), then the hardcoded `/sdcard` path. Resolved once.
+///
+/// Returns `None` if no Android Context has been registered yet (see
+/// [`register_android_context`]), so callers pick their own fallback
+/// instead of panicking.
+#[cfg(target_os = "android")]
+pub fn android_output_directory() -> Option<PathBuf> {
+    static DIRECTORY: std::sync::OnceLock<PathBuf> = std::sync::OnceLock::new();
+
+    if !android::has_context() {
+        return None;
+    }
+
+    let directory = DIRECTORY
+        .get_or_init(|| {
+            let candidates = [
+                android::external_storage_directory().map(|d| d.join("QuestShadowplay")),
+                android::external_files_directory(),
+            ];
+
+            for dir in candidates.into_iter().flatten() {
+                if is_writable_directory(&dir) {
+                    log::info!("Using clips directory: {:?}", dir);
+                    return dir;
+                }
+                log::warn!("Clips directory not writable: {:?}", dir);
+            }
+
+            log::warn!("Falling back to {}", ANDROID_DEFAULT_DIRECTORY);
+            PathBuf::from(ANDROID_DEFAULT_DIRECTORY)
+        })
+        .clone();
+    Some(directory)
+}
+
+/// Creates a directory if needed and checks that files can be written
+/// to it, so a bad export target fails before any work is done.
+pub fn ensure_writable_directory(path: &Path) -> ShadowplayResult<()> {
+    if is_writable_directory(path) {
+        Ok(())
+    } else {
+        Err(ShadowplayError::Storage(format!("Directory {:?} is not writable", path)))
+    }
+}
+
+/// Returns true if the directory exists (or can be created) and accepts files.
+fn is_writable_directory(path: &Path) -> bool {
+    if fs::create_dir_all(path).is_err() {
+        return false;
+    }
+
+    let probe = path.join(".write_test");
+    let writable = fs::write(&probe, b"").is_ok();
+    let _ = fs::remove_file(&probe);
+    writable
+}
+
+/// Free bytes always left on the drive, for sidecars, thumbnails and
+/// the rest of the system
+pub const RESERVED_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Returns the bytes an unprivileged app can still write to the drive
+/// holding `path`.
+///
+/// Fails if the path can't be looked up, e.g. when the SD card or
+/// shared storage isn't mounted.
+pub fn available_storage(path: &Path) -> ShadowplayResult<u64> {
+    let not_mounted = |e: std::io::Error| {
+        ShadowplayError::Storage(format!("Storage not mounted at {:?}: {}", path, e))
+    };
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+
+        let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())
+            .map_err(|e| not_mounted(std::io::Error::new(std::io::ErrorKind::InvalidInput, e)))?;
+        let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statvfs(c_path.as_ptr(), &mut stats) } != 0 {
+            return Err(not_mounted(std::io::Error::last_os_error()));
+        }
+        // Field widths differ between platforms
+        #[allow(clippy::unnecessary_cast)]
+        Ok(stats.f_bavail as u64 * stats.f_frsize as u64)
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStrExt;
+
+        #[link(name = "kernel32")]
+        extern "system" {
+            fn GetDiskFreeSpaceExW(
+                directory: *const u16,
+                free_to_caller: *mut u64,
+                total: *mut u64,
+                total_free: *mut u64,
+            ) -> i32;
+        }
+
+        let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+        let mut free = 0u64;
+        let null = std::ptr::null_mut();
+        if unsafe { GetDiskFreeSpaceExW(wide.as_ptr(), &mut free, null, null) } == 0 {
+            return Err(not_mounted(std::io::Error::last_os_error()));
+        }
+        Ok(free)
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    {
+        Err(not_mounted(std::io::ErrorKind::Unsupported.into()))
+    }
 }
 
 /// Checks if storage permission is granted (placeholder).
@@ -171,6 +789,33 @@ mod tests {
         assert!(filename.ends_with(".qsp"));
     }
 
+    #[test]
+    fn test_filename_from_trigger_time() {
+        use chrono::TimeZone;
+        let time = Local.with_ymd_and_hms(2024, 3, 5, 14, 7, 9).unwrap();
+        let filename = StorageManager::generate_filename_at("/test/", time);
+        assert!(filename.ends_with("clip_20240305_140709.qsp"));
+
+        let filename = StorageManager::generate_profile_filename_at("/test/", "highlight", time);
+        assert!(filename.ends_with("clip_20240305_140709_highlight.qsp"));
+    }
+
+    #[test]
+    fn test_filename_same_second() {
+        let dir = tempdir().unwrap();
+        let manager = StorageManager::new(dir.path().to_str().unwrap()).unwrap();
+
+        // Saves in a tight loop land in the same second but never share a name
+        let mut paths = Vec::new();
+        for _ in 0..3 {
+            let path = manager.next_clip_path().unwrap();
+            assert!(!paths.contains(&path));
+            fs::write(&path, b"clip").unwrap();
+            paths.push(path);
+        }
+        assert!(paths.iter().all(|path| path.starts_with(dir.path())));
+    }
+
     #[test]
     fn test_storage_manager_creation() {
         let dir = tempdir().unwrap();
@@ -178,18 +823,296 @@ mod tests {
         assert!(manager.is_ok());
     }
 
+    #[test]
+    fn test_directory_deleted_while_running() {
+        let dir = tempdir().unwrap();
+        let output = dir.path().join("clips");
+        let manager = StorageManager::new(output.to_str().unwrap()).unwrap();
+        write_clips(&output, 2);
+        assert_eq!(manager.list_clips().unwrap().len(), 2);
+
+        fs::remove_dir_all(&output).unwrap();
+        assert!(manager.list_clips().unwrap().is_empty());
+        assert!(output.is_dir());
+
+        // Saving works again once it is back
+        write_clips(&output, 1);
+        assert_eq!(manager.list_clips().unwrap().len(), 1);
+        assert!(!recreate_if_missing(&output).unwrap());
+    }
+
     #[test]
     fn test_clip_info_size_human() {
         let clip = ClipInfo {
             path: PathBuf::from("/test.qsp"),
+            proxy: None,
+            proxy_bytes: 0,
             filename: "test.qsp".to_string(),
             size_bytes: 5 * 1024 * 1024,
             modified: None,
+            favorite: false,
+            metadata: None,
         };
         assert!(clip.size_human().contains("5"));
         assert!(clip.size_human().contains("MB"));
     }
 
+    /// Writes `count` clips with increasing modification times, oldest first.
+    fn write_clips(dir: &Path, count: usize) -> Vec<PathBuf> {
+        (0..count)
+            .map(|i| {
+                let path = dir.join(format!("clip_{}.qsp", i));
+                fs::write(&path, vec![0u8; 100]).unwrap();
+                let modified =
+                    std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(i as u64);
+                fs::File::options()
+                    .write(true)
+                    .open(&path)
+                    .unwrap()
+                    .set_modified(modified)
+                    .unwrap();
+                path
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_cleanup_skips_favorites() {
+        let dir = tempdir().unwrap();
+        let manager = StorageManager::new(dir.path().to_str().unwrap()).unwrap();
+        let clips = write_clips(dir.path(), 4);
+        ClipMetadata::set_favorite(&clips[0], true).unwrap();
+
+        // The oldest clip is a favorite, so the next oldest go instead
+        assert_eq!(manager.cleanup_to_count(2).unwrap(), 2);
+        assert!(clips[0].exists());
+        assert!(!clips[1].exists() && !clips[2].exists());
+        assert!(clips[3].exists());
+
+        // Only a favorite is left to delete: nothing happens
+        assert_eq!(manager.cleanup_to_limit(100).unwrap(), 1);
+        assert_eq!(manager.cleanup_to_limit(0).unwrap(), 0);
+        assert!(clips[0].exists());
+    }
+
+    #[test]
+    fn test_cleanup_many_clips() {
+        let dir = tempdir().unwrap();
+        let manager = StorageManager::new(dir.path().to_str().unwrap()).unwrap();
+        let clips = write_clips(dir.path(), 50);
+
+        // 100 bytes each: keeping 1000 bytes leaves the newest 10. It all
+        // comes from one listing, so a clip saved after it is neither
+        // counted nor deleted
+        let listing = manager.list_clips().unwrap();
+        let late = dir.path().join("clip_late.qsp");
+        fs::write(&late, vec![0u8; 100]).unwrap();
+        assert_eq!(manager.cleanup_listed(listing, 1000).unwrap(), 40);
+        assert!(clips[..40].iter().all(|clip| !clip.exists()));
+        assert!(clips[40..].iter().all(|clip| clip.exists()));
+        assert!(late.exists());
+        assert_eq!(manager.total_storage_used().unwrap(), 1100);
+
+        assert_eq!(manager.cleanup_to_limit(1000).unwrap(), 1);
+        assert!(!clips[40].exists() && late.exists());
+    }
+
+    #[test]
+    fn test_enforce_retention() {
+        let dir = tempdir().unwrap();
+        let manager = StorageManager::new(dir.path().to_str().unwrap()).unwrap();
+        let clips = write_clips(dir.path(), 4);
+        ClipMetadata::set_favorite(&clips[1], true).unwrap();
+        let fresh = dir.path().join("clip_new.qsp");
+        fs::write(&fresh, vec![0u8; 100]).unwrap();
+
+        // Written clips date from 1970; only the new one is young enough
+        let day = Duration::from_secs(24 * 60 * 60);
+        assert!(manager.list_clips().unwrap()[0].age().unwrap() < day);
+        assert_eq!(manager.enforce_retention(RetentionPolicy::MaxAge(day)).unwrap(), 3);
+        assert!(clips[1].exists() && fresh.exists());
+
+        assert_eq!(manager.enforce_retention(RetentionPolicy::MaxCount(1)).unwrap(), 1);
+        assert!(clips[1].exists() && !fresh.exists());
+        assert_eq!(manager.enforce_retention(RetentionPolicy::MaxBytes(0)).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_available_storage() {
+        let dir = tempdir().unwrap();
+        let manager = StorageManager::new(dir.path().to_str().unwrap()).unwrap();
+        let free = manager.available_storage().unwrap();
+        assert!(free > 0);
+        assert!(manager.has_space_for(0).unwrap() == (free >= RESERVED_BYTES));
+        assert!(!manager.has_space_for(u64::MAX).unwrap());
+
+        let err = manager.ensure_space_for(free).unwrap_err();
+        assert!(err.to_string().contains("Disk full"));
+
+        // Cleanup can't make room for more than the drive holds, so it
+        // doesn't delete anything trying
+        let clips = write_clips(dir.path(), 3);
+        ClipMetadata::set_favorite(&clips[2], true).unwrap();
+        assert!(manager.make_room_for(free, false).is_err());
+        assert!(clips[0].exists());
+        let err = manager.make_room_for(free, true).unwrap_err();
+        assert_eq!(err.storage_kind(), Some(StorageErrorKind::DiskFull));
+        assert!(clips.iter().all(|clip| clip.exists()));
+        assert!(available_storage(&dir.path().join("not_mounted")).is_err());
+    }
+
+    #[test]
+    fn test_proxy_listed_with_master() {
+        let dir = tempdir().unwrap();
+        let manager = StorageManager::new(dir.path().to_str().unwrap()).unwrap();
+        let clips = write_clips(dir.path(), 2);
+        fs::write(proxy_path(&clips[1]), b"proxy").unwrap();
+
+        let listed = manager.list_clips().unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].proxy, Some(proxy_path(&clips[1])));
+        assert_eq!(listed[1].proxy, None);
+        assert_eq!(listed[0].total_bytes(), 105);
+        assert_eq!(manager.total_storage_used().unwrap(), 205);
+
+        // The clips fit in 200 bytes only without the proxy, so the
+        // oldest has to go
+        assert_eq!(manager.cleanup_to_limit(200).unwrap(), 1);
+        assert!(!clips[0].exists());
+
+        manager.delete_clip(&clips[1]).unwrap();
+        assert!(!proxy_path(&clips[1]).exists());
+    }
+
+    #[test]
+    fn test_tags() {
+        let dir = tempdir().unwrap();
+        let manager = StorageManager::new(dir.path().to_str().unwrap()).unwrap();
+        let clips = write_clips(dir.path(), 3);
+        ClipMetadata::set_favorite(&clips[0], true).unwrap();
+
+        manager.add_tag(&clips[0], "Boss Fight").unwrap();
+        manager.add_tag(&clips[0], " boss fight ").unwrap();
+        manager.add_tag(&clips[1], "speedrun").unwrap();
+        assert!(manager.add_tag(&clips[2], "  ").is_err());
+
+        // Tags sit alongside the other metadata, once each
+        let metadata = ClipMetadata::load(&clips[0]).unwrap().unwrap();
+        assert_eq!(metadata.tags, ["Boss Fight"]);
+        assert!(metadata.favorite);
+
+        let found = manager.find_clips_by_tag("BOSS FIGHT").unwrap();
+        assert_eq!(found.iter().map(|c| &c.path).collect::<Vec<_>>(), [&clips[0]]);
+        assert_eq!(manager.search_clips("SPEED").unwrap()[0].path, clips[1]);
+        assert_eq!(manager.search_clips("clip_2").unwrap()[0].path, clips[2]);
+        assert_eq!(manager.search_clips("clip").unwrap().len(), 3);
+
+        assert!(manager.remove_tag(&clips[0], "boss fight").unwrap());
+        assert!(!manager.remove_tag(&clips[0], "boss fight").unwrap());
+        assert!(!manager.remove_tag(&clips[2], "speedrun").unwrap());
+        assert!(manager.find_clips_by_tag("boss fight").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_organize_by_date() {
+        use chrono::TimeZone;
+        let time = Local.with_ymd_and_hms(2024, 3, 5, 14, 7, 9).unwrap();
+        let folder = StorageManager::clip_directory_at("/test", time, true);
+        assert_eq!(PathBuf::from(folder), PathBuf::from("/test/2024-03-05"));
+        assert_eq!(StorageManager::clip_directory_at("/test", time, false), "/test");
+
+        let dir = tempdir().unwrap();
+        let manager = StorageManager::new(dir.path().to_str().unwrap())
+            .unwrap()
+            .with_date_folders(true);
+        let flat = write_clips(dir.path(), 1);
+        let dated = manager.next_clip_path().unwrap();
+        fs::write(&dated, b"clip").unwrap();
+        let folder = dated.parent().unwrap();
+        assert_eq!(folder.parent().unwrap(), dir.path());
+        assert!(is_date_folder(&folder.file_name().unwrap().to_string_lossy()));
+
+        // Both are listed, newest first; other folders aren't searched
+        fs::create_dir(dir.path().join("misc")).unwrap();
+        fs::write(dir.path().join("misc").join("clip_x.qsp"), b"clip").unwrap();
+        let listed = manager.list_clips().unwrap();
+        assert_eq!(listed.iter().map(|c| &c.path).collect::<Vec<_>>(), [&dated, &flat[0]]);
+
+        // Deleting by its folder-relative name removes the emptied folder
+        let name = dated.strip_prefix(dir.path()).unwrap().to_string_lossy().to_string();
+        manager.delete_clip_by_name(&name).unwrap();
+        assert!(!folder.exists());
+        assert_eq!(manager.list_clips().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_delete_clip_by_name() {
+        let dir = tempdir().unwrap();
+        let clips_dir = dir.path().join("clips");
+        let manager = StorageManager::new(clips_dir.to_str().unwrap()).unwrap();
+        let clips = write_clips(&clips_dir, 1);
+        fs::write(thumbnail_path(&clips[0]), b"jpeg").unwrap();
+        ClipMetadata::default().save(&clips[0]).unwrap();
+        let outside = dir.path().join("secret.qsp");
+        fs::write(&outside, b"keep").unwrap();
+
+        let absolute = outside.to_string_lossy().into_owned();
+        for name in ["../secret.qsp", "..", "", "sub/clip.qsp", "sub\\clip.qsp", &absolute] {
+            let err = manager.delete_clip_by_name(name).unwrap_err();
+            assert_eq!(err.storage_kind(), Some(StorageErrorKind::PermissionDenied), "{}", name);
+        }
+        let climbing = clips_dir.join("..").join("secret.qsp");
+        let err = manager.delete_clip(&climbing).unwrap_err();
+        assert_eq!(err.storage_kind(), Some(StorageErrorKind::PermissionDenied));
+        assert!(manager.delete_clip_by_name("clip_missing.qsp").is_err());
+
+        // A link inside the folder that points outside it is refused too
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&outside, clips_dir.join("link.qsp")).unwrap();
+            assert!(manager.delete_clip_by_name("link.qsp").is_err());
+        }
+        assert!(outside.exists());
+
+        manager.delete_clip_by_name("clip_0.qsp").unwrap();
+        assert!(!clips[0].exists() && !thumbnail_path(&clips[0]).exists());
+        assert!(!ClipMetadata::sidecar_path(&clips[0]).exists());
+    }
+
+    #[test]
+    fn test_clip_metadata_listed() {
+        let dir = tempdir().unwrap();
+        let manager = StorageManager::new(dir.path().to_str().unwrap()).unwrap();
+        let clips = write_clips(dir.path(), 2);
+        let metadata = ClipMetadata {
+            app: Some("Beat Saber".to_string()),
+            note: Some("boss fight".to_string()),
+            ..ClipMetadata::default()
+        };
+        metadata.save(&clips[1]).unwrap();
+
+        // Only the clip with a sidecar has metadata
+        let listed = manager.list_clips().unwrap();
+        assert_eq!(listed[0].metadata.as_ref(), Some(&metadata));
+        assert_eq!(listed[0].load_metadata(), Some(metadata));
+        assert_eq!(listed[1].metadata, None);
+        assert_eq!(listed[1].load_metadata(), None);
+    }
+
+    #[test]
+    fn test_is_writable_directory() {
+        let dir = tempdir().unwrap();
+        let nested = dir.path().join("a").join("b");
+        assert!(is_writable_directory(&nested));
+        assert!(nested.exists());
+
+        // A path below a file can never be a directory
+        let file = dir.path().join("file");
+        fs::write(&file, b"").unwrap();
+        assert!(ensure_writable_directory(&nested).is_ok());
+        assert!(ensure_writable_directory(&file.join("exports")).is_err());
+    }
+
     #[test]
     fn test_ensure_directory() {
         let dir = tempdir().unwrap();
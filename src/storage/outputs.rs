@@ -0,0 +1,389 @@
+//! # Save Outputs
+//!
+//! Writes every file a save should produce in one pass over the frames.
+//!
+//! ## Plain English
+//!
+//! A save can produce more than the clip itself: a small proxy copy, a
+//! GIF for sharing, or a folder of numbered pictures ready for ffmpeg
+//! to turn into an MP4. Each of those needs the frames unpacked
+//! (decoded), which is the slow part. Rather than unpack everything
+//! once per file, each frame is unpacked once and handed to every file
+//! that wants it. If unpacking fails partway, half-written files are
+//! removed rather than left looking finished.
+
+use std::borrow::Borrow;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use image::RgbaImage;
+
+use super::proxy::{downscale_decoded, write_scaled_proxy};
+use crate::capture::{CapturedFrame, FrameCompressor, FrameFormat};
+use crate::config::Config;
+use crate::encoder::{
+    frame_file_name, write_manifest, ClipMark, DumpedFrame, ExportAspect, GifWriter,
+    ParallelDecoder, VideoEncoder,
+};
+use crate::error::{ShadowplayError, ShadowplayResult};
+
+/// One file (or folder) a save produces.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SaveOutput {
+    /// The full-resolution clip itself
+    Master,
+
+    /// A low-resolution copy next to the clip (see [`super::proxy_path`])
+    Proxy,
+
+    /// Numbered JPEGs plus a manifest in a folder next to the clip (see
+    /// [`frames_directory`]), optionally reframed; ffmpeg's MP4 input
+    Frames(Option<ExportAspect>),
+
+    /// A looping animated GIF next to the clip (see [`gif_path`]),
+    /// shrunk and thinned as [`VideoEncoder::encode_gif`] does
+    Gif,
+}
+
+impl SaveOutput {
+    /// Parses an output name: `"master"`, `"proxy"`, `"frames"` or
+    /// `"gif"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "master" => Some(Self::Master),
+            "proxy" => Some(Self::Proxy),
+            "frames" => Some(Self::Frames(None)),
+            "gif" => Some(Self::Gif),
+            _ => None,
+        }
+    }
+
+    /// Returns the outputs a save produces when none are asked for:
+    /// the clip, plus its proxy if `generate_proxy` is on.
+    pub fn defaults(config: &Config) -> Vec<Self> {
+        let mut outputs = vec![Self::Master];
+        if config.generate_proxy {
+            outputs.push(Self::Proxy);
+        }
+        outputs
+    }
+}
+
+/// Returns the folder [`SaveOutput::Frames`] writes to for a clip.
+pub fn frames_directory(clip_path: &Path) -> PathBuf {
+    clip_path.with_extension("frames")
+}
+
+/// Returns the file [`SaveOutput::Gif`] writes for a clip.
+pub fn gif_path(clip_path: &Path) -> PathBuf {
+    clip_path.with_extension("gif")
+}
+
+/// Writes `outputs` for a clip saved at `clip_path`, decoding each
+/// frame at most once for all of them (in parallel, on
+/// `config.decode_workers` threads).
+///
+/// The master is written first and its failure fails the save. The
+/// other outputs are conveniences: one that fails is logged, its
+/// partial file or folder removed, and the rest carry on. Returns the
+/// paths written.
+///
+/// `frames` may be the buffer's shared frames, so nothing is copied.
+pub fn write_outputs<F: Borrow<CapturedFrame> + Sync>(
+    frames: &[F],
+    marks: &[ClipMark],
+    clip_path: &Path,
+    outputs: &[SaveOutput],
+    config: &Config,
+) -> ShadowplayResult<Vec<PathBuf>> {
+    let mut written = Vec::new();
+    if outputs.contains(&SaveOutput::Master) {
+        let path = clip_path.to_string_lossy();
+        VideoEncoder::encode_frames_with_marks(frames, marks, &path, config)?;
+        written.push(clip_path.to_path_buf());
+    }
+
+    let mut proxy = outputs.contains(&SaveOutput::Proxy).then(Vec::new);
+    let mut dump = outputs.iter().find_map(|o| match o {
+        SaveOutput::Frames(aspect) => Some(FrameDump::new(
+            clip_path,
+            aspect.map(|aspect| aspect.with_filter(config.scale_filter)),
+        )),
+        _ => None,
+    });
+
+    // GIF frames are picked up front, so only those are decoded for it
+    let gif_frames = match outputs.contains(&SaveOutput::Gif) {
+        true => VideoEncoder::gif_frames(frames, config),
+        false => Vec::new(),
+    };
+    let mut gif = gif_frames.first().and_then(|&(first, _)| {
+        let frame = frames[first].borrow();
+        let path = gif_path(clip_path);
+        match GifWriter::create(&path, frame.width, frame.height, config) {
+            Ok(writer) => Some((writer, path)),
+            Err(e) => {
+                log::warn!("Failed to start GIF for {:?}: {}", clip_path, e);
+                discard_file(&path);
+                None
+            }
+        }
+    });
+
+    if proxy.is_none() && dump.is_none() && gif.is_none() {
+        return Ok(written);
+    }
+
+    let wants_proxy = proxy.is_some();
+    let dump_aspect = dump.as_ref().map(|d| d.aspect);
+    let gif_delay = |index: usize| {
+        gif_frames
+            .binary_search_by_key(&index, |&(i, _)| i)
+            .ok()
+            .map(|at| gif_frames[at].1)
+    };
+    let wanted = |index: usize, frame: &CapturedFrame| {
+        wants_proxy
+            || gif_delay(index).is_some()
+            || dump_aspect.is_some_and(|aspect| dump_needs_pixels(aspect, frame))
+    };
+    let decoded = ParallelDecoder::new(config.decode_workers).and_then(|decoder| {
+        decoder.decode_in_order(frames, wanted, |index, frame, image| {
+            if let Some(frame_dump) = &mut dump {
+                if let Err(e) = frame_dump.write(index, frame, image.as_ref(), config.jpeg_quality)
+                {
+                    log::warn!("Failed to write frames for {:?}: {}", clip_path, e);
+                    if let Some(dump) = dump.take() {
+                        dump.discard();
+                    }
+                }
+            }
+            if let (Some((writer, _)), Some(delay), Some(image)) =
+                (&mut gif, gif_delay(index), image.as_ref())
+            {
+                if let Err(e) = writer.write_frame(image, delay) {
+                    log::warn!("Failed to write GIF for {:?}: {}", clip_path, e);
+                    if let Some((_, path)) = gif.take() {
+                        discard_file(&path);
+                    }
+                }
+            }
+            if let (Some(scaled), Some(image)) = (&mut proxy, image) {
+                match downscale_decoded(
+                    image,
+                    frame,
+                    config.proxy_width,
+                    config.scale_filter,
+                    config.jpeg_quality,
+                ) {
+                    Ok(frame) => scaled.push(frame),
+                    Err(e) => {
+                        log::warn!("Failed to scale proxy for {:?}: {}", clip_path, e);
+                        proxy = None;
+                    }
+                }
+            }
+            Ok(())
+        })
+    });
+    if let Err(e) = decoded {
+        log::warn!("Skipping extra outputs for {:?}: {}", clip_path, e);
+        if let Some(dump) = dump {
+            dump.discard();
+        }
+        if let Some((writer, path)) = gif {
+            drop(writer);
+            discard_file(&path);
+        }
+        return Ok(written);
+    }
+
+    if let Some(scaled) = proxy {
+        match write_scaled_proxy(&scaled, marks, clip_path, config) {
+            Ok(path) => written.push(path),
+            Err(e) => log::warn!("Failed to write proxy for {:?}: {}", clip_path, e),
+        }
+    }
+    if let Some(dump) = dump {
+        match write_manifest(&dump.directory, &dump.manifest) {
+            Ok(()) => written.push(dump.directory),
+            Err(e) => {
+                log::warn!("Failed to write frames for {:?}: {}", clip_path, e);
+                dump.discard();
+            }
+        }
+    }
+    if let Some((writer, path)) = gif {
+        match writer.finish() {
+            Ok(()) => written.push(path),
+            Err(e) => {
+                log::warn!("Failed to write GIF for {:?}: {}", clip_path, e);
+                discard_file(&path);
+            }
+        }
+    }
+    Ok(written)
+}
+
+/// Removes a partly written output file, if it got created.
+fn discard_file(path: &Path) {
+    if path.exists() {
+        if let Err(e) = fs::remove_file(path) {
+            log::warn!("Failed to remove partial output {:?}: {}", path, e);
+        }
+    }
+}
+
+/// A [`SaveOutput::Frames`] folder being filled in.
+struct FrameDump {
+    directory: PathBuf,
+    aspect: Option<ExportAspect>,
+    manifest: Vec<DumpedFrame>,
+}
+
+impl FrameDump {
+    fn new(clip_path: &Path, aspect: Option<ExportAspect>) -> Self {
+        Self {
+            directory: frames_directory(clip_path),
+            aspect,
+            manifest: Vec::new(),
+        }
+    }
+
+    /// Removes the partly written folder, so it isn't mistaken for a
+    /// finished dump without a manifest.
+    fn discard(self) {
+        if self.directory.is_dir() {
+            if let Err(e) = fs::remove_dir_all(&self.directory) {
+                log::warn!("Failed to remove partial frames {:?}: {}", self.directory, e);
+            }
+        }
+    }
+
+    /// Writes one frame, re-compressed from `image` when it needs pixels.
+    fn write(
+        &mut self,
+        index: usize,
+        frame: &CapturedFrame,
+        image: Option<&RgbaImage>,
+        jpeg_quality: u8,
+    ) -> ShadowplayResult<()> {
+        if self.manifest.is_empty() {
+            fs::create_dir_all(&self.directory)?;
+        }
+
+        let (data, width, height) = match image.filter(|_| dump_needs_pixels(self.aspect, frame)) {
+            None => (frame.data.clone(), frame.width, frame.height),
+            Some(image) => {
+                let reframed;
+                let image = match &self.aspect {
+                    Some(aspect) => {
+                        reframed = aspect.apply(image)?;
+                        &reframed
+                    }
+                    None => image,
+                };
+                let data = FrameCompressor::new(jpeg_quality)
+                    .compress(image.as_raw(), image.width(), image.height())
+                    .map_err(|e| ShadowplayError::Encoder(e.to_string()))?;
+                (data, image.width(), image.height())
+            }
+        };
+
+        let file = frame_file_name(index);
+        fs::write(self.directory.join(&file), data)?;
+        self.manifest.push(DumpedFrame {
+            file,
+            timestamp_ns: frame.timestamp_ns,
+            eye_index: frame.eye_index,
+            width,
+            height,
+        });
+        Ok(())
+    }
+}
+
+/// Returns true if dumping `frame` needs its decoded pixels; JPEG
+/// frames kept at their size are copied as they are.
+fn dump_needs_pixels(aspect: Option<ExportAspect>, frame: &CapturedFrame) -> bool {
+    aspect.is_some() || frame.format != FrameFormat::Jpeg
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoder::{AspectMode, FrameReader};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_write_outputs() {
+        let dir = tempdir().unwrap();
+        let clip = dir.path().join("clip.qsp");
+        let compressor = FrameCompressor::new(80);
+        let frames: Vec<_> = (0..3u64)
+            .map(|i| {
+                let data = compressor.compress(&vec![90u8; 960 * 540 * 4], 960, 540).unwrap();
+                CapturedFrame::with_timestamp(data, 0, 960, 540, i * 1_000)
+            })
+            .collect();
+
+        let config = Config {
+            proxy_width: 480,
+            ..Config::default()
+        };
+        let aspect = ExportAspect::new(AspectMode::CropCenter, 1, 1);
+        let outputs = [
+            SaveOutput::Master,
+            SaveOutput::Proxy,
+            SaveOutput::Frames(Some(aspect)),
+            SaveOutput::Gif,
+        ];
+        let written = write_outputs(&frames, &[], &clip, &outputs, &config).unwrap();
+        assert_eq!(written.len(), 4);
+        assert_eq!(written[3], gif_path(&clip));
+        let gif = gif::DecodeOptions::new().read_info(fs::File::open(&written[3]).unwrap());
+        assert_eq!(gif.unwrap().width() as u32, config.gif_max_width.min(960));
+
+        let master = FrameReader::open(&clip.to_string_lossy()).unwrap();
+        assert_eq!(master.dimensions(), (960, 540));
+        let proxy = FrameReader::open(&written[1].to_string_lossy()).unwrap();
+        assert_eq!((proxy.frame_count(), proxy.dimensions()), (3, (480, 270)));
+
+        let frame = image::open(frames_directory(&clip).join("frame_00002.jpg")).unwrap();
+        assert_eq!((frame.width(), frame.height()), (540, 540));
+        assert!(frames_directory(&clip).join(crate::encoder::MANIFEST_FILE_NAME).exists());
+    }
+
+    #[test]
+    fn test_failed_decode_leaves_no_partial_outputs() {
+        let dir = tempdir().unwrap();
+        let clip = dir.path().join("clip.qsp");
+        let compressor = FrameCompressor::new(80);
+        let mut frames: Vec<_> = (0..20u64)
+            .map(|i| {
+                let data = compressor.compress(&vec![90u8; 64 * 64 * 4], 64, 64).unwrap();
+                CapturedFrame::with_timestamp(data, 0, 64, 64, i * 1_000_000)
+            })
+            .collect();
+        frames[15].data = vec![0xFF, 0xD8, 0x00];
+
+        let aspect = ExportAspect::new(AspectMode::CropCenter, 1, 2);
+        let outputs = [SaveOutput::Master, SaveOutput::Frames(Some(aspect)), SaveOutput::Gif];
+        let written = write_outputs(&frames, &[], &clip, &outputs, &Config::default()).unwrap();
+        assert_eq!(written, vec![clip.clone()]);
+        assert!(!frames_directory(&clip).exists());
+        assert!(!gif_path(&clip).exists());
+    }
+
+    #[test]
+    fn test_output_names() {
+        assert_eq!(SaveOutput::from_name("proxy"), Some(SaveOutput::Proxy));
+        assert_eq!(SaveOutput::from_name("gif"), Some(SaveOutput::Gif));
+        assert_eq!(SaveOutput::from_name("mp4"), None);
+        assert_eq!(SaveOutput::defaults(&Config::default()), vec![SaveOutput::Master]);
+    }
+}
@@ -0,0 +1,190 @@
+//! # Proxy Clips
+//!
+//! Low-resolution copies of clips, saved next to the full-resolution
+//! master (`clip_20240101_120000.qsp` → `clip_20240101_120000.proxy.qsp`).
+//!
+//! ## Plain English
+//!
+//! Scrubbing through a full-resolution clip means decoding big frames,
+//! which is slow. Editors work on a small copy (the proxy) while
+//! browsing and cutting, then use the master for the final export.
+//! The proxy is the same clip, frame for frame, just smaller.
+
+use std::path::{Path, PathBuf};
+
+use image::{imageops, RgbaImage};
+
+use crate::capture::{CapturedFrame, FrameCompressor};
+use crate::config::{Config, ScaleFilter};
+use crate::encoder::{ClipMark, VideoEncoder};
+use crate::error::{ShadowplayError, ShadowplayResult};
+
+/// File suffix that marks a proxy, replacing the master's `qsp`
+const PROXY_EXTENSION: &str = "proxy.qsp";
+
+/// Returns the proxy path for a master clip.
+pub fn proxy_path(clip_path: &Path) -> PathBuf {
+    clip_path.with_extension(PROXY_EXTENSION)
+}
+
+/// Returns true if `path` is a proxy rather than a master clip.
+pub fn is_proxy(path: &Path) -> bool {
+    path.to_string_lossy().ends_with(&format!(".{}", PROXY_EXTENSION))
+}
+
+/// Returns a clip's proxy path if one has been written.
+pub fn existing_proxy(clip_path: &Path) -> Option<PathBuf> {
+    Some(proxy_path(clip_path)).filter(|p| p.exists())
+}
+
+/// Scales a frame down to at most `max_width` pixels wide (never up)
+/// with `filter`, keeping its aspect ratio, and re-compresses it at
+/// `jpeg_quality`.
+pub fn downscale_frame(
+    frame: &CapturedFrame,
+    max_width: u32,
+    filter: ScaleFilter,
+    jpeg_quality: u8,
+) -> ShadowplayResult<CapturedFrame> {
+    downscale_decoded(VideoEncoder::decode_frame(frame)?, frame, max_width, filter, jpeg_quality)
+}
+
+/// Like [`downscale_frame`], from pixels already decoded from `frame`.
+pub(crate) fn downscale_decoded(
+    image: RgbaImage,
+    frame: &CapturedFrame,
+    max_width: u32,
+    filter: ScaleFilter,
+    jpeg_quality: u8,
+) -> ShadowplayResult<CapturedFrame> {
+    let (width, height) = image.dimensions();
+    let image = if width > max_width {
+        let scaled_height = (height as u64 * max_width as u64 / width as u64).max(1);
+        imageops::resize(&image, max_width, scaled_height as u32, filter.filter_type())
+    } else {
+        image
+    };
+
+    let data = FrameCompressor::new(jpeg_quality)
+        .compress(image.as_raw(), image.width(), image.height())
+        .map_err(|e| ShadowplayError::Encoder(e.to_string()))?;
+    let mut scaled = CapturedFrame::with_timestamp(
+        data,
+        frame.eye_index,
+        image.width(),
+        image.height(),
+        frame.timestamp_ns,
+    );
+    scaled.motion_score = frame.motion_score;
+    Ok(scaled)
+}
+
+/// Writes the proxy for a clip saved at `clip_path` from the same
+/// frames and marks, scaled to `config.proxy_width`.
+///
+/// Returns the proxy path.
+pub fn write_proxy(
+    frames: &[CapturedFrame],
+    marks: &[ClipMark],
+    clip_path: &Path,
+    config: &Config,
+) -> ShadowplayResult<PathBuf> {
+    let scaled = frames
+        .iter()
+        .map(|f| downscale_frame(f, config.proxy_width, config.scale_filter, config.jpeg_quality))
+        .collect::<ShadowplayResult<Vec<_>>>()?;
+    write_scaled_proxy(&scaled, marks, clip_path, config)
+}
+
+/// Writes the proxy for a clip from frames already scaled down.
+pub(crate) fn write_scaled_proxy(
+    scaled: &[CapturedFrame],
+    marks: &[ClipMark],
+    clip_path: &Path,
+    config: &Config,
+) -> ShadowplayResult<PathBuf> {
+    let path = proxy_path(clip_path);
+    VideoEncoder::encode_frames_with_marks(scaled, marks, &path.to_string_lossy(), config)?;
+    log::info!("Wrote proxy {:?}", path);
+    Ok(path)
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoder::FrameReader;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_proxy_path() {
+        let path = proxy_path(Path::new("/clips/clip_1.qsp"));
+        assert_eq!(path, PathBuf::from("/clips/clip_1.proxy.qsp"));
+        assert!(is_proxy(&path));
+        assert!(!is_proxy(Path::new("/clips/clip_1.qsp")));
+    }
+
+    #[test]
+    fn test_write_proxy() {
+        let dir = tempdir().unwrap();
+        let clip = dir.path().join("clip.qsp");
+        let compressor = FrameCompressor::new(80);
+        let frames: Vec<_> = (0..3u64)
+            .map(|i| {
+                let data = compressor.compress(&vec![90u8; 960 * 540 * 4], 960, 540).unwrap();
+                CapturedFrame::with_timestamp(data, 0, 960, 540, i * 1_000)
+            })
+            .collect();
+
+        let config = Config {
+            proxy_width: 480,
+            ..Config::default()
+        };
+        assert_eq!(existing_proxy(&clip), None);
+        let path = write_proxy(&frames, &[], &clip, &config).unwrap();
+        assert_eq!(existing_proxy(&clip), Some(path.clone()));
+
+        let reader = FrameReader::open(&path.to_string_lossy()).unwrap();
+        assert_eq!(reader.frame_count(), 3);
+        assert_eq!(reader.dimensions(), (480, 270));
+        assert_eq!(reader.frames()[2].timestamp_ns, 2_000);
+    }
+
+    #[test]
+    fn test_scale_filters() {
+        // A one-pixel checkerboard is the worst case for shrinking: a
+        // nearest pick keeps it harsh, blending filters smooth it out
+        let pixels: Vec<u8> = (0..192 * 192)
+            .flat_map(|i| {
+                let shade = if (i % 192 + i / 192) % 2 == 0 { 0 } else { 255 };
+                [shade, shade, shade, 255]
+            })
+            .collect();
+        let data = FrameCompressor::new(100).compress(&pixels, 192, 192).unwrap();
+        let frame = CapturedFrame::with_timestamp(data, 0, 192, 192, 0);
+
+        // Average brightness step between neighboring pixels
+        let sharpness = |filter: ScaleFilter| {
+            let scaled = downscale_frame(&frame, 64, filter, 95).unwrap();
+            let image = VideoEncoder::decode_frame(&scaled).unwrap();
+            let steps: u64 = image
+                .as_raw()
+                .chunks_exact(4)
+                .collect::<Vec<_>>()
+                .windows(2)
+                .map(|pair| pair[0][0].abs_diff(pair[1][0]) as u64)
+                .sum();
+            (steps * 4 / image.as_raw().len() as u64, scaled.data.len())
+        };
+
+        let (nearest, nearest_bytes) = sharpness(ScaleFilter::Nearest);
+        let (triangle, triangle_bytes) = sharpness(ScaleFilter::Triangle);
+        let (lanczos, _) = sharpness(ScaleFilter::Lanczos3);
+        assert!(nearest > triangle && nearest > lanczos, "{} {} {}", nearest, triangle, lanczos);
+        assert!(nearest_bytes > triangle_bytes);
+        assert_eq!(ScaleFilter::default(), Config::default().scale_filter);
+    }
+}
@@ -0,0 +1,126 @@
+//! # Saved Settings
+//!
+//! Settings changed at runtime, kept in `settings.json` in the output
+//! directory so they survive a restart.
+//!
+//! ## Plain English
+//!
+//! The built-in defaults live in [`Config`]. When the user changes
+//! something from the app (like which buttons save a clip), the choice
+//! is written here and laid over the defaults the next time the app
+//! starts.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, TriggerButton};
+use crate::error::{ShadowplayError, ShadowplayResult};
+
+/// Name of the settings file in the output directory
+pub const SETTINGS_FILE_NAME: &str = "settings.json";
+
+/// Settings the user changed at runtime.
+///
+/// Every field is optional: only what was changed is saved, and missing
+/// fields keep the `Config` default.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedSettings {
+    /// Button combo that saves a clip
+    pub trigger_button: Option<TriggerButton>,
+
+    /// Directory for exports, if not the clips directory
+    pub export_directory: Option<String>,
+}
+
+impl SavedSettings {
+    /// Returns the settings file path in `directory`.
+    pub fn path(directory: &Path) -> PathBuf {
+        directory.join(SETTINGS_FILE_NAME)
+    }
+
+    /// Loads saved settings, or defaults if there is no file yet.
+    pub fn load(directory: &Path) -> ShadowplayResult<Self> {
+        let path = Self::path(directory);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let json = fs::read_to_string(&path)?;
+        serde_json::from_str(&json)
+            .map_err(|e| ShadowplayError::Storage(format!("Invalid settings {:?}: {}", path, e)))
+    }
+
+    /// Writes the settings to `directory`.
+    pub fn save(&self, directory: &Path) -> ShadowplayResult<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| ShadowplayError::Storage(format!("Failed to encode settings: {}", e)))?;
+        fs::create_dir_all(directory)?;
+        fs::write(Self::path(directory), json)?;
+        Ok(())
+    }
+
+    /// Lays the saved values over `config`.
+    ///
+    /// Values that don't validate are skipped with a warning, so a bad
+    /// edit to the file can't stop the app from starting.
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(button) = &self.trigger_button {
+            match button.validate() {
+                Ok(()) => config.trigger_button = button.clone(),
+                Err(e) => log::warn!("Ignoring saved trigger button: {}", e),
+            }
+        }
+        if let Some(directory) = &self.export_directory {
+            config.export_directory = Some(directory.clone());
+        }
+    }
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ControllerAxis;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_settings_round_trip() {
+        let dir = tempdir().unwrap();
+        assert_eq!(SavedSettings::load(dir.path()).unwrap(), SavedSettings::default());
+
+        let settings = SavedSettings {
+            trigger_button: Some(TriggerButton::custom(&[
+                ControllerAxis::LeftGrip,
+                ControllerAxis::RightTrigger,
+            ])),
+            export_directory: Some("/exports".to_string()),
+        };
+        settings.save(dir.path()).unwrap();
+        assert_eq!(SavedSettings::load(dir.path()).unwrap(), settings);
+
+        let mut config = Config::default();
+        settings.apply(&mut config);
+        assert_eq!(config.trigger_button, settings.trigger_button.unwrap());
+        assert_eq!(config.export_directory_or_default(), "/exports");
+    }
+
+    #[test]
+    fn test_invalid_binding_not_applied() {
+        let settings = SavedSettings {
+            trigger_button: Some(TriggerButton::custom(&[
+                ControllerAxis::LeftGrip,
+                ControllerAxis::LeftGrip,
+            ])),
+            ..Default::default()
+        };
+        let mut config = Config::default();
+        settings.apply(&mut config);
+        assert_eq!(config.trigger_button, TriggerButton::default());
+    }
+}
@@ -0,0 +1,126 @@
+//! # Clip Thumbnails
+//!
+//! Small preview images of clips, cached next to each clip
+//! (`clip_20240101_120000.qsp` → `clip_20240101_120000.thumb.jpg`).
+//!
+//! ## Plain English
+//!
+//! Making a preview means opening the clip and decoding its first
+//! frame, which is slow when a gallery shows dozens of clips at once.
+//! The first time a preview is made it is shrunk and saved beside the
+//! clip; after that the small file is read straight back.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use image::imageops;
+
+use super::proxy::existing_proxy;
+use crate::capture::FrameCompressor;
+use crate::config::ScaleFilter;
+use crate::encoder::{FrameReader, VideoEncoder};
+use crate::error::{ShadowplayError, ShadowplayResult};
+
+/// Width of cached thumbnails in pixels (height keeps the aspect ratio)
+pub const THUMBNAIL_WIDTH: u32 = 320;
+
+/// JPEG quality of cached thumbnails
+const THUMBNAIL_QUALITY: u8 = 80;
+
+/// Returns the cached thumbnail path for a clip.
+pub fn thumbnail_path(clip_path: &Path) -> PathBuf {
+    clip_path.with_extension("thumb.jpg")
+}
+
+/// Makes a thumbnail JPEG from a clip's first frame.
+///
+/// Reads the clip's proxy when it has one, since its frames are
+/// cheaper to decode. The frame is scaled down to [`THUMBNAIL_WIDTH`]
+/// (never up) with `filter` and encoded progressive, so a preview
+/// paints before it fully loads.
+pub fn generate_thumbnail(clip_path: &Path, filter: ScaleFilter) -> ShadowplayResult<Vec<u8>> {
+    let source = existing_proxy(clip_path).unwrap_or_else(|| clip_path.to_path_buf());
+    let reader = FrameReader::open(&source.to_string_lossy())?;
+    let first = reader
+        .frames()
+        .first()
+        .ok_or_else(|| ShadowplayError::Storage(format!("Clip {:?} has no frames", clip_path)))?;
+
+    let image = VideoEncoder::decode_frame(first)?;
+    let (width, height) = image.dimensions();
+    let image = if width > THUMBNAIL_WIDTH {
+        let scaled_height = (height as u64 * THUMBNAIL_WIDTH as u64 / width as u64).max(1);
+        imageops::resize(&image, THUMBNAIL_WIDTH, scaled_height as u32, filter.filter_type())
+    } else {
+        image
+    };
+
+    let mut compressor = FrameCompressor::new(THUMBNAIL_QUALITY);
+    compressor.set_progressive(true);
+    compressor
+        .compress(image.as_raw(), image.width(), image.height())
+        .map_err(|e| ShadowplayError::Encoder(e.to_string()))
+}
+
+/// Returns a clip's thumbnail, from the cache if it is up to date.
+///
+/// A missing cache, or one older than the clip, is regenerated and
+/// written. Failing to write the cache is only logged.
+pub fn cached_thumbnail(clip_path: &Path, filter: ScaleFilter) -> ShadowplayResult<Vec<u8>> {
+    let cache = thumbnail_path(clip_path);
+    if is_fresh(&cache, clip_path) {
+        return Ok(fs::read(&cache)?);
+    }
+
+    let jpeg = generate_thumbnail(clip_path, filter)?;
+    if let Err(e) = fs::write(&cache, &jpeg) {
+        log::warn!("Failed to cache thumbnail {:?}: {}", cache, e);
+    }
+    Ok(jpeg)
+}
+
+/// Returns true if `cache` exists and was written no earlier than the clip.
+pub(super) fn is_fresh(cache: &Path, clip_path: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    match (modified(cache), modified(clip_path)) {
+        (Some(cached), Some(clip)) => cached >= clip,
+        _ => false,
+    }
+}
+
+// ============================================
+// TESTS
+// ============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::CapturedFrame;
+    use crate::config::Config;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_thumbnail_path() {
+        let path = thumbnail_path(Path::new("/clips/clip_1.qsp"));
+        assert_eq!(path, PathBuf::from("/clips/clip_1.thumb.jpg"));
+    }
+
+    #[test]
+    fn test_cached_thumbnail() {
+        let dir = tempdir().unwrap();
+        let clip = dir.path().join("clip.qsp");
+        let compressor = FrameCompressor::new(80);
+        let data = compressor.compress(&vec![90u8; 640 * 360 * 4], 640, 360).unwrap();
+        let frames = vec![CapturedFrame::with_timestamp(data, 0, 640, 360, 0)];
+        VideoEncoder::encode_frames(&frames, &clip.to_string_lossy(), &Config::default()).unwrap();
+
+        let jpeg = cached_thumbnail(&clip, ScaleFilter::default()).unwrap();
+        let image = image::load_from_memory(&jpeg).unwrap();
+        assert_eq!((image.width(), image.height()), (320, 180));
+        assert_eq!(fs::read(thumbnail_path(&clip)).unwrap(), jpeg);
+
+        // Served from the cache while it is up to date
+        fs::write(thumbnail_path(&clip), b"cached").unwrap();
+        assert_eq!(cached_thumbnail(&clip, ScaleFilter::Lanczos3).unwrap(), b"cached");
+    }
+}
@@ -12,6 +12,21 @@ let isRecording = false;
 let isNativeRecording = false;
 let statusInterval = null;
 let nativeStatsInterval = null;
+let capabilities = null;
+let gamepadInterval = null;
+let mockTriggerButton = 'left_grip_and_trigger';
+
+/** Controls that can make up a custom save combo */
+const TRIGGER_AXES = {
+    left_grip: 'Left grip',
+    left_trigger: 'Left trigger',
+    right_grip: 'Right grip',
+    right_trigger: 'Right trigger',
+    a: 'A button',
+    b: 'B button',
+    x: 'X button',
+    y: 'Y button'
+};
 
 // ============================================
 // TAURI BRIDGE
@@ -41,20 +56,80 @@ function mockCommand(cmd, args) {
                 buffer_fill_percent: isRecording ? Math.random() * 100 : 0,
                 frame_count: isRecording ? Math.floor(Math.random() * 900) : 0,
                 buffer_capacity: 900,
-                clips_count: 0
+                frames_overwritten: 0,
+                clips_count: 0,
+                session_clips_saved: 0,
+                session_clip_limit: 0,
+                compression_ratio: isRecording ? 12 + Math.random() * 4 : 0,
+                session_remaining_seconds: null,
+                auto_stopped: false,
+                paused_after_save: false,
+                estimated_save_seconds: isRecording ? 3 : 0
+            };
+        case 'get_capture_info':
+            return { source_name: 'Simulated', fps: 30, width: 256, height: 256 };
+        case 'get_capabilities':
+            return {
+                ffmpeg: true,
+                mp4_export: true,
+                hardware_h264: false,
+                hardware_h265: false,
+                software_encoder: true,
+                native_recording: false
+            };
+        case 'get_jpeg_quality':
+        case 'set_jpeg_quality': {
+            const quality = args.quality ?? 80;
+            return {
+                quality,
+                estimated_memory_mb: 85.8 * quality / 80,
+                buffer_memory_mb: 0,
+                warnings: []
             };
+        }
+        case 'estimate_quality': {
+            const [width, height, fps] = [256, 256, 30];
+            const bitsPerPixel = args.bitrate / (width * height * fps);
+            const motion = bitsPerPixel / 0.07;
+            return {
+                bitrate: args.bitrate,
+                width,
+                height,
+                fps,
+                bits_per_pixel: bitsPerPixel,
+                motion_factor: motion,
+                rating: motion >= 4 ? 'excellent' : motion >= 2 ? 'good' : motion >= 1 ? 'fair' : 'poor',
+                good_bitrate: width * height * fps * 2 * 0.07,
+                excellent_bitrate: width * height * fps * 4 * 0.07
+            };
+        }
+        case 'get_trigger_button':
+            return mockTriggerButton;
+        case 'set_trigger_button':
+            mockTriggerButton = args.button;
+            return mockTriggerButton;
+        case 'update_input':
+            return false;
         case 'start_recording':
             isRecording = true;
             return true;
         case 'stop_recording':
             isRecording = false;
             return true;
+        case 'generate_all_thumbnails':
+            return 0;
+        case 'calibrate_encoder':
+            return 1 / 3;
+        case 'acknowledge_save':
+            return true;
         case 'save_clip':
             return { success: true, message: 'Mock saved!', clip_id: 'mock_clip.qsp' };
         case 'list_clips':
             return [];
         case 'delete_clip':
             return true;
+        case 'set_clip_favorite':
+            return args.favorite;
         default:
             return null;
     }
@@ -78,13 +153,36 @@ async function updateStatus() {
             `${status.buffer_fill_percent}%`;
         document.getElementById('frame-count').textContent = status.frame_count;
         document.getElementById('frame-capacity').textContent = status.buffer_capacity;
+        document.getElementById('frames-overwritten').textContent = status.frames_overwritten > 0
+            ? ` (${status.frames_overwritten} overwritten)`
+            : '';
+        document.getElementById('compression-info').textContent = status.compression_ratio > 0
+            ? `Compressing ${status.compression_ratio.toFixed(1)}:1`
+            : '';
+        document.getElementById('save-estimate').textContent = status.frame_count > 0
+            ? `≈${Math.ceil(status.estimated_save_seconds)}s to save`
+            : '';
         
         // Update recording state
+        if (isRecording && !status.is_recording && status.auto_stopped) {
+            const limit = status.session_clip_limit;
+            const reason = limit > 0 && status.session_clips_saved >= limit
+                ? `${status.session_clips_saved} clips saved this session`
+                : 'session time limit reached';
+            showToast(`Recording stopped: ${reason}`, 'info');
+        }
         isRecording = status.is_recording;
         updateRecordingUI();
+
+        // Auto-stop countdown
+        const remaining = status.session_remaining_seconds;
+        document.getElementById('session-info').textContent = remaining == null
+            ? ''
+            : `Auto-stop in ${formatDuration(remaining)}`;
         
         // Update save button
         document.getElementById('btn-save').disabled = status.frame_count === 0;
+        document.getElementById('btn-resume').hidden = !status.paused_after_save;
         
         // Update clip count
         document.getElementById('clip-count').textContent = 
@@ -95,6 +193,152 @@ async function updateStatus() {
     }
 }
 
+/**
+ * Formats seconds as m:ss (or h:mm:ss)
+ */
+function formatDuration(seconds) {
+    const total = Math.ceil(seconds);
+    const h = Math.floor(total / 3600);
+    const m = Math.floor(total / 60) % 60;
+    const s = String(total % 60).padStart(2, '0');
+    return h > 0 ? `${h}:${String(m).padStart(2, '0')}:${s}` : `${m}:${s}`;
+}
+
+/**
+ * Shows which capture source is active and at what rate
+ */
+async function loadCaptureInfo() {
+    try {
+        const info = await invoke('get_capture_info');
+        document.getElementById('capture-info').textContent =
+            `${info.source_name} @ ${info.fps}fps (${info.width}×${info.height})`;
+    } catch (error) {
+        console.error('Failed to get capture info:', error);
+    }
+}
+
+/**
+ * Shows the JPEG quality and what it costs in memory
+ */
+function updateQualityUI(info) {
+    document.getElementById('jpeg-quality').value = info.quality;
+    document.getElementById('jpeg-quality-value').textContent = info.quality;
+    document.getElementById('memory-info').textContent =
+        `~${info.estimated_memory_mb.toFixed(0)} MB when full (now ${info.buffer_memory_mb.toFixed(1)} MB)`;
+    document.getElementById('config-warnings').textContent = info.warnings.join('\n');
+}
+
+/**
+ * Loads the current JPEG quality
+ */
+async function loadJpegQuality() {
+    try {
+        updateQualityUI(await invoke('get_jpeg_quality'));
+    } catch (error) {
+        console.error('Failed to get JPEG quality:', error);
+    }
+}
+
+/**
+ * Changes the JPEG quality for new frames
+ */
+async function setJpegQuality(value) {
+    try {
+        updateQualityUI(await invoke('set_jpeg_quality', { quality: parseInt(value, 10) }));
+    } catch (error) {
+        console.error('Failed to set JPEG quality:', error);
+        showToast(`Invalid quality: ${error}`, 'error');
+        await loadJpegQuality();
+    }
+}
+
+/**
+ * Shows what a bitrate is likely to look like at the capture resolution
+ */
+async function previewBitrate(mbps) {
+    document.getElementById('bitrate-value').textContent = mbps;
+    try {
+        const estimate = await invoke('estimate_quality', { bitrate: parseInt(mbps, 10) * 1_000_000 });
+        const excellentMbps = (estimate.excellent_bitrate / 1_000_000).toFixed(0);
+        document.getElementById('bitrate-guide').textContent =
+            `${estimate.rating} (${estimate.bits_per_pixel.toFixed(3)} bits/pixel at ` +
+            `${estimate.width}×${estimate.height} ${estimate.fps}fps; ` +
+            `${excellentMbps} Mbps for fast motion)`;
+    } catch (error) {
+        console.error('Failed to estimate quality:', error);
+    }
+}
+
+/**
+ * Shows the active save combo (a preset name or { custom: [a, b] })
+ */
+function updateTriggerUI(button) {
+    const custom = typeof button === 'object' ? button.custom : null;
+    document.getElementById('trigger-button').value = custom ? 'custom' : button;
+    document.getElementById('trigger-custom').hidden = !custom;
+    if (custom) {
+        document.getElementById('trigger-axis-1').value = custom[0];
+        document.getElementById('trigger-axis-2').value = custom[1];
+    }
+}
+
+/**
+ * Loads the active save combo
+ */
+async function loadTriggerButton() {
+    for (const id of ['trigger-axis-1', 'trigger-axis-2']) {
+        const select = document.getElementById(id);
+        select.innerHTML = Object.entries(TRIGGER_AXES)
+            .map(([value, label]) => `<option value="${value}">${label}</option>`)
+            .join('');
+    }
+    document.getElementById('trigger-axis-2').value = 'left_trigger';
+
+    try {
+        updateTriggerUI(await invoke('get_trigger_button'));
+    } catch (error) {
+        console.error('Failed to get trigger button:', error);
+    }
+}
+
+/**
+ * Applies and saves the save combo picked in the UI
+ */
+async function setTriggerButton() {
+    let button = document.getElementById('trigger-button').value;
+    if (button === 'custom') {
+        button = {
+            custom: [
+                document.getElementById('trigger-axis-1').value,
+                document.getElementById('trigger-axis-2').value
+            ]
+        };
+    }
+
+    try {
+        updateTriggerUI(await invoke('set_trigger_button', { button }));
+    } catch (error) {
+        console.error('Failed to set trigger button:', error);
+        showToast(`Invalid save combo: ${error}`, 'error');
+        await loadTriggerButton();
+    }
+}
+
+/**
+ * Loads encode/export capabilities and hides unsupported options
+ */
+async function loadCapabilities() {
+    try {
+        capabilities = await invoke('get_capabilities');
+        const nativePanel = document.querySelector('.native-panel');
+        if (nativePanel && !capabilities.native_recording) {
+            nativePanel.style.display = 'none';
+        }
+    } catch (error) {
+        console.error('Failed to get capabilities:', error);
+    }
+}
+
 /**
  * Updates the UI to reflect recording state
  */
@@ -161,6 +405,7 @@ function createClipCard(clip) {
     const sizeKB = (clip.size_bytes / 1024).toFixed(1);
     const sizeMB = (clip.size_bytes / (1024 * 1024)).toFixed(1);
     const sizeDisplay = clip.size_bytes > 1024 * 1024 ? `${sizeMB} MB` : `${sizeKB} KB`;
+    const canExport = !capabilities || capabilities.mp4_export;
     
     card.innerHTML = `
         <div class="clip-thumbnail" data-clip-id="${clip.id}">
@@ -168,12 +413,18 @@ function createClipCard(clip) {
         </div>
         <div class="clip-info">
             <div class="clip-time">${clip.timestamp || 'Unknown time'}</div>
-            <div class="clip-size">${sizeDisplay}</div>
+            <div class="clip-size">${sizeDisplay}${clip.proxy_id ? ' · proxy' : ''}</div>
         </div>
         <div class="clip-actions">
-            <button class="clip-action-btn export" onclick="exportToMp4('${clip.id}', event)">
+            <button class="clip-action-btn export" onclick="exportToMp4('${clip.id}', event)"
+                ${canExport ? '' : 'disabled title="ffmpeg not found"'}>
                 🎬 MP4
             </button>
+            <button class="clip-action-btn favorite${clip.favorite ? ' active' : ''}"
+                onclick="toggleFavorite('${clip.id}', ${!clip.favorite}, event)"
+                title="Favorites are never auto-deleted">
+                ${clip.favorite ? '★' : '☆'}
+            </button>
             <button class="clip-action-btn delete" onclick="deleteClip('${clip.id}', event)">
                 🗑️ Delete
             </button>
@@ -199,6 +450,41 @@ async function loadThumbnail(clipId, card) {
     }
 }
 
+/**
+ * Caches every clip's thumbnail in the background, showing each one as
+ * it is ready
+ */
+async function generateAllThumbnails() {
+    if (window.__TAURI__) {
+        await window.__TAURI__.event.listen('thumbnail-progress', (event) => {
+            const clipId = event.payload.clip_id;
+            const card = document.querySelector(`.clip-card[data-clip-id="${clipId}"]`);
+            if (card && !card.querySelector('.clip-thumbnail img')) {
+                loadThumbnail(clipId, card);
+            }
+        });
+    }
+
+    try {
+        const count = await invoke('generate_all_thumbnails');
+        console.log(`Caching thumbnails for ${count} clips`);
+    } catch (error) {
+        console.warn('Failed to start thumbnail caching:', error);
+    }
+}
+
+/**
+ * Times a sample save so the "≈Ns to save" estimate fits this device
+ */
+async function calibrateEncoder() {
+    try {
+        const ratio = await invoke('calibrate_encoder');
+        console.log(`Saving runs at ${ratio.toFixed(2)}s per second of footage`);
+    } catch (error) {
+        console.warn('Encoder calibration failed:', error);
+    }
+}
+
 // ============================================
 // ACTIONS
 // ============================================
@@ -260,6 +546,19 @@ async function saveClip() {
     }
 }
 
+/**
+ * Resumes recording paused after a save
+ */
+async function acknowledgeSave() {
+    try {
+        await invoke('acknowledge_save');
+        await updateStatus();
+    } catch (error) {
+        console.error('Failed to resume recording:', error);
+        showToast(`Failed to resume: ${error}`, 'error');
+    }
+}
+
 /**
  * Deletes a clip
  */
@@ -281,6 +580,22 @@ async function deleteClip(clipId, event) {
     }
 }
 
+/**
+ * Marks or unmarks a clip as a favorite
+ */
+async function toggleFavorite(clipId, favorite, event) {
+    event.stopPropagation();
+    
+    try {
+        await invoke('set_clip_favorite', { id: clipId, favorite });
+        showToast(favorite ? 'Added to favorites' : 'Removed from favorites', 'info');
+        await loadClips();
+    } catch (error) {
+        console.error('Favorite failed:', error);
+        showToast(`Favorite failed: ${error}`, 'error');
+    }
+}
+
 // ============================================
 // NATIVE RECORDING (Hardware Accelerated)
 // ============================================
@@ -378,9 +693,9 @@ function stopNativeStatsPolling() {
 }
 
 /**
- * Exports a clip to MP4
+ * Exports a clip to MP4, optionally reframed to 16:9 ('crop', 'pad' or 'stretch')
  */
-async function exportToMp4(clipId, event) {
+async function exportToMp4(clipId, event, aspect = null) {
     event.stopPropagation();
     
     const btn = event.target;
@@ -390,7 +705,7 @@ async function exportToMp4(clipId, event) {
     
     try {
         showToast('Exporting to MP4...', 'info');
-        const result = await invoke('export_to_mp4', { id: clipId });
+        const result = await invoke('export_to_mp4', { id: clipId, aspect });
         
         if (result.success) {
             showToast(`Exported! ${result.message}`, 'success');
@@ -443,6 +758,88 @@ function showToast(message, type = 'info') {
 // INITIALIZATION
 // ============================================
 
+// ============================================
+// CONTROLLER INPUT
+// ============================================
+
+/**
+ * Reads connected gamepads into the backend's InputState shape.
+ *
+ * Quest controllers report as one "xr-standard" gamepad per hand;
+ * other pads use the standard layout, with bumpers standing in for grips.
+ */
+function readGamepads() {
+    const input = {};
+    const pads = navigator.getGamepads ? navigator.getGamepads() : [];
+    for (const pad of pads) {
+        if (!pad) continue;
+        const value = (i) => (pad.buttons[i] ? pad.buttons[i].value : 0);
+        const pressed = (i) => Boolean(pad.buttons[i] && pad.buttons[i].pressed);
+        const axis = (i) => pad.axes[i] || 0;
+
+        if (pad.mapping === 'xr-standard' && pad.hand) {
+            const side = pad.hand === 'left' ? 'left' : 'right';
+            input[`${side}_trigger`] = value(0);
+            input[`${side}_grip`] = value(1);
+            input[`${side}_stick_x`] = axis(2);
+            input[`${side}_stick_y`] = axis(3);
+            if (side === 'left') {
+                input.left_x = pressed(4);
+                input.left_y = pressed(5);
+            } else {
+                input.right_a = pressed(4);
+                input.right_b = pressed(5);
+            }
+        } else if (pad.mapping === 'standard') {
+            input.left_trigger = value(6);
+            input.right_trigger = value(7);
+            input.left_grip = value(4);
+            input.right_grip = value(5);
+            input.left_stick_x = axis(0);
+            input.left_stick_y = axis(1);
+            input.right_stick_x = axis(2);
+            input.right_stick_y = axis(3);
+            input.right_a = pressed(0);
+            input.right_b = pressed(1);
+            input.left_x = pressed(2);
+            input.left_y = pressed(3);
+            input.left_menu = pressed(9);
+        }
+    }
+    return input;
+}
+
+/**
+ * Sends controller state to the backend, saving when the combo fires
+ */
+async function pollGamepads() {
+    try {
+        const triggered = await invoke('update_input', { input: readGamepads() });
+        if (triggered && isRecording) {
+            await saveClip();
+        }
+    } catch (error) {
+        console.error('Failed to send controller input:', error);
+    }
+}
+
+/**
+ * Polls controllers while any are connected
+ */
+function watchGamepads() {
+    window.addEventListener('gamepadconnected', () => {
+        if (!gamepadInterval) {
+            gamepadInterval = setInterval(pollGamepads, 50); // 20Hz
+        }
+    });
+    window.addEventListener('gamepaddisconnected', () => {
+        if (gamepadInterval && !Array.from(navigator.getGamepads()).some(Boolean)) {
+            clearInterval(gamepadInterval);
+            gamepadInterval = null;
+        }
+    });
+}
+
 /**
  * Initialize the application
  */
@@ -451,9 +848,17 @@ async function init() {
     
     // Initial status update
     await updateStatus();
+    await loadCaptureInfo();
+    await loadCapabilities();
+    await loadJpegQuality();
+    await previewBitrate(document.getElementById('bitrate').value);
+    await loadTriggerButton();
+    watchGamepads();
     
-    // Load clips
+    // Load clips, then cache any missing thumbnails
     await loadClips();
+    await generateAllThumbnails();
+    await calibrateEncoder();
     
     // Start status polling (every 500ms when recording, 2s otherwise)
     statusInterval = setInterval(async () => {